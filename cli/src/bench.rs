@@ -0,0 +1,100 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use ton_liteapi::client::LiteClient;
+use ton_liteapi::types::LiteServer;
+
+use crate::Result;
+
+/// Measurements of a single liteserver, `None` for the ones not reached because of `error`.
+struct Measurement {
+    server: LiteServer,
+    handshake: Option<Duration>,
+    /// Median round trip of `getTime`
+    rtt: Option<Duration>,
+    seqno: Option<u32>,
+    error: Option<String>,
+}
+
+impl Measurement {
+    async fn take(server: LiteServer, samples: u32, timeout: Duration) -> Self {
+        let mut measurement = Self { server, handshake: None, rtt: None, seqno: None, error: None };
+        if let Err(e) = measurement.measure(samples, timeout).await {
+            measurement.error = Some(error_chain(&*e));
+        }
+        measurement
+    }
+
+    async fn measure(&mut self, samples: u32, timeout: Duration) -> Result<()> {
+        let started = Instant::now();
+        let connect = LiteClient::builder().connect_server(&self.server);
+        let mut client = tokio::time::timeout(timeout, connect).await.map_err(|_| "handshake timed out")??;
+        self.handshake = Some(started.elapsed());
+
+        let mut rtts = Vec::new();
+        for _ in 0..samples.max(1) {
+            let started = Instant::now();
+            tokio::time::timeout(timeout, client.get_time()).await.map_err(|_| "getTime timed out")??;
+            rtts.push(started.elapsed());
+        }
+        rtts.sort();
+        self.rtt = Some(rtts[rtts.len() / 2]);
+
+        let info = tokio::time::timeout(timeout, client.get_masterchain_info()).await
+            .map_err(|_| "getMasterchainInfo timed out")??;
+        self.seqno = Some(info.last.seqno);
+        Ok(())
+    }
+}
+
+/// Connect to every server concurrently, measure the handshake time, the `getTime` round trip and how many
+/// masterchain blocks the server lags behind the freshest one, and print the servers from the best one.
+pub async fn run(servers: Vec<LiteServer>, samples: u32, timeout: Duration, json: bool) -> Result<()> {
+    let mut results = futures::future::join_all(servers.into_iter().map(|server| Measurement::take(server, samples, timeout))).await;
+    let max_seqno = results.iter().filter_map(|m| m.seqno).max();
+    let lag = |m: &Measurement| m.seqno.zip(max_seqno).map(|(seqno, max)| max - seqno);
+    // reachable servers first, then the fresher and the faster ones
+    results.sort_by_key(|m| (m.error.is_some(), lag(m), m.rtt, m.handshake));
+
+    if json {
+        let rows: Vec<_> = results.iter().enumerate().map(|(i, m)| serde_json::json!({
+            "rank": i + 1,
+            "address": m.server.address.to_string(),
+            "public_key": hex::encode(m.server.public_key),
+            "handshake_ms": m.handshake.map(millis),
+            "rtt_ms": m.rtt.map(millis),
+            "seqno": m.seqno,
+            "lag": lag(m),
+            "error": m.error,
+        })).collect();
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    println!("{:>3}  {:<86}  {:>10}  {:>10}  {:>10}  {:>5}", "#", "LITESERVER", "HANDSHAKE", "RTT", "SEQNO", "LAG");
+    for (i, m) in results.iter().enumerate() {
+        let ms = |d: Option<Duration>| d.map_or("-".to_owned(), |d| format!("{:.1} ms", millis(d)));
+        let or_dash = |v: Option<u32>| v.map_or("-".to_owned(), |v| v.to_string());
+        print!("{:>3}  {:<86}  {:>10}  {:>10}  {:>10}  {:>5}", i + 1, m.server, ms(m.handshake), ms(m.rtt), or_dash(m.seqno), or_dash(lag(m)));
+        match &m.error {
+            Some(error) => println!("  {}", error),
+            None => println!(),
+        }
+    }
+    Ok(())
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// `error` followed by its sources, the innermost one says what actually went wrong.
+fn error_chain(error: &dyn Error) -> String {
+    let mut text = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        text += &format!(": {}", e);
+        source = e.source();
+    }
+    text
+}
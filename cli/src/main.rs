@@ -1,10 +1,12 @@
 mod arg_parsers;
+mod bench;
 
 use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
 use rand::seq::SliceRandom as _;
 use ton_liteapi::tl::common::{AccountId, BlockId, BlockIdExt, Int256, TransactionId3};
 use ton_liteapi::client::LiteClient;
+use ton_liteapi::types::{ConfigMode, LiteServer};
 use pretty_hex::PrettyHex;
 use ton_networkconfig::ConfigGlobal;
 use std::error::Error;
@@ -236,22 +238,39 @@ enum Commands {
     GetLibraries {
         library_list: Vec<Int256>,
     },
+    /// Measure handshake time, getTime round trip and last block lag of every liteserver of the config
+    /// and print them ranked from the best one
+    Bench {
+        /// Number of getTime queries to take the median round trip of
+        #[clap(long, default_value_t = 5)]
+        samples: u32,
+        /// Seconds a server has for the handshake and for each query
+        #[clap(long, default_value_t = 5)]
+        timeout: u64,
+        /// Print JSON instead of a table
+        #[clap(long)]
+        json: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
     let args = Args::parse();
-    
+
+    if let Commands::Bench { samples, timeout, json } = args.command {
+        let servers = if let (Some(address), Some(public_key)) = (args.address, args.public_key) {
+            vec![LiteServer::new(address, public_key)]
+        } else {
+            LiteServer::from_config(&load_config(args.config, args.testnet).await?)
+        };
+        return bench::run(servers, samples, Duration::from_secs(timeout), json).await;
+    }
+
     let client = if let (Some(address), Some(public_key)) = (&args.address, &args.public_key) {
         LiteClient::connect(address, public_key).await?
     } else {
-        let config_json = if let Some(config) = args.config {
-            read_to_string(config)?
-        } else {
-            download_config(args.testnet).await?
-        };
-        let config: ConfigGlobal = ConfigGlobal::from_str(&config_json)?;
+        let config = load_config(args.config, args.testnet).await?;
         let ls = config.liteservers.choose(&mut rand::thread_rng()).unwrap();
         let public_key: [u8; 32] = ls.id.clone().into();
         LiteClient::connect(ls.socket_addr(), public_key).await?
@@ -378,38 +397,37 @@ async fn execute_command(client: &mut LiteClient, command: &Commands) -> Result<
             println!("{:#?}", result);
         }
         Commands::GetConfigAll { block_id_ext, with_state_root, with_libraries, with_state_extra_root, with_shard_hashes, with_validator_set, with_special_smc, with_accounts_root, with_prev_blocks, with_workchain_info, with_capabilities, extract_from_key_block } => {
-            let result = client.get_config_all(
-                block_id_ext.clone(),
-                *with_state_root,
-                *with_libraries,
-                *with_state_extra_root,
-                *with_shard_hashes,
-                *with_validator_set,
-                *with_special_smc,
-                *with_accounts_root,
-                *with_prev_blocks,
-                *with_workchain_info,
-                *with_capabilities,
-                *extract_from_key_block,
-            ).await?;
+            let mode = ConfigMode {
+                with_state_root: *with_state_root,
+                with_libraries: *with_libraries,
+                with_state_extra_root: *with_state_extra_root,
+                with_shard_hashes: *with_shard_hashes,
+                with_validator_set: *with_validator_set,
+                with_special_smc: *with_special_smc,
+                with_accounts_root: *with_accounts_root,
+                with_prev_blocks: *with_prev_blocks,
+                with_workchain_info: *with_workchain_info,
+                with_capabilities: *with_capabilities,
+                extract_from_key_block: *extract_from_key_block,
+            };
+            let result = client.get_config_all(block_id_ext.clone(), mode).await?;
             println!("{:#?}", result);
         }
         Commands::GetConfigParams { block_id_ext, param_list, with_state_root, with_libraries, with_state_extra_root, with_shard_hashes, with_validator_set, with_special_smc, with_accounts_root, with_prev_blocks, with_workchain_info, with_capabilities, extract_from_key_block } => {
-            let result = client.get_config_params(
-                block_id_ext.clone(),
-                param_list.clone(),
-                *with_state_root,
-                *with_libraries,
-                *with_state_extra_root,
-                *with_shard_hashes,
-                *with_validator_set,
-                *with_special_smc,
-                *with_accounts_root,
-                *with_prev_blocks,
-                *with_workchain_info,
-                *with_capabilities,
-                *extract_from_key_block,
-            ).await?;
+            let mode = ConfigMode {
+                with_state_root: *with_state_root,
+                with_libraries: *with_libraries,
+                with_state_extra_root: *with_state_extra_root,
+                with_shard_hashes: *with_shard_hashes,
+                with_validator_set: *with_validator_set,
+                with_special_smc: *with_special_smc,
+                with_accounts_root: *with_accounts_root,
+                with_prev_blocks: *with_prev_blocks,
+                with_workchain_info: *with_workchain_info,
+                with_capabilities: *with_capabilities,
+                extract_from_key_block: *extract_from_key_block,
+            };
+            let result = client.get_config_params(block_id_ext.clone(), param_list.clone(), mode).await?;
             println!("{:#?}", result);
         }
         Commands::GetValidatorStats { block_id_ext, limit, start_after, modified_after } => {
@@ -425,10 +443,20 @@ async fn execute_command(client: &mut LiteClient, command: &Commands) -> Result<
             let result = client.get_libraries(library_list.clone()).await?;
             println!("{:#?}", result);
         }
+        Commands::Bench { .. } => unreachable!("bench connects to the servers itself"),
     };
     Ok(())
 }
 
+async fn load_config(config: Option<PathBuf>, testnet: bool) -> Result<ConfigGlobal> {
+    let config_json = if let Some(config) = config {
+        read_to_string(config)?
+    } else {
+        download_config(testnet).await?
+    };
+    Ok(ConfigGlobal::from_str(&config_json)?)
+}
+
 async fn download_config(testnet: bool) -> Result<String> {
     let url = if testnet {
         "https://ton.org/testnet-global.config.json"
@@ -0,0 +1,12 @@
+use ton_liteapi::handle::LiteHandle;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+    let server_public = hex::decode("9f69357376ad875d1543faea6f0bb9fbcd283521b743b1c0d2d432587fe9dbae")?;
+    let server_address = ("127.0.0.1", 8080);
+    let handle = LiteHandle::connect(server_address, server_public).await?;
+    let (time, info) = tokio::try_join!(handle.get_time(), handle.get_masterchain_info())?;
+    println!("{} {:?}", time, info.last);
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::fs::read_to_string;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use adnl::crypto::{KeyPair, SecretKey};
+use adnl::AdnlAddress;
+use clap::{ArgEnum, Parser};
+use ton_liteapi::client::LiteClient;
+use ton_liteapi::pool::{LeastLatency, LitePool, Role, ServerPreference, WeightedRandom};
+use ton_liteapi::proxy::{AnswerCache, LiteProxy, RateLimit};
+use ton_liteapi::server::serve;
+use ton_liteapi::types::{LiteServer, Network};
+use ton_networkconfig::ConfigGlobal;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const KEY_VAR: &str = "LITE_PROXY_KEY";
+
+/// Liteserver proxy balancing queries over upstream liteservers
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Network config with the upstream liteservers, which may set their `weight` and `priority`
+    #[clap(short, long, parse(from_os_str), value_name = "FILE", required_unless_present_any = ["upstream", "route"])]
+    config: Option<PathBuf>,
+    /// Upstream liteserver in addition to the ones of the config, may be repeated
+    #[clap(long, value_name = "IP:PORT#PUBLIC_KEY")]
+    upstream: Vec<LiteServer>,
+    /// Dedicate an upstream liteserver to a role (general, send-message or workchain:N), may be repeated.
+    /// The server is added if it isn't an upstream already
+    #[clap(long, value_name = "ROLE=IP:PORT#PUBLIC_KEY", value_parser = parse_route)]
+    route: Vec<(Role, LiteServer)>,
+    /// Treat upstream liteservers differing only in the port as the same server and keep the first one
+    #[clap(long)]
+    merge_ports: bool,
+    /// How to choose the upstream liteserver for a query
+    #[clap(long, arg_enum, default_value = "round-robin")]
+    policy: Policy,
+    /// Number of upstream liteservers a query is tried on before its error is returned
+    #[clap(long, default_value_t = 3)]
+    failover: usize,
+    /// Interval between health checks of the upstream liteservers in seconds
+    #[clap(long, default_value_t = 10)]
+    health_check_interval: u64,
+    /// Address to accept liteserver connections on
+    #[clap(long, default_value = "0.0.0.0:3333")]
+    listen: SocketAddr,
+    /// File with the private key of the proxy (hex-encoded), clients connect with its public key.
+    /// Without it the key is taken from the LITE_PROXY_KEY environment variable
+    #[clap(long, parse(from_os_str), value_name = "FILE")]
+    key_file: Option<PathBuf>,
+    /// Memory for cached answers in megabytes, 0 disables the cache
+    #[clap(long, default_value_t = 256)]
+    cache_size: usize,
+    /// Queries per second allowed for each client IP address, 0 disables the limit
+    #[clap(long, default_value_t = 0)]
+    rate_limit: u32,
+    /// Queries a client may send at once before the rate limit applies
+    #[clap(long, default_value_t = 100)]
+    burst: u32,
+    /// Interval between logging per-method statistics of the queries in seconds, 0 disables them
+    #[clap(long, default_value_t = 60)]
+    stats_interval: u64,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug)]
+enum Policy {
+    RoundRobin,
+    LeastLatency,
+    Random,
+}
+
+fn parse_route(s: &str) -> std::result::Result<(Role, LiteServer), String> {
+    let (role, server) = s.split_once('=').ok_or("expected ROLE=IP:PORT#PUBLIC_KEY")?;
+    Ok((role.parse()?, server.parse()?))
+}
+
+fn parse_key(s: &str) -> std::result::Result<[u8; 32], String> {
+    hex::decode(s).map_err(|e| e.to_string())?.try_into().map_err(|_| "key must be 32 bytes".to_owned())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+    // the key isn't taken on the command line, where it would show up in the process list
+    let key = match &args.key_file {
+        Some(path) => read_to_string(path)?,
+        None => std::env::var(KEY_VAR).map_err(|_| format!("either --key-file or {} is required", KEY_VAR))?,
+    };
+    let key = parse_key(key.trim())?;
+
+    let mut servers = args.upstream.clone();
+    let mut builder = LiteClient::builder();
+    let mut preferences = Vec::new();
+    if let Some(config) = &args.config {
+        let config = ConfigGlobal::parse(&read_to_string(config)?)?;
+        servers.extend(LiteServer::from_config(&config));
+        preferences = ServerPreference::from_config(&config);
+        // upstream servers of another network are refused
+        if let Some(network) = Network::from_config(&config) {
+            builder = builder.with_network(network);
+        }
+    }
+    servers.extend(args.route.iter().map(|(_, server)| server.clone()));
+    let dedup = LiteServer::dedup(servers, args.merge_ports).log();
+    // servers which are down at startup are retried by the health checks
+    let pool = LitePool::connect_in_background(dedup.servers.clone(), builder)?
+        .with_failover(args.failover)
+        .with_preferences(preferences.into_iter().map(|(server, preference)| (dedup.kept(&server).clone(), preference)))
+        .with_roles(args.route.iter().map(|(role, server)| (dedup.kept(server).clone(), *role)));
+    let pool = match args.policy {
+        Policy::RoundRobin => pool,
+        Policy::LeastLatency => pool.with_policy(LeastLatency),
+        Policy::Random => pool.with_policy(WeightedRandom::default()),
+    };
+    let connected = pool.ready(1).await?;
+    log::info!("Connected to {} upstream liteservers", connected);
+    tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.run_health_checks(Duration::from_secs(args.health_check_interval)).await }
+    });
+
+    let mut proxy = LiteProxy::new(pool);
+    if args.cache_size > 0 {
+        proxy = proxy.with_cache(Arc::new(AnswerCache::new(args.cache_size << 20)));
+    }
+    if args.rate_limit > 0 {
+        proxy = proxy.with_rate_limit(Arc::new(RateLimit::new(args.rate_limit, args.burst)));
+    }
+
+    if args.stats_interval > 0 {
+        let request_log = proxy.request_log().clone();
+        let interval = Duration::from_secs(args.stats_interval);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let mut methods: Vec<_> = request_log.snapshot().into_iter().collect();
+                methods.sort_by_key(|(_, histogram)| std::cmp::Reverse(histogram.requests));
+                for (method, histogram) in methods {
+                    log::info!("{}: {} queries, {} server errors, {} failed, p50 {:?}, p99 {:?}, {} bytes in, {} bytes out",
+                        method, histogram.requests, histogram.server_errors, histogram.errors,
+                        histogram.quantile(0.5), histogram.quantile(0.99), histogram.bytes_sent, histogram.bytes_received);
+                }
+            }
+        });
+    }
+
+    let keypair = KeyPair::from(&SecretKey::from_bytes(key));
+    log::info!("Listening on {}, public key {}, ADNL address {}",
+        args.listen,
+        hex::encode(keypair.public_key.as_bytes()),
+        hex::encode(AdnlAddress::from(&keypair.public_key).as_bytes()),
+    );
+    serve(&args.listen, keypair, proxy).await
+}
@@ -0,0 +1,178 @@
+//! Caches of get-method results and of the last masterchain block.
+//!
+//! The result of a get-method at a given block never changes, so [`GetMethodCache`] keeps results by block,
+//! account, method and parameters, and evicts the least recently used ones once it's full. Share one cache
+//! between clients with [`LiteClientBuilder::with_get_method_cache`](crate::client::LiteClientBuilder::with_get_method_cache).
+//!
+//! The last masterchain block changes every few seconds, so [`MasterchainInfoCache`] keeps the last
+//! `getMasterchainInfo` answer for a short time instead, saving a round trip for each of a burst of queries
+//! of the latest state.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+use crate::tl::common::{AccountId, BlockIdExt};
+use crate::tl::response::{MasterchainInfo, RunMethodResult};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    mode: u32,
+    id: BlockIdExt,
+    workchain: i32,
+    account: [u8; 32],
+    method_id: u64,
+    params_hash: [u8; 32],
+}
+
+impl CacheKey {
+    fn new(mode: u32, id: &BlockIdExt, account: &AccountId, method_id: u64, params: &[u8]) -> Self {
+        Self {
+            mode,
+            id: id.clone(),
+            workchain: account.workchain,
+            account: account.id.0,
+            method_id,
+            params_hash: Sha256::digest(params).into(),
+        }
+    }
+}
+
+/// Results of `runSmcMethod` at fixed blocks, see the [module docs](self).
+pub struct GetMethodCache {
+    capacity: usize,
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    results: HashMap<CacheKey, (RunMethodResult, u64)>,
+    /// Keys by the tick of their last use
+    recent: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl GetMethodCache {
+    /// Cache holding up to `capacity` results.
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, inner: Default::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, mode: u32, id: &BlockIdExt, account: &AccountId, method_id: u64, params: &[u8]) -> Option<RunMethodResult> {
+        let key = CacheKey::new(mode, id, account, method_id, params);
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        let (result, last_used) = inner.results.get_mut(&key)?;
+        let result = result.clone();
+        let previous = std::mem::replace(last_used, tick);
+        inner.recent.remove(&previous);
+        inner.recent.insert(tick, key);
+        Some(result)
+    }
+
+    pub fn insert(&self, mode: u32, id: &BlockIdExt, account: &AccountId, method_id: u64, params: &[u8], result: &RunMethodResult) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = CacheKey::new(mode, id, account, method_id, params);
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        if let Some((_, previous)) = inner.results.insert(key.clone(), (result.clone(), tick)) {
+            inner.recent.remove(&previous);
+        }
+        inner.recent.insert(tick, key);
+        while inner.results.len() > self.capacity {
+            let Some((_, evicted)) = inner.recent.pop_first() else {
+                break;
+            };
+            inner.results.remove(&evicted);
+        }
+    }
+}
+
+/// Last `getMasterchainInfo` answer, reused for a short time, see the [module docs](self).
+///
+/// Used by the "latest" methods of [`LiteClient`](crate::client::LiteClient), such as
+/// [`LiteClient::get_latest_account_state`](crate::client::LiteClient::get_latest_account_state), of the
+/// clients sharing it. An answer older than the cached one, e.g. from a lagging server of a pool, doesn't
+/// replace it until it expires.
+pub struct MasterchainInfoCache {
+    ttl: Duration,
+    last: Mutex<Option<(Instant, MasterchainInfo)>>,
+}
+
+impl MasterchainInfoCache {
+    /// Cache keeping an answer for `ttl`, a second or two lag behind the chain at most by a block.
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, last: Mutex::new(None) }
+    }
+
+    /// Cached answer unless it expired.
+    pub fn get(&self) -> Option<MasterchainInfo> {
+        let last = self.last.lock().unwrap();
+        last.as_ref().filter(|(received, _)| received.elapsed() < self.ttl).map(|(_, info)| info.clone())
+    }
+
+    pub fn insert(&self, info: &MasterchainInfo) {
+        let mut last = self.last.lock().unwrap();
+        let fresher = match last.as_ref() {
+            Some((received, cached)) => received.elapsed() >= self.ttl || info.last.seqno >= cached.last.seqno,
+            None => true,
+        };
+        if fresher {
+            *last = Some((Instant::now(), info.clone()));
+        }
+    }
+
+    /// Seqno of the cached block, also after it expired.
+    pub fn last_seqno(&self) -> Option<u32> {
+        self.last.lock().unwrap().as_ref().map(|(_, info)| info.last.seqno)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tl::common::{Int256, ZeroStateIdExt};
+
+    use super::*;
+
+    fn info(seqno: u32) -> MasterchainInfo {
+        MasterchainInfo {
+            last: BlockIdExt { workchain: -1, shard: 0x8000000000000000, seqno, root_hash: Int256::default(), file_hash: Int256::default() },
+            state_root_hash: Int256::default(),
+            init: ZeroStateIdExt { workchain: -1, root_hash: Int256::default(), file_hash: Int256::default() },
+        }
+    }
+
+    #[test]
+    fn test_masterchain_info_cache() {
+        let cache = MasterchainInfoCache::new(Duration::from_millis(50));
+        assert_eq!((cache.get(), cache.last_seqno()), (None, None));
+        cache.insert(&info(10));
+        assert_eq!(cache.get(), Some(info(10)));
+        // an answer of a lagging server doesn't replace a fresher one
+        cache.insert(&info(9));
+        assert_eq!(cache.get(), Some(info(10)));
+        cache.insert(&info(11));
+        assert_eq!(cache.get(), Some(info(11)));
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(cache.get(), None);
+        assert_eq!(cache.last_seqno(), Some(11));
+        // once expired, any answer replaces it
+        cache.insert(&info(9));
+        assert_eq!(cache.get(), Some(info(9)));
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{ArcCell, Cell, CellError, LevelMask};
+
+const BOC_GENERIC: u32 = 0xb5ee9c72;
+const BOC_INDEXED: u32 = 0x68ff65f3;
+const BOC_INDEXED_CRC32C: u32 = 0xacc3a728;
+
+const CRC32C: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISCSI);
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read(&mut self, len: usize) -> Result<&'a [u8], CellError> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len())
+            .ok_or(CellError::InvalidBoc("unexpected end"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, CellError> {
+        Ok(self.read(1)?[0])
+    }
+
+    fn read_uint(&mut self, len: usize) -> Result<usize, CellError> {
+        if len > std::mem::size_of::<usize>() {
+            return Err(CellError::InvalidBoc("integer too long"));
+        }
+        Ok(self.read(len)?.iter().fold(0, |acc, b| (acc << 8) | *b as usize))
+    }
+}
+
+struct RawCell<'a> {
+    data: &'a [u8],
+    bit_len: usize,
+    references: Vec<usize>,
+    exotic: bool,
+}
+
+/// Deserialize all root cells of a bag of cells.
+pub fn deserialize_boc(boc: &[u8]) -> Result<Vec<ArcCell>, CellError> {
+    let mut reader = Reader { data: boc, pos: 0 };
+    let magic = u32::from_be_bytes(reader.read(4)?.try_into().unwrap());
+    let flags = reader.read_u8()?;
+    let (has_index, has_crc32c, has_roots) = match magic {
+        BOC_GENERIC => (flags & 0x80 != 0, flags & 0x40 != 0, true),
+        BOC_INDEXED => (true, false, false),
+        BOC_INDEXED_CRC32C => (true, true, false),
+        _ => return Err(CellError::InvalidBoc("unknown magic")),
+    };
+    if has_crc32c {
+        if boc.len() < 4 {
+            return Err(CellError::InvalidBoc("unexpected end"));
+        }
+        let (payload, crc) = boc.split_at(boc.len() - 4);
+        if CRC32C.checksum(payload) != u32::from_le_bytes(crc.try_into().unwrap()) {
+            return Err(CellError::ChecksumMismatch);
+        }
+    }
+    let ref_size = (flags & 0x07) as usize;
+    let offset_size = reader.read_u8()? as usize;
+    if ref_size == 0 || offset_size == 0 {
+        return Err(CellError::InvalidBoc("invalid header"));
+    }
+    let cell_count = reader.read_uint(ref_size)?;
+    let root_count = reader.read_uint(ref_size)?;
+    let _absent_count = reader.read_uint(ref_size)?;
+    let _data_size = reader.read_uint(offset_size)?;
+    if root_count == 0 || root_count > cell_count || (!has_roots && root_count != 1) {
+        return Err(CellError::InvalidBoc("invalid root count"));
+    }
+    let roots = if has_roots {
+        (0..root_count).map(|_| reader.read_uint(ref_size)).collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![0]
+    };
+    if has_index {
+        reader.read(cell_count.checked_mul(offset_size).ok_or(CellError::InvalidBoc("invalid header"))?)?;
+    }
+
+    let mut raw_cells = Vec::with_capacity(cell_count.min(boc.len()));
+    for i in 0..cell_count {
+        let d1 = reader.read_u8()?;
+        let d2 = reader.read_u8()?;
+        let ref_count = (d1 & 7) as usize;
+        let exotic = d1 & 8 != 0;
+        if ref_count > super::MAX_REFS {
+            return Err(CellError::InvalidBoc("too many references"));
+        }
+        if d1 & 16 != 0 {
+            // stored hashes and depths are recomputed anyway
+            reader.read(LevelMask::new(d1 >> 5).hash_count() * (32 + 2))?;
+        }
+        let data = reader.read(d2.div_ceil(2) as usize)?;
+        let bit_len = if d2 % 2 == 0 {
+            data.len() * 8
+        } else {
+            let last = *data.last().ok_or(CellError::InvalidBoc("invalid cell data"))?;
+            if last == 0 {
+                return Err(CellError::InvalidBoc("missing completion tag"));
+            }
+            data.len() * 8 - 1 - last.trailing_zeros() as usize
+        };
+        let references = (0..ref_count).map(|_| reader.read_uint(ref_size)).collect::<Result<Vec<_>, _>>()?;
+        if references.iter().any(|r| *r <= i || *r >= cell_count) {
+            return Err(CellError::InvalidBoc("invalid reference"));
+        }
+        raw_cells.push(RawCell { data, bit_len, references, exotic });
+    }
+
+    let mut cells: Vec<Option<ArcCell>> = vec![None; cell_count];
+    for (i, raw) in raw_cells.into_iter().enumerate().rev() {
+        let references = raw.references.iter()
+            .map(|r| cells[*r].clone().unwrap())
+            .collect();
+        cells[i] = Some(Arc::new(Cell::new(raw.data.to_vec(), raw.bit_len, references, raw.exotic)?));
+    }
+    roots.into_iter()
+        .map(|r| cells.get(r).cloned().flatten().ok_or(CellError::InvalidBoc("invalid root index")))
+        .collect()
+}
+
+/// Deserialize a bag of cells with exactly one root.
+pub fn deserialize_boc_single(boc: &[u8]) -> Result<ArcCell, CellError> {
+    let mut roots = deserialize_boc(boc)?;
+    if roots.len() != 1 {
+        return Err(CellError::InvalidBoc("expected a single root"));
+    }
+    Ok(roots.remove(0))
+}
+
+/// Serialize a bag of cells with a single root, deduplicating equal cells.
+pub fn serialize_boc(root: &Cell, has_crc32c: bool) -> Vec<u8> {
+    serialize_boc_roots(&[root], has_crc32c)
+}
+
+/// Serialize a bag of cells with several roots sharing their cells, such as a block proof and a state proof.
+pub fn serialize_boc_roots(roots: &[&Cell], has_crc32c: bool) -> Vec<u8> {
+    let mut indices = HashMap::new();
+    let mut order = Vec::new();
+    // post-order walk with an explicit stack, every cell is pushed after all of its references
+    let mut stack = Vec::new();
+    for &root in roots {
+        if indices.insert(root.repr_hash(), 0).is_none() {
+            stack.push((root, 0));
+        }
+    }
+    while let Some((cell, next)) = stack.pop() {
+        match cell.references().get(next) {
+            Some(r) => {
+                stack.push((cell, next + 1));
+                if indices.insert(r.repr_hash(), 0).is_none() {
+                    stack.push((r.as_ref(), 0));
+                }
+            }
+            None => order.push(cell),
+        }
+    }
+    // reversed post-order puts every cell before its references
+    order.reverse();
+    for (i, cell) in order.iter().enumerate() {
+        indices.insert(cell.repr_hash(), i);
+    }
+
+    let ref_size = byte_len(order.len());
+    let mut cells_data = Vec::new();
+    for cell in &order {
+        cells_data.extend_from_slice(&cell.descriptors(cell.level_mask()));
+        cells_data.extend_from_slice(&cell.data_with_tag());
+        for r in cell.references() {
+            write_uint(&mut cells_data, indices[&r.repr_hash()], ref_size);
+        }
+    }
+    let offset_size = byte_len(cells_data.len());
+
+    let mut boc = Vec::with_capacity(cells_data.len() + 32);
+    boc.extend_from_slice(&BOC_GENERIC.to_be_bytes());
+    boc.push(if has_crc32c { 0x40 } else { 0 } | ref_size as u8);
+    boc.push(offset_size as u8);
+    write_uint(&mut boc, order.len(), ref_size);
+    write_uint(&mut boc, roots.len(), ref_size);
+    write_uint(&mut boc, 0, ref_size);
+    write_uint(&mut boc, cells_data.len(), offset_size);
+    for root in roots {
+        write_uint(&mut boc, indices[&root.repr_hash()], ref_size);
+    }
+    boc.extend_from_slice(&cells_data);
+    if has_crc32c {
+        let crc = CRC32C.checksum(&boc);
+        boc.extend_from_slice(&crc.to_le_bytes());
+    }
+    boc
+}
+
+fn write_uint(out: &mut Vec<u8>, value: usize, size: usize) {
+    out.extend_from_slice(&(value as u64).to_be_bytes()[8 - size..]);
+}
+
+fn byte_len(value: usize) -> usize {
+    ((usize::BITS - value.leading_zeros()) as usize).div_ceil(8).max(1)
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use super::{ArcCell, Cell, CellError, CellSlice, MAX_BITS, MAX_REFS};
+
+/// Builder for ordinary and exotic [`Cell`]s.
+#[derive(Debug, Clone, Default)]
+pub struct CellBuilder {
+    data: Vec<u8>,
+    bit_len: usize,
+    references: Vec<ArcCell>,
+}
+
+impl CellBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    pub fn ref_count(&self) -> usize {
+        self.references.len()
+    }
+
+    pub fn store_bit(&mut self, bit: bool) -> Result<&mut Self, CellError> {
+        if self.bit_len >= MAX_BITS {
+            return Err(CellError::Overflow);
+        }
+        if self.bit_len.is_multiple_of(8) {
+            self.data.push(0);
+        }
+        if bit {
+            let last = self.data.len() - 1;
+            self.data[last] |= 0x80 >> (self.bit_len % 8);
+        }
+        self.bit_len += 1;
+        Ok(self)
+    }
+
+    /// Store the lower `bits` bits of `value`, most significant first.
+    pub fn store_uint(&mut self, bits: usize, value: u64) -> Result<&mut Self, CellError> {
+        if bits > 64 || (bits < 64 && value >> bits != 0) {
+            return Err(CellError::Overflow);
+        }
+        self.ensure_bits(bits)?;
+        for i in (0..bits).rev() {
+            self.store_bit((value >> i) & 1 != 0)?;
+        }
+        Ok(self)
+    }
+
+    /// Store the lower `bits` bits of a signed `value` in two's complement.
+    pub fn store_int(&mut self, bits: usize, value: i64) -> Result<&mut Self, CellError> {
+        if bits == 0 || bits > 64 {
+            return Err(CellError::Overflow);
+        }
+        let shift = 64 - bits;
+        if (value << shift) >> shift != value {
+            return Err(CellError::Overflow);
+        }
+        let mask = if bits == 64 { u64::MAX } else { (1u64 << bits) - 1 };
+        self.store_uint(bits, value as u64 & mask)
+    }
+
+    pub fn store_u8(&mut self, value: u8) -> Result<&mut Self, CellError> {
+        self.store_uint(8, value as u64)
+    }
+
+    pub fn store_u32(&mut self, value: u32) -> Result<&mut Self, CellError> {
+        self.store_uint(32, value as u64)
+    }
+
+    pub fn store_u64(&mut self, value: u64) -> Result<&mut Self, CellError> {
+        self.store_uint(64, value)
+    }
+
+    /// Store first `bits` bits of left-aligned `data`.
+    pub fn store_bits(&mut self, data: &[u8], bits: usize) -> Result<&mut Self, CellError> {
+        if data.len() * 8 < bits {
+            return Err(CellError::Underflow);
+        }
+        self.ensure_bits(bits)?;
+        for i in 0..bits {
+            self.store_bit((data[i / 8] >> (7 - i % 8)) & 1 != 0)?;
+        }
+        Ok(self)
+    }
+
+    pub fn store_u256(&mut self, value: &[u8; 32]) -> Result<&mut Self, CellError> {
+        self.store_bits(value, 256)
+    }
+
+    /// Store `VarUInteger 16`, which is used for Grams.
+    pub fn store_coins(&mut self, value: u128) -> Result<&mut Self, CellError> {
+        let len = 16 - value.leading_zeros() as usize / 8;
+        if len > 15 {
+            return Err(CellError::Overflow);
+        }
+        self.store_uint(4, len as u64)?;
+        self.store_bits(&value.to_be_bytes()[16 - len..], len * 8)
+    }
+
+    pub fn store_reference(&mut self, cell: ArcCell) -> Result<&mut Self, CellError> {
+        if self.references.len() >= MAX_REFS {
+            return Err(CellError::Overflow);
+        }
+        self.references.push(cell);
+        Ok(self)
+    }
+
+    pub fn store_maybe_reference(&mut self, cell: Option<ArcCell>) -> Result<&mut Self, CellError> {
+        match cell {
+            Some(cell) => self.store_bit(true)?.store_reference(cell),
+            None => self.store_bit(false),
+        }
+    }
+
+    /// Store remaining bits and references of `slice`.
+    pub fn store_slice(&mut self, slice: &CellSlice) -> Result<&mut Self, CellError> {
+        let (data, bits) = slice.peek_bits();
+        self.store_bits(&data, bits)?;
+        for r in slice.refs() {
+            self.store_reference(r.clone())?;
+        }
+        Ok(self)
+    }
+
+    /// Store bits and references of `cell` inline.
+    pub fn store_cell_data(&mut self, cell: &Cell) -> Result<&mut Self, CellError> {
+        self.store_slice(&cell.parser()?)
+    }
+
+    /// Store `Either X ^X`, keeping `cell` inline when it fits after `reserve_bits` more bits.
+    pub fn store_either(&mut self, cell: ArcCell, reserve_bits: usize) -> Result<&mut Self, CellError> {
+        let fits = self.bit_len + 1 + cell.bit_len() + reserve_bits <= MAX_BITS
+            && self.references.len() + cell.references().len() <= MAX_REFS
+            && !cell.is_exotic();
+        if fits {
+            self.store_bit(false)?.store_cell_data(&cell)
+        } else {
+            self.store_bit(true)?.store_reference(cell)
+        }
+    }
+
+    fn ensure_bits(&self, bits: usize) -> Result<(), CellError> {
+        if self.bit_len + bits > MAX_BITS {
+            return Err(CellError::Overflow);
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Result<ArcCell, CellError> {
+        Ok(Arc::new(Cell::new(self.data.clone(), self.bit_len, self.references.clone(), false)?))
+    }
+
+    pub fn build_exotic(&self) -> Result<ArcCell, CellError> {
+        Ok(Arc::new(Cell::new(self.data.clone(), self.bit_len, self.references.clone(), true)?))
+    }
+}
@@ -0,0 +1,405 @@
+//! Minimal cell and bag of cells (BOC) implementation.
+//!
+//! Liteserver responses carry blocks, states, transactions and proofs as serialized BOCs,
+//! this module is enough to parse them, walk the cells and compute representation hashes:
+//!
+//! ```
+//! use ton_liteapi::cell::{Cell, CellBuilder};
+//!
+//! let cell = CellBuilder::new().store_u32(0xdeadbeef)?.build()?;
+//! let boc = cell.to_boc();
+//! let parsed = Cell::from_boc(&boc)?;
+//! assert_eq!(parsed.repr_hash(), cell.repr_hash());
+//! assert_eq!(parsed.parser()?.load_u32()?, 0xdeadbeef);
+//! # Ok::<(), ton_liteapi::cell::CellError>(())
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+mod boc;
+mod builder;
+mod slice;
+
+pub use boc::*;
+pub use builder::*;
+pub use slice::*;
+
+pub type ArcCell = Arc<Cell>;
+
+/// Maximum number of data bits in a cell
+pub const MAX_BITS: usize = 1023;
+/// Maximum number of references in a cell
+pub const MAX_REFS: usize = 4;
+/// Maximum level of a cell, [`Cell::hash`] at this level is the representation hash
+pub const MAX_LEVEL: u8 = 3;
+/// Maximum depth of a cell, deeper trees are rejected by [`Cell::new`] and [`deserialize_boc`]
+pub const MAX_DEPTH: u16 = 1024;
+
+const PRUNED_NO_MASK_BITS: usize = 8 + 256 + 16;
+
+#[derive(Debug, Error)]
+pub enum CellError {
+    #[error("Invalid BOC: {0}")]
+    InvalidBoc(&'static str),
+    #[error("BOC checksum mismatch")]
+    ChecksumMismatch,
+    #[error("Invalid exotic cell: {0}")]
+    InvalidExotic(&'static str),
+    #[error("Cell overflow")]
+    Overflow,
+    #[error("Cell underflow")]
+    Underflow,
+    #[error("Cell depth exceeds {}", MAX_DEPTH)]
+    DepthOverflow,
+    #[error("Cell is pruned")]
+    Pruned,
+    #[error("Invalid address")]
+    InvalidAddress,
+    #[error("Invalid amount")]
+    InvalidAmount,
+    #[error("Unexpected tag {0:#x}")]
+    UnexpectedTag(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellType {
+    Ordinary,
+    PrunedBranch,
+    Library,
+    MerkleProof,
+    MerkleUpdate,
+}
+
+impl CellType {
+    fn from_exotic_tag(tag: u8) -> Result<Self, CellError> {
+        match tag {
+            1 => Ok(Self::PrunedBranch),
+            2 => Ok(Self::Library),
+            3 => Ok(Self::MerkleProof),
+            4 => Ok(Self::MerkleUpdate),
+            _ => Err(CellError::InvalidExotic("unknown type")),
+        }
+    }
+
+    pub fn is_exotic(&self) -> bool {
+        *self != Self::Ordinary
+    }
+
+    pub fn is_merkle(&self) -> bool {
+        matches!(self, Self::MerkleProof | Self::MerkleUpdate)
+    }
+}
+
+/// Set of levels at which a cell has distinct hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LevelMask(u8);
+
+impl LevelMask {
+    pub fn new(mask: u8) -> Self {
+        Self(mask & 7)
+    }
+
+    pub fn mask(&self) -> u8 {
+        self.0
+    }
+
+    pub fn level(&self) -> u8 {
+        8 - self.0.leading_zeros() as u8
+    }
+
+    pub fn hash_index(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn hash_count(&self) -> usize {
+        self.hash_index() + 1
+    }
+
+    pub fn apply(&self, level: u8) -> Self {
+        Self(self.0 & ((1u8 << level) - 1))
+    }
+
+    pub fn is_significant(&self, level: u8) -> bool {
+        level == 0 || (self.0 >> (level - 1)) & 1 != 0
+    }
+}
+
+/// Immutable TVM cell with precomputed hashes and depths.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cell {
+    data: Vec<u8>,
+    bit_len: usize,
+    references: Vec<ArcCell>,
+    cell_type: CellType,
+    level_mask: LevelMask,
+    hashes: Vec<[u8; 32]>,
+    depths: Vec<u16>,
+}
+
+impl Cell {
+    /// Create a cell from `bit_len` bits of `data` and `references`.
+    pub fn new(mut data: Vec<u8>, bit_len: usize, references: Vec<ArcCell>, exotic: bool) -> Result<Self, CellError> {
+        if bit_len > MAX_BITS || references.len() > MAX_REFS || data.len() * 8 < bit_len {
+            return Err(CellError::Overflow);
+        }
+        data.truncate(bit_len.div_ceil(8));
+        if !bit_len.is_multiple_of(8) {
+            let last = data.len() - 1;
+            data[last] &= 0xff << (8 - bit_len % 8);
+        }
+        let (cell_type, level_mask) = if exotic {
+            Self::exotic_level_mask(&data, bit_len, &references)?
+        } else {
+            let mask = references.iter().fold(0, |mask, r| mask | r.level_mask.mask());
+            (CellType::Ordinary, LevelMask::new(mask))
+        };
+        let mut cell = Self {
+            data,
+            bit_len,
+            references,
+            cell_type,
+            level_mask,
+            hashes: Vec::new(),
+            depths: Vec::new(),
+        };
+        cell.calculate_hashes()?;
+        Ok(cell)
+    }
+
+    fn exotic_level_mask(data: &[u8], bit_len: usize, references: &[ArcCell]) -> Result<(CellType, LevelMask), CellError> {
+        if bit_len < 8 {
+            return Err(CellError::InvalidExotic("no type"));
+        }
+        let cell_type = CellType::from_exotic_tag(data[0])?;
+        let mask = match cell_type {
+            CellType::PrunedBranch => {
+                if !references.is_empty() {
+                    return Err(CellError::InvalidExotic("bad pruned branch"));
+                }
+                // some config proofs contain level 1 pruned branches without the level mask byte
+                if bit_len == PRUNED_NO_MASK_BITS {
+                    return Ok((cell_type, LevelMask::new(1)));
+                }
+                if bit_len < 16 {
+                    return Err(CellError::InvalidExotic("bad pruned branch"));
+                }
+                let mask = LevelMask::new(data[1]);
+                if mask.mask() == 0 || mask.mask() != data[1] || bit_len != 16 + mask.hash_index() * (256 + 16) {
+                    return Err(CellError::InvalidExotic("bad pruned branch"));
+                }
+                mask
+            }
+            CellType::Library => {
+                if bit_len != 8 + 256 || !references.is_empty() {
+                    return Err(CellError::InvalidExotic("bad library cell"));
+                }
+                LevelMask::new(0)
+            }
+            CellType::MerkleProof => {
+                if bit_len != 8 + 256 + 16 || references.len() != 1 {
+                    return Err(CellError::InvalidExotic("bad merkle proof"));
+                }
+                LevelMask::new(references[0].level_mask.mask() >> 1)
+            }
+            CellType::MerkleUpdate => {
+                if bit_len != 8 + 2 * (256 + 16) || references.len() != 2 {
+                    return Err(CellError::InvalidExotic("bad merkle update"));
+                }
+                LevelMask::new((references[0].level_mask.mask() | references[1].level_mask.mask()) >> 1)
+            }
+            CellType::Ordinary => unreachable!(),
+        };
+        Ok((cell_type, mask))
+    }
+
+    fn calculate_hashes(&mut self) -> Result<(), CellError> {
+        let is_pruned = self.cell_type == CellType::PrunedBranch;
+        let total = self.level_mask.hash_count();
+        // pruned branch stores hashes of lower levels in its data, only the top one is computed
+        let offset = if is_pruned { total - 1 } else { 0 };
+        let mut hash_i = 0;
+        for level in 0..=self.level_mask.level() {
+            if !self.level_mask.is_significant(level) {
+                continue;
+            }
+            if hash_i < offset {
+                hash_i += 1;
+                continue;
+            }
+            let mask = if is_pruned { self.level_mask } else { self.level_mask.apply(level) };
+            let mut hasher = Sha256::new();
+            hasher.update(self.descriptors(mask));
+            if hash_i == offset {
+                hasher.update(self.data_with_tag());
+            } else {
+                hasher.update(self.hashes[hash_i - offset - 1]);
+            }
+            let child_level = if self.cell_type.is_merkle() { level + 1 } else { level };
+            let mut depth = 0;
+            for r in &self.references {
+                let child_depth = r.depth(child_level);
+                hasher.update(child_depth.to_be_bytes());
+                depth = depth.max(child_depth.saturating_add(1));
+            }
+            if depth > MAX_DEPTH {
+                return Err(CellError::DepthOverflow);
+            }
+            for r in &self.references {
+                hasher.update(r.hash(child_level));
+            }
+            self.hashes.push(hasher.finalize().into());
+            self.depths.push(depth);
+            hash_i += 1;
+        }
+        Ok(())
+    }
+
+    pub(super) fn descriptors(&self, mask: LevelMask) -> [u8; 2] {
+        let d1 = self.references.len() as u8 + if self.cell_type.is_exotic() { 8 } else { 0 } + mask.mask() * 32;
+        let d2 = (self.bit_len / 8 + self.bit_len.div_ceil(8)) as u8;
+        [d1, d2]
+    }
+
+    /// Data padded with the completion tag as in the standard cell representation.
+    pub(super) fn data_with_tag(&self) -> Vec<u8> {
+        let mut data = self.data.clone();
+        if !self.bit_len.is_multiple_of(8) {
+            let last = data.len() - 1;
+            data[last] |= 0x80 >> (self.bit_len % 8);
+        }
+        data
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    pub fn references(&self) -> &[ArcCell] {
+        &self.references
+    }
+
+    pub fn reference(&self, index: usize) -> Result<&ArcCell, CellError> {
+        self.references.get(index).ok_or(CellError::Underflow)
+    }
+
+    pub fn cell_type(&self) -> CellType {
+        self.cell_type
+    }
+
+    pub fn is_exotic(&self) -> bool {
+        self.cell_type.is_exotic()
+    }
+
+    pub fn level_mask(&self) -> LevelMask {
+        self.level_mask
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level_mask.level()
+    }
+
+    /// Hash of this cell at `level`, see [`Cell::repr_hash`] for the usual cell hash.
+    pub fn hash(&self, level: u8) -> [u8; 32] {
+        let hash_index = self.level_mask.apply(level.min(MAX_LEVEL)).hash_index();
+        if self.cell_type == CellType::PrunedBranch {
+            let this_index = self.level_mask.hash_index();
+            if hash_index != this_index {
+                let offset = self.pruned_header_len() + hash_index * 32;
+                return self.data[offset..offset + 32].try_into().unwrap();
+            }
+            return self.hashes[0];
+        }
+        self.hashes[hash_index]
+    }
+
+    pub fn depth(&self, level: u8) -> u16 {
+        let hash_index = self.level_mask.apply(level.min(MAX_LEVEL)).hash_index();
+        if self.cell_type == CellType::PrunedBranch {
+            let this_index = self.level_mask.hash_index();
+            if hash_index != this_index {
+                let offset = self.pruned_header_len() + this_index * 32 + hash_index * 2;
+                return u16::from_be_bytes([self.data[offset], self.data[offset + 1]]);
+            }
+            return self.depths[0];
+        }
+        self.depths[hash_index]
+    }
+
+    fn pruned_header_len(&self) -> usize {
+        if self.bit_len == PRUNED_NO_MASK_BITS { 1 } else { 2 }
+    }
+
+    /// Representation hash, which is used to identify cells.
+    pub fn repr_hash(&self) -> [u8; 32] {
+        self.hash(MAX_LEVEL)
+    }
+
+    pub fn repr_depth(&self) -> u16 {
+        self.depth(MAX_LEVEL)
+    }
+
+    /// Hash of the referenced library for library cells.
+    pub fn library_hash(&self) -> Option<[u8; 32]> {
+        if self.cell_type != CellType::Library {
+            return None;
+        }
+        Some(self.data[1..33].try_into().unwrap())
+    }
+
+    /// Hashes of all libraries referenced by this cell and its descendants.
+    pub fn find_libraries(&self) -> Vec<[u8; 32]> {
+        let mut visited = HashSet::new();
+        let mut libraries = Vec::new();
+        let mut stack = vec![self];
+        while let Some(cell) = stack.pop() {
+            if !visited.insert(cell.repr_hash()) {
+                continue;
+            }
+            if let Some(hash) = cell.library_hash() {
+                libraries.push(hash);
+            }
+            stack.extend(cell.references.iter().map(|r| r.as_ref()));
+        }
+        libraries
+    }
+
+    /// Deserialize a bag of cells with exactly one root.
+    pub fn from_boc(boc: &[u8]) -> Result<ArcCell, CellError> {
+        deserialize_boc_single(boc)
+    }
+
+    /// Serialize this cell and its descendants as a bag of cells with crc32c.
+    pub fn to_boc(&self) -> Vec<u8> {
+        serialize_boc(self, true)
+    }
+
+    /// Parser over the data and references of this cell, pruned branches can't be parsed.
+    pub fn parser(&self) -> Result<CellSlice<'_>, CellError> {
+        if self.cell_type == CellType::PrunedBranch {
+            return Err(CellError::Pruned);
+        }
+        Ok(CellSlice::new(self))
+    }
+}
+
+impl fmt::Debug for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cell{{{:?}, {}[0x{}]", self.cell_type, self.bit_len, hex::encode(&self.data))?;
+        for r in &self.references {
+            write!(f, ", {:?}", r)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests;
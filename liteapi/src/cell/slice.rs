@@ -0,0 +1,139 @@
+use super::{ArcCell, Cell, CellError};
+
+/// Reader over the bits and references of a [`Cell`].
+#[derive(Debug, Clone)]
+pub struct CellSlice<'a> {
+    cell: &'a Cell,
+    bit_pos: usize,
+    ref_pos: usize,
+}
+
+impl<'a> CellSlice<'a> {
+    pub(super) fn new(cell: &'a Cell) -> Self {
+        Self { cell, bit_pos: 0, ref_pos: 0 }
+    }
+
+    pub fn remaining_bits(&self) -> usize {
+        self.cell.bit_len() - self.bit_pos
+    }
+
+    pub fn remaining_refs(&self) -> usize {
+        self.cell.references().len() - self.ref_pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining_bits() == 0 && self.remaining_refs() == 0
+    }
+
+    fn ensure_bits(&self, bits: usize) -> Result<(), CellError> {
+        if bits > self.remaining_bits() {
+            return Err(CellError::Underflow);
+        }
+        Ok(())
+    }
+
+    fn bit_at(&self, pos: usize) -> bool {
+        (self.cell.data()[pos / 8] >> (7 - pos % 8)) & 1 != 0
+    }
+
+    pub fn load_bit(&mut self) -> Result<bool, CellError> {
+        self.ensure_bits(1)?;
+        let bit = self.bit_at(self.bit_pos);
+        self.bit_pos += 1;
+        Ok(bit)
+    }
+
+    /// Load an unsigned integer of up to 64 bits.
+    pub fn load_uint(&mut self, bits: usize) -> Result<u64, CellError> {
+        if bits > 64 {
+            return Err(CellError::Overflow);
+        }
+        self.ensure_bits(bits)?;
+        let mut value = 0u64;
+        for i in 0..bits {
+            value = (value << 1) | self.bit_at(self.bit_pos + i) as u64;
+        }
+        self.bit_pos += bits;
+        Ok(value)
+    }
+
+    /// Load a signed integer of up to 64 bits.
+    pub fn load_int(&mut self, bits: usize) -> Result<i64, CellError> {
+        let value = self.load_uint(bits)?;
+        if bits == 0 || bits == 64 {
+            return Ok(value as i64);
+        }
+        let shift = 64 - bits;
+        Ok(((value << shift) as i64) >> shift)
+    }
+
+    pub fn load_u8(&mut self) -> Result<u8, CellError> {
+        Ok(self.load_uint(8)? as u8)
+    }
+
+    pub fn load_u32(&mut self) -> Result<u32, CellError> {
+        Ok(self.load_uint(32)? as u32)
+    }
+
+    pub fn load_u64(&mut self) -> Result<u64, CellError> {
+        self.load_uint(64)
+    }
+
+    /// Load `bits` bits, left-aligned into bytes.
+    pub fn load_bits(&mut self, bits: usize) -> Result<Vec<u8>, CellError> {
+        self.ensure_bits(bits)?;
+        let mut data = vec![0u8; bits.div_ceil(8)];
+        for i in 0..bits {
+            if self.bit_at(self.bit_pos + i) {
+                data[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+        self.bit_pos += bits;
+        Ok(data)
+    }
+
+    pub fn load_u256(&mut self) -> Result<[u8; 32], CellError> {
+        Ok(self.load_bits(256)?.try_into().unwrap())
+    }
+
+    /// Load `VarUInteger 16`, which is used for Grams.
+    pub fn load_coins(&mut self) -> Result<u128, CellError> {
+        let len = self.load_uint(4)? as usize;
+        let mut value = 0u128;
+        for _ in 0..len {
+            value = (value << 8) | self.load_u8()? as u128;
+        }
+        Ok(value)
+    }
+
+    pub fn skip_bits(&mut self, bits: usize) -> Result<(), CellError> {
+        self.ensure_bits(bits)?;
+        self.bit_pos += bits;
+        Ok(())
+    }
+
+    pub fn load_ref(&mut self) -> Result<&'a ArcCell, CellError> {
+        let cell = self.cell.reference(self.ref_pos)?;
+        self.ref_pos += 1;
+        Ok(cell)
+    }
+
+    pub fn load_maybe_ref(&mut self) -> Result<Option<&'a ArcCell>, CellError> {
+        if self.load_bit()? {
+            Ok(Some(self.load_ref()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Remaining references without advancing the slice.
+    pub fn refs(&self) -> &'a [ArcCell] {
+        &self.cell.references()[self.ref_pos..]
+    }
+
+    /// Remaining bits without advancing the slice.
+    pub fn peek_bits(&self) -> (Vec<u8>, usize) {
+        let bits = self.remaining_bits();
+        (self.clone().load_bits(bits).unwrap(), bits)
+    }
+}
@@ -0,0 +1,76 @@
+use std::error::Error;
+
+use crate::cell::*;
+
+#[test]
+fn test_empty_cell_hash() -> Result<(), Box<dyn Error>> {
+    let cell = CellBuilder::new().build()?;
+    assert_eq!(hex::encode(cell.repr_hash()), "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7");
+    assert_eq!(cell.repr_depth(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_boc_roundtrip() -> Result<(), Box<dyn Error>> {
+    let raw = hex::decode("b5ee9c7241010201000c00020901020304c001010001b021e7d1ca")?;
+    let child = CellBuilder::new().store_uint(3, 5)?.build()?;
+    let cell = CellBuilder::new()
+        .store_u32(0x01020304)?
+        .store_bit(true)?
+        .store_reference(child.clone())?
+        .store_reference(child)?
+        .build()?;
+    assert_eq!(hex::encode(cell.repr_hash()), "c28068c567f1b2409a2e93aaceab1dacf9b9c0f4d725a0b4961be1e9225257f4");
+    assert_eq!(cell.repr_depth(), 1);
+    assert_eq!(cell.to_boc(), raw);
+
+    let parsed = Cell::from_boc(&raw)?;
+    assert_eq!(parsed, cell);
+    let mut parser = parsed.parser()?;
+    assert_eq!(parser.load_u32()?, 0x01020304);
+    assert!(parser.load_bit()?);
+    assert_eq!(parser.load_ref()?.parser()?.load_uint(3)?, 5);
+    assert!(matches!(parser.load_bit(), Err(CellError::Underflow)));
+
+    let mut corrupted = raw.clone();
+    corrupted[10] ^= 1;
+    assert!(matches!(Cell::from_boc(&corrupted), Err(CellError::ChecksumMismatch)));
+
+    // the second root is shared with the first one and stored once
+    let child = parsed.reference(0)?;
+    let roots = deserialize_boc(&serialize_boc_roots(&[&cell, child], true))?;
+    assert_eq!(roots.iter().map(|root| root.repr_hash()).collect::<Vec<_>>(), [cell.repr_hash(), child.repr_hash()]);
+    assert!(matches!(deserialize_boc_single(&serialize_boc_roots(&[child, &cell], false)), Err(CellError::InvalidBoc(_))));
+    Ok(())
+}
+
+/// Bag of cells holding a chain of `len` empty cells, each one referencing the next one.
+fn chain_boc(len: usize) -> Vec<u8> {
+    let mut cells = Vec::new();
+    for i in 0..len {
+        if i + 1 < len {
+            cells.extend_from_slice(&[1, 0]);
+            cells.extend_from_slice(&(i as u32 + 1).to_be_bytes());
+        } else {
+            cells.extend_from_slice(&[0, 0]);
+        }
+    }
+    let mut boc = vec![0xb5, 0xee, 0x9c, 0x72, 4, 4];
+    for value in [len, 1, 0, cells.len(), 0] {
+        boc.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+    boc.extend_from_slice(&cells);
+    boc
+}
+
+#[test]
+fn test_max_depth() -> Result<(), Box<dyn Error>> {
+    let root = Cell::from_boc(&chain_boc(MAX_DEPTH as usize + 1))?;
+    assert_eq!(root.repr_depth(), MAX_DEPTH);
+    assert_eq!(Cell::from_boc(&root.to_boc())?, root);
+    assert!(matches!(CellBuilder::new().store_reference(root)?.build(), Err(CellError::DepthOverflow)));
+
+    assert!(matches!(Cell::from_boc(&chain_boc(MAX_DEPTH as usize + 2)), Err(CellError::DepthOverflow)));
+    assert!(matches!(Cell::from_boc(&chain_boc(100_000)), Err(CellError::DepthOverflow)));
+    Ok(())
+}
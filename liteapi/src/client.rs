@@ -1,12 +1,100 @@
-use adnl::AdnlPeer;
-use tokio::net::ToSocketAddrs;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use adnl::crypto::{KeyPair, PublicKey, SecretKey};
+use adnl::{AdnlAddress, AdnlBuilder, AdnlError, AdnlPeer};
+use futures::future::{self, BoxFuture};
+use futures::{stream, Sink, Stream, TryStreamExt as _};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::{Mutex, MutexGuard};
+use tokio_util::bytes::Bytes;
+use tokio_util::codec::Framed;
+use tokio_util::sync::CancellationToken;
 use tokio_tower::multiplex;
-use tower::{Service as _, ServiceBuilder, ServiceExt as _};
+use tower::limit::ConcurrencyLimitLayer;
+use tower::{Layer, Service, ServiceBuilder, ServiceExt as _};
 
-use crate::{layers::{UnwrapErrorLayer, WrapMessagesLayer}, peer::LitePeer, tl::{common::*, request::*, response::*, utils::FromResponse}, types::LiteError};
+use crate::{cell::{deserialize_boc_single, Cell, CellError}, tlb::{block_global_id, state_global_id, CreatorStats, ExternalMessage, FeeConfig, FeeEstimate, FeeParams, ProvenAccount, Transaction}, types::{BlockFull, ConfigMode, LiteServer, Network, PeerInfo, RunMethodWithLibraries, SentMessage, WithRaw}};
+#[cfg(feature = "emulator")]
+use crate::{emulator::{GetMethodOutput, GetMethodParams, TvmEmulator}, tlb::{Account, AccountStatus}};
+use crate::{cache::{GetMethodCache, MasterchainInfoCache}, codec::{client_handshake, LiteCodec}, correlation, poll::PollPolicy, pool::RequestContext, layers::{KeepAlive, KeepAliveService, RequestLog, RequestLogLayer, Shutdown, ShutdownLayer, UnwrapErrorLayer, Verification, VerifyLayer, WrapMessagesLayer}, peer::{FrameLimits, LitePeer, LiteRng, RawAnswer, SharedRng, DEFAULT_MAX_FRAME_LEN, TRANSPORT_MAX_FRAME_LEN}, tl::{common::*, request::*, response::*, utils::FromResponse}, types::LiteError};
 
 type Result<T> = std::result::Result<T, LiteError>;
 
+/// Maximum number of libraries liteservers return for a single `getLibraries` query
+const MAX_LIBRARIES_PER_QUERY: usize = 16;
+/// Number of transactions requested at once when scanning account history
+const TRANSACTIONS_PAGE: u32 = 16;
+/// Transactions requested per `listBlockTransactionsExt` page by `get_block_full`
+const BLOCK_TRANSACTIONS_PAGE: u32 = 256;
+/// Default limit of queries in flight over a single connection, see [`LiteClientBuilder::with_max_in_flight`]
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 256;
+
+/// Methods returning the answer of a [`LiteFunction`] as is, after the receiver of the methods one per line:
+/// `name(args) -> Answer = function;`
+macro_rules! lite_methods {
+    (&mut $this:ident; $($(#[$meta:meta])* $name:ident($($arg:ident: $ty:ty),*) -> $response:ty = $function:expr;)*) => {$(
+        $(#[$meta])*
+        pub async fn $name(&mut $this, $($arg: $ty),*) -> Result<$response> {
+            $this.query($function).await
+        }
+    )*};
+    (&$this:ident; $($(#[$meta:meta])* $name:ident($($arg:ident: $ty:ty),*) -> $response:ty = $function:expr;)*) => {$(
+        $(#[$meta])*
+        pub async fn $name(&$this, $($arg: $ty),*) -> Result<$response> {
+            $this.query($function).await
+        }
+    )*};
+}
+
+pub(crate) use lite_methods;
+
+/// Pass-through methods shared by [`LiteClient`] and [`LiteHandle`](crate::handle::LiteHandle) for the receiver
+/// `&mut self` or `&self`.
+macro_rules! pass_through_methods {
+    ($($receiver:tt)*) => {
+        $crate::client::lite_methods! {
+            $($receiver)*;
+            get_masterchain_info() -> MasterchainInfo = GetMasterchainInfo;
+            get_masterchain_info_ext(mode: u32) -> MasterchainInfoExt = GetMasterchainInfoExt { mode };
+            get_version() -> Version = GetVersion;
+            get_account_state(id: BlockIdExt, account: AccountId) -> AccountState = GetAccountState { id, account };
+            get_shard_info(id: BlockIdExt, workchain: i32, shard: u64, exact: bool) -> ShardInfo = GetShardInfo { id, workchain, shard, exact };
+            get_all_shards_info(id: BlockIdExt) -> AllShardsInfo = GetAllShardsInfo { id };
+            get_one_transaction(id: BlockIdExt, account: AccountId, lt: u64) -> TransactionInfo = GetOneTransaction { id, account, lt };
+            get_transactions(count: u32, account: AccountId, lt: u64, hash: Int256) -> TransactionList = GetTransactions { count, account, lt, hash };
+            #[allow(clippy::too_many_arguments)]
+            lookup_block(
+                mode: (), id: BlockId, seqno: Option<()>, lt: Option<u64>, utime: Option<u32>, with_state_update: bool, with_value_flow: bool,
+                with_extra: bool, with_shard_hashes: bool, with_prev_blk_signatures: bool
+            ) -> BlockHeader = LookupBlock {
+                mode, id, seqno, lt, utime,
+                with_state_update: with_state_update.then_some(()),
+                with_value_flow: with_value_flow.then_some(()),
+                with_extra: with_extra.then_some(()),
+                with_shard_hashes: with_shard_hashes.then_some(()),
+                with_prev_blk_signatures: with_prev_blk_signatures.then_some(()),
+            };
+            list_block_transactions(id: BlockIdExt, count: u32, after: Option<TransactionId3>, reverse_order: bool, want_proof: bool) -> BlockTransactions = ListBlockTransactions {
+                id, mode: (), count, after, reverse_order: reverse_order.then_some(()), want_proof: want_proof.then_some(()),
+            };
+            get_block_proof(known_block: BlockIdExt, target_block: Option<BlockIdExt>, allow_weak_target: bool, base_block_from_request: bool) -> PartialBlockProof = GetBlockProof {
+                mode: (), known_block, target_block,
+                allow_weak_target: allow_weak_target.then_some(()),
+                base_block_from_request: base_block_from_request.then_some(()),
+            };
+            get_validator_stats(id: BlockIdExt, limit: u32, start_after: Option<Int256>, modified_after: Option<u32>) -> ValidatorStats = GetValidatorStats {
+                mode: (), id, limit, start_after, modified_after,
+            };
+        }
+    };
+}
+
+pub(crate) use pass_through_methods;
+
 pub struct LiteClient {
     inner: tower::util::BoxService<
         WrappedRequest,
@@ -14,17 +102,384 @@ pub struct LiteClient {
         LiteError,
     >,
     wait_seqno: Option<u32>,
+    read_your_writes: bool,
+    /// Last masterchain block seen after sending a message, with [`LiteClient::with_read_your_writes`]
+    written_seqno: Option<u32>,
+    correlation_id: Option<Arc<str>>,
+    cancellation: Option<CancellationToken>,
+    network: Option<Network>,
+    get_method_cache: Option<Arc<GetMethodCache>>,
+    masterchain_info_cache: Option<Arc<MasterchainInfoCache>>,
+    poll_policy: PollPolicy,
+    shutdown: Shutdown,
+    peer: Option<PeerInfo>,
+}
+
+/// Connection options of [`LiteClient`], see [`LiteClient::builder`].
+#[derive(Clone)]
+pub struct LiteClientBuilder {
+    max_frame_len: usize,
+    max_state_frame_len: usize,
+    local_key: Option<KeyPair>,
+    request_log: Option<RequestLog>,
+    verification: Verification,
+    network: Option<Network>,
+    get_method_cache: Option<Arc<GetMethodCache>>,
+    masterchain_info_cache: Option<Arc<MasterchainInfoCache>>,
+    rng: Option<SharedRng>,
+    keep_alive: Option<KeepAlive>,
+    max_in_flight: usize,
+}
+
+impl LiteClientBuilder {
+    /// Reject frames longer than `max_frame_len` bytes, see [`LitePeer::with_max_frame_len`].
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Accept answers to `getState` up to `max_state_frame_len` bytes, see [`LitePeer::with_max_state_frame_len`].
+    pub fn with_max_state_frame_len(mut self, max_state_frame_len: usize) -> Self {
+        self.max_state_frame_len = max_state_frame_len;
+        self
+    }
+
+    /// Authenticate with the ed25519 key derived from `seed` instead of a new random key for every connection,
+    /// e.g. for liteservers which only accept whitelisted clients.
+    pub fn with_local_key(mut self, seed: [u8; 32]) -> Self {
+        self.local_key = Some(KeyPair::from(&SecretKey::from_bytes(seed)));
+        self
+    }
+
+    /// ADNL id of the key set with [`LiteClientBuilder::with_local_key`], which is what liteservers whitelist.
+    pub fn local_adnl_id(&self) -> Option<[u8; 32]> {
+        self.local_key.map(|key| AdnlAddress::from(&key.public_key).to_bytes())
+    }
+
+    /// Log every request and collect per-method histograms into `log`, see [`RequestLogLayer`].
+    pub fn with_request_log(mut self, log: RequestLog) -> Self {
+        self.request_log = Some(log);
+        self
+    }
+
+    /// Check the proofs of all answers with [`Verification::Strict`], answers failing the check fail with
+    /// [`LiteError::HashMismatch`] or [`LiteError::InvalidProof`], and requests whose answers can't be
+    /// checked fail with [`LiteError::Unverifiable`].
+    pub fn with_verification(mut self, verification: Verification) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    /// Check every new connection with [`LiteClient::check_network`], connecting to a liteserver of another
+    /// network fails with [`LiteError::WrongNetwork`].
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Answer repeated get-methods at the same block from `cache`, see [`LiteClient::with_get_method_cache`].
+    pub fn with_get_method_cache(mut self, cache: Arc<GetMethodCache>) -> Self {
+        self.get_method_cache = Some(cache);
+        self
+    }
+
+    /// Find the last masterchain block in `cache`, see [`LiteClient::with_masterchain_info_cache`].
+    pub fn with_masterchain_info_cache(mut self, cache: Arc<MasterchainInfoCache>) -> Self {
+        self.masterchain_info_cache = Some(cache);
+        self
+    }
+
+    /// Ping the liteserver while the connection is open, so that a dead connection is noticed and closed
+    /// before a query fails on it, and optionally close idle connections, see [`KeepAlive`].
+    pub fn with_keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// Send at most `max_in_flight` queries over the connection at once, [`DEFAULT_MAX_IN_FLIGHT`] by default.
+    ///
+    /// Further queries, e.g. from clones of a [`LiteHandle`](crate::handle::LiteHandle) or a pool, wait until
+    /// earlier ones are answered instead of queueing their answers in memory.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    /// Take the local key, ADNL session parameters and query ids from `rng` instead of the OS generator,
+    /// so that the traffic of a test or a recorded fixture is reproducible byte for byte.
+    ///
+    /// Never use a seeded generator in production, the session keys would be predictable.
+    pub fn with_rng(mut self, rng: impl LiteRng + 'static) -> Self {
+        self.rng = Some(Arc::new(std::sync::Mutex::new(rng)));
+        self
+    }
+
+    pub async fn connect<A: ToSocketAddrs>(self, address: A, public_key: impl AsRef<[u8]>) -> Result<LiteClient> {
+        let (local_key, aes_params) = match &self.rng {
+            Some(rng) => {
+                let mut rng = rng.lock().unwrap();
+                let local_key = self.local_key.unwrap_or_else(|| KeyPair::generate(&mut &mut *rng));
+                (local_key, AdnlBuilder::with_random_aes_params(&mut &mut *rng))
+            }
+            None => {
+                let local_key = self.local_key.unwrap_or_else(|| KeyPair::generate(&mut rand::rngs::OsRng));
+                (local_key, AdnlBuilder::with_random_aes_params(&mut rand::rngs::OsRng))
+            }
+        };
+        let limits = self.frame_limits();
+        let (transport, peer) = connect_adnl(address, public_key.as_ref(), &local_key, aes_params, limits.clone()).await?;
+        self.build(transport, Some(peer), limits).checked().await
+    }
+
+    /// Connect to a liteserver, e.g. one listed in a global config, see `LiteServer::from_config`.
+    pub async fn connect_server(self, server: &LiteServer) -> Result<LiteClient> {
+        self.connect(server.address, server.public_key).await
+    }
+
+    /// Connect to a node over ADNL over UDP instead of the TCP liteserver protocol, see [`crate::udp`].
+    ///
+    /// Datagrams lost on the way aren't resent, so set a [`KeepAlive`] or a timeout on queries to notice them.
+    #[cfg(feature = "udp")]
+    pub async fn connect_udp<A: ToSocketAddrs>(self, address: A, public_key: impl AsRef<[u8]>) -> Result<LiteClient> {
+        let local_key = match &self.rng {
+            Some(rng) => self.local_key.unwrap_or_else(|| KeyPair::generate(&mut &mut *rng.lock().unwrap())),
+            None => self.local_key.unwrap_or_else(|| KeyPair::generate(&mut rand::rngs::OsRng)),
+        };
+        let (transport, peer) = crate::udp::AdnlUdp::connect(address, public_key.as_ref(), local_key).await?;
+        let limits = self.frame_limits();
+        self.build(transport, Some(peer), limits).checked().await
+    }
+
+    /// Connect to one of the liteservers of a global config, trying them in random order until one succeeds.
+    ///
+    /// Unless set with [`LiteClientBuilder::with_network`], the network is taken from the config, so servers of
+    /// another network are skipped. Fails with the error of the last server tried, or [`LiteError::NoServers`]
+    /// if the config lists none.
+    #[cfg(feature = "network-config")]
+    pub async fn connect_any(mut self, config: &ton_networkconfig::ConfigGlobal) -> Result<LiteClient> {
+        use rand::seq::SliceRandom as _;
+
+        if self.network.is_none() {
+            self.network = Network::from_config(config);
+        }
+        let mut servers = LiteServer::from_config(config);
+        servers.shuffle(&mut rand::thread_rng());
+        let mut last_error = LiteError::NoServers;
+        for server in &servers {
+            match self.clone().connect_server(server).await {
+                Ok(client) => return Ok(client),
+                Err(e) => {
+                    log::debug!("Connection to liteserver {} failed: {:?}", server, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Use an ADNL connection established by the caller, e.g. over another transport or with custom
+    /// handshake parameters, and check it with [`LiteClientBuilder::with_network`] if set.
+    pub async fn connect_with_adnl<T>(self, adnl: AdnlPeer<T>) -> Result<LiteClient>
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let limits = self.frame_limits();
+        self.build(adnl, None, limits).checked().await
+    }
+
+    fn frame_limits(&self) -> FrameLimits {
+        FrameLimits::new(self.max_frame_len, self.max_state_frame_len)
+    }
+
+    fn build<T, E>(self, transport: T, peer: Option<PeerInfo>, limits: FrameLimits) -> PendingClient
+    where
+        T: Sink<Bytes, Error = E> + Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+        E: Into<LiteError>,
+    {
+        let shutdown = Shutdown::with_transport();
+        let mut lite = LitePeer::with_shutdown(transport, &shutdown).with_frame_limits(limits);
+        if let Some(rng) = self.rng {
+            lite = lite.with_rng(rng);
+        }
+        let on_error = {
+            let shutdown = shutdown.clone();
+            let server = peer.as_ref().map(|peer| peer.server.to_string()).unwrap_or_default();
+            move |e: LiteError| {
+                log::warn!("Connection to liteserver {} failed: {:?}", server, e);
+                shutdown.close();
+            }
+        };
+        let messages = multiplex::Client::<_, LiteError, _>::with_error_handler(lite, on_error);
+        let messages = match self.keep_alive {
+            Some(keep_alive) => KeepAliveService::spawn(messages, keep_alive, shutdown.clone()).boxed(),
+            None => messages.boxed(),
+        };
+        let mut service = ServiceBuilder::new()
+            .layer(UnwrapErrorLayer)
+            .layer(WrapMessagesLayer)
+            .service(messages)
+            .boxed();
+        if self.verification == Verification::Strict {
+            service = VerifyLayer.layer(service).boxed();
+        }
+        if let Some(log) = self.request_log {
+            service = RequestLogLayer::new(log).layer(service).boxed();
+        }
+        let service = ConcurrencyLimitLayer::new(self.max_in_flight).layer(service);
+        let mut client = LiteClient::with_shutdown(service, shutdown);
+        client.peer = peer;
+        client.get_method_cache = self.get_method_cache;
+        client.masterchain_info_cache = self.masterchain_info_cache;
+        PendingClient { client, network: self.network }
+    }
+
+    /// Return a client immediately and connect on its first request, e.g. for tools which may never send one.
+    ///
+    /// A failed connection attempt is reported by the request which triggered it, the next request tries
+    /// again. A connection closed after being idle, see [`KeepAlive::max_idle`], or failed is opened again by
+    /// the next request. The connection is closed when the client is dropped.
+    pub fn connect_lazy<A>(self, address: A, public_key: impl AsRef<[u8]>) -> LiteClient
+    where
+        A: ToSocketAddrs + Clone + Send + Sync + 'static,
+    {
+        LiteClient::new(LazyConnection {
+            builder: self,
+            address,
+            public_key: public_key.as_ref().to_vec(),
+            state: LazyState::Idle,
+        })
+    }
+}
+
+/// Client built on a new connection, which is yet to be checked against the expected network.
+struct PendingClient {
+    client: LiteClient,
+    network: Option<Network>,
+}
+
+impl PendingClient {
+    async fn checked(mut self) -> Result<LiteClient> {
+        if let Some(network) = &self.network {
+            if let Err(e) = self.client.check_network(network).await {
+                let shutdown = self.client.shutdown.clone();
+                shutdown.abort();
+                shutdown.terminated().await;
+                return Err(e);
+            }
+        }
+        Ok(self.client)
+    }
+}
+
+impl Default for LiteClientBuilder {
+    fn default() -> Self {
+        Self {
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            max_state_frame_len: TRANSPORT_MAX_FRAME_LEN,
+            local_key: None,
+            request_log: None,
+            verification: Verification::None,
+            network: None,
+            get_method_cache: None,
+            masterchain_info_cache: None,
+            rng: None,
+            keep_alive: None,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+        }
+    }
 }
 
 impl LiteClient {
     pub async fn connect<A: ToSocketAddrs>(address: A, public_key: impl AsRef<[u8]>) -> Result<Self> {
-        let adnl = AdnlPeer::connect(public_key, address).await?;
-        let lite = LitePeer::new(adnl);
+        Self::builder().connect(address, public_key).await
+    }
+
+    /// Connect rejecting frames longer than `max_frame_len` bytes, see [`LitePeer::with_max_frame_len`].
+    pub async fn connect_with_max_frame_len<A: ToSocketAddrs>(address: A, public_key: impl AsRef<[u8]>, max_frame_len: usize) -> Result<Self> {
+        Self::builder().with_max_frame_len(max_frame_len).connect(address, public_key).await
+    }
+
+    pub fn builder() -> LiteClientBuilder {
+        LiteClientBuilder::default()
+    }
+
+    /// Connect to one of the liteservers of a global config parsed with `ConfigGlobal::parse`, see
+    /// [`LiteClientBuilder::connect_any`].
+    #[cfg(feature = "network-config")]
+    pub async fn connect_any(config: &ton_networkconfig::ConfigGlobal) -> Result<Self> {
+        Self::builder().connect_any(config).await
+    }
+
+    /// Use an ADNL connection established by the caller with the default options, see
+    /// [`LiteClientBuilder::connect_with_adnl`].
+    pub fn from_adnl<T>(adnl: AdnlPeer<T>) -> Self
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let builder = Self::builder();
+        let limits = builder.frame_limits();
+        builder.build(adnl, None, limits).client
+    }
+
+    /// Build a client on top of an arbitrary lite service, e.g. a [`crate::handle::LiteHandle`].
+    ///
+    /// Closing such a client only affects requests made through it, `service` itself is dropped.
+    pub fn new<S>(service: S) -> Self
+    where
+        S: Service<WrappedRequest, Response = Response, Error = LiteError> + Send + 'static,
+        S::Future: Send + 'static,
+    {
+        Self::with_shutdown(service, Shutdown::new())
+    }
+
+    fn with_shutdown<S>(service: S, shutdown: Shutdown) -> Self
+    where
+        S: Service<WrappedRequest, Response = Response, Error = LiteError> + Send + 'static,
+        S::Future: Send + 'static,
+    {
         let service = ServiceBuilder::new()
-            .layer(UnwrapErrorLayer)
-            .layer(WrapMessagesLayer)
-            .service(multiplex::Client::<_, Box<dyn std::error::Error + Send + Sync + 'static>, _>::new(lite));
-        Ok(Self { inner: service.boxed(), wait_seqno: None })
+            .layer(ShutdownLayer::new(shutdown.clone()))
+            .service(service);
+        Self {
+            inner: service.boxed(),
+            wait_seqno: None,
+            read_your_writes: false,
+            written_seqno: None,
+            correlation_id: None,
+            cancellation: None,
+            network: None,
+            get_method_cache: None,
+            masterchain_info_cache: None,
+            poll_policy: PollPolicy::default(),
+            shutdown,
+            peer: None,
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> (tower::util::BoxService<WrappedRequest, Response, LiteError>, Shutdown, Option<PeerInfo>) {
+        (self.inner, self.shutdown, self.peer)
+    }
+
+    /// Liteserver this client is connected to, `None` for clients built on top of another service.
+    pub fn peer_info(&self) -> Option<&PeerInfo> {
+        self.peer.as_ref()
+    }
+
+    /// Gracefully shut down the connection: stop accepting new requests, wait for answers to the
+    /// outstanding ones and close the ADNL session.
+    pub async fn shutdown(&self) {
+        self.shutdown.drain().await;
+        self.close().await;
+    }
+
+    /// Close the connection immediately. Outstanding requests fail with [`LiteError::Closed`].
+    pub async fn close(&self) {
+        self.shutdown.abort();
+        self.shutdown.terminated().await;
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shutdown.is_closed()
     }
 
     pub fn wait_masterchain_seqno(mut self, seqno: u32) -> Self {
@@ -32,45 +487,204 @@ impl LiteClient {
         self
     }
 
+    /// After every successful [`LiteClient::send_message`], remember the last masterchain block and make all
+    /// following queries wait for it with `waitMasterchainSeqno`. If the block can't be fetched, the message
+    /// is still reported as sent and the queries wait only for the blocks remembered before.
+    ///
+    /// Useful over a pool, where the next query may go to a liteserver lagging behind the one which accepted
+    /// the message and see the state before it.
+    pub fn with_read_your_writes(mut self, enabled: bool) -> Self {
+        self.read_your_writes = enabled;
+        self
+    }
+
+    /// Attach `id` to all following queries of this client, see [`crate::correlation`].
+    pub fn with_correlation_id(mut self, id: impl Into<Arc<str>>) -> Self {
+        self.correlation_id = Some(id.into());
+        self
+    }
+
+    /// Replace the correlation id of the following queries, `None` falls back to the one of the current
+    /// [`crate::correlation::scope`].
+    pub fn set_correlation_id(&mut self, id: Option<Arc<str>>) {
+        self.correlation_id = id;
+    }
+
+    /// Answer repeated `runSmcMethod` queries at the same block from `cache` instead of the liteserver.
+    pub fn with_get_method_cache(mut self, cache: Arc<GetMethodCache>) -> Self {
+        self.get_method_cache = Some(cache);
+        self
+    }
+
+    /// Take the last masterchain block of the "latest" methods, such as [`LiteClient::get_latest_account_state`],
+    /// from `cache` while it's fresh, see [`LiteClient::get_latest_masterchain_info`].
+    pub fn with_masterchain_info_cache(mut self, cache: Arc<MasterchainInfoCache>) -> Self {
+        self.masterchain_info_cache = Some(cache);
+        self
+    }
+
+    /// Pace of the account checks of [`LiteClient::find_transaction_by_message`], a check per second by default.
+    pub fn with_poll_policy(mut self, policy: PollPolicy) -> Self {
+        self.poll_policy = policy;
+        self
+    }
+
+    /// Fail pending and following queries of this client with [`LiteError::Cancelled`] once `token` is cancelled.
+    ///
+    /// Like dropping a query future, this only abandons the query: its answer is discarded when it arrives,
+    /// matched by the query id, and the connection stays usable for other clients sharing it.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Replace the cancellation token of the following queries, see [`LiteClient::with_cancellation`].
+    pub fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    async fn call(&mut self, request: Request) -> Result<Response> {
+        self.call_in(request, RequestContext::current()).await
+    }
+
+    async fn call_in(&mut self, request: Request, context: RequestContext) -> Result<Response> {
+        let Some(cancellation) = self.cancellation.clone() else {
+            return self.dispatch(request, context).await;
+        };
+        tokio::select! {
+            biased;
+            _ = cancellation.cancelled() => Err(LiteError::Cancelled),
+            response = self.dispatch(request, context) => response,
+        }
+    }
+
+    async fn dispatch(&mut self, request: Request, context: RequestContext) -> Result<Response> {
+        let method = request.name();
+        let server = self.peer.as_ref().map(|peer| peer.server.clone());
+        let wrapped_request = WrappedRequest {
+            wait_masterchain_seqno: self.wait_seqno.take().max(self.written_seqno).map(|seqno| WaitMasterchainSeqno { seqno, timeout_ms: 10000 }),
+            request,
+            context,
+        };
+        let inner = &mut self.inner;
+        let send = async move {
+            inner.ready().await?.call(wrapped_request).await.map_err(|e| e.in_query(method, server.as_ref()))
+        };
+        let Some(id) = self.correlation_id.clone().or_else(correlation::current) else {
+            return send.await;
+        };
+        correlation::scope(id.clone(), send).await
+            .map_err(|source| LiteError::Correlated { id, source: Box::new(source) })
+    }
+
     async fn send_request<T: FromResponse>(&mut self, request: Request) -> Result<T>
     {
-        let wrapped_request = WrappedRequest {
-            wait_masterchain_seqno: self.wait_seqno.take().map(|seqno| WaitMasterchainSeqno { seqno, timeout_ms: 10000 }),
-            request: request.into(),
+        T::from_response(self.call(request).await?)
+    }
+
+    /// Send any function of the scheme and return its typed answer, e.g. `client.query(GetTime).await?.now`.
+    pub async fn query<F: LiteFunction>(&mut self, function: F) -> Result<F::Response> {
+        self.send_request(function.into()).await
+    }
+
+    /// Send a pre-serialized liteserver function, e.g. one which isn't in the scheme yet, and return the serialized answer.
+    ///
+    /// `function_bytes` must start with the constructor id of the function. Server errors are still returned as
+    /// [`LiteError::ServerError`], wrapped in [`LiteError::Query`] like the errors of other queries, see
+    /// [`LiteError::kind`].
+    pub async fn lite_query_raw(&mut self, function_bytes: &[u8]) -> Result<Vec<u8>> {
+        match self.call(Request::Raw(function_bytes.to_vec())).await? {
+            Response::Raw(data) => Ok(data),
+            response => Ok(tl_proto::serialize(response)),
+        }
+    }
+
+    /// Send any request and return the parsed answer together with the answer exactly as the liteserver sent it,
+    /// e.g. `query_with_raw::<BlockHeader>(Request::GetBlockHeader(..))` to persist the header proof.
+    ///
+    /// Answers of services which don't receive them over a connection, e.g. a fake one built with
+    /// [`LiteClient::new`], have no such form and are serialized instead.
+    pub async fn query_with_raw<T: FromResponse>(&mut self, request: Request) -> Result<WithRaw<T>> {
+        let raw_answer = RawAnswer::default();
+        let mut context = RequestContext::current();
+        context.raw_answer = Some(raw_answer.clone());
+        let response = self.call_in(request, context).await?;
+        let raw = match raw_answer.lock().unwrap().take() {
+            Some(raw) => raw,
+            None => tl_proto::serialize(&response),
         };
-        T::from_response(self.inner.ready().await?.call(wrapped_request).await?)
+        Ok(WithRaw { value: T::from_response(response)?, raw })
     }
 
-    pub async fn get_masterchain_info(&mut self) -> Result<MasterchainInfo> {
-        let response: MasterchainInfo = self.send_request(Request::GetMasterchainInfo).await?;
-        Ok(response)
+    pass_through_methods!(&mut self);
+
+    /// Check that the liteserver belongs to `network`: its zerostate must match, the last masterchain block
+    /// must have the `global_id` of the network, and the init block, if any, must be the masterchain block
+    /// with its seqno.
+    ///
+    /// Catches e.g. a testnet config used with mainnet liteservers early. The init block is only checked if
+    /// the liteserver still has it. Afterwards [`LiteClient::network`] returns `network` with the `global_id`
+    /// of the liteserver, and downloaded blocks and states of another network fail with [`LiteError::WrongNetwork`].
+    pub async fn check_network(&mut self, network: &Network) -> Result<()> {
+        let info = self.get_masterchain_info().await?;
+        if info.init != network.zero_state {
+            return Err(LiteError::WrongNetwork);
+        }
+        let header = self.get_block_header(info.last, false, false, false, false, false).await?;
+        let global_id = block_global_id(&*Cell::from_boc(&header)?)?;
+        if network.global_id.is_some_and(|expected| expected != global_id) {
+            return Err(LiteError::WrongNetwork);
+        }
+        if let Some(init_block) = network.init_block.as_ref().filter(|id| id.seqno > 0) {
+            let id = BlockId { workchain: init_block.workchain, shard: init_block.shard, seqno: init_block.seqno };
+            match self.lookup_block((), id, Some(()), None, None, false, false, false, false, false).await {
+                Ok(header) if header.id != *init_block => return Err(LiteError::WrongNetwork),
+                Ok(_) => {}
+                Err(e) => match e.kind() {
+                    LiteError::ServerError(error) => log::debug!("Can't look up the init block {}: {:?}", init_block, error),
+                    _ => return Err(e),
+                },
+            }
+        }
+        self.network = Some(Network { global_id: Some(global_id), ..network.clone() });
+        Ok(())
     }
 
-    pub async fn get_masterchain_info_ext(&mut self, mode: u32) -> Result<MasterchainInfoExt> {
-        let request = Request::GetMasterchainInfoExt(GetMasterchainInfoExt { mode });
-        let response: MasterchainInfoExt = self.send_request(request).await?;
-        Ok(response)
+    /// Network of the liteserver once checked with [`LiteClient::check_network`], e.g. by
+    /// [`LiteClientBuilder::with_network`].
+    pub fn network(&self) -> Option<&Network> {
+        self.network.as_ref()
     }
 
-    pub async fn get_time(&mut self) -> Result<u32> {
-        let response: CurrentTime = self.send_request(Request::GetTime).await?;
-        Ok(response.now)
+    /// Fail with [`LiteError::WrongNetwork`] if `global_id`, read with `read`, isn't the one of the checked network.
+    fn check_global_id(&self, read: impl FnOnce() -> std::result::Result<i32, CellError>) -> Result<()> {
+        match self.network.as_ref().and_then(|network| network.global_id) {
+            Some(expected) if read()? != expected => Err(LiteError::WrongNetwork),
+            _ => Ok(()),
+        }
     }
 
-    pub async fn get_version(&mut self) -> Result<Version> {
-        let response: Version = self.send_request(Request::GetVersion).await?;
-        Ok(response)
+    pub async fn get_time(&mut self) -> Result<u32> {
+        let response = self.query(GetTime).await?;
+        Ok(response.now)
     }
 
+    /// Download the block `id`, checking its hashes with [`BlockData::verify`].
     pub async fn get_block(&mut self, id: BlockIdExt) -> Result<Vec<u8>> {
-        let request = Request::GetBlock(GetBlock { id });
-        let response: BlockData = self.send_request(request).await?;
+        let request = GetBlock { id: id.clone() };
+        let response = self.query(request).await?;
+        response.verify(&id)?;
+        self.check_global_id(|| block_global_id(&*response.root()?))?;
         Ok(response.data)
     }
 
+    /// Download the state after block `id`, checking it with [`BlockState::verify`] against the block header.
     pub async fn get_state(&mut self, id: BlockIdExt) -> Result<BlockState> {
-        let request = Request::GetState(GetState { id });
-        let response: BlockState = self.send_request(request).await?;
+        let header = self.get_block_header(id.clone(), true, false, false, false, false).await?;
+        let request = GetState { id: id.clone() };
+        let response = self.query(request).await?;
+        response.verify(&id, &header)?;
+        self.check_global_id(|| state_global_id(&*response.root()?))?;
         Ok(response)
     }
 
@@ -83,7 +697,7 @@ pub async fn get_block_header(
     with_shard_hashes: bool,
     with_prev_blk_signatures: bool,
 ) -> Result<Vec<u8>> {
-        let request = Request::GetBlockHeader(GetBlockHeader {
+        let request = GetBlockHeader {
             id,
             mode: (),
             with_state_update: if with_state_update { Some(()) } else { None },
@@ -91,91 +705,242 @@ pub async fn get_block_header(
             with_extra: if with_extra { Some(()) } else { None },
             with_shard_hashes: if with_shard_hashes { Some(()) } else { None },
             with_prev_blk_signatures: if with_prev_blk_signatures { Some(()) } else { None },
-        });
-        let response: BlockHeader = self.send_request(request).await?;
+        };
+        let response = self.query(request).await?;
+        self.check_global_id(|| block_global_id(&*Cell::from_boc(&response.header_proof)?))?;
         Ok(response.header_proof)
     }
 
+    /// Send an external message and return the status of the liteserver.
+    ///
+    /// With [`LiteClient::with_read_your_writes`] the last masterchain block is fetched after the message was
+    /// accepted. This is best effort: the message is already sent, so a failure to fetch the block is only
+    /// logged and the following queries don't wait for it.
     pub async fn send_message(&mut self, body: Vec<u8>) -> Result<u32> {
-        let request = Request::SendMessage(SendMessage { body });
-        let response: SendMsgStatus = self.send_request(request).await?;
+        let request = SendMessage { body };
+        let response = self.query(request).await?;
+        if self.read_your_writes {
+            match self.get_masterchain_info_ext(0).await {
+                Ok(info) => self.written_seqno = self.written_seqno.max(Some(info.last.seqno)),
+                Err(e) => log::warn!("Message sent, but the last masterchain block is unknown: {:?}", e),
+            }
+        }
         Ok(response.status)
     }
 
-    pub async fn get_account_state(&mut self, id: BlockIdExt, account: AccountId) -> Result<AccountState> {
-        let request = Request::GetAccountState(GetAccountState { id, account });
-        let response: AccountState = self.send_request(request).await?;
-        Ok(response)
+    /// Send an external message and return its hash and normalized hash along with the status.
+    ///
+    /// The message is parsed before sending, so a malformed `body` fails without reaching the liteserver.
+    pub async fn send_message_tracked(&mut self, body: Vec<u8>) -> Result<SentMessage> {
+        let cell = deserialize_boc_single(&body)?;
+        let hash = Int256(cell.repr_hash());
+        let normalized_hash = Int256(ExternalMessage::load(&cell)?.normalized_hash()?);
+        let status = self.send_message(body).await?;
+        Ok(SentMessage { status, hash, normalized_hash })
+    }
+
+    /// Account state with all its proofs checked against the masterchain block `id`.
+    ///
+    /// Unlike an empty answer of [`LiteClient::get_account_state`], [`ProvenAccount::Nonexistent`] means the
+    /// shard state is proven not to contain the account, i.e. it was never deployed or was deleted.
+    pub async fn get_account_state_verified(&mut self, id: BlockIdExt, account: AccountId) -> Result<ProvenAccount> {
+        let state = self.get_account_state(id.clone(), account.clone()).await?;
+        state.proven_account(&id, &account)
+    }
+
+    /// Account state as of `utime`, from the last block of the account's shard generated before it.
+    pub async fn get_account_state_at(&mut self, utime: u32, account: AccountId) -> Result<AccountState> {
+        // lookupBlock finds the shard containing this prefix, so the account's own prefix works for any split
+        let shard = u64::from_be_bytes(account.id.0[..8].try_into().unwrap()) | 1;
+        let id = BlockId { workchain: account.workchain, shard, seqno: 0 };
+        let header = self.lookup_block((), id, None, None, Some(utime), false, false, false, false, false).await?;
+        self.get_account_state(header.id, account).await
     }
 
     pub async fn run_smc_method(&mut self, mode: u32, id: BlockIdExt, account: AccountId, method_id: u64, params: Vec<u8>) -> Result<RunMethodResult> {
-        let request = Request::RunSmcMethod(RunSmcMethod { mode, id, account, method_id, params });
-        let response: RunMethodResult = self.send_request(request).await?;
+        if let Some(result) = self.get_method_cache.as_ref().and_then(|cache| cache.get(mode, &id, &account, method_id, &params)) {
+            return Ok(result);
+        }
+        let cache = self.get_method_cache.clone().map(|cache| (cache, id.clone(), account.clone(), params.clone()));
+        let request = RunSmcMethod { mode, id, account, method_id, params };
+        let response = self.query(request).await?;
+        if let Some((cache, id, account, params)) = cache {
+            cache.insert(mode, &id, &account, method_id, &params, &response);
+        }
         Ok(response)
     }
 
-    pub async fn get_shard_info(&mut self, id: BlockIdExt, workchain: i32, shard: u64, exact: bool) -> Result<ShardInfo> {
-        let request = Request::GetShardInfo(GetShardInfo { id, workchain, shard, exact });
-        let response: ShardInfo = self.send_request(request).await?;
-        Ok(response)
+    /// Run a get-method and fetch the libraries used by the account.
+    ///
+    /// Liteservers only load libraries published in the masterchain and can't be given any others, so a
+    /// get-method using a private library fails there no matter what the client has. Library cells referenced
+    /// by the account state (and, recursively, by the fetched libraries) are requested with `getLibraries`
+    /// and returned alongside the result, so that such a method can be run locally with them, e.g. with
+    /// `run_get_method_local` of the `emulator` feature. Libraries are fetched whatever the result, which costs an extra
+    /// `getAccountState` and nothing else for accounts without libraries.
+    pub async fn run_smc_method_with_libraries(&mut self, mode: u32, id: BlockIdExt, account: AccountId, method_id: u64, params: Vec<u8>) -> Result<RunMethodWithLibraries> {
+        let result = self.run_smc_method(mode, id.clone(), account.clone(), method_id, params).await?;
+        let state = self.get_account_state(id, account).await?;
+        let pending = match state.state_root()? {
+            Some(root) => root.find_libraries(),
+            None => Vec::new(),
+        };
+        let (libraries, missing) = self.resolve_libraries(pending).await?;
+        Ok(RunMethodWithLibraries { result, libraries, missing })
     }
 
-    pub async fn get_all_shards_info(&mut self, id: BlockIdExt) -> Result<AllShardsInfo> {
-        let request = Request::GetAllShardsInfo(GetAllShardsInfo { id });
-        let response: AllShardsInfo = self.send_request(request).await?;
-        Ok(response)
+    /// Fetch libraries with the given hashes and all libraries they reference, returning found and missing ones.
+    ///
+    /// Fails with `HashMismatch` if the liteserver answers with a library which wasn't requested or doesn't match its hash.
+    async fn resolve_libraries(&mut self, mut pending: Vec<[u8; 32]>) -> Result<(Vec<LibraryEntry>, Vec<Int256>)> {
+        let mut requested = HashSet::new();
+        let mut libraries = Vec::new();
+        let mut missing = Vec::new();
+        loop {
+            let batch: Vec<Int256> = std::iter::from_fn(|| pending.pop())
+                .filter(|hash| requested.insert(*hash))
+                .take(MAX_LIBRARIES_PER_QUERY)
+                .map(Int256)
+                .collect();
+            if batch.is_empty() {
+                break;
+            }
+            let fetched = self.get_libraries(batch.clone()).await?;
+            missing.extend(batch.iter().filter(|hash| !fetched.iter().any(|l| l.hash == **hash)).cloned());
+            for library in fetched {
+                // a library is identified by its hash, so the hash is all the proof it needs
+                let root = Cell::from_boc(&library.data)?;
+                if !batch.contains(&library.hash) || root.repr_hash() != library.hash.0 {
+                    return Err(LiteError::HashMismatch);
+                }
+                pending.extend(root.find_libraries());
+                libraries.push(library);
+            }
+        }
+        Ok((libraries, missing))
     }
 
-    pub async fn get_one_transaction(&mut self, id: BlockIdExt, account: AccountId, lt: u64) -> Result<TransactionInfo> {
-        let request = Request::GetOneTransaction(GetOneTransaction { id, account, lt });
-        let response: TransactionInfo = self.send_request(request).await?;
-        Ok(response)
+    /// Run a get-method locally with `emulator`, using account state, config and libraries from the liteserver.
+    ///
+    /// `id` must be a masterchain block, its state provides the configuration and the current time. Nothing
+    /// reaches the emulator unproven: the account state and the configuration are checked against `id` with
+    /// [`AccountState::proven_account`] and [`ConfigInfo::verify`], and the libraries against their hashes.
+    #[cfg(feature = "emulator")]
+    pub async fn run_get_method_local<E: TvmEmulator>(&mut self, emulator: &E, id: BlockIdExt, account: AccountId, method_id: u64, params: Vec<u8>) -> Result<GetMethodOutput> {
+        let state = self.get_account_state(id.clone(), account.clone()).await?;
+        let account = match state.proven_account(&id, &account)? {
+            ProvenAccount::Exists(shard_account) => Account::load(&shard_account.account)?,
+            _ => None,
+        };
+        let (address, balance, init) = match account {
+            Some(Account { address, balance, state: AccountStatus::Active(init), .. }) => (address, balance.grams.nanotons(), init),
+            _ => return Err(LiteError::InactiveAccount),
+        };
+        let (code, data) = match (init.code, init.data) {
+            (Some(code), Some(data)) => (code, data),
+            _ => return Err(LiteError::InactiveAccount),
+        };
+        let config = self.get_config_all(id.clone(), ConfigMode::default()).await?;
+        config.verify(&id)?;
+        let config = config.config()?;
+        let mut pending = code.find_libraries();
+        pending.extend(data.find_libraries());
+        let (libraries, _) = self.resolve_libraries(pending).await?;
+        emulator.run_get_method(GetMethodParams {
+            address,
+            code,
+            data,
+            balance,
+            libraries,
+            config: config.config,
+            unixtime: config.gen_utime,
+            method_id,
+            stack: params,
+        })
     }
 
-    pub async fn get_transactions(&mut self, count: u32, account: AccountId, lt: u64, hash: Int256) -> Result<TransactionList> {
-        let request = Request::GetTransactions(GetTransactions { count, account, lt, hash });
-        let response: TransactionList = self.send_request(request).await?;
-        Ok(response)
+    /// Shard block of `mc_block` containing `account`, which is `mc_block` itself for masterchain accounts.
+    pub async fn get_account_shard(&mut self, mc_block: BlockIdExt, account: &AccountId) -> Result<BlockIdExt> {
+        if account.workchain == -1 {
+            return Ok(mc_block);
+        }
+        let shards = self.get_all_shards_info(mc_block).await?.shard_hashes()?;
+        let shard = shards.find(account.workchain, &account.id.0).ok_or(LiteError::ShardNotFound)?;
+        Ok(shard.block_id())
     }
 
-pub async fn lookup_block(
-    &mut self,
-    mode: (),
-    id: BlockId,
-    seqno: Option<()>,
-    lt: Option<u64>,
-    utime: Option<u32>,
-    with_state_update: bool,
-    with_value_flow: bool,
-    with_extra: bool,
-    with_shard_hashes: bool,
-    with_prev_blk_signatures: bool,
-) -> Result<BlockHeader> {
-        let request = Request::LookupBlock(LookupBlock {
-            mode,
-            id,
-            seqno,
-            lt,
-            utime,
-        with_state_update: if with_state_update { Some(()) } else { None },
-        with_value_flow: if with_value_flow { Some(()) } else { None },
-        with_extra: if with_extra { Some(()) } else { None },
-        with_shard_hashes: if with_shard_hashes { Some(()) } else { None },
-        with_prev_blk_signatures: if with_prev_blk_signatures { Some(()) } else { None },
-    });
-        let response: BlockHeader = self.send_request(request).await?;
-        Ok(response)
+    /// Find the transaction of `account` which processed the inbound message with the given hash.
+    ///
+    /// `message_hash` is either the message hash or its normalized hash, see [`LiteClient::send_message_tracked`].
+    /// Transactions newer than `after_lt` are checked, and the account is polled until `timeout`
+    /// expires, so this can be called right after sending the message. Returns `None` on timeout.
+    ///
+    /// The account is polled as set with [`LiteClient::with_poll_policy`], failed checks are retried
+    /// within the timeout if the policy retries errors.
+    pub async fn find_transaction_by_message(&mut self, account: AccountId, message_hash: Int256, after_lt: u64, timeout: Duration) -> Result<Option<TransactionInfo>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let policy = self.poll_policy;
+        let mut scanned_lt = after_lt;
+        let mut errors = 0;
+        loop {
+            match self.scan_transactions(&account, &message_hash, &mut scanned_lt).await {
+                Ok(Some(found)) => return Ok(Some(found)),
+                Ok(None) => errors = 0,
+                Err(e) => {
+                    let retry = policy.retry_delay(errors + 1);
+                    if retry.is_none_or(|delay| tokio::time::Instant::now() + delay > deadline) {
+                        return Err(e);
+                    }
+                    policy.backoff(&mut errors, e).await?;
+                    continue;
+                }
+            }
+            let delay = policy.delay();
+            if tokio::time::Instant::now() + delay > deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(delay).await;
+        }
     }
 
-pub async fn list_block_transactions(
+    /// The transaction of `account` newer than `scanned_lt` which processed the message, `scanned_lt` is
+    /// advanced to the last transaction of the account if there's none.
+    async fn scan_transactions(&mut self, account: &AccountId, message_hash: &Int256, scanned_lt: &mut u64) -> Result<Option<TransactionInfo>> {
+        let state = self.get_latest_account_state(account.clone()).await?;
+        let Some(last) = state.shard_account(&account.id.0)?.filter(|last| last.last_trans_lt > *scanned_lt) else {
+            return Ok(None);
+        };
+        let (mut lt, mut hash) = (last.last_trans_lt, Int256(last.last_trans_hash));
+        while lt > *scanned_lt {
+            let list = self.get_transactions(TRANSACTIONS_PAGE, account.clone(), lt, hash.clone()).await?;
+            let roots = list.transaction_roots()?;
+            if roots.is_empty() {
+                break;
+            }
+            for (id, root) in list.ids.into_iter().zip(roots) {
+                let transaction = Transaction::load(&root)?;
+                if transaction.lt <= *scanned_lt {
+                    break;
+                }
+                if transaction.in_msg_matches(&message_hash.0) {
+                    return Ok(Some(self.get_one_transaction(id, account.clone(), transaction.lt).await?));
+                }
+                (lt, hash) = (transaction.prev_trans_lt, Int256(transaction.prev_trans_hash));
+            }
+        }
+        *scanned_lt = last.last_trans_lt;
+        Ok(None)
+    }
+
+pub async fn list_block_transactions_ext(
     &mut self,
     id: BlockIdExt,
     count: u32,
     after: Option<TransactionId3>,
     reverse_order: bool,
     want_proof: bool
-) -> Result<BlockTransactions> {
-    let request = Request::ListBlockTransactions(ListBlockTransactions {
+) -> Result<BlockTransactionsExt> {
+    let request = Request::ListBlockTransactionsExt(ListBlockTransactions {
         id,
         mode: (),
         count,
@@ -183,59 +948,68 @@ pub async fn list_block_transactions(
         reverse_order: if reverse_order { Some(()) } else { None },
         want_proof: if want_proof { Some(()) } else { None },
     });
-        let response: BlockTransactions = self.send_request(request).await?;
+        let response: BlockTransactionsExt = self.send_request(request).await?;
         Ok(response)
     }
 
-pub async fn get_block_proof(
-    &mut self,
-    known_block: BlockIdExt,
-    target_block: Option<BlockIdExt>,
-    allow_weak_target: bool,
-    base_block_from_request: bool
-) -> Result<PartialBlockProof> {
-        let request = Request::GetBlockProof(GetBlockProof {
+    /// Header, all transactions and, for masterchain blocks, the shard list of block `id`.
+    pub async fn get_block_full(&mut self, id: BlockIdExt) -> Result<BlockFull> {
+        let request = GetBlockHeader {
+            id: id.clone(),
             mode: (),
-            known_block,
-            target_block,
-        allow_weak_target: if allow_weak_target { Some(()) } else { None },
-        base_block_from_request: if base_block_from_request { Some(()) } else { None },
-        });
-        let response: PartialBlockProof = self.send_request(request).await?;
-        Ok(response)
+            with_state_update: None,
+            with_value_flow: None,
+            with_extra: None,
+            with_shard_hashes: None,
+            with_prev_blk_signatures: None,
+        };
+        let header = self.query(request).await?;
+        let mut transactions = Vec::new();
+        let mut after = None;
+        loop {
+            let page = self.list_block_transactions_ext(id.clone(), BLOCK_TRANSACTIONS_PAGE, after, false, false).await?;
+            let roots = page.transaction_roots()?;
+            if roots.is_empty() {
+                break;
+            }
+            for root in roots {
+                transactions.push(Transaction::load(&root)?);
+            }
+            if !page.incomplete {
+                break;
+            }
+            let last = transactions.last().unwrap();
+            after = Some(TransactionId3 { account: Int256(last.account_addr), lt: last.lt });
+        }
+        let shards = if id.is_masterchain() {
+            Some(self.get_all_shards_info(id).await?.shard_hashes()?)
+        } else {
+            None
+        };
+        Ok(BlockFull { header, transactions, shards })
     }
 
 pub async fn get_config_all(
     &mut self,
     id: BlockIdExt,
-    with_state_root: bool,
-    with_libraries: bool,
-    with_state_extra_root: bool,
-    with_shard_hashes: bool,
-    with_validator_set: bool,
-    with_special_smc: bool,
-    with_accounts_root: bool,
-    with_prev_blocks: bool,
-    with_workchain_info: bool,
-    with_capabilities: bool,
-    extract_from_key_block: bool,
+    mode: ConfigMode,
 ) -> Result<ConfigInfo> {
-        let request = Request::GetConfigAll(GetConfigAll {
+        let request = GetConfigAll {
             mode: (),
             id,
-            with_state_root: if with_state_root { Some(()) } else { None },
-            with_libraries: if with_libraries { Some(()) } else { None },
-            with_state_extra_root: if with_state_extra_root { Some(()) } else { None },
-            with_shard_hashes: if with_shard_hashes { Some(()) } else { None },
-            with_validator_set: if with_validator_set { Some(()) } else { None },
-            with_special_smc: if with_special_smc { Some(()) } else { None },
-            with_accounts_root: if with_accounts_root { Some(()) } else { None },
-            with_prev_blocks: if with_prev_blocks { Some(()) } else { None },
-            with_workchain_info: if with_workchain_info { Some(()) } else { None },
-            with_capabilities: if with_capabilities { Some(()) } else { None },
-            extract_from_key_block: if extract_from_key_block { Some(()) } else { None },
-        });
-        let response: ConfigInfo = self.send_request(request).await?;
+            with_state_root: if mode.with_state_root { Some(()) } else { None },
+            with_libraries: if mode.with_libraries { Some(()) } else { None },
+            with_state_extra_root: if mode.with_state_extra_root { Some(()) } else { None },
+            with_shard_hashes: if mode.with_shard_hashes { Some(()) } else { None },
+            with_validator_set: if mode.with_validator_set { Some(()) } else { None },
+            with_special_smc: if mode.with_special_smc { Some(()) } else { None },
+            with_accounts_root: if mode.with_accounts_root { Some(()) } else { None },
+            with_prev_blocks: if mode.with_prev_blocks { Some(()) } else { None },
+            with_workchain_info: if mode.with_workchain_info { Some(()) } else { None },
+            with_capabilities: if mode.with_capabilities { Some(()) } else { None },
+            extract_from_key_block: if mode.extract_from_key_block { Some(()) } else { None },
+        };
+        let response = self.query(request).await?;
         Ok(response)
     }
 
@@ -243,47 +1017,469 @@ pub async fn get_config_params(
     &mut self,
     id: BlockIdExt,
     param_list: Vec<i32>,
-    with_state_root: bool,
-    with_libraries: bool,
-    with_state_extra_root: bool,
-    with_shard_hashes: bool,
-    with_validator_set: bool,
-    with_special_smc: bool,
-    with_accounts_root: bool,
-    with_prev_blocks: bool,
-    with_workchain_info: bool,
-    with_capabilities: bool,
-    extract_from_key_block: bool,
+    mode: ConfigMode,
 ) -> Result<ConfigInfo> {
-        let request = Request::GetConfigParams(GetConfigParams {
+        let request = GetConfigParams {
             mode: (),
             id,
             param_list,
-            with_state_root: if with_state_root { Some(()) } else { None },
-            with_libraries: if with_libraries { Some(()) } else { None },
-            with_state_extra_root: if with_state_extra_root { Some(()) } else { None },
-            with_shard_hashes: if with_shard_hashes { Some(()) } else { None },
-            with_validator_set: if with_validator_set { Some(()) } else { None },
-            with_special_smc: if with_special_smc { Some(()) } else { None },
-            with_accounts_root: if with_accounts_root { Some(()) } else { None },
-            with_prev_blocks: if with_prev_blocks { Some(()) } else { None },
-            with_workchain_info: if with_workchain_info { Some(()) } else { None },
-            with_capabilities: if with_capabilities { Some(()) } else { None },
-            extract_from_key_block: if extract_from_key_block { Some(()) } else { None },
-        });
-        let response: ConfigInfo = self.send_request(request).await?;
+            with_state_root: if mode.with_state_root { Some(()) } else { None },
+            with_libraries: if mode.with_libraries { Some(()) } else { None },
+            with_state_extra_root: if mode.with_state_extra_root { Some(()) } else { None },
+            with_shard_hashes: if mode.with_shard_hashes { Some(()) } else { None },
+            with_validator_set: if mode.with_validator_set { Some(()) } else { None },
+            with_special_smc: if mode.with_special_smc { Some(()) } else { None },
+            with_accounts_root: if mode.with_accounts_root { Some(()) } else { None },
+            with_prev_blocks: if mode.with_prev_blocks { Some(()) } else { None },
+            with_workchain_info: if mode.with_workchain_info { Some(()) } else { None },
+            with_capabilities: if mode.with_capabilities { Some(()) } else { None },
+            extract_from_key_block: if mode.extract_from_key_block { Some(()) } else { None },
+        };
+        let response = self.query(request).await?;
         Ok(response)
     }
 
-    pub async fn get_validator_stats(&mut self, id: BlockIdExt, limit: u32, start_after: Option<Int256>, modified_after: Option<u32>) -> Result<ValidatorStats> {
-        let request = Request::GetValidatorStats(GetValidatorStats { mode: (), id, limit, start_after, modified_after });
-        let response: ValidatorStats = self.send_request(request).await?;
-        Ok(response)
+    /// Fee prices of the configuration at the masterchain block `id`, see [`FeeConfig::PARAMS`].
+    pub async fn get_fee_config(&mut self, id: BlockIdExt) -> Result<FeeConfig> {
+        let params = FeeConfig::PARAMS.iter().map(|p| *p as i32).collect();
+        let config = self.get_config_params(id, params, ConfigMode::default()).await?.config()?;
+        FeeConfig::from_config(&config)?.ok_or(LiteError::MissingConfig)
+    }
+
+    /// Estimate the fees of processing an inbound external message with the latest configuration.
+    pub async fn estimate_fees(&mut self, params: &FeeParams) -> Result<FeeEstimate> {
+        let last = self.get_latest_masterchain_info().await?.last;
+        Ok(self.get_fee_config(last).await?.estimate(params))
+    }
+
+    /// Stream over all entries of the validator stats dictionary at block `id`, requested `page_size` entries at a time.
+    pub fn validator_stats_stream(&mut self, id: BlockIdExt, page_size: u32) -> impl Stream<Item = Result<CreatorStats>> + '_ {
+        // `None` once the last page was received
+        let start: Option<Option<Int256>> = Some(None);
+        stream::try_unfold((self, start), move |(client, start_after)| {
+            let id = id.clone();
+            async move {
+                let Some(start_after) = start_after else {
+                    return Result::Ok(None);
+                };
+                let stats = client.get_validator_stats(id, page_size, start_after.clone(), None).await?;
+                let mut entries = stats.creator_stats()?;
+                entries.retain(|entry| start_after.as_ref().is_none_or(|after| entry.public_key > after.0));
+                entries.truncate(stats.count as usize);
+                let next = match entries.last() {
+                    Some(last) if !stats.complete => Some(Some(Int256(last.public_key))),
+                    _ => None,
+                };
+                Ok(Some((entries, (client, next))))
+            }
+        })
+        .map_ok(|entries| stream::iter(entries.into_iter().map(Ok)))
+        .try_flatten()
     }
 
     pub async fn get_libraries(&mut self, library_list: Vec<Int256>) -> Result<Vec<LibraryEntry>> {
-        let request = Request::GetLibraries(GetLibraries { library_list });
-        let response: LibraryResult = self.send_request(request).await?;
+        let request = GetLibraries { library_list };
+        let response = self.query(request).await?;
         Ok(response.result)
     }
-}
\ No newline at end of file
+
+    /// `getMasterchainInfo`, answered from the [`MasterchainInfoCache`] while it's fresh and always from the
+    /// liteserver without one. Used by the "latest" methods below.
+    pub async fn get_latest_masterchain_info(&mut self) -> Result<MasterchainInfo> {
+        if let Some(info) = self.masterchain_info_cache.as_ref().and_then(|cache| cache.get()) {
+            return Ok(info);
+        }
+        let info = self.get_masterchain_info().await?;
+        if let Some(cache) = &self.masterchain_info_cache {
+            cache.insert(&info);
+        }
+        Ok(info)
+    }
+
+    /// Account state at the last masterchain block.
+    pub async fn get_latest_account_state(&mut self, account: AccountId) -> Result<AccountState> {
+        let last = self.get_latest_masterchain_info().await?.last;
+        self.get_account_state(last, account).await
+    }
+
+    /// Run a get-method at the last masterchain block.
+    pub async fn run_smc_method_latest(&mut self, mode: u32, account: AccountId, method_id: u64, params: Vec<u8>) -> Result<RunMethodResult> {
+        let last = self.get_latest_masterchain_info().await?.last;
+        self.run_smc_method(mode, last, account, method_id, params).await
+    }
+
+    /// All config params at the last masterchain block, see [`ConfigInfo::config`].
+    pub async fn get_latest_config(&mut self) -> Result<ConfigInfo> {
+        let last = self.get_latest_masterchain_info().await?.last;
+        self.get_config_all(last, ConfigMode::default()).await
+    }
+
+    /// Fix the last masterchain block, so that all queries of the returned [`Snapshot`] see the same state.
+    pub async fn snapshot(&mut self) -> Result<Snapshot<'_>> {
+        let id = self.get_latest_masterchain_info().await?.last;
+        Ok(self.snapshot_at(id))
+    }
+
+    /// Same as [`LiteClient::snapshot`] for the masterchain block `id`.
+    pub fn snapshot_at(&mut self, id: BlockIdExt) -> Snapshot<'_> {
+        Snapshot { client: self, id }
+    }
+}
+
+/// Queries against a fixed masterchain block, see [`LiteClient::snapshot`].
+pub struct Snapshot<'a> {
+    client: &'a mut LiteClient,
+    id: BlockIdExt,
+}
+
+impl Snapshot<'_> {
+    /// Masterchain block all queries run against.
+    pub fn id(&self) -> &BlockIdExt {
+        &self.id
+    }
+
+    pub async fn account_state(&mut self, account: AccountId) -> Result<AccountState> {
+        self.client.get_account_state(self.id.clone(), account).await
+    }
+
+    pub async fn run_method(&mut self, mode: u32, account: AccountId, method_id: u64, params: Vec<u8>) -> Result<RunMethodResult> {
+        self.client.run_smc_method(mode, self.id.clone(), account, method_id, params).await
+    }
+
+    /// All config params, see [`ConfigInfo::config`].
+    pub async fn config(&mut self) -> Result<ConfigInfo> {
+        self.client.get_config_all(self.id.clone(), ConfigMode::default()).await
+    }
+
+    pub async fn config_params(&mut self, param_list: Vec<i32>) -> Result<ConfigInfo> {
+        self.client.get_config_params(self.id.clone(), param_list, ConfigMode::default()).await
+    }
+
+    pub async fn all_shards_info(&mut self) -> Result<AllShardsInfo> {
+        self.client.get_all_shards_info(self.id.clone()).await
+    }
+}
+
+/// Cloneable handle to a single [`LiteClient`] which can be shared between threads and tasks.
+///
+/// Requests are serialized through an async mutex, so every caller gets exclusive access
+/// to the connection for the duration of its query.
+#[derive(Clone)]
+pub struct SharedLiteClient {
+    inner: Arc<Mutex<LiteClient>>,
+}
+
+impl SharedLiteClient {
+    pub fn new(client: LiteClient) -> Self {
+        Self { inner: Arc::new(Mutex::new(client)) }
+    }
+
+    pub async fn connect<A: ToSocketAddrs>(address: A, public_key: impl AsRef<[u8]>) -> Result<Self> {
+        Ok(Self::new(LiteClient::connect(address, public_key).await?))
+    }
+
+    /// Wait for exclusive access to the underlying client.
+    pub async fn lock(&self) -> MutexGuard<'_, LiteClient> {
+        self.inner.lock().await
+    }
+
+    /// See [`LiteClient::shutdown`].
+    pub async fn shutdown(&self) {
+        self.inner.lock().await.shutdown().await
+    }
+
+    /// See [`LiteClient::close`].
+    pub async fn close(&self) {
+        self.inner.lock().await.close().await
+    }
+}
+
+impl From<LiteClient> for SharedLiteClient {
+    fn from(client: LiteClient) -> Self {
+        Self::new(client)
+    }
+}
+
+enum LazyState {
+    Idle,
+    Connecting(BoxFuture<'static, Result<LiteClient>>),
+    Connected(Box<LiteClient>),
+}
+
+/// Service connecting on first use, see [`LiteClientBuilder::connect_lazy`].
+struct LazyConnection<A> {
+    builder: LiteClientBuilder,
+    address: A,
+    public_key: Vec<u8>,
+    state: LazyState,
+}
+
+impl<A> Service<WrappedRequest> for LazyConnection<A> where A: ToSocketAddrs + Clone + Send + Sync + 'static {
+    type Response = Response;
+    type Error = LiteError;
+    type Future = BoxFuture<'static, Result<Response>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        loop {
+            match &mut self.state {
+                LazyState::Idle => {
+                    let connect = self.builder.clone().connect(self.address.clone(), self.public_key.clone());
+                    self.state = LazyState::Connecting(Box::pin(connect));
+                }
+                LazyState::Connecting(connect) => match ready!(connect.as_mut().poll(cx)) {
+                    Ok(client) => self.state = LazyState::Connected(Box::new(client)),
+                    Err(e) => {
+                        self.state = LazyState::Idle;
+                        return Poll::Ready(Err(e));
+                    }
+                },
+                // closed by the keep-alive after being idle or by the server, reconnect
+                LazyState::Connected(client) if client.is_closed() => self.state = LazyState::Idle,
+                LazyState::Connected(client) => {
+                    let result = ready!(client.inner.poll_ready(cx));
+                    if result.is_err() {
+                        self.state = LazyState::Idle;
+                    }
+                    return Poll::Ready(result);
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, request: WrappedRequest) -> Self::Future {
+        match &mut self.state {
+            LazyState::Connected(client) => {
+                let method = request.request.name();
+                let server = client.peer.as_ref().map(|peer| peer.server.clone());
+                let fut = client.inner.call(request);
+                Box::pin(async move { fut.await.map_err(|e| e.in_query(method, server.as_ref())) })
+            }
+            // `poll_ready` wasn't called
+            _ => Box::pin(future::err(LiteError::Closed)),
+        }
+    }
+}
+
+/// Open a TCP connection and perform the ADNL handshake using `local_key` and the session parameters of `builder`.
+async fn connect_adnl<A: ToSocketAddrs>(address: A, public_key: &[u8], local_key: &KeyPair, builder: AdnlBuilder, limits: FrameLimits) -> Result<(Framed<TcpStream, LiteCodec>, PeerInfo)> {
+    let transport = TcpStream::connect(address).await.map_err(AdnlError::IoError)?;
+    let address = transport.peer_addr().map_err(AdnlError::IoError)?;
+    let server_key: [u8; 32] = public_key.try_into().map_err(|_| AdnlError::InvalidPublicKey)?;
+    let remote_public = PublicKey::from_bytes(server_key).ok_or(AdnlError::InvalidPublicKey)?;
+    let handshake = builder.perform_ecdh(local_key, &remote_public);
+    let transport = client_handshake(transport, &handshake, limits).await?;
+    let peer = PeerInfo {
+        server: LiteServer::new(address, server_key),
+        server_adnl_id: AdnlAddress::from(&remote_public).to_bytes(),
+        local_public_key: local_key.public_key.to_bytes(),
+        local_adnl_id: AdnlAddress::from(&local_key.public_key).to_bytes(),
+    };
+    Ok((transport, peer))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tower::make::Shared;
+
+    use super::*;
+    #[cfg(feature = "emulator")]
+    use crate::{cell::{serialize_boc_roots, ArcCell, CellBuilder}, tlb::{build_hashmap, MsgAddressInt, StateInit}};
+    use crate::handle::LiteHandle;
+    use crate::layers::{UnwrapMessagesLayer, WrapErrorLayer};
+    use crate::server::serve;
+
+    /// Answer `getTime` with `answer` on a free local port, returning its address and the public key of the server.
+    async fn start_server(answer: Response) -> (SocketAddr, [u8; 32]) {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let keypair = KeyPair::from(&SecretKey::from_bytes([7; 32]));
+        let public_key = keypair.public_key.to_bytes();
+        let service = ServiceBuilder::new()
+            .buffer(16)
+            .layer(UnwrapMessagesLayer)
+            .layer(WrapErrorLayer)
+            .service_fn(move |request: WrappedRequest| {
+                let answer = answer.clone();
+                async move {
+                    match request.request {
+                        Request::GetTime => Ok(answer),
+                        _ => Err(LiteError::UnexpectedMessage),
+                    }
+                }
+            });
+        tokio::spawn(async move { serve(&address, keypair, Shared::new(service)).await.unwrap() });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        (address, public_key)
+    }
+
+    #[tokio::test]
+    async fn test_lazy_reconnect() {
+        let (address, public_key) = start_server(Response::CurrentTime(CurrentTime { now: 1234 })).await;
+        let ms = Duration::from_millis;
+        let keep_alive = KeepAlive { ping_interval: ms(50), pong_timeout: ms(500), max_idle: Some(ms(200)) };
+        let mut client = LiteClient::builder().with_keep_alive(keep_alive).connect_lazy(address, public_key);
+        assert_eq!(client.get_time().await.unwrap(), 1234);
+        // the idle connection is closed, the next query opens a new one
+        tokio::time::sleep(ms(500)).await;
+        assert_eq!(client.get_time().await.unwrap(), 1234);
+        assert!(!client.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_query_with_raw() {
+        // a valid answer with trailing bytes, which re-serializing it would lose
+        let mut raw = tl_proto::serialize(Response::CurrentTime(CurrentTime { now: 1234 }));
+        raw.extend([1, 2, 3, 4]);
+        let (address, public_key) = start_server(Response::Raw(raw.clone())).await;
+        let mut client = LiteClient::connect(address, public_key).await.unwrap();
+        let answer = client.query_with_raw::<CurrentTime>(Request::GetTime).await.unwrap();
+        assert_eq!(answer.value.now, 1234);
+        assert_eq!(answer.raw, raw);
+
+        // also through a handle, whose connection is owned by another task
+        let handle = LiteHandle::new(client);
+        let answer = handle.client().query_with_raw::<CurrentTime>(Request::GetTime).await.unwrap();
+        assert_eq!(answer.raw, raw);
+    }
+
+    /// Masterchain block `id` whose state holds `account` at the address `0x33..` and a configuration
+    /// with param 0, as `getAccountState` and `getConfigAll` answers.
+    #[cfg(feature = "emulator")]
+    fn proven_state(account: &ArcCell) -> std::result::Result<(AccountState, ConfigInfo), CellError> {
+        let empty = CellBuilder::new().build()?;
+        // depth_balance$_ split_depth:(#<= 30) balance:CurrencyCollection, then account_descr$_
+        let mut value = CellBuilder::new();
+        value.store_uint(5, 0)?.store_coins(0)?.store_bit(false)?;
+        value.store_reference(account.clone())?.store_u256(&[0; 32])?.store_u64(1000)?;
+        let accounts_root = build_hashmap(&[([0x33; 32].to_vec(), value.build()?)], 256)?;
+        let mut accounts = CellBuilder::new();
+        accounts.store_maybe_reference(accounts_root)?.store_uint(5, 0)?.store_coins(0)?.store_bit(false)?;
+        let param = CellBuilder::new().store_u256(&[0x55; 32])?.build()?;
+        let config = build_hashmap(&[(0u32.to_be_bytes().to_vec(), CellBuilder::new().store_reference(param)?.build()?)], 32)?;
+        let mut extra = CellBuilder::new();
+        extra.store_uint(16, 0xcc26)?.store_bit(false)?.store_u256(&[0x55; 32])?.store_maybe_reference(config)?;
+        let mut state = CellBuilder::new();
+        state.store_u32(0x9023afe2)?.store_u32(0)?.store_uint(8, 0)?.store_int(32, -1)?.store_u64(1 << 63)?;
+        state.store_u32(1)?.store_u32(0)?.store_u32(1700000000)?.store_u64(1000)?.store_u32(0)?.store_bit(false)?;
+        state.store_reference(empty.clone())?.store_reference(accounts.build()?)?.store_reference(empty.clone())?;
+        state.store_maybe_reference(Some(extra.build()?))?;
+        let state = state.build()?;
+
+        let mut state_proof = CellBuilder::new();
+        state_proof.store_u8(3)?.store_u256(&state.hash(0))?.store_uint(16, state.depth(0) as u64)?.store_reference(state.clone())?;
+        let state_proof = state_proof.build_exotic()?;
+        let mut update = CellBuilder::new();
+        update.store_u8(4)?.store_u256(&empty.hash(0))?.store_u256(&state.hash(0))?.store_uint(16, 0)?.store_uint(16, state.depth(0) as u64)?;
+        update.store_reference(empty.clone())?.store_reference(state)?;
+        let mut block = CellBuilder::new();
+        block.store_u32(0x11ef55aa)?.store_u32(0)?;
+        for cell in [empty.clone(), empty.clone(), update.build_exotic()?, empty] {
+            block.store_reference(cell)?;
+        }
+        let block = block.build()?;
+
+        let id = BlockIdExt { workchain: -1, shard: 1 << 63, seqno: 1, root_hash: Int256(block.hash(0)), file_hash: Int256([0; 32]) };
+        let account = AccountState {
+            id: id.clone(),
+            shardblk: id.clone(),
+            shard_proof: Vec::new(),
+            proof: serialize_boc_roots(&[&block, &state_proof], true),
+            state: account.to_boc(),
+        };
+        let config = ConfigInfo {
+            mode: (),
+            id,
+            state_proof: block.to_boc(),
+            config_proof: state_proof.to_boc(),
+            with_state_root: None,
+            with_libraries: None,
+            with_state_extra_root: None,
+            with_shard_hashes: None,
+            with_validator_set: None,
+            with_special_smc: None,
+            with_accounts_root: None,
+            with_prev_blocks: None,
+            with_workchain_info: None,
+            with_capabilities: None,
+            extract_from_key_block: None,
+        };
+        Ok((account, config))
+    }
+
+    #[cfg(feature = "emulator")]
+    #[tokio::test]
+    async fn test_run_get_method_local() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        use std::sync::Mutex;
+
+
+        /// Emulator which records what it was given.
+        #[derive(Default)]
+        struct Recorder(Mutex<Option<GetMethodParams>>);
+
+        impl TvmEmulator for Recorder {
+            fn run_get_method(&self, params: GetMethodParams) -> std::result::Result<GetMethodOutput, LiteError> {
+                *self.0.lock().unwrap() = Some(params);
+                Ok(GetMethodOutput { exit_code: 0, gas_used: 100, stack: Vec::new() })
+            }
+        }
+
+        fn client(account: AccountState, config: ConfigInfo, library: LibraryEntry) -> LiteClient {
+            LiteClient::new(tower::service_fn(move |request: WrappedRequest| {
+                let answer = match request.request {
+                    Request::GetAccountState(_) => Ok(Response::AccountState(account.clone())),
+                    Request::GetConfigAll(_) => Ok(Response::ConfigInfo(config.clone())),
+                    Request::GetLibraries(_) => Ok(Response::LibraryResult(LibraryResult { result: vec![library.clone()] })),
+                    _ => Err(LiteError::UnexpectedMessage),
+                };
+                future::ready(answer)
+            }))
+        }
+
+        // code is a library cell, which the emulator receives with the library itself
+        let library = CellBuilder::new().store_u32(0xc0de)?.build()?;
+        let code = CellBuilder::new().store_u8(2)?.store_u256(&library.repr_hash())?.build_exotic()?;
+        let data = CellBuilder::new().store_u32(7)?.build()?;
+        let address = MsgAddressInt::std(-1, [0x33; 32]);
+        let account_with = |data: ArcCell| -> std::result::Result<ArcCell, CellError> {
+            // account$1 addr storage_used:(0 cells, 0 bits) storage_extra_none$000 last_paid due_payment:nothing
+            let mut account = CellBuilder::new();
+            account.store_bit(true)?;
+            address.store(&mut account)?;
+            account.store_uint(3, 0)?.store_uint(3, 0)?.store_uint(3, 0)?.store_u32(0)?.store_bit(false)?;
+            // last_trans_lt balance:(grams, no extra currencies) account_active$1
+            account.store_u64(1000)?.store_coins(5_000_000_000)?.store_bit(false)?.store_bit(true)?;
+            StateInit::new(code.clone(), data).store(&mut account)?;
+            account.build()
+        };
+        let account = account_with(data.clone())?;
+        let (state, config) = proven_state(&account)?;
+        let id = state.id.clone();
+        let account_id = AccountId { workchain: -1, id: Int256([0x33; 32]) };
+        let entry = LibraryEntry { hash: Int256(library.repr_hash()), data: library.to_boc() };
+
+        let emulator = Recorder::default();
+        let mut lite = client(state.clone(), config.clone(), entry.clone());
+        let output = lite.run_get_method_local(&emulator, id.clone(), account_id.clone(), 85143, vec![1, 2, 3]).await?;
+        assert_eq!(output.gas_used, 100);
+        let params = emulator.0.lock().unwrap().take().unwrap();
+        assert_eq!(params.address, address);
+        assert_eq!((params.code.repr_hash(), params.data.repr_hash()), (code.repr_hash(), data.repr_hash()));
+        assert_eq!(params.balance, 5_000_000_000);
+        assert_eq!(params.libraries, vec![entry.clone()]);
+        assert_eq!(params.config.parser()?.load_ref()?.parser()?.load_u256()?, [0x55; 32]);
+        assert_eq!(params.unixtime, 1700000000);
+        assert_eq!((params.method_id, params.stack), (85143, vec![1, 2, 3]));
+
+        // a state not matching the proof, a config of another block and a forged library never reach the emulator
+        let forged = AccountState { state: account_with(CellBuilder::new().store_u32(8)?.build()?)?.to_boc(), ..state.clone() };
+        let other_block = ConfigInfo { state_proof: proven_state(&data)?.1.state_proof, ..config.clone() };
+        let fake_library = LibraryEntry { data: data.to_boc(), ..entry.clone() };
+        for (state, config, entry) in [(forged, config.clone(), entry.clone()), (state.clone(), other_block, entry), (state, config, fake_library)] {
+            let result = client(state, config, entry).run_get_method_local(&emulator, id.clone(), account_id.clone(), 85143, Vec::new()).await;
+            assert!(matches!(result, Err(LiteError::HashMismatch)), "{result:?}");
+            assert!(emulator.0.lock().unwrap().is_none());
+        }
+        Ok(())
+    }
+}
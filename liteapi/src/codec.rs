@@ -0,0 +1,277 @@
+//! Framing of the TCP liteserver protocol.
+//!
+//! After the handshake both sides exchange frames `length:u32 nonce:bytes32 payload checksum:bytes32`, where
+//! the checksum is the sha256 of the nonce and the payload, encrypted with the AES-CTR stream of their direction.
+//! [`LiteCodec`] decrypts and checks frames in the read buffer and hands out the payload as a slice of it, so a
+//! received block isn't copied before it's parsed. A frame longer than its [`FrameLimits`] is rejected by its
+//! length prefix, or by the query id at its start for answers to `getState`, before the rest is buffered.
+
+use adnl::crypto::KeyPair;
+use adnl::{AdnlAesParams, AdnlError, AdnlHandshake};
+use aes::cipher::{KeyIvInit, StreamCipher};
+use futures::StreamExt as _;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+use tokio_util::bytes::{Buf as _, BufMut as _, Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+use crate::peer::{FrameLimits, ANSWER_PREFIX_LEN, TRANSPORT_MAX_FRAME_LEN};
+use crate::types::LiteError;
+
+type Result<T> = std::result::Result<T, LiteError>;
+
+pub(crate) type Aes = ctr::Ctr128BE<aes::Aes256>;
+
+const NONCE_LEN: usize = 32;
+const CHECKSUM_LEN: usize = 32;
+
+/// Encryption and framing of an ADNL TCP connection, see the [module docs](self).
+pub struct LiteCodec {
+    rx: Aes,
+    tx: Aes,
+    limits: FrameLimits,
+    /// Length of the frame being received, once its length prefix is decrypted
+    frame_len: Option<usize>,
+    /// Whether the frame being received is known to be within the limits
+    accepted: bool,
+    /// Bytes of the frame being received which are decrypted already
+    decrypted: usize,
+}
+
+impl LiteCodec {
+    /// Codec of the side which sent the handshake with `params`.
+    pub fn client(params: &AdnlAesParams) -> Self {
+        Self {
+            rx: Aes::new(params.rx_key().into(), params.rx_nonce().into()),
+            tx: Aes::new(params.tx_key().into(), params.tx_nonce().into()),
+            limits: FrameLimits::default(),
+            frame_len: None,
+            accepted: false,
+            decrypted: 0,
+        }
+    }
+
+    /// Codec of the side which received the handshake with `params`.
+    pub fn server(params: &AdnlAesParams) -> Self {
+        Self {
+            rx: Aes::new(params.tx_key().into(), params.tx_nonce().into()),
+            tx: Aes::new(params.rx_key().into(), params.rx_nonce().into()),
+            limits: FrameLimits::default(),
+            frame_len: None,
+            accepted: false,
+            decrypted: 0,
+        }
+    }
+
+    /// Reject received frames beyond `limits`, shared with the [`LitePeer`](crate::peer::LitePeer) above the codec.
+    pub fn with_frame_limits(mut self, limits: FrameLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+impl Decoder for LiteCodec {
+    type Item = Bytes;
+    type Error = LiteError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>> {
+        let frame_len = match self.frame_len {
+            Some(frame_len) => frame_len,
+            None => {
+                if src.len() < 4 {
+                    return Ok(None);
+                }
+                self.rx.apply_keystream(&mut src[..4]);
+                let frame_len = src.get_u32_le() as usize;
+                if frame_len < NONCE_LEN + CHECKSUM_LEN {
+                    return Err(AdnlError::TooShortPacket.into());
+                }
+                let payload_len = frame_len - NONCE_LEN - CHECKSUM_LEN;
+                let accepted = self.limits.accepts(payload_len);
+                if payload_len > TRANSPORT_MAX_FRAME_LEN || accepted == Some(false) {
+                    return Err(LiteError::FrameTooLong(Some(payload_len)));
+                }
+                self.frame_len = Some(frame_len);
+                self.accepted = accepted.is_some();
+                frame_len
+            }
+        };
+        if !self.accepted {
+            // only answers to `getState` may be longer, look at the query id before buffering the rest
+            let prefix_len = NONCE_LEN + ANSWER_PREFIX_LEN;
+            if src.len() < prefix_len {
+                src.reserve(prefix_len - src.len());
+                return Ok(None);
+            }
+            self.rx.apply_keystream(&mut src[..prefix_len]);
+            self.decrypted = prefix_len;
+            if !self.limits.accepts_answer(&src[NONCE_LEN..prefix_len]) {
+                return Err(LiteError::FrameTooLong(Some(frame_len - NONCE_LEN - CHECKSUM_LEN)));
+            }
+            self.accepted = true;
+        }
+        if src.len() < frame_len {
+            src.reserve(frame_len - src.len());
+            return Ok(None);
+        }
+        self.frame_len = None;
+        self.accepted = false;
+
+        let mut frame = src.split_to(frame_len);
+        self.rx.apply_keystream(&mut frame[std::mem::take(&mut self.decrypted)..]);
+        let checksum = frame.split_off(frame_len - CHECKSUM_LEN);
+        if Sha256::digest(&frame).as_slice() != &checksum[..] {
+            return Err(LiteError::IntegrityError);
+        }
+        Ok(Some(frame.split_off(NONCE_LEN).freeze()))
+    }
+}
+
+impl Encoder<Bytes> for LiteCodec {
+    type Error = LiteError;
+
+    fn encode(&mut self, payload: Bytes, dst: &mut BytesMut) -> Result<()> {
+        if payload.len() > TRANSPORT_MAX_FRAME_LEN {
+            return Err(LiteError::FrameTooLong(Some(payload.len())));
+        }
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let checksum = Sha256::new().chain_update(nonce).chain_update(&payload).finalize();
+        let start = dst.len();
+        dst.reserve(4 + NONCE_LEN + payload.len() + CHECKSUM_LEN);
+        dst.put_u32_le((NONCE_LEN + payload.len() + CHECKSUM_LEN) as u32);
+        dst.put_slice(&nonce);
+        dst.put_slice(&payload);
+        dst.put_slice(&checksum);
+        self.tx.apply_keystream(&mut dst[start..]);
+        Ok(())
+    }
+}
+
+/// Send `handshake` over `transport` and wait for the empty frame by which the server proves it knows the keys.
+pub async fn client_handshake<T>(mut transport: T, handshake: &AdnlHandshake, limits: FrameLimits) -> Result<Framed<T, LiteCodec>>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    transport.write_all(&handshake.to_bytes()).await?;
+    let mut framed = Framed::new(transport, LiteCodec::client(handshake.aes_params()).with_frame_limits(limits));
+    framed.next().await.ok_or(AdnlError::EndOfStream)??;
+    Ok(framed)
+}
+
+/// Receive a handshake addressed to `key` over `transport` and confirm it with an empty frame.
+pub async fn server_handshake<T>(mut transport: T, key: &KeyPair, limits: FrameLimits) -> Result<Framed<T, LiteCodec>>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut packet = [0; 256];
+    transport.read_exact(&mut packet).await?;
+    let handshake = AdnlHandshake::decrypt_from_raw(&packet, |_| Some(*key))?;
+    let mut framed = Framed::new(transport, LiteCodec::server(handshake.aes_params()).with_frame_limits(limits));
+    futures::SinkExt::send(&mut framed, Bytes::new()).await?;
+    Ok(framed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use adnl::AdnlCodec;
+
+    use super::*;
+    use crate::tl::common::Int256;
+
+    fn params() -> AdnlAesParams {
+        AdnlAesParams::from(std::array::from_fn::<u8, 160, _>(|i| i as u8))
+    }
+
+    fn encode(codec: &mut impl Encoder<Bytes, Error = impl std::fmt::Debug>, payloads: &[&[u8]]) -> BytesMut {
+        let mut buffer = BytesMut::new();
+        for payload in payloads {
+            codec.encode(Bytes::copy_from_slice(payload), &mut buffer).unwrap();
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_codec() {
+        let payloads: [&[u8]; 3] = [b"", b"hello", &[7; 1000]];
+        let mut client = LiteCodec::client(&params());
+        let mut server = LiteCodec::server(&params());
+
+        // frames arriving a few bytes at a time
+        let encoded = encode(&mut client, &payloads);
+        let mut received = BytesMut::new();
+        let mut decoded = Vec::new();
+        for chunk in encoded.chunks(7) {
+            received.extend_from_slice(chunk);
+            while let Some(payload) = server.decode(&mut received).unwrap() {
+                decoded.push(payload);
+            }
+        }
+        assert_eq!(decoded, payloads);
+        assert!(received.is_empty());
+
+        // the other direction, frames are compatible with the codec of adnl-rs
+        let mut encoded = encode(&mut server, &payloads);
+        let mut adnl = AdnlCodec::client(&params());
+        for payload in payloads {
+            assert_eq!(adnl.decode(&mut encoded).unwrap().unwrap(), payload);
+        }
+        let mut encoded = encode(&mut AdnlCodec::server(&params()), &payloads);
+        for payload in payloads {
+            assert_eq!(client.decode(&mut encoded).unwrap().unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn test_codec_errors() {
+        let mut encoded = encode(&mut LiteCodec::client(&params()), &[b"hello"]);
+        encoded[40] ^= 1;
+        assert!(matches!(LiteCodec::server(&params()).decode(&mut encoded), Err(LiteError::IntegrityError)));
+
+        // a length prefix claiming more than the transport accepts fails before the frame is buffered
+        let mut length = BytesMut::from(&((1u32 << 24) + 1).to_le_bytes()[..]);
+        Aes::new(params().tx_key().into(), params().tx_nonce().into()).apply_keystream(&mut length);
+        let mut server = LiteCodec::server(&params());
+        assert!(matches!(server.decode(&mut length), Err(LiteError::FrameTooLong(Some(len))) if len == (1 << 24) + 1 - 64));
+        assert!(length.capacity() < 1 << 24);
+    }
+
+    #[test]
+    fn test_state_frame_limit() {
+        let answer = |query_id: u8, len: usize| {
+            let mut payload = vec![0x16, 0x84, 0xac, 0x0f];
+            payload.extend([query_id; 32]);
+            payload.resize(len, 0);
+            payload
+        };
+        let limits = FrameLimits::new(100, 1000);
+        // a new connection for every frame, since a rejected frame breaks the connection
+        let decode = |payload: &[u8]| {
+            let mut frame = encode(&mut LiteCodec::server(&params()), &[payload]);
+            LiteCodec::client(&params()).with_frame_limits(limits.clone()).decode(&mut frame)
+        };
+        assert!(decode(&answer(1, 100)).is_ok());
+        // no state download is in flight
+        assert!(matches!(decode(&answer(1, 101)), Err(LiteError::FrameTooLong(Some(101)))));
+
+        let caller = Arc::new(());
+        limits.expect_state_answer(Int256([1; 32]), Some(Arc::downgrade(&caller)));
+        limits.expect_state_answer(Int256([3; 32]), None);
+        assert!(decode(&answer(1, 1000)).unwrap().is_some());
+        assert!(decode(&answer(3, 1000)).unwrap().is_some());
+        // answers to other queries are held to the frame limit
+        assert!(matches!(decode(&answer(2, 101)), Err(LiteError::FrameTooLong(Some(101)))));
+        assert!(matches!(decode(&answer(1, 1001)), Err(LiteError::FrameTooLong(Some(1001)))));
+        // the query id is enough to reject a frame, the rest of it isn't waited for
+        let mut frame = encode(&mut LiteCodec::server(&params()), &[&answer(2, 1000)]);
+        frame.truncate(4 + NONCE_LEN + ANSWER_PREFIX_LEN);
+        let mut client = LiteCodec::client(&params()).with_frame_limits(limits.clone());
+        assert!(matches!(client.decode(&mut frame), Err(LiteError::FrameTooLong(Some(1000)))));
+
+        // once the caller gives up, e.g. on a timeout, the query is forgotten
+        drop(caller);
+        assert!(matches!(decode(&answer(1, 101)), Err(LiteError::FrameTooLong(Some(101)))));
+        assert!(decode(&answer(3, 1000)).unwrap().is_some());
+    }
+}
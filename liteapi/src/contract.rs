@@ -0,0 +1,348 @@
+//! Typed bindings to get-methods of contracts.
+//!
+//! [`contract!`](crate::contract!) declares a contract with its get-methods and their signatures, and generates
+//! a struct whose methods encode the arguments into a [`VmStack`], call `runSmcMethod` and decode the result:
+//!
+//! ```no_run
+//! use ton_liteapi::cell::ArcCell;
+//! use ton_liteapi::tlb::MsgAddressInt;
+//!
+//! ton_liteapi::contract! {
+//!     /// Jetton master (TEP-74)
+//!     pub struct JettonMaster {
+//!         fn get_jetton_data() -> (u128, bool, MsgAddressInt, ArcCell, ArcCell);
+//!         fn get_wallet_address(owner: MsgAddressInt) -> MsgAddressInt;
+//!     }
+//! }
+//! ```
+//!
+//! Arguments implement [`ToStack`], results are a [`FromStack`] value or a tuple of them, one per returned
+//! stack entry. Methods exiting with an error code fail with [`LiteError::MethodFailed`].
+
+use num_bigint::BigInt;
+
+use crate::cell::{ArcCell, CellBuilder, CellError};
+use crate::tl::response::RunMethodResult;
+use crate::tlb::{MsgAddressInt, StackSlice, StackValue, VmStack};
+use crate::types::LiteError;
+
+/// `runSmcMethod` mode of the generated bindings, requesting only the result stack
+pub const RUN_METHOD_MODE: u32 = 4;
+
+/// Id of the get-method `name`, as computed by the FunC and Tact compilers.
+pub fn method_id(name: &str) -> u64 {
+    let crc = crc::Crc::<u16>::new(&crc::CRC_16_XMODEM).checksum(name.as_bytes());
+    crc as u64 | 0x10000
+}
+
+/// Get-method argument.
+pub trait ToStack {
+    fn to_stack(&self) -> Result<StackValue, CellError>;
+}
+
+/// Value returned by a get-method, `None` if the stack entry has another type or doesn't fit.
+pub trait FromStack: Sized {
+    fn from_stack(value: StackValue) -> Option<Self>;
+}
+
+/// Result of a get-method, decoded from the top entries of its stack.
+pub trait FromStackValues: Sized {
+    fn from_stack_values(values: Vec<StackValue>) -> Option<Self>;
+}
+
+impl ToStack for StackValue {
+    fn to_stack(&self) -> Result<StackValue, CellError> {
+        Ok(self.clone())
+    }
+}
+
+impl FromStack for StackValue {
+    fn from_stack(value: StackValue) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl ToStack for BigInt {
+    fn to_stack(&self) -> Result<StackValue, CellError> {
+        Ok(StackValue::Int(self.clone()))
+    }
+}
+
+impl FromStack for BigInt {
+    fn from_stack(value: StackValue) -> Option<Self> {
+        match value {
+            StackValue::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_stack_int {
+    ($($int:ty),*) => {$(
+        impl ToStack for $int {
+            fn to_stack(&self) -> Result<StackValue, CellError> {
+                Ok(StackValue::int(*self))
+            }
+        }
+
+        impl FromStack for $int {
+            fn from_stack(value: StackValue) -> Option<Self> {
+                value.as_int()?.try_into().ok()
+            }
+        }
+    )*};
+}
+
+impl_stack_int!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+/// TVM booleans are `-1` and `0`, any non-zero integer is decoded as `true`.
+impl ToStack for bool {
+    fn to_stack(&self) -> Result<StackValue, CellError> {
+        Ok(StackValue::int(-(*self as i8)))
+    }
+}
+
+impl FromStack for bool {
+    fn from_stack(value: StackValue) -> Option<Self> {
+        Some(value.as_int()?.sign() != num_bigint::Sign::NoSign)
+    }
+}
+
+impl ToStack for ArcCell {
+    fn to_stack(&self) -> Result<StackValue, CellError> {
+        Ok(StackValue::Cell(self.clone()))
+    }
+}
+
+impl FromStack for ArcCell {
+    fn from_stack(value: StackValue) -> Option<Self> {
+        match value {
+            StackValue::Cell(cell) => Some(cell),
+            _ => None,
+        }
+    }
+}
+
+impl ToStack for StackSlice {
+    fn to_stack(&self) -> Result<StackValue, CellError> {
+        Ok(StackValue::Slice(self.clone()))
+    }
+}
+
+impl FromStack for StackSlice {
+    fn from_stack(value: StackValue) -> Option<Self> {
+        match value {
+            StackValue::Slice(slice) => Some(slice),
+            _ => None,
+        }
+    }
+}
+
+/// Addresses are passed as slices.
+impl ToStack for MsgAddressInt {
+    fn to_stack(&self) -> Result<StackValue, CellError> {
+        let mut builder = CellBuilder::new();
+        self.store(&mut builder)?;
+        Ok(StackValue::Slice(StackSlice::full(builder.build()?)))
+    }
+}
+
+impl FromStack for MsgAddressInt {
+    fn from_stack(value: StackValue) -> Option<Self> {
+        let slice = StackSlice::from_stack(value)?;
+        MsgAddressInt::load(&mut slice.parser().ok()?).ok()
+    }
+}
+
+/// `None` is passed as, and decoded from, a null.
+impl<T: ToStack> ToStack for Option<T> {
+    fn to_stack(&self) -> Result<StackValue, CellError> {
+        match self {
+            Some(value) => value.to_stack(),
+            None => Ok(StackValue::Null),
+        }
+    }
+}
+
+impl<T: FromStack> FromStack for Option<T> {
+    fn from_stack(value: StackValue) -> Option<Self> {
+        match value {
+            StackValue::Null => Some(None),
+            value => T::from_stack(value).map(Some),
+        }
+    }
+}
+
+/// Vectors are passed as, and decoded from, TVM tuples.
+impl<T: ToStack> ToStack for Vec<T> {
+    fn to_stack(&self) -> Result<StackValue, CellError> {
+        Ok(StackValue::Tuple(self.iter().map(ToStack::to_stack).collect::<Result<_, _>>()?))
+    }
+}
+
+impl<T: FromStack> FromStack for Vec<T> {
+    fn from_stack(value: StackValue) -> Option<Self> {
+        match value {
+            StackValue::Tuple(items) => items.into_iter().map(T::from_stack).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromStack> FromStackValues for T {
+    fn from_stack_values(mut values: Vec<StackValue>) -> Option<Self> {
+        T::from_stack(values.pop()?)
+    }
+}
+
+impl FromStackValues for () {
+    fn from_stack_values(_: Vec<StackValue>) -> Option<Self> {
+        Some(())
+    }
+}
+
+macro_rules! impl_from_stack_values {
+    ($($len:literal => ($($name:ident),+),)*) => {$(
+        impl<$($name: FromStack),+> FromStackValues for ($($name,)+) {
+            fn from_stack_values(mut values: Vec<StackValue>) -> Option<Self> {
+                let mut values = values.drain(values.len().checked_sub($len)?..);
+                Some(($($name::from_stack(values.next()?)?,)+))
+            }
+        }
+    )*};
+}
+
+impl_from_stack_values! {
+    1 => (A),
+    2 => (A, B),
+    3 => (A, B, C),
+    4 => (A, B, C, D),
+    5 => (A, B, C, D, E),
+    6 => (A, B, C, D, E, F),
+    7 => (A, B, C, D, E, F, G),
+    8 => (A, B, C, D, E, F, G, H),
+}
+
+/// Parameters of `runSmcMethod` for the given arguments.
+#[doc(hidden)]
+pub fn encode_args(args: &[&dyn ToStack]) -> Result<Vec<u8>, LiteError> {
+    let values = args.iter().map(|arg| arg.to_stack()).collect::<Result<_, _>>()?;
+    Ok(VmStack::new(values).to_boc()?)
+}
+
+/// Decode the result of a get-method called with [`RUN_METHOD_MODE`].
+pub fn decode_result<T: FromStackValues>(result: &RunMethodResult) -> Result<T, LiteError> {
+    if !result.exit().is_success() {
+        return Err(LiteError::MethodFailed(result.exit()));
+    }
+    let stack = result.stack()?.ok_or(LiteError::UnexpectedResult)?;
+    T::from_stack_values(stack.values).ok_or(LiteError::UnexpectedResult)
+}
+
+/// Declare typed bindings to the get-methods of a contract, see [`mod@crate::contract`].
+///
+/// The struct holds the address of the contract. Every method takes the client and the block to run at,
+/// followed by the declared arguments. The get-method id is computed from the method name.
+#[macro_export]
+macro_rules! contract {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $contract:ident {
+            $(
+                $(#[$method_meta:meta])*
+                fn $method:ident($($arg:ident: $arg_ty:ty),* $(,)?) -> $ret:ty;
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        $vis struct $contract {
+            pub address: $crate::tl::common::AccountId,
+        }
+
+        impl $contract {
+            pub fn new(address: $crate::tl::common::AccountId) -> Self {
+                Self { address }
+            }
+
+            $(
+                $(#[$method_meta])*
+                pub async fn $method(
+                    &self,
+                    client: &mut $crate::client::LiteClient,
+                    id: $crate::tl::common::BlockIdExt,
+                    $($arg: $arg_ty),*
+                ) -> ::std::result::Result<$ret, $crate::types::LiteError> {
+                    let params = $crate::contract::encode_args(&[$(&$arg as &dyn $crate::contract::ToStack),*])?;
+                    let method_id = $crate::contract::method_id(stringify!($method));
+                    let result = client.run_smc_method($crate::contract::RUN_METHOD_MODE, id, self.address.clone(), method_id, params).await?;
+                    $crate::contract::decode_result(&result)
+                }
+            )*
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tl::common::{BlockIdExt, Int256};
+    use crate::tlb::ExitCode;
+
+    use super::*;
+
+    fn result(exit_code: i32, values: Option<Vec<StackValue>>) -> RunMethodResult {
+        let id = BlockIdExt { workchain: -1, shard: 0x8000000000000000, seqno: 1, root_hash: Int256::default(), file_hash: Int256::default() };
+        RunMethodResult {
+            mode: (),
+            id: id.clone(),
+            shardblk: id,
+            shard_proof: None,
+            proof: None,
+            state_proof: None,
+            init_c7: None,
+            lib_extras: None,
+            exit_code,
+            result: values.map(|values| VmStack::new(values).to_boc().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_method_id() {
+        assert_eq!(method_id("seqno"), 85143);
+        assert_eq!(method_id("get_public_key"), 78748);
+    }
+
+    #[test]
+    fn test_encode_args() -> Result<(), LiteError> {
+        let address = MsgAddressInt::std(0, [7; 32]);
+        let params = encode_args(&[&5u8, &address, &true, &None::<ArcCell>])?;
+        let values = VmStack::from_boc(&params)?.values;
+        // the first argument is the deepest entry
+        assert_eq!(values.len(), 4);
+        assert_eq!(values[0], StackValue::int(5));
+        assert_eq!(MsgAddressInt::from_stack(values[1].clone()), Some(address));
+        assert_eq!(values[2], StackValue::int(-1));
+        assert!(values[3].is_null());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_result() -> Result<(), LiteError> {
+        let values = vec![StackValue::int(1), StackValue::int(2), StackValue::int(3)];
+        // a single value is the top of the stack, a tuple the top entries from the deepest one
+        assert_eq!(decode_result::<u8>(&result(0, Some(values.clone())))?, 3);
+        assert_eq!(decode_result::<(u8, u16)>(&result(0, Some(values.clone())))?, (2, 3));
+        assert_eq!(decode_result::<(i32, u8, u64)>(&result(0, Some(values.clone())))?, (1, 2, 3));
+        decode_result::<()>(&result(0, Some(vec![])))?;
+
+        // wrong types, values not fitting and missing entries
+        assert!(matches!(decode_result::<(u8, ArcCell)>(&result(0, Some(values.clone()))), Err(LiteError::UnexpectedResult)));
+        assert!(matches!(decode_result::<u8>(&result(0, Some(vec![StackValue::int(256)]))), Err(LiteError::UnexpectedResult)));
+        assert!(matches!(decode_result::<(u8, u8, u8, u8)>(&result(0, Some(values.clone()))), Err(LiteError::UnexpectedResult)));
+        assert!(matches!(decode_result::<u8>(&result(0, None)), Err(LiteError::UnexpectedResult)));
+
+        let failed = decode_result::<u8>(&result(11, Some(values)));
+        assert!(matches!(failed, Err(LiteError::MethodFailed(code)) if code == ExitCode::from(11)));
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+//! Correlation ids of queries.
+//!
+//! A correlation id is an opaque string which ties the queries of a multi-step workflow together, e.g.
+//! sending a message, waiting for its transaction and verifying it. Set it on a client with
+//! [`LiteClient::with_correlation_id`](crate::client::LiteClient::with_correlation_id), or for everything
+//! running inside a future with [`scope`]. The id of a query prefixes its log lines, and errors of the query
+//! are returned as [`LiteError::Correlated`](crate::types::LiteError::Correlated).
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+tokio::task_local! {
+    static CORRELATION_ID: Arc<str>;
+}
+
+/// Run `future` with the correlation id `id`, it applies to the queries of all clients which don't have their own.
+pub async fn scope<F: Future>(id: impl Into<Arc<str>>, future: F) -> F::Output {
+    CORRELATION_ID.scope(id.into(), future).await
+}
+
+/// Correlation id of the current [`scope`].
+pub fn current() -> Option<Arc<str>> {
+    CORRELATION_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Log prefix with the correlation id of the current scope, empty outside of one.
+pub(crate) fn prefix() -> impl fmt::Display {
+    Prefix(current())
+}
+
+struct Prefix(Option<Arc<str>>);
+
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(id) => write!(f, "[{}] ", id),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn nested() -> Option<Arc<str>> {
+        tokio::task::yield_now().await;
+        current()
+    }
+
+    #[tokio::test]
+    async fn test_scope() {
+        assert_eq!(current(), None);
+        assert_eq!(prefix().to_string(), "");
+        scope("transfer-1", async {
+            assert_eq!(current().as_deref(), Some("transfer-1"));
+            assert_eq!(prefix().to_string(), "[transfer-1] ");
+            // inherited by the futures awaited inside, an inner scope takes precedence until it ends
+            assert_eq!(nested().await.as_deref(), Some("transfer-1"));
+            assert_eq!(scope("inner", nested()).await.as_deref(), Some("inner"));
+            assert_eq!(current().as_deref(), Some("transfer-1"));
+        })
+        .await;
+        assert_eq!(current(), None);
+    }
+}
@@ -0,0 +1,41 @@
+//! Local execution of get-methods, enabled by the `emulator` feature.
+//!
+//! The crate doesn't bundle a TVM. Implement [`TvmEmulator`] on top of one, e.g. the `emulator`
+//! library from the TON monorepo, and pass it to [`LiteClient::run_get_method_local`](crate::client::LiteClient::run_get_method_local),
+//! which downloads everything the method needs from the liteserver and checks it against the requested block.
+
+use crate::cell::ArcCell;
+use crate::tl::common::LibraryEntry;
+use crate::tlb::MsgAddressInt;
+use crate::types::LiteError;
+
+/// Everything needed to run a get-method of an account.
+#[derive(Debug, Clone)]
+pub struct GetMethodParams {
+    pub address: MsgAddressInt,
+    pub code: ArcCell,
+    pub data: ArcCell,
+    pub balance: u128,
+    /// Libraries referenced by `code` and `data`
+    pub libraries: Vec<LibraryEntry>,
+    /// Root of `Hashmap 32 ^Cell` with blockchain configuration
+    pub config: ArcCell,
+    /// Generation time of the masterchain state, used as `NOW`
+    pub unixtime: u32,
+    pub method_id: u64,
+    /// Serialized `VmStack`, same as `params` of `runSmcMethod`
+    pub stack: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetMethodOutput {
+    pub exit_code: i32,
+    pub gas_used: u64,
+    /// Serialized `VmStack`, same as `result` of `runSmcMethod`
+    pub stack: Vec<u8>,
+}
+
+/// TVM implementation used to run get-methods locally, only ever given proven inputs.
+pub trait TvmEmulator {
+    fn run_get_method(&self, params: GetMethodParams) -> Result<GetMethodOutput, LiteError>;
+}
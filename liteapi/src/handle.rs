@@ -0,0 +1,174 @@
+use std::task::{Context, Poll};
+
+use futures::future::{self, BoxFuture};
+use tokio::net::ToSocketAddrs;
+use tower::buffer::Buffer;
+use tower::util::BoxService;
+use tower::{BoxError, Service, ServiceExt as _};
+
+use crate::client::{pass_through_methods, LiteClient};
+use crate::layers::Shutdown;
+use crate::tl::common::{AccountId, BlockId, BlockIdExt, Int256, LibraryEntry, TransactionId3};
+use crate::tl::request::*;
+use crate::tl::response::*;
+use crate::tl::utils::FromResponse;
+use crate::types::{ConfigMode, LiteError, PeerInfo};
+
+type Result<T> = std::result::Result<T, LiteError>;
+
+/// Default number of requests which may wait in the channel before callers are backpressured.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Cloneable handle to a connection owned by a background task.
+///
+/// The worker task is spawned on the current tokio runtime and keeps the connection alive while
+/// at least one handle exists. Requests from all clones are sent over a channel and pipelined
+/// over the same connection, so handles may be freely moved into other tasks.
+#[derive(Clone)]
+pub struct LiteHandle {
+    inner: Buffer<BoxService<WrappedRequest, Response, LiteError>, WrappedRequest>,
+    /// Requests accepted by the handles, including the ones still waiting in the buffer
+    queue: Shutdown,
+    shutdown: Shutdown,
+    peer: Option<PeerInfo>,
+}
+
+impl LiteHandle {
+    pub async fn connect<A: ToSocketAddrs>(address: A, public_key: impl AsRef<[u8]>) -> Result<Self> {
+        Ok(Self::new(LiteClient::connect(address, public_key).await?))
+    }
+
+    /// Move `client` into a background task. Must be called from within a tokio runtime.
+    pub fn new(client: LiteClient) -> Self {
+        Self::with_capacity(client, DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(client: LiteClient, capacity: usize) -> Self {
+        let (service, shutdown, peer) = client.into_parts();
+        Self { inner: Buffer::new(service, capacity), queue: Shutdown::new(), shutdown, peer }
+    }
+
+    /// Liteserver of the connection, see [`LiteClient::peer_info`].
+    pub fn peer_info(&self) -> Option<&PeerInfo> {
+        self.peer.as_ref()
+    }
+
+    /// Gracefully shut down the connection for all clones of this handle: reject new requests,
+    /// wait for the outstanding ones, including the ones still queued in the channel, and close
+    /// the ADNL session.
+    pub async fn shutdown(&self) {
+        self.queue.drain().await;
+        self.shutdown.drain().await;
+        self.close().await;
+    }
+
+    /// Close the connection for all clones immediately, outstanding and queued requests fail with
+    /// [`LiteError::Closed`].
+    pub async fn close(&self) {
+        self.queue.close();
+        self.shutdown.abort();
+        self.shutdown.terminated().await;
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed() || self.shutdown.is_closed()
+    }
+
+    /// Typed client whose requests are routed through this handle.
+    pub fn client(&self) -> LiteClient {
+        LiteClient::new(self.clone())
+    }
+
+    /// Send any function of the scheme without requiring `&mut self`, see [`LiteClient::query`].
+    ///
+    /// Errors carry the function and the liteserver, see [`LiteError::Query`].
+    pub async fn query<F: LiteFunction>(&self, function: F) -> Result<F::Response> {
+        let request: Request = function.into();
+        let method = request.name();
+        let wrapped_request = WrappedRequest::new(request);
+        let response = self.clone().oneshot(wrapped_request).await
+            .map_err(|e| e.in_query(method, self.peer.as_ref().map(|peer| &peer.server)))?;
+        F::Response::from_response(response)
+    }
+
+    pass_through_methods!(&self);
+
+    pub async fn get_time(&self) -> Result<u32> {
+        Ok(self.query(GetTime).await?.now)
+    }
+
+    pub async fn get_block(&self, id: BlockIdExt) -> Result<Vec<u8>> {
+        let response = self.query(GetBlock { id: id.clone() }).await?;
+        response.verify(&id)?;
+        Ok(response.data)
+    }
+
+    pub async fn get_block_header(
+        &self,
+        id: BlockIdExt,
+        with_state_update: bool,
+        with_value_flow: bool,
+        with_extra: bool,
+        with_shard_hashes: bool,
+        with_prev_blk_signatures: bool,
+    ) -> Result<Vec<u8>> {
+        self.client().get_block_header(id, with_state_update, with_value_flow, with_extra, with_shard_hashes, with_prev_blk_signatures).await
+    }
+
+    pub async fn get_state(&self, id: BlockIdExt) -> Result<BlockState> {
+        self.client().get_state(id).await
+    }
+
+    pub async fn send_message(&self, body: Vec<u8>) -> Result<u32> {
+        Ok(self.query(SendMessage { body }).await?.status)
+    }
+
+    pub async fn run_smc_method(&self, mode: u32, id: BlockIdExt, account: AccountId, method_id: u64, params: Vec<u8>) -> Result<RunMethodResult> {
+        self.query(RunSmcMethod { mode, id, account, method_id, params }).await
+    }
+
+    pub async fn get_config_all(&self, id: BlockIdExt, mode: ConfigMode) -> Result<ConfigInfo> {
+        self.client().get_config_all(id, mode).await
+    }
+
+    pub async fn get_config_params(&self, id: BlockIdExt, param_list: Vec<i32>, mode: ConfigMode) -> Result<ConfigInfo> {
+        self.client().get_config_params(id, param_list, mode).await
+    }
+
+    pub async fn get_libraries(&self, library_list: Vec<Int256>) -> Result<Vec<LibraryEntry>> {
+        Ok(self.query(GetLibraries { library_list }).await?.result)
+    }
+}
+
+impl Service<WrappedRequest> for LiteHandle {
+    type Response = Response;
+    type Error = LiteError;
+    type Future = BoxFuture<'static, Result<Response>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        // closed handle stays ready, every call fails with `LiteError::Closed` instead
+        if self.queue.is_closed() {
+            return Poll::Ready(Ok(()));
+        }
+        self.inner.poll_ready(cx).map_err(unbox_error)
+    }
+
+    fn call(&mut self, request: WrappedRequest) -> Self::Future {
+        let Some(guard) = self.queue.enter() else {
+            return Box::pin(future::err(LiteError::Closed));
+        };
+        let fut = self.inner.call(request);
+        Box::pin(async move {
+            let _guard = guard;
+            fut.await.map_err(unbox_error)
+        })
+    }
+}
+
+/// Errors produced by the inner service are boxed by the buffer, restore them if possible.
+fn unbox_error(error: BoxError) -> LiteError {
+    match error.downcast::<LiteError>() {
+        Ok(error) => *error,
+        Err(error) => LiteError::UnknownError(error),
+    }
+}
@@ -0,0 +1,659 @@
+//! Sequential indexing of all masterchain and shard blocks.
+//!
+//! [`Indexer`] walks masterchain blocks one by one starting from a given seqno. For each of them, the shard
+//! blocks registered since the previous masterchain block are written into a [`BlockSink`] first, in the order
+//! they were created, followed by the masterchain block itself. The seqno of the last completely processed
+//! masterchain block can be persisted to a checkpoint file, so indexing resumes where it stopped.
+//!
+//! Instead of a sink, blocks can be consumed as a stream with [`Indexer::stream`]. Either way, shard blocks
+//! are only delivered once they're registered in a masterchain block, i.e. final.
+//!
+//! Historical ranges can be downloaded faster with [`Indexer::backfill`], which fetches several masterchain
+//! blocks concurrently but still writes the blocks in order.
+//!
+//! Every block is checked to follow the previously indexed blocks of its chain by the `prev` links of its
+//! header. Masterchain blocks are final, but different liteservers may disagree, and shards may emit forked
+//! candidates around splits and merges. A block that doesn't follow is reported with [`BlockSink::reorg`]
+//! before it's written, which stops indexing unless the sink handles it.
+//!
+//! How often the indexer checks for new blocks once it caught up, and whether it retries failed requests,
+//! is set with [`Indexer::with_poll_policy`].
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+use futures::{stream, Stream, StreamExt as _, TryStreamExt as _};
+
+use crate::cell::Cell;
+use crate::client::LiteClient;
+use crate::poll::PollPolicy;
+use crate::pool::{priority, LitePool, Priority};
+use crate::sink::BlockSink;
+use crate::tl::common::{BlockId, BlockIdExt};
+use crate::time;
+use crate::tlb::{prev_blocks, BlockInfo, ShardIdent};
+use crate::types::{BlockFull, LiteError};
+
+type Result<T> = std::result::Result<T, LiteError>;
+
+/// Shard prefix of the masterchain.
+const MASTERCHAIN_SHARD: u64 = 1 << 63;
+
+/// Switch of the masterchain or a shard chain to another fork, see [`BlockSink::reorg`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reorg {
+    /// Block last written for the chain
+    pub from: BlockIdExt,
+    /// Block written next, which doesn't descend from `from`
+    ///
+    /// Blocks since the common ancestor of both belong to an abandoned fork, and blocks of the new fork
+    /// before `to` are not written.
+    pub to: BlockIdExt,
+}
+
+impl fmt::Display for Reorg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} doesn't follow {}", self.to, self.from)
+    }
+}
+
+/// Block yielded by [`Indexer::stream`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinalBlock {
+    /// Seqno of the masterchain block which registered the block, its own seqno for masterchain blocks
+    pub mc_seqno: u32,
+    pub block: BlockFull,
+}
+
+/// The last processed masterchain block and the shard blocks registered in it.
+#[derive(Debug, Clone, Default)]
+struct Tops {
+    masterchain: Option<BlockIdExt>,
+    shards: HashSet<BlockIdExt>,
+}
+
+/// Blocks of a masterchain block in the order they're written, see [`masterchain_blocks`].
+struct Batch {
+    blocks: Vec<BlockFull>,
+    /// Previous block of the masterchain block
+    prev: Vec<BlockIdExt>,
+    /// Creation time of the masterchain block
+    gen_utime: u32,
+    /// Forks of shard chains
+    reorgs: Vec<Reorg>,
+    tops: Tops,
+}
+
+pub struct Indexer {
+    next_seqno: u32,
+    checkpoint: Option<PathBuf>,
+    batch_size: u32,
+    /// Masterchain blocks written into the sink since the last flush
+    unflushed: u32,
+    known: Option<Tops>,
+    poll: PollPolicy,
+    /// Creation time of the last processed masterchain block
+    last_utime: Option<u32>,
+}
+
+impl Indexer {
+    pub fn new(start_seqno: u32) -> Self {
+        Self {
+            next_seqno: start_seqno,
+            checkpoint: None,
+            batch_size: 1,
+            unflushed: 0,
+            known: None,
+            poll: PollPolicy::default(),
+            last_utime: None,
+        }
+    }
+
+    /// Save the last processed masterchain seqno to `path` and resume from the saved one if the file exists.
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        match std::fs::read_to_string(&path) {
+            Ok(saved) => {
+                let seqno: u32 = saved.trim().parse()
+                    .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid checkpoint"))?;
+                self.next_seqno = seqno + 1;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        self.checkpoint = Some(path);
+        Ok(self)
+    }
+
+    /// Flush the sink and save the checkpoint once per `batch_size` masterchain blocks instead of after each one.
+    ///
+    /// Up to `batch_size` masterchain blocks are delivered again after a restart.
+    pub fn with_batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Pace of [`Indexer::run`] and [`Indexer::stream`] once they caught up, with retries of failed requests and
+    /// a warning when the processed blocks lag behind, a check per second by default.
+    ///
+    /// A [`Reorg`] is never retried, since the same blocks would be fetched again.
+    pub fn with_poll_policy(mut self, poll: PollPolicy) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    /// Seqno of the masterchain block to be processed next.
+    pub fn next_seqno(&self) -> u32 {
+        self.next_seqno
+    }
+
+    /// Process the next masterchain block, returns `false` if it doesn't exist yet.
+    ///
+    /// If `sink` fails, the masterchain block is processed again by the next call.
+    pub async fn index_next<S: BlockSink>(&mut self, client: &mut LiteClient, sink: &mut S) -> Result<bool> {
+        let seqno = self.next_seqno;
+        if client.get_masterchain_info().await?.last.seqno < seqno {
+            return Ok(false);
+        }
+        if self.known.is_none() && seqno > 0 {
+            self.known = Some(shard_tops(client, seqno - 1).await?);
+        }
+        let known = self.known.get_or_insert_with(Tops::default);
+        let batch = masterchain_blocks(client, seqno, known).await?;
+        self.write_batch(sink, seqno, batch)?;
+        Ok(true)
+    }
+
+    /// Process masterchain blocks up to `end_seqno` (exclusive), downloading up to `parallelism` of them
+    /// concurrently over `pool`, e.g. a pool of archival liteservers for the initial sync.
+    ///
+    /// Blocks are written into `sink` in the same order as with [`Indexer::index_next`]. The requests have
+    /// [`Priority::Background`], so a pool with [`LitePool::with_priorities`] keeps serving interactive requests.
+    pub async fn backfill<S: BlockSink>(&mut self, pool: &LitePool, end_seqno: u32, parallelism: usize, sink: &mut S) -> Result<()> {
+        let mut batches = stream::iter(self.next_seqno..end_seqno)
+            .map(|seqno| {
+                let mut client = pool.client();
+                priority(Priority::Background, async move {
+                    let known = match seqno {
+                        0 => Tops::default(),
+                        seqno => shard_tops(&mut client, seqno - 1).await?,
+                    };
+                    let batch = masterchain_blocks(&mut client, seqno, &known).await?;
+                    Result::Ok((seqno, batch))
+                })
+            })
+            .buffered(parallelism.max(1));
+        while let Some((seqno, batch)) = batches.try_next().await? {
+            self.write_batch(sink, seqno, batch)?;
+        }
+        self.flush(sink)
+    }
+
+    /// Flush `sink` and save the checkpoint, if anything was written since the last flush.
+    pub fn flush<S: BlockSink>(&mut self, sink: &mut S) -> Result<()> {
+        if self.unflushed == 0 {
+            return Ok(());
+        }
+        sink.flush()?;
+        if let Some(path) = &self.checkpoint {
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, (self.next_seqno - 1).to_string())?;
+            std::fs::rename(&tmp, path)?;
+        }
+        self.unflushed = 0;
+        Ok(())
+    }
+
+    fn write_batch<S: BlockSink>(&mut self, sink: &mut S, seqno: u32, batch: Batch) -> Result<()> {
+        // the batch may come from another liteserver than the previous one
+        let last = self.known.as_ref().and_then(|known| known.masterchain.as_ref());
+        if let (Some(last), Some(block)) = (last, batch.tops.masterchain.as_ref()) {
+            if batch.prev != [last.clone()] {
+                sink.reorg(&Reorg { from: last.clone(), to: block.clone() })?;
+            }
+        }
+        batch.reorgs.iter().try_for_each(|reorg| sink.reorg(reorg))?;
+        batch.blocks.iter().try_for_each(|block| sink.write(block))?;
+        self.last_utime = Some(batch.gen_utime);
+        self.processed(sink, seqno, batch.tops)
+    }
+
+    fn processed<S: BlockSink>(&mut self, sink: &mut S, seqno: u32, tops: Tops) -> Result<()> {
+        self.known = Some(tops);
+        self.next_seqno = seqno + 1;
+        self.unflushed += 1;
+        if self.unflushed >= self.batch_size {
+            self.flush(sink)?;
+        }
+        Ok(())
+    }
+
+    /// Stream of blocks as they appear, in the same order as they're written into a sink by [`Indexer::run`].
+    ///
+    /// Shard blocks are yielded with the seqno of the masterchain block which registered them. The stream fails
+    /// on a [`Reorg`]. A checkpoint is saved once the blocks are fetched rather than consumed, so blocks
+    /// may be skipped after a restart if the consumer stopped before handling them.
+    pub fn stream<'a>(&'a mut self, client: &'a mut LiteClient) -> impl Stream<Item = Result<FinalBlock>> + 'a {
+        stream::try_unfold((self, client), |(indexer, client)| async move {
+            let mut errors = 0;
+            loop {
+                let mc_seqno = indexer.next_seqno;
+                let mut blocks = Vec::new();
+                let mut sink = |block: &BlockFull| {
+                    blocks.push(FinalBlock { mc_seqno, block: block.clone() });
+                    Ok(())
+                };
+                match indexer.index_next(client, &mut sink).await {
+                    Ok(true) => {
+                        indexer.check_lag();
+                        return Result::Ok(Some((blocks, (indexer, client))));
+                    }
+                    Ok(false) => {
+                        errors = 0;
+                        indexer.poll.wait().await;
+                    }
+                    Err(e) => indexer.retry(&mut errors, e).await?,
+                }
+            }
+        })
+        .map_ok(|blocks| stream::iter(blocks.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    /// Process masterchain blocks as they appear, returns only on error.
+    ///
+    /// The sink is also flushed whenever the indexer catches up with the last masterchain block.
+    pub async fn run<S: BlockSink>(&mut self, client: &mut LiteClient, sink: &mut S) -> Result<()> {
+        let mut errors = 0;
+        loop {
+            match self.index_next(client, sink).await {
+                Ok(true) => {
+                    errors = 0;
+                    self.check_lag();
+                }
+                Ok(false) => {
+                    errors = 0;
+                    self.flush(sink)?;
+                    self.poll.wait().await;
+                }
+                Err(e) => self.retry(&mut errors, e).await?,
+            }
+        }
+    }
+
+    /// Wait before processing the block again after `error` according to the poll policy.
+    async fn retry(&self, errors: &mut u32, error: LiteError) -> Result<()> {
+        match error {
+            LiteError::Reorg(_) => Err(error),
+            error => self.poll.backoff(errors, error).await,
+        }
+    }
+
+    fn check_lag(&self) {
+        if let Some(utime) = self.last_utime {
+            self.poll.check_lag(format_args!("Indexer at masterchain block {}", self.next_seqno - 1), time::age(utime));
+        }
+    }
+}
+
+/// Masterchain block `seqno` and the shard blocks registered in it.
+async fn shard_tops(client: &mut LiteClient, seqno: u32) -> Result<Tops> {
+    let id = lookup_masterchain(client, seqno).await?;
+    let shards = client.get_all_shards_info(id.clone()).await?.shard_hashes()?;
+    Ok(Tops { masterchain: Some(id), shards: shards.shards.iter().map(|s| s.block_id()).collect() })
+}
+
+/// Shard blocks registered in masterchain block `seqno` after the `known` ones in the order they were created,
+/// followed by the masterchain block itself.
+///
+/// Shard blocks are followed back by their `prev` links until the known ones. A block which isn't known but
+/// isn't newer than a known block of an overlapping shard belongs to another fork: it's written with a
+/// [`Reorg`], and its own history isn't followed.
+async fn masterchain_blocks(client: &mut LiteClient, seqno: u32, known: &Tops) -> Result<Batch> {
+    let id = lookup_masterchain(client, seqno).await?;
+    let block = client.get_block_full(id.clone()).await?;
+    let proof = Cell::from_boc(&block.header.header_proof)?;
+    let gen_utime = BlockInfo::from_proof(&proof)?.gen_utime;
+    let prev = match seqno {
+        0 => Vec::new(),
+        _ => prev_blocks(&proof)?,
+    };
+    let tops: HashSet<_> = block.shards.iter().flat_map(|s| &s.shards).map(|s| s.block_id()).collect();
+    let mut pending: Vec<_> = tops.iter().cloned().collect();
+    let mut seen = HashSet::new();
+    let mut blocks = Vec::new();
+    let mut reorgs = Vec::new();
+    while let Some(id) = pending.pop() {
+        if known.shards.contains(&id) || !seen.insert(id.clone()) {
+            continue;
+        }
+        let shard = id.shard_ident();
+        let forked = known.shards.iter()
+            .find(|k| k.seqno >= id.seqno && ShardIdent::from(*k).intersects(&shard));
+        let shard_block = client.get_block_full(id.clone()).await?;
+        match forked {
+            Some(from) => reorgs.push(Reorg { from: from.clone(), to: id }),
+            None if shard_block.header.id.seqno > 0 => {
+                pending.extend(prev_blocks(&*Cell::from_boc(&shard_block.header.header_proof)?)?);
+            }
+            None => {}
+        }
+        blocks.push(shard_block);
+    }
+    // a block is always created after the blocks it follows, so this keeps every shard in order
+    blocks.sort_by_key(|b| b.header.id.seqno);
+    blocks.push(block);
+    Ok(Batch { blocks, prev, gen_utime, reorgs, tops: Tops { masterchain: Some(id), shards: tops } })
+}
+
+async fn lookup_masterchain(client: &mut LiteClient, seqno: u32) -> Result<BlockIdExt> {
+    let id = BlockId { workchain: -1, shard: MASTERCHAIN_SHARD, seqno };
+    let header = client.lookup_block((), id, Some(()), None, None, false, false, false, false, false).await?;
+    Ok(header.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::cell::{ArcCell, CellBuilder, CellError};
+    use crate::tl::common::{Int256, ZeroStateIdExt};
+    use crate::tl::request::{Request, WrappedRequest};
+    use crate::tl::response::{AllShardsInfo, BlockHeader, BlockTransactionsExt, MasterchainInfo, Response};
+    use crate::handle::LiteHandle;
+    use crate::tlb::build_hashmap;
+    use crate::types::LiteServer;
+
+    /// Block of the whole `workchain`, blocks of another `branch` have other hashes.
+    fn block(workchain: i32, seqno: u32, branch: u8) -> BlockIdExt {
+        let mut root_hash = [0; 32];
+        root_hash[0] = branch;
+        root_hash[1..5].copy_from_slice(&seqno.to_be_bytes());
+        root_hash[5] = workchain as u8;
+        BlockIdExt { workchain, shard: MASTERCHAIN_SHARD, seqno, root_hash: Int256(root_hash), file_hash: Int256([0; 32]) }
+    }
+
+    fn mc(seqno: u32) -> BlockIdExt {
+        block(-1, seqno, 0)
+    }
+
+    fn shard(seqno: u32) -> BlockIdExt {
+        block(0, seqno, 0)
+    }
+
+    /// Fake liteserver with a masterchain block registering the shard block `tops[seqno]` for every seqno.
+    #[derive(Default)]
+    struct Chain {
+        tops: Vec<BlockIdExt>,
+        /// Masterchain block which doesn't follow the previous one
+        mc_fork: Option<u32>,
+        /// Answer the masterchain blocks with higher seqnos first
+        reversed: bool,
+        priorities: Mutex<Vec<Option<Priority>>>,
+    }
+
+    impl Chain {
+        fn new(tops: impl IntoIterator<Item = u32>) -> Self {
+            Self { tops: tops.into_iter().map(shard).collect(), ..Default::default() }
+        }
+
+        fn client(self: &Arc<Self>) -> LiteClient {
+            let chain = self.clone();
+            LiteClient::new(tower::service_fn(move |request: WrappedRequest| {
+                let chain = chain.clone();
+                async move { chain.answer(request).await }
+            }))
+        }
+
+        async fn answer(&self, request: WrappedRequest) -> Result<Response> {
+            self.priorities.lock().unwrap().push(request.context.priority);
+            let header = |id: BlockIdExt| -> Result<Response> {
+                let prev = match id.workchain {
+                    -1 => block(-1, id.seqno.saturating_sub(1), (self.mc_fork == Some(id.seqno)) as u8),
+                    _ => block(id.workchain, id.seqno.saturating_sub(1), id.root_hash.0[0]),
+                };
+                let header_proof = header_proof(&id, &prev)?;
+                Ok(Response::BlockHeader(BlockHeader {
+                    id,
+                    mode: (),
+                    with_state_update: None,
+                    with_value_flow: None,
+                    with_extra: None,
+                    with_shard_hashes: None,
+                    with_prev_blk_signatures: None,
+                    header_proof,
+                }))
+            };
+            match request.request {
+                Request::GetMasterchainInfo => Ok(Response::MasterchainInfo(MasterchainInfo {
+                    last: mc(self.tops.len() as u32 - 1),
+                    state_root_hash: Int256([0; 32]),
+                    init: ZeroStateIdExt { workchain: -1, root_hash: Int256([0; 32]), file_hash: Int256([0; 32]) },
+                })),
+                Request::LookupBlock(req) => {
+                    if self.reversed {
+                        tokio::time::sleep(Duration::from_millis(5 * (self.tops.len() as u64 - req.id.seqno as u64))).await;
+                    }
+                    header(mc(req.id.seqno))
+                }
+                Request::GetBlockHeader(req) => header(req.id),
+                Request::ListBlockTransactionsExt(req) => Ok(Response::BlockTransactionsExt(BlockTransactionsExt {
+                    transactions: transaction(&req.id)?.to_boc(),
+                    id: req.id,
+                    req_count: req.count,
+                    incomplete: false,
+                    proof: Vec::new(),
+                })),
+                Request::GetAllShardsInfo(req) => Ok(Response::AllShardsInfo(AllShardsInfo {
+                    data: shard_hashes(&self.tops[req.id.seqno as usize])?,
+                    id: req.id,
+                    proof: Vec::new(),
+                })),
+                _ => Err(LiteError::UnexpectedMessage),
+            }
+        }
+    }
+
+    /// `Block` with the header of `id` and its previous block `prev`.
+    fn header_proof(id: &BlockIdExt, prev: &BlockIdExt) -> std::result::Result<Vec<u8>, CellError> {
+        let ext_blk_ref = |id: &BlockIdExt| {
+            CellBuilder::new().store_u64(0)?.store_u32(id.seqno)?.store_u256(&id.root_hash.0)?.store_u256(&id.file_hash.0)?.build()
+        };
+        let mut info = CellBuilder::new();
+        info.store_u32(0x9bc7a987)?.store_u32(0)?.store_bit(!id.is_masterchain())?.store_uint(7, 0)?.store_u8(0)?;
+        info.store_u32(id.seqno)?.store_u32(0)?.store_uint(8, 0)?.store_int(32, id.workchain as i64)?.store_u64(0)?;
+        info.store_u32(1700000000 + id.seqno)?.store_u64(0)?.store_u64(0)?;
+        info.store_u32(0)?.store_u32(0)?.store_u32(0)?.store_u32(0)?;
+        if !id.is_masterchain() {
+            info.store_reference(ext_blk_ref(&mc(0))?)?;
+        }
+        info.store_reference(ext_blk_ref(prev)?)?;
+        let empty = CellBuilder::new().build()?;
+        let mut block = CellBuilder::new();
+        block.store_u32(0x11ef55aa)?.store_u32(0)?;
+        for cell in [info.build()?, empty.clone(), empty.clone(), empty] {
+            block.store_reference(cell)?;
+        }
+        Ok(block.build()?.to_boc())
+    }
+
+    /// Transaction with logical time `seqno`, every block has one.
+    fn transaction(id: &BlockIdExt) -> std::result::Result<ArcCell, CellError> {
+        let empty = CellBuilder::new().build()?;
+        let messages = CellBuilder::new().store_bit(false)?.store_bit(false)?.build()?;
+        let mut transaction = CellBuilder::new();
+        transaction.store_uint(4, 0b0111)?.store_u256(&id.root_hash.0)?.store_u64(id.seqno as u64)?;
+        transaction.store_u256(&[0; 32])?.store_u64(0)?.store_u32(0)?.store_uint(15, 0)?.store_uint(4, 0)?;
+        transaction.store_reference(messages)?.store_coins(0)?.store_bit(false)?;
+        transaction.store_reference(empty.clone())?.store_reference(empty)?;
+        transaction.build()
+    }
+
+    /// `ShardHashes` with a single shard of the workchain, whose last block is `top`.
+    fn shard_hashes(top: &BlockIdExt) -> std::result::Result<Vec<u8>, CellError> {
+        let mut leaf = CellBuilder::new();
+        leaf.store_bit(false)?.store_uint(4, 0xb)?.store_u32(top.seqno)?.store_u32(0)?.store_u64(0)?.store_u64(0)?;
+        leaf.store_u256(&top.root_hash.0)?.store_u256(&top.file_hash.0)?.store_uint(8, 0)?;
+        leaf.store_u32(0)?.store_u64(0)?.store_u32(0)?.store_u32(0)?;
+        leaf.store_bit(false)?.store_coins(0)?.store_bit(false)?.store_coins(0)?.store_bit(false)?;
+        let tree = CellBuilder::new().store_reference(leaf.build()?)?.build()?;
+        let dict = build_hashmap(&[(top.workchain.to_be_bytes().to_vec(), tree)], 32)?;
+        Ok(CellBuilder::new().store_maybe_reference(dict)?.build()?.to_boc())
+    }
+
+    /// Sink recording the blocks, the reorgs and the checkpoint at the time each block is written.
+    #[derive(Default)]
+    struct Recorder {
+        blocks: Vec<BlockIdExt>,
+        reorgs: Vec<Reorg>,
+        checkpoint: Option<PathBuf>,
+        saved: Vec<Option<String>>,
+        /// Fail writing this block
+        fail_at: Option<BlockIdExt>,
+    }
+
+    impl BlockSink for Recorder {
+        fn write(&mut self, block: &BlockFull) -> Result<()> {
+            if self.fail_at.as_ref() == Some(&block.header.id) {
+                return Err(LiteError::UnexpectedMessage);
+            }
+            assert_eq!(block.transactions[0].lt, block.header.id.seqno as u64);
+            self.blocks.push(block.header.id.clone());
+            self.saved.push(self.checkpoint.as_ref().and_then(|path| std::fs::read_to_string(path).ok()));
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn reorg(&mut self, reorg: &Reorg) -> Result<()> {
+            self.reorgs.push(reorg.clone());
+            Ok(())
+        }
+    }
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ton-liteapi-{}-{}.seqno", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint() -> Result<()> {
+        let path = checkpoint_path("checkpoint");
+        std::fs::write(&path, "4\n")?;
+        let chain = Arc::new(Chain::new(0..7));
+        let mut indexer = Indexer::new(0).with_checkpoint(&path)?;
+        assert_eq!(indexer.next_seqno(), 5);
+
+        let mut sink = Recorder::default();
+        while indexer.index_next(&mut chain.client(), &mut sink).await? {}
+        assert_eq!(sink.blocks, [shard(5), mc(5), shard(6), mc(6)]);
+        // saved through a temporary file, which is renamed over the checkpoint
+        assert_eq!(std::fs::read_to_string(&path)?, "6");
+        assert!(!path.with_extension("tmp").exists());
+        assert_eq!(Indexer::new(0).with_checkpoint(&path)?.next_seqno(), 7);
+
+        // with batches, the checkpoint is saved once per batch and on flush
+        std::fs::write(&path, "2")?;
+        let mut indexer = Indexer::new(0).with_checkpoint(&path)?.with_batch_size(2);
+        for saved in ["2", "4", "4"] {
+            indexer.index_next(&mut chain.client(), &mut sink).await?;
+            assert_eq!(std::fs::read_to_string(&path)?, saved);
+        }
+        indexer.flush(&mut sink)?;
+        assert_eq!(std::fs::read_to_string(&path)?, "5");
+
+        std::fs::write(&path, "seqno")?;
+        assert!(matches!(Indexer::new(0).with_checkpoint(&path), Err(LiteError::IoError(_))));
+        std::fs::remove_file(&path)?;
+        assert_eq!(Indexer::new(3).with_checkpoint(&path)?.next_seqno(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shard_blocks_in_order() -> Result<()> {
+        // the second masterchain block registers three shard blocks at once
+        let chain = Arc::new(Chain::new([0, 1, 4, 4, 5]));
+        let mut indexer = Indexer::new(1);
+        let mut sink = Recorder::default();
+        while indexer.index_next(&mut chain.client(), &mut sink).await? {}
+        assert_eq!(sink.blocks, [shard(1), mc(1), shard(2), shard(3), shard(4), mc(2), mc(3), shard(5), mc(4)]);
+        assert!(sink.reorgs.is_empty());
+
+        // the same order from the stream, with the seqno of the registering masterchain block
+        let mut indexer = Indexer::new(2);
+        let mut client = chain.client();
+        let blocks: Vec<_> = indexer.stream(&mut client).take(4).map_ok(|b| (b.mc_seqno, b.block.header.id)).try_collect().await?;
+        assert_eq!(blocks, [(2, shard(2)), (2, shard(3)), (2, shard(4)), (2, mc(2))]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reorg() -> Result<()> {
+        // the third masterchain block doesn't follow the second one
+        let chain = Arc::new(Chain { mc_fork: Some(3), ..Chain::new(0..5) });
+        let mut indexer = Indexer::new(1);
+        let mut sink = Recorder::default();
+        while indexer.index_next(&mut chain.client(), &mut sink).await? {}
+        assert_eq!(sink.reorgs, [Reorg { from: mc(2), to: mc(3) }]);
+        assert_eq!(sink.blocks.len(), 8);
+
+        // which stops indexing with a sink not handling it
+        let mut indexer = Indexer::new(1);
+        let mut blocks = Vec::new();
+        let mut sink = |block: &BlockFull| {
+            blocks.push(block.header.id.clone());
+            Ok(())
+        };
+        while let Ok(true) = indexer.index_next(&mut chain.client(), &mut sink).await {}
+        assert_eq!(indexer.next_seqno(), 3);
+        assert!(matches!(indexer.index_next(&mut chain.client(), &mut sink).await, Err(LiteError::Reorg(_))));
+        assert_eq!(blocks, [shard(1), mc(1), shard(2), mc(2)]);
+
+        // a shard block of another fork, not newer than the last known one
+        let mut tops: Vec<_> = [0, 1, 2].map(shard).into();
+        tops.push(block(0, 2, 1));
+        let chain = Arc::new(Chain { tops, ..Default::default() });
+        let mut indexer = Indexer::new(1);
+        let mut sink = Recorder::default();
+        while indexer.index_next(&mut chain.client(), &mut sink).await? {}
+        assert_eq!(sink.reorgs, [Reorg { from: shard(2), to: block(0, 2, 1) }]);
+        assert_eq!(sink.blocks[4..], [block(0, 2, 1), mc(3)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backfill() -> Result<()> {
+        // later masterchain blocks are answered first
+        let chain = Arc::new(Chain { reversed: true, ..Chain::new(0..6) });
+        let server = LiteServer::new(([127, 0, 0, 1], 1).into(), [1; 32]);
+        let pool = LitePool::new([(server, LiteHandle::new(chain.client()))])?;
+        let path = checkpoint_path("backfill");
+        std::fs::write(&path, "1")?;
+        let mut indexer = Indexer::new(0).with_checkpoint(&path)?;
+        let mut sink = Recorder { checkpoint: Some(path.clone()), ..Default::default() };
+        indexer.backfill(&pool, 5, 4, &mut sink).await?;
+
+        assert_eq!(sink.blocks, [shard(2), mc(2), shard(3), mc(3), shard(4), mc(4)]);
+        let priorities = std::mem::take(&mut *chain.priorities.lock().unwrap());
+        assert!(!priorities.is_empty() && priorities.iter().all(|p| *p == Some(Priority::Background)));
+        // blocks of a masterchain block are written before the checkpoint moves past it
+        let saved = ["1", "1", "2", "2", "3", "3"].map(|s| Some(s.to_owned()));
+        assert_eq!(sink.saved, saved);
+        assert_eq!(std::fs::read_to_string(&path)?, "4");
+        assert_eq!(indexer.next_seqno(), 5);
+
+        // a block which fails to be written is fetched again by the next backfill
+        std::fs::write(&path, "1")?;
+        let mut indexer = Indexer::new(0).with_checkpoint(&path)?;
+        let mut sink = Recorder { fail_at: Some(mc(3)), ..Default::default() };
+        assert!(indexer.backfill(&pool, 5, 4, &mut sink).await.is_err());
+        assert_eq!(sink.blocks, [shard(2), mc(2), shard(3)]);
+        assert_eq!(std::fs::read_to_string(&path)?, "2");
+        assert_eq!(indexer.next_seqno(), 3);
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
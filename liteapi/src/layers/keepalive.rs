@@ -0,0 +1,123 @@
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tower::buffer::Buffer;
+use tower::util::BoxService;
+use tower::{BoxError, Service, ServiceExt as _};
+
+use crate::tl::adnl::Message;
+use crate::types::LiteError;
+
+use super::Shutdown;
+
+/// Keep-alive options of a connection, see [`LiteClientBuilder::with_keep_alive`](crate::client::LiteClientBuilder::with_keep_alive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepAlive {
+    /// Interval between `tcp.ping` messages
+    pub ping_interval: Duration,
+    /// Time the liteserver has to answer a ping before the connection is aborted
+    pub pong_timeout: Duration,
+    /// Close the connection once it had no queries for this long, `None` to keep it open
+    ///
+    /// Lazy clients and pools reconnect on demand, so this replaces long idle connections, which
+    /// middleboxes tend to drop silently, with fresh ones.
+    pub max_idle: Option<Duration>,
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        Self { ping_interval: Duration::from_secs(30), pong_timeout: Duration::from_secs(10), max_idle: None }
+    }
+}
+
+type MessageService = Buffer<BoxService<Message, Message, LiteError>, Message>;
+
+/// Message service which pings the liteserver from a background task, see [`KeepAlive`].
+///
+/// The task stops when the service is dropped or the connection is closed.
+pub struct KeepAliveService {
+    inner: MessageService,
+    last_active: Arc<Mutex<Instant>>,
+    _stop: DropGuard,
+}
+
+impl KeepAliveService {
+    /// Start pinging over `service`. Must be called from within a tokio runtime.
+    pub fn spawn<S>(service: S, options: KeepAlive, shutdown: Shutdown) -> Self
+    where
+        S: Service<Message, Response = Message, Error = LiteError> + Send + 'static,
+        S::Future: Send + 'static,
+    {
+        let inner = Buffer::new(BoxService::new(service), 1024);
+        let last_active = Arc::new(Mutex::new(Instant::now()));
+        let stop = CancellationToken::new();
+        tokio::spawn(ping(inner.clone(), options, last_active.clone(), shutdown, stop.clone()));
+        Self { inner, last_active, _stop: stop.drop_guard() }
+    }
+}
+
+impl Service<Message> for KeepAliveService {
+    type Response = Message;
+    type Error = LiteError;
+    type Future = BoxFuture<'static, Result<Message, LiteError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(unbox_error)
+    }
+
+    fn call(&mut self, request: Message) -> Self::Future {
+        *self.last_active.lock().unwrap() = Instant::now();
+        let last_active = self.last_active.clone();
+        let fut = self.inner.call(request);
+        Box::pin(async move {
+            let response = fut.await.map_err(unbox_error);
+            *last_active.lock().unwrap() = Instant::now();
+            response
+        })
+    }
+}
+
+/// Errors of the connection itself reach the buffer as `ServiceError`s, which don't keep their type.
+fn unbox_error(error: BoxError) -> LiteError {
+    match error.downcast::<LiteError>() {
+        Ok(error) => *error,
+        Err(_) => LiteError::ConnectionClosed { during_query: true },
+    }
+}
+
+async fn ping(mut service: MessageService, options: KeepAlive, last_active: Arc<Mutex<Instant>>, shutdown: Shutdown, stop: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = stop.cancelled() => return,
+            _ = shutdown.closing.cancelled() => return,
+            _ = tokio::time::sleep(options.ping_interval) => {}
+        }
+        if let Some(max_idle) = options.max_idle {
+            let idle = last_active.lock().unwrap().elapsed();
+            if idle >= max_idle && shutdown.in_flight.load(Ordering::Acquire) == 0 {
+                log::debug!("Closing liteserver connection idle for {:?}", idle);
+                shutdown.abort();
+                return;
+            }
+        }
+        let pong = async { service.ready().await?.call(Message::Ping { random_id: 0 }).await };
+        match tokio::time::timeout(options.pong_timeout, pong).await {
+            Ok(Ok(Message::Pong { .. })) => {}
+            Ok(Ok(message)) => log::warn!("Unexpected answer to a ping: {:?}", message),
+            Ok(Err(e)) => {
+                log::warn!("Keep-alive ping failed: {:?}", e);
+                shutdown.abort();
+                return;
+            }
+            Err(_) => {
+                log::warn!("Liteserver didn't answer a ping in {:?}, aborting the connection", options.pong_timeout);
+                shutdown.abort();
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use tl_proto::TlWrite;
+use tower::{Layer, Service};
+
+use crate::correlation;
+use crate::tl::{request::WrappedRequest, response::Response};
+use crate::types::LiteError;
+
+/// Upper bounds of the latency histogram buckets, the last bucket counts all slower requests.
+pub const LATENCY_BUCKETS: [Duration; 12] = [
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(20),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+];
+
+/// Requests of a single liteserver function seen by a [`RequestLogLayer`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodHistogram {
+    pub requests: u64,
+    /// Requests answered with `liteServer.error`
+    pub server_errors: u64,
+    /// Requests which failed on the transport or protocol level
+    pub errors: u64,
+    /// Size of the TL-serialized requests
+    pub bytes_sent: u64,
+    /// Size of the TL-serialized answers
+    pub bytes_received: u64,
+    /// Number of requests by latency, see [`LATENCY_BUCKETS`]
+    pub latency: [u64; LATENCY_BUCKETS.len() + 1],
+    pub total_latency: Duration,
+}
+
+impl MethodHistogram {
+    /// Upper bound of the latency of the `q` quantile of requests, `None` if there were no requests
+    /// or the quantile falls into the last unbounded bucket.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let rank = (self.requests as f64 * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (count, bound) in self.latency.iter().zip(LATENCY_BUCKETS) {
+            seen += count;
+            if seen >= rank {
+                return Some(bound);
+            }
+        }
+        None
+    }
+
+    pub fn mean_latency(&self) -> Option<Duration> {
+        (self.requests > 0).then(|| self.total_latency / self.requests as u32)
+    }
+
+    fn record(&mut self, sent: usize, elapsed: Duration, result: &Result<Response, LiteError>) {
+        self.requests += 1;
+        self.bytes_sent += sent as u64;
+        match result {
+            Ok(response) => {
+                self.bytes_received += response.max_size_hint() as u64;
+                // on the server side errors are already turned into answers
+                if let Response::Error(_) = response {
+                    self.server_errors += 1;
+                }
+            }
+            Err(LiteError::ServerError(_)) => self.server_errors += 1,
+            Err(_) => self.errors += 1,
+        }
+        let bucket = LATENCY_BUCKETS.iter().position(|bound| elapsed <= *bound).unwrap_or(LATENCY_BUCKETS.len());
+        self.latency[bucket] += 1;
+        self.total_latency += elapsed;
+    }
+}
+
+/// Histograms collected by a [`RequestLogLayer`], cloning shares them.
+#[derive(Clone, Default)]
+pub struct RequestLog {
+    methods: Arc<Mutex<HashMap<&'static str, MethodHistogram>>>,
+}
+
+impl RequestLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Histograms by function name, see [`crate::tl::request::Request::name`].
+    pub fn snapshot(&self) -> HashMap<&'static str, MethodHistogram> {
+        self.methods.lock().unwrap().clone()
+    }
+
+    pub fn reset(&self) {
+        self.methods.lock().unwrap().clear();
+    }
+}
+
+/// Logs every request with its duration, size and outcome at the debug level, and collects them
+/// into a [`RequestLog`].
+///
+/// Works on both sides: wrap the service of a [`crate::client::LiteClient`], or put it under
+/// [`super::UnwrapMessagesLayer`] of a server to see the requests of its clients.
+#[derive(Clone)]
+pub struct RequestLogLayer {
+    log: RequestLog,
+}
+
+impl RequestLogLayer {
+    pub fn new(log: RequestLog) -> Self {
+        Self { log }
+    }
+}
+
+impl<S> Layer<S> for RequestLogLayer {
+    type Service = RequestLogService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RequestLogService { service, log: self.log.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestLogService<S> {
+    service: S,
+    log: RequestLog,
+}
+
+impl<S> Service<WrappedRequest> for RequestLogService<S>
+where
+    S: Service<WrappedRequest, Response = Response, Error = LiteError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = LiteError;
+    type Future = BoxFuture<'static, Result<Response, LiteError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: WrappedRequest) -> Self::Future {
+        let method = request.request.name();
+        let sent = request.max_size_hint();
+        let request_log = self.log.clone();
+        let fut = self.service.call(request);
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = fut.await;
+            let elapsed = started.elapsed();
+            match &result {
+                Ok(response) => log::debug!("{}{} took {:?}, sent {} bytes, received {} bytes", correlation::prefix(), method, elapsed, sent, response.max_size_hint()),
+                Err(LiteError::ServerError(e)) => log::debug!("{}{} took {:?}, sent {} bytes, server error {}: {:?}", correlation::prefix(), method, elapsed, sent, e.code, e.message),
+                Err(e) => log::debug!("{}{} took {:?}, sent {} bytes, failed: {:?}", correlation::prefix(), method, elapsed, sent, e),
+            }
+            request_log.methods.lock().unwrap().entry(method).or_default().record(sent, elapsed, &result);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tl::response::{CurrentTime, Error};
+
+    #[test]
+    fn test_histogram_buckets() {
+        let empty = MethodHistogram::default();
+        assert_eq!((empty.quantile(0.5), empty.quantile(1.0), empty.mean_latency()), (None, None, None));
+
+        let response = Response::CurrentTime(CurrentTime { now: 1 });
+        let answer = Ok(response.clone());
+        let mut histogram = MethodHistogram::default();
+        // bucket bounds are inclusive
+        let ns = Duration::from_nanos(1);
+        for elapsed in [Duration::ZERO, LATENCY_BUCKETS[0], LATENCY_BUCKETS[0] + ns, LATENCY_BUCKETS[11], LATENCY_BUCKETS[11] + ns] {
+            histogram.record(10, elapsed, &answer);
+        }
+        assert_eq!(histogram.latency, [2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+        assert_eq!(histogram.quantile(0.0), Some(LATENCY_BUCKETS[0]));
+        assert_eq!(histogram.quantile(0.4), Some(LATENCY_BUCKETS[0]));
+        assert_eq!(histogram.quantile(0.6), Some(LATENCY_BUCKETS[1]));
+        assert_eq!(histogram.quantile(0.8), Some(LATENCY_BUCKETS[11]));
+        // the slowest request is beyond the last bound
+        assert_eq!(histogram.quantile(1.0), None);
+        assert_eq!(histogram.mean_latency(), Some((LATENCY_BUCKETS[0] * 2 + ns + LATENCY_BUCKETS[11] * 2 + ns) / 5));
+        assert_eq!((histogram.requests, histogram.bytes_sent), (5, 50));
+        assert_eq!(histogram.bytes_received, 5 * response.max_size_hint() as u64);
+    }
+
+    #[test]
+    fn test_histogram_errors() {
+        let error = Error { code: 651, message: "not found".into() };
+        let mut histogram = MethodHistogram::default();
+        // an answer of a server and an error of a client both count as server errors
+        histogram.record(0, Duration::ZERO, &Ok(Response::Error(error.clone())));
+        histogram.record(0, Duration::ZERO, &Err(LiteError::ServerError(error)));
+        histogram.record(0, Duration::ZERO, &Err(LiteError::UnexpectedMessage));
+        histogram.record(0, Duration::ZERO, &Ok(Response::CurrentTime(CurrentTime { now: 1 })));
+        assert_eq!((histogram.requests, histogram.server_errors, histogram.errors), (4, 2, 1));
+    }
+}
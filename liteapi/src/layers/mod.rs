@@ -1,6 +1,18 @@
+mod keepalive;
+mod logging;
+mod verify;
+
+pub use keepalive::{KeepAlive, KeepAliveService};
+pub use logging::{MethodHistogram, RequestLog, RequestLogLayer, RequestLogService, LATENCY_BUCKETS};
+pub use verify::{is_verifiable, verify_response, Verification, VerifyLayer, VerifyService};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use futures::future::{self, BoxFuture};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
 use tower::{Layer, Service};
 
 use crate::tl::common::Int256;
@@ -37,10 +49,16 @@ where
     type Future = BoxFuture<'static, Result<Response, LiteError>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.service.poll_ready(cx).map_err(Into::into)
+        self.service.poll_ready(cx).map_err(|e| match e.into() {
+            LiteError::ConnectionClosed { .. } => LiteError::ConnectionClosed { during_query: false },
+            e => e,
+        })
     }
 
-    fn call(&mut self, request: WrappedRequest) -> Self::Future {
+    fn call(&mut self, mut request: WrappedRequest) -> Self::Future {
+        // the peer expects the answer only while this future waits for it
+        let caller = Arc::new(());
+        request.context.caller = Some(Arc::downgrade(&caller));
         let fut = self.service.call(Message::Query {
             query_id: Int256::default(), 
             query: LiteQuery {
@@ -49,6 +67,7 @@ where
         });
         Box::pin(async move {
             let response = fut.await.map_err(Into::into)?.into();
+            drop(caller);
 
             match response {
                 Message::Answer { answer, .. } => Ok(answer),
@@ -199,4 +218,157 @@ impl<S> Layer<S> for UnwrapErrorLayer {
             service
         }
     }
+}
+
+/// Shared shutdown state of a connection.
+///
+/// Closing stops accepting new requests, aborting additionally cancels in-flight requests with
+/// [`LiteError::Closed`] and makes the [`crate::peer::LitePeer`] close its transport.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    closing: CancellationToken,
+    aborted: CancellationToken,
+    terminated: Option<CancellationToken>,
+    in_flight: Arc<AtomicUsize>,
+    idle: Arc<Notify>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// State for a connection whose transport reports when it is torn down.
+    pub(crate) fn with_transport() -> Self {
+        Self { terminated: Some(CancellationToken::new()), ..Default::default() }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closing.is_cancelled()
+    }
+
+    /// Stop accepting new requests and wait until all in-flight requests are answered.
+    pub async fn drain(&self) {
+        self.closing.cancel();
+        loop {
+            let idle = self.idle.notified();
+            if self.in_flight.load(Ordering::Acquire) == 0 {
+                break;
+            }
+            idle.await;
+        }
+    }
+
+    /// Stop accepting new requests, in-flight requests are not affected.
+    pub fn close(&self) {
+        self.closing.cancel();
+    }
+
+    /// Stop accepting new requests and cancel in-flight ones.
+    pub fn abort(&self) {
+        self.closing.cancel();
+        self.aborted.cancel();
+    }
+
+    /// Wait until the transport of this connection is dropped, if there is one.
+    pub async fn terminated(&self) {
+        if let Some(terminated) = &self.terminated {
+            terminated.cancelled().await;
+        }
+    }
+
+    /// Count a request as in flight until the returned guard is dropped, `None` once closed.
+    pub(crate) fn enter(&self) -> Option<InFlightGuard> {
+        if self.is_closed() {
+            return None;
+        }
+        Some(InFlightGuard::new(self.clone()))
+    }
+
+    pub(crate) fn aborted_token(&self) -> CancellationToken {
+        self.aborted.clone()
+    }
+
+    pub(crate) fn terminated_token(&self) -> Option<CancellationToken> {
+        self.terminated.clone()
+    }
+}
+
+pub(crate) struct InFlightGuard {
+    shutdown: Shutdown,
+}
+
+impl InFlightGuard {
+    fn new(shutdown: Shutdown) -> Self {
+        shutdown.in_flight.fetch_add(1, Ordering::AcqRel);
+        Self { shutdown }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.shutdown.in_flight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shutdown.idle.notify_waiters();
+        }
+    }
+}
+
+pub struct ShutdownLayer {
+    shutdown: Shutdown,
+}
+
+impl ShutdownLayer {
+    pub fn new(shutdown: Shutdown) -> Self {
+        Self { shutdown }
+    }
+}
+
+impl<S> Layer<S> for ShutdownLayer {
+    type Service = ShutdownService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        ShutdownService {
+            service,
+            shutdown: self.shutdown.clone(),
+        }
+    }
+}
+
+pub struct ShutdownService<S> {
+    service: S,
+    shutdown: Shutdown,
+}
+
+impl<S> Service<WrappedRequest> for ShutdownService<S>
+where
+    S: Service<WrappedRequest, Response = Response, Error = LiteError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = LiteError;
+    type Future = BoxFuture<'static, Result<Response, LiteError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // closed service stays ready, every call fails with `LiteError::Closed` instead
+        if self.shutdown.is_closed() {
+            return Poll::Ready(Ok(()));
+        }
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: WrappedRequest) -> Self::Future {
+        let Some(guard) = self.shutdown.enter() else {
+            return Box::pin(future::err(LiteError::Closed));
+        };
+        let aborted = self.shutdown.aborted_token();
+        let fut = self.service.call(request);
+        Box::pin(async move {
+            let _guard = guard;
+            tokio::select! {
+                biased;
+                _ = aborted.cancelled() => Err(LiteError::Closed),
+                response = fut => response,
+            }
+        })
+    }
 }
\ No newline at end of file
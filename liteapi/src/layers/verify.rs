@@ -0,0 +1,152 @@
+use std::task::{Context, Poll};
+
+use futures::future::{self, BoxFuture};
+use tower::{Layer, Service};
+
+use crate::tl::{request::{Request, WrappedRequest}, response::Response};
+use crate::types::LiteError;
+
+/// How thoroughly the answers of liteservers are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verification {
+    /// Answers are taken as they are, only the methods documenting it check the proofs
+    #[default]
+    None,
+    /// Every answer is checked with [`verify_response`], answers failing it are rejected. Requests whose
+    /// answers can't be checked, see [`is_verifiable`], fail with [`LiteError::Unverifiable`] without being sent.
+    Strict,
+}
+
+/// Whether [`verify_response`] can check the answers to `request`.
+///
+/// True for the requests it checks the proofs of and for `getMasterchainInfo`, `getTime`, `getVersion` and
+/// `sendMessage`, whose answers have nothing to prove and are the starting point of the checks.
+pub fn is_verifiable(request: &Request) -> bool {
+    matches!(
+        request,
+        Request::GetMasterchainInfo
+            | Request::GetMasterchainInfoExt(_)
+            | Request::GetTime
+            | Request::GetVersion
+            | Request::SendMessage(_)
+            | Request::GetBlock(_)
+            | Request::GetBlockHeader(_)
+            | Request::GetAccountState(_)
+            | Request::GetAccountStatePrunned(_)
+            | Request::GetShardInfo(_)
+            | Request::GetAllShardsInfo(_)
+            | Request::GetConfigAll(_)
+            | Request::GetConfigParams(_)
+            | Request::GetOneTransaction(_)
+            | Request::GetTransactions(_)
+    )
+}
+
+/// Check `response` against the proofs it carries and the block requested by `request`.
+///
+/// Covers block data and headers, account states, transactions, shard info and configs. Other answers fail
+/// with [`LiteError::Unverifiable`]: those with proofs this doesn't check, e.g. `runSmcMethod`, `getBlockProof`
+/// or `listBlockTransactions` with `want_proof`, and those with data which isn't proven, e.g. `getState` or
+/// `lookupBlock`. The exceptions are the answers listed in [`is_verifiable`], which are accepted as they are.
+pub fn verify_response(request: &Request, response: &Response) -> Result<(), LiteError> {
+    match (request, response) {
+        (Request::GetBlock(req), Response::BlockData(data)) => data.verify(&req.id),
+        (Request::GetBlockHeader(req), Response::BlockHeader(header)) => header.verify(&req.id),
+        (Request::GetAccountState(req) | Request::GetAccountStatePrunned(req), Response::AccountState(state)) => state.verify(&req.id, &req.account),
+        (Request::GetShardInfo(req), Response::ShardInfo(info)) => info.verify(&req.id),
+        (Request::GetAllShardsInfo(req), Response::AllShardsInfo(info)) => info.verify(&req.id),
+        (Request::GetConfigAll(req), Response::ConfigInfo(config)) => config.verify(&req.id),
+        (Request::GetConfigParams(req), Response::ConfigInfo(config)) => config.verify(&req.id),
+        (Request::GetOneTransaction(req), Response::TransactionInfo(info)) => info.verify(&req.id, &req.account, req.lt),
+        (Request::GetTransactions(req), Response::TransactionList(list)) => list.verify(req.count, req.lt, req.hash.0),
+        (Request::GetMasterchainInfo, Response::MasterchainInfo(_))
+        | (Request::GetMasterchainInfoExt(_), Response::MasterchainInfoExt(_))
+        | (Request::GetTime, Response::CurrentTime(_))
+        | (Request::GetVersion, Response::Version(_))
+        | (Request::SendMessage(_), Response::SendMsgStatus(_)) => Ok(()),
+        (request, _) if !is_verifiable(request) => Err(LiteError::Unverifiable(request.name())),
+        _ => Err(LiteError::UnexpectedMessage),
+    }
+}
+
+/// Rejects answers failing [`verify_response`], see [`Verification::Strict`].
+#[derive(Clone)]
+pub struct VerifyLayer;
+
+impl<S> Layer<S> for VerifyLayer {
+    type Service = VerifyService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        VerifyService { service }
+    }
+}
+
+#[derive(Clone)]
+pub struct VerifyService<S> {
+    service: S,
+}
+
+impl<S> Service<WrappedRequest> for VerifyService<S>
+where
+    S: Service<WrappedRequest, Response = Response, Error = LiteError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = LiteError;
+    type Future = BoxFuture<'static, Result<Response, LiteError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: WrappedRequest) -> Self::Future {
+        if !is_verifiable(&request.request) {
+            return Box::pin(future::err(LiteError::Unverifiable(request.request.name())));
+        }
+        let query = request.request.clone();
+        let fut = self.service.call(request);
+        Box::pin(async move {
+            let response = fut.await?;
+            verify_response(&query, &response)?;
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tower::ServiceExt as _;
+
+    use crate::tl::request::GetLibraries;
+    use crate::tl::response::CurrentTime;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_strict_verification() {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = sent.clone();
+        let service = VerifyLayer.layer(tower::service_fn(move |_: WrappedRequest| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            future::ok::<_, LiteError>(Response::CurrentTime(CurrentTime { now: 1 }))
+        }));
+
+        let response = service.clone().oneshot(WrappedRequest::new(Request::GetTime)).await.unwrap();
+        assert_eq!(response, Response::CurrentTime(CurrentTime { now: 1 }));
+        // a mismatching answer isn't accepted unchecked
+        let request = WrappedRequest::new(Request::GetVersion);
+        assert!(matches!(service.clone().oneshot(request).await, Err(LiteError::UnexpectedMessage)));
+        assert_eq!(sent.load(Ordering::Relaxed), 2);
+
+        // requests whose answers can't be checked aren't sent
+        for request in [Request::GetLibraries(GetLibraries { library_list: vec![] }), Request::Raw(vec![1, 2, 3])] {
+            let name = request.name();
+            let result = service.clone().oneshot(WrappedRequest::new(request)).await;
+            assert!(matches!(result, Err(LiteError::Unverifiable(n)) if n == name));
+        }
+        assert_eq!(sent.load(Ordering::Relaxed), 2);
+    }
+}
@@ -1,6 +1,28 @@
 pub mod tl;
 pub mod types;
+pub mod time;
+pub mod cell;
+pub mod tlb;
 pub mod peer;
+pub mod codec;
+#[cfg(feature = "udp")]
+pub mod udp;
 pub mod layers;
 pub mod client;
-pub mod server;
\ No newline at end of file
+pub mod contract;
+pub mod cache;
+pub mod correlation;
+pub mod handle;
+pub mod pool;
+pub mod poll;
+pub mod monitor;
+pub mod trace;
+pub mod indexer;
+pub mod sink;
+#[cfg(feature = "emulator")]
+pub mod emulator;
+#[cfg(feature = "crypto")]
+pub mod mnemonic;
+pub mod server;
+pub mod proxy;
+pub mod wallet;
\ No newline at end of file
@@ -0,0 +1,142 @@
+//! TON mnemonics, enabled by the `crypto` feature.
+//!
+//! A mnemonic of 24 words, optionally protected by a password, derives the ed25519 key of a wallet the same way
+//! TON wallet apps do. Unlike BIP-39 there's no checksum word: a mnemonic is valid when a hash of it starts
+//! with a zero byte. Generating mnemonics needs the BIP-39 word list, which isn't bundled.
+
+use std::fmt;
+use std::str::FromStr;
+
+use adnl::crypto::{KeyPair, SecretKey};
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use thiserror::Error;
+
+const PBKDF_ITERATIONS: u32 = 100_000;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MnemonicError {
+    #[error("Mnemonic has {0} words instead of 24")]
+    WordCount(usize),
+    #[error("Invalid mnemonic word {0:?}")]
+    InvalidWord(String),
+    /// The words or the password are wrong, or the mnemonic needs a password
+    #[error("Invalid mnemonic or password")]
+    Invalid,
+}
+
+/// Validated mnemonic with its password.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Mnemonic {
+    words: Vec<String>,
+    password: String,
+}
+
+impl Mnemonic {
+    pub const WORDS: usize = 24;
+
+    /// Mnemonic protected by `password`, an empty password for mnemonics without one.
+    pub fn with_password(phrase: &str, password: &str) -> Result<Self, MnemonicError> {
+        let words: Vec<String> = phrase.split_whitespace().map(str::to_lowercase).collect();
+        if words.len() != Self::WORDS {
+            return Err(MnemonicError::WordCount(words.len()));
+        }
+        if let Some(word) = words.iter().find(|word| !word.bytes().all(|c| c.is_ascii_lowercase())) {
+            return Err(MnemonicError::InvalidWord(word.clone()));
+        }
+        if !password.is_empty() {
+            // a mnemonic created with a password is marked as such, and isn't valid without one
+            let passless = entropy(&words, "");
+            if pbkdf2(&passless, "TON fast seed version", 1)[0] != 1 || is_basic_seed(&passless) {
+                return Err(MnemonicError::Invalid);
+            }
+        }
+        if !is_basic_seed(&entropy(&words, password)) {
+            return Err(MnemonicError::Invalid);
+        }
+        Ok(Self { words, password: password.to_owned() })
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// Ed25519 seed of the wallet key.
+    pub fn secret_key(&self) -> [u8; 32] {
+        let seed = pbkdf2(&entropy(&self.words, &self.password), "TON default seed", PBKDF_ITERATIONS);
+        seed[..32].try_into().unwrap()
+    }
+
+    /// Key pair signing the messages of the wallet.
+    pub fn keypair(&self) -> KeyPair {
+        KeyPair::from(&SecretKey::from_bytes(self.secret_key()))
+    }
+
+    /// Public key of the wallet, as stored in its data and returned by its `get_public_key`.
+    pub fn public_key(&self) -> [u8; 32] {
+        self.keypair().public_key.to_bytes()
+    }
+}
+
+/// Mnemonic without a password, words separated by whitespace.
+impl FromStr for Mnemonic {
+    type Err = MnemonicError;
+
+    fn from_str(phrase: &str) -> Result<Self, Self::Err> {
+        Self::with_password(phrase, "")
+    }
+}
+
+/// Doesn't print the words.
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mnemonic").finish_non_exhaustive()
+    }
+}
+
+fn entropy(words: &[String], password: &str) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(words.join(" ").as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(password.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+fn pbkdf2(entropy: &[u8; 64], salt: &str, rounds: u32) -> [u8; 64] {
+    let mut seed = [0; 64];
+    pbkdf2::pbkdf2_hmac::<Sha512>(entropy, salt.as_bytes(), rounds, &mut seed);
+    seed
+}
+
+fn is_basic_seed(entropy: &[u8; 64]) -> bool {
+    pbkdf2(entropy, "TON seed version", (PBKDF_ITERATIONS / 256).max(1))[0] == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vector of ton-crypto
+    const PHRASE: &str = "dose ice enrich trigger test dove century still betray gas diet dune use other base gym mad law immense village world example praise game";
+
+    #[test]
+    fn test_keypair() -> Result<(), MnemonicError> {
+        let mnemonic: Mnemonic = PHRASE.parse()?;
+        assert_eq!(hex::encode(mnemonic.secret_key()), "119dcf2840a3d56521d260b2f125eedc0d4f3795b9e627269a4b5a6dca8257bd");
+        assert_eq!(hex::encode(mnemonic.public_key()), "c04ad1885c127fe863abb00752fa844e6439bb04f264d70de7cea580b32637ab");
+        // whitespace and case don't matter
+        let spaced = format!(" {} ", PHRASE.to_uppercase().replace(' ', "   \n"));
+        assert_eq!(spaced.parse::<Mnemonic>()?, mnemonic);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() {
+        let words: Vec<&str> = PHRASE.split(' ').collect();
+        assert_eq!(words[..12].join(" ").parse::<Mnemonic>(), Err(MnemonicError::WordCount(12)));
+        assert_eq!(PHRASE.replace("gym", "gym1").parse::<Mnemonic>(), Err(MnemonicError::InvalidWord("gym1".to_owned())));
+        let mut swapped = words.clone();
+        swapped.swap(0, 1);
+        assert_eq!(swapped.join(" ").parse::<Mnemonic>(), Err(MnemonicError::Invalid));
+        // the mnemonic isn't marked as one with a password
+        assert_eq!(Mnemonic::with_password(PHRASE, "secret"), Err(MnemonicError::Invalid));
+    }
+}
@@ -0,0 +1,358 @@
+//! Monitoring of on-chain state relevant to node operators.
+//!
+//! Monitors are polled periodically with a [`LiteClient`] and report what changed since the previous poll:
+//! [`ValidatorMonitor`] tracks validator set membership (config param 34) and whether a validator
+//! signed the key blocks proven since then, [`ConfigWatcher`] reports changes of selected config params.
+//! The polling loop can be paced with a [`PollPolicy`](crate::poll::PollPolicy).
+
+use std::collections::HashMap;
+
+use crate::cell::{ArcCell, Cell};
+use crate::client::LiteClient;
+use crate::tl::common::{BlockIdExt, BlockLink};
+use crate::tlb::{node_id_short, BlockInfo, ValidatorSet};
+use crate::types::{ConfigMode, LiteError};
+
+type Result<T> = std::result::Result<T, LiteError>;
+
+/// Config param with the current validator set.
+const CURRENT_VALIDATORS: u32 = 34;
+
+/// Change observed by [`ValidatorMonitor::poll`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidatorEvent {
+    /// The validator appeared in the current validator set
+    Elected { utime_since: u32, utime_until: u32, weight: u64 },
+    /// The validator is no longer in the current validator set
+    Removed,
+    /// The validator's signature is present on the masterchain block
+    Signed { block: BlockIdExt },
+    /// The masterchain block was signed while the validator was elected, but without its signature
+    Missed { block: BlockIdExt },
+}
+
+/// Participation monitor for the validator with the given public key.
+#[derive(Debug, Clone)]
+pub struct ValidatorMonitor {
+    public_key: [u8; 32],
+    node_id: [u8; 32],
+    elected: bool,
+    last_block: Option<BlockIdExt>,
+}
+
+impl ValidatorMonitor {
+    pub fn new(public_key: [u8; 32]) -> Self {
+        Self { public_key, node_id: node_id_short(&public_key), elected: false, last_block: None }
+    }
+
+    pub fn public_key(&self) -> &[u8; 32] {
+        &self.public_key
+    }
+
+    /// Whether the validator was in the current validator set at the last poll.
+    pub fn is_elected(&self) -> bool {
+        self.elected
+    }
+
+    /// Check the last masterchain block and return events since the previous poll.
+    ///
+    /// Signatures are taken from the forward links of a block proof from the previously seen block,
+    /// so the first poll only reports the validator set membership.
+    pub async fn poll(&mut self, client: &mut LiteClient) -> Result<Vec<ValidatorEvent>> {
+        let mut events = Vec::new();
+        let last = client.get_masterchain_info().await?.last;
+        if self.last_block.as_ref() == Some(&last) {
+            return Ok(events);
+        }
+
+        let config = client.get_config_params(last.clone(), vec![CURRENT_VALIDATORS as i32], ConfigMode::default()).await?.config()?;
+        let validators = match config.param(CURRENT_VALIDATORS)? {
+            Some(cell) => Some(ValidatorSet::load(&cell)?),
+            None => None,
+        };
+        let entry = validators.as_ref().and_then(|set| set.find(&self.public_key).map(|v| (set, v)));
+        match entry {
+            Some((set, validator)) if !self.elected => events.push(ValidatorEvent::Elected {
+                utime_since: set.utime_since,
+                utime_until: set.utime_until,
+                weight: validator.weight,
+            }),
+            None if self.elected => events.push(ValidatorEvent::Removed),
+            _ => {}
+        }
+        let was_elected = self.elected;
+        self.elected = entry.is_some();
+
+        if let Some(mut known) = self.last_block.replace(last.clone()) {
+            while known != last {
+                let proof = client.get_block_proof(known, Some(last.clone()), false, false).await?;
+                for step in &proof.steps {
+                    if let BlockLink::BlockLinkForward { to, signatures, .. } = step {
+                        if signatures.signatures.iter().any(|s| s.node_id_short.0 == self.node_id) {
+                            events.push(ValidatorEvent::Signed { block: to.clone() });
+                        } else if was_elected || self.elected {
+                            events.push(ValidatorEvent::Missed { block: to.clone() });
+                        }
+                    }
+                }
+                if proof.complete || proof.to == proof.from {
+                    break;
+                }
+                known = proof.to;
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Change of a config param observed by [`ConfigWatcher::poll`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub param: u32,
+    /// Seqno of the key block which introduced the change
+    pub key_block_seqno: u32,
+    /// Previous value, `None` if the param wasn't set
+    pub old: Option<ArcCell>,
+    /// New value, `None` if the param was removed
+    pub new: Option<ArcCell>,
+}
+
+/// Watcher of selected config params, which are checked whenever a new key block appears.
+#[derive(Debug, Clone)]
+pub struct ConfigWatcher {
+    params: Vec<u32>,
+    key_block_seqno: Option<u32>,
+    values: HashMap<u32, Option<ArcCell>>,
+}
+
+impl ConfigWatcher {
+    /// Gas prices (20, 21), current validator set (34) and size limits (43).
+    pub const DEFAULT_PARAMS: [u32; 4] = [20, 21, 34, 43];
+
+    pub fn new(params: impl IntoIterator<Item = u32>) -> Self {
+        Self { params: params.into_iter().collect(), key_block_seqno: None, values: HashMap::new() }
+    }
+
+    pub fn params(&self) -> &[u32] {
+        &self.params
+    }
+
+    /// Current value of a watched param, known after the first poll.
+    pub fn value(&self, param: u32) -> Option<&ArcCell> {
+        self.values.get(&param)?.as_ref()
+    }
+
+    /// Check the last key block and return changed params.
+    ///
+    /// The first poll only records the current values and returns no changes.
+    pub async fn poll(&mut self, client: &mut LiteClient) -> Result<Vec<ConfigChange>> {
+        let last = client.get_masterchain_info().await?.last;
+        let header = client.get_block_header(last.clone(), false, false, false, false, false).await?;
+        let key_block_seqno = BlockInfo::from_proof(&*Cell::from_boc(&header)?)?.last_key_block_seqno();
+        if self.key_block_seqno == Some(key_block_seqno) {
+            return Ok(Vec::new());
+        }
+
+        let params = self.params.iter().map(|p| *p as i32).collect();
+        let config = client.get_config_params(last, params, ConfigMode::default()).await?.config()?;
+        let mut changes = Vec::new();
+        for param in &self.params {
+            let new = config.param(*param)?;
+            let Some(old) = self.values.insert(*param, new.clone()) else {
+                continue;
+            };
+            if old.as_ref().map(|c| c.repr_hash()) != new.as_ref().map(|c| c.repr_hash()) {
+                changes.push(ConfigChange { param: *param, key_block_seqno, old, new });
+            }
+        }
+        self.key_block_seqno = Some(key_block_seqno);
+        Ok(changes)
+    }
+}
+
+impl Default for ConfigWatcher {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PARAMS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::future;
+
+    use super::*;
+    use crate::cell::{CellBuilder, CellError};
+    use crate::tl::common::{Int256, Signature, SignatureSet, ZeroStateIdExt};
+    use crate::tl::request::{Request, WrappedRequest};
+    use crate::tl::response::{BlockHeader, ConfigInfo, MasterchainInfo, PartialBlockProof, Response};
+    use crate::tlb::build_hashmap;
+
+    /// State of the fake liteserver, changed between polls.
+    #[derive(Debug, Clone, Default)]
+    struct Chain {
+        seqno: u32,
+        key_block_seqno: u32,
+        /// Public keys of the current validator set
+        validators: Vec<[u8; 32]>,
+        /// Node ids signing the blocks
+        signers: Vec<[u8; 32]>,
+        /// Value of config param 20
+        gas_price: u64,
+    }
+
+    fn mc(seqno: u32) -> BlockIdExt {
+        BlockIdExt { workchain: -1, shard: 1 << 63, seqno, root_hash: Int256([seqno as u8; 32]), file_hash: Int256([0; 32]) }
+    }
+
+    impl Chain {
+        fn answer(&self, request: Request) -> std::result::Result<Response, CellError> {
+            Ok(match request {
+                Request::GetMasterchainInfo => Response::MasterchainInfo(MasterchainInfo {
+                    last: mc(self.seqno),
+                    state_root_hash: Int256([0; 32]),
+                    init: ZeroStateIdExt { workchain: -1, root_hash: Int256([0; 32]), file_hash: Int256([0; 32]) },
+                }),
+                Request::GetConfigParams(req) => Response::ConfigInfo(ConfigInfo {
+                    mode: (),
+                    id: req.id,
+                    state_proof: Vec::new(),
+                    config_proof: self.state()?.to_boc(),
+                    with_state_root: None,
+                    with_libraries: None,
+                    with_state_extra_root: None,
+                    with_shard_hashes: None,
+                    with_validator_set: None,
+                    with_special_smc: None,
+                    with_accounts_root: None,
+                    with_prev_blocks: None,
+                    with_workchain_info: None,
+                    with_capabilities: None,
+                    extract_from_key_block: None,
+                }),
+                Request::GetBlockHeader(req) => Response::BlockHeader(BlockHeader {
+                    id: req.id,
+                    mode: (),
+                    with_state_update: None,
+                    with_value_flow: None,
+                    with_extra: None,
+                    with_shard_hashes: None,
+                    with_prev_blk_signatures: None,
+                    header_proof: self.header()?.to_boc(),
+                }),
+                Request::GetBlockProof(req) => {
+                    let to = req.target_block.unwrap();
+                    let signatures: Vec<_> = self.signers.iter()
+                        .map(|node_id| Signature { node_id_short: Int256(*node_id), signature: vec![0; 64] })
+                        .collect();
+                    let steps = (req.known_block.seqno..to.seqno)
+                        .map(|seqno| BlockLink::BlockLinkForward {
+                            to_key_block: false,
+                            from: mc(seqno),
+                            to: mc(seqno + 1),
+                            dest_proof: Vec::new(),
+                            config_proof: Vec::new(),
+                            signatures: SignatureSet { validator_set_hash: 0, catchain_seqno: 0, signatures: signatures.clone() },
+                        })
+                        .collect();
+                    Response::PartialBlockProof(PartialBlockProof { complete: true, from: req.known_block, to, steps, unsupported: None })
+                }
+                _ => Response::Error(crate::tl::response::Error { code: 0, message: "unexpected".into() }),
+            })
+        }
+
+        /// Masterchain state with the validator set and the gas price in its config.
+        fn state(&self) -> std::result::Result<ArcCell, CellError> {
+            let descr = |key: &[u8; 32]| CellBuilder::new().store_u8(0x53)?.store_u32(0x8e81278a)?.store_u256(key)?.store_u64(10)?.build();
+            let entries = self.validators.iter().enumerate()
+                .map(|(i, key)| Ok(((i as u16).to_be_bytes().to_vec(), descr(key)?)))
+                .collect::<std::result::Result<Vec<_>, CellError>>()?;
+            let mut validators = CellBuilder::new();
+            validators.store_u8(0x12)?.store_u32(1000)?.store_u32(2000)?.store_uint(16, entries.len() as u64)?.store_uint(16, 1)?;
+            validators.store_u64(10 * entries.len() as u64)?.store_maybe_reference(build_hashmap(&entries, 16)?)?;
+            let gas_price = CellBuilder::new().store_u64(self.gas_price)?.build()?;
+            let params = [(20u32, gas_price), (CURRENT_VALIDATORS, validators.build()?)]
+                .map(|(param, value)| Ok((param.to_be_bytes().to_vec(), CellBuilder::new().store_reference(value)?.build()?)))
+                .into_iter()
+                .collect::<std::result::Result<Vec<_>, CellError>>()?;
+            let mut extra = CellBuilder::new();
+            extra.store_uint(16, 0xcc26)?.store_bit(false)?.store_u256(&[0x55; 32])?.store_maybe_reference(build_hashmap(&params, 32)?)?;
+            let empty = CellBuilder::new().build()?;
+            let mut state = CellBuilder::new();
+            state.store_u32(0x9023afe2)?.store_u32(0)?.store_uint(8, 0)?.store_int(32, -1)?.store_u64(1 << 63)?;
+            state.store_u32(self.seqno)?.store_u32(0)?.store_u32(0)?.store_u64(0)?.store_u32(0)?.store_bit(false)?;
+            state.store_reference(empty.clone())?.store_reference(empty.clone())?.store_reference(empty)?;
+            state.store_maybe_reference(Some(extra.build()?))?;
+            state.build()
+        }
+
+        /// Block with the last key block seqno in its header.
+        fn header(&self) -> std::result::Result<ArcCell, CellError> {
+            let mut info = CellBuilder::new();
+            info.store_u32(0x9bc7a987)?.store_u32(0)?.store_uint(8, 0)?.store_u8(0)?;
+            info.store_u32(self.seqno)?.store_u32(0)?.store_uint(8, 0)?.store_int(32, -1)?.store_u64(0)?;
+            info.store_u32(0)?.store_u64(0)?.store_u64(0)?;
+            info.store_u32(0)?.store_u32(0)?.store_u32(0)?.store_u32(self.key_block_seqno)?;
+            let mut block = CellBuilder::new();
+            block.store_u32(0x11ef55aa)?.store_u32(0)?.store_reference(info.build()?)?;
+            block.build()
+        }
+    }
+
+    /// Client of a fake liteserver answering from the current `chain`.
+    fn client(chain: &Arc<Mutex<Chain>>) -> LiteClient {
+        let chain = chain.clone();
+        LiteClient::new(tower::service_fn(move |request: WrappedRequest| {
+            let answer = chain.lock().unwrap().answer(request.request).map_err(LiteError::from);
+            future::ready(answer)
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_validator_monitor() -> Result<()> {
+        let key = [1; 32];
+        let node_id = node_id_short(&key);
+        let chain = Arc::new(Mutex::new(Chain { seqno: 1, validators: vec![key, [2; 32]], ..Default::default() }));
+        let mut client = client(&chain);
+        let mut monitor = ValidatorMonitor::new(key);
+
+        let elected = ValidatorEvent::Elected { utime_since: 1000, utime_until: 2000, weight: 10 };
+        assert_eq!(monitor.poll(&mut client).await?, [elected]);
+        assert!(monitor.is_elected());
+        // nothing happens until a new masterchain block
+        assert_eq!(monitor.poll(&mut client).await?, []);
+
+        chain.lock().unwrap().seqno = 3;
+        chain.lock().unwrap().signers = vec![[9; 32], node_id];
+        assert_eq!(monitor.poll(&mut client).await?, [ValidatorEvent::Signed { block: mc(2) }, ValidatorEvent::Signed { block: mc(3) }]);
+
+        // blocks signed without the validator count as missed in the round it leaves the set
+        *chain.lock().unwrap() = Chain { seqno: 4, validators: vec![[2; 32]], signers: vec![[9; 32]], ..Default::default() };
+        assert_eq!(monitor.poll(&mut client).await?, [ValidatorEvent::Removed, ValidatorEvent::Missed { block: mc(4) }]);
+        assert!(!monitor.is_elected());
+        chain.lock().unwrap().seqno = 5;
+        assert_eq!(monitor.poll(&mut client).await?, []);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_watcher() -> Result<()> {
+        let chain = Arc::new(Mutex::new(Chain { seqno: 10, key_block_seqno: 8, gas_price: 1000, ..Default::default() }));
+        let mut client = client(&chain);
+        let mut watcher = ConfigWatcher::new([20, 34, 43]);
+        assert_eq!(watcher.poll(&mut client).await?, []);
+        let old = watcher.value(20).cloned();
+        assert!(old.is_some() && watcher.value(43).is_none());
+
+        // the config is only read again after a new key block
+        chain.lock().unwrap().gas_price = 2000;
+        chain.lock().unwrap().seqno = 11;
+        assert_eq!(watcher.poll(&mut client).await?, []);
+        chain.lock().unwrap().key_block_seqno = 11;
+        let changes = watcher.poll(&mut client).await?;
+        assert_eq!(changes, [ConfigChange { param: 20, key_block_seqno: 11, old, new: watcher.value(20).cloned() }]);
+        assert_eq!(watcher.value(20).unwrap().parser()?.load_u64()?, 2000);
+        Ok(())
+    }
+}
@@ -1,60 +1,294 @@
-use std::task::Poll;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{ready, Poll};
 
-use adnl::AdnlError;
 use futures::{Sink, Stream};
 use pin_project::pin_project;
-use rand::random;
+use rand::{CryptoRng, RngCore};
+use tl_proto::TlRead as _;
 use tokio_tower::multiplex::TagStore;
 use tokio_util::bytes::Bytes;
+use tokio_util::sync::{DropGuard, WaitForCancellationFutureOwned};
 
-use crate::{tl::{adnl::Message, common::Int256}, types::LiteError};
+use crate::{layers::Shutdown, tl::{adnl::Message, common::Int256, request::Request}, types::LiteError};
+
+/// Largest frame accepted by default.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 4 << 20;
+/// Largest frame the ADNL transport accepts at all, it rejects longer length prefixes before allocating the frame.
+pub const TRANSPORT_MAX_FRAME_LEN: usize = (1 << 24) - 64;
+
+/// Random number generator usable for query ids and keys, e.g. a seeded `StdRng` for reproducible tests.
+pub trait LiteRng: RngCore + CryptoRng + Send {}
+
+impl<T> LiteRng for T where T: RngCore + CryptoRng + Send {}
+
+/// Random number generator shared between connections, see [`LitePeer::with_rng`].
+pub type SharedRng = Arc<Mutex<dyn LiteRng>>;
+
+/// Slot for an answer exactly as received, see [`LiteClient::query_with_raw`](crate::client::LiteClient::query_with_raw).
+pub(crate) type RawAnswer = Arc<Mutex<Option<Vec<u8>>>>;
+
+/// Constructor of `adnl.message.answer`, followed by the query id in an answer frame.
+const ANSWER_ID: [u8; 4] = 0x0fac8416u32.to_le_bytes();
+/// Bytes at the start of a frame identifying the query it answers.
+pub(crate) const ANSWER_PREFIX_LEN: usize = 4 + 32;
+
+/// Limits on the length of received frames, see [`LitePeer::with_max_frame_len`] and
+/// [`LitePeer::with_max_state_frame_len`].
+///
+/// Clones share the `getState` queries in flight, so that a [`LiteCodec`](crate::codec::LiteCodec) under the peer
+/// rejects a frame by its length prefix, before the frame is buffered.
+#[derive(Debug, Clone)]
+pub struct FrameLimits {
+    max_frame_len: usize,
+    max_state_frame_len: usize,
+    /// Queries downloading a state, whose answers may be longer than `max_frame_len`, with the token their caller
+    /// holds while waiting for the answer, if any
+    state_queries: Arc<Mutex<HashMap<Int256, Option<Weak<()>>>>>,
+}
+
+impl FrameLimits {
+    pub fn new(max_frame_len: usize, max_state_frame_len: usize) -> Self {
+        Self { max_frame_len, max_state_frame_len, state_queries: Default::default() }
+    }
+
+    /// Accept an answer to the `getState` query `query_id` up to the state limit while `caller` is alive.
+    pub(crate) fn expect_state_answer(&self, query_id: Int256, caller: Option<Weak<()>>) {
+        self.state_queries.lock().unwrap().insert(query_id, caller);
+    }
+
+    /// Whether a frame with `len` bytes of payload is accepted, `None` if it depends on the query it answers,
+    /// see [`FrameLimits::accepts_answer`].
+    pub(crate) fn accepts(&self, len: usize) -> Option<bool> {
+        if len <= self.max_frame_len {
+            return Some(true);
+        }
+        if len > self.max_state_frame_len || len < ANSWER_PREFIX_LEN {
+            return Some(false);
+        }
+        let mut state_queries = self.state_queries.lock().unwrap();
+        // queries whose caller gave up, e.g. on a timeout, are never answered to anyone
+        state_queries.retain(|_, caller| caller.as_ref().is_none_or(|caller| caller.strong_count() > 0));
+        if state_queries.is_empty() {
+            return Some(false);
+        }
+        None
+    }
+
+    /// Whether a frame starting with `prefix` answers a `getState` query in flight, so the state limit applies to it.
+    pub(crate) fn accepts_answer(&self, prefix: &[u8]) -> bool {
+        let Some(query_id) = prefix.strip_prefix(&ANSWER_ID).and_then(|id| id.get(..32)) else {
+            return false;
+        };
+        let query_id = Int256(query_id.try_into().unwrap());
+        let state_queries = self.state_queries.lock().unwrap();
+        state_queries.get(&query_id).is_some_and(|caller| caller.as_ref().is_none_or(|caller| caller.strong_count() > 0))
+    }
+
+    fn check(&self, frame: &[u8]) -> Result<(), LiteError> {
+        match self.accepts(frame.len()) {
+            Some(true) => Ok(()),
+            None if self.accepts_answer(frame) => Ok(()),
+            _ => Err(LiteError::FrameTooLong(Some(frame.len()))),
+        }
+    }
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FRAME_LEN, TRANSPORT_MAX_FRAME_LEN)
+    }
+}
 
 #[pin_project]
 pub struct LitePeer<T> {
     #[pin]
     inner: T,
+    aborted: Option<Pin<Box<WaitForCancellationFutureOwned>>>,
+    aborting: bool,
+    _terminated: Option<DropGuard>,
+    limits: FrameLimits,
+    /// Queries whose answer is also kept as received
+    raw_queries: HashMap<Int256, RawAnswer>,
+    rng: Option<SharedRng>,
 }
 
 impl<T> LitePeer<T> {
     pub fn new(inner: T) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            aborted: None,
+            aborting: false,
+            _terminated: None,
+            limits: FrameLimits::default(),
+            raw_queries: HashMap::new(),
+            rng: None,
+        }
+    }
+
+    /// Peer which closes `inner` once `shutdown` is aborted and reports when it's dropped.
+    pub fn with_shutdown(inner: T, shutdown: &Shutdown) -> Self {
+        Self {
+            inner,
+            aborted: Some(Box::pin(shutdown.aborted_token().cancelled_owned())),
+            aborting: false,
+            _terminated: shutdown.terminated_token().map(|token| token.drop_guard()),
+            limits: FrameLimits::default(),
+            raw_queries: HashMap::new(),
+            rng: None,
+        }
+    }
+
+    /// Reject sent and received frames longer than `max_frame_len` bytes with [`LiteError::FrameTooLong`].
+    ///
+    /// Limits above [`TRANSPORT_MAX_FRAME_LEN`] have no effect, since the transport rejects such frames anyway.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.limits.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Accept answers to `getState` queries up to `max_state_frame_len` bytes, [`TRANSPORT_MAX_FRAME_LEN`] by default.
+    /// Other frames are held to the limit of [`LitePeer::with_max_frame_len`].
+    pub fn with_max_state_frame_len(mut self, max_state_frame_len: usize) -> Self {
+        self.limits.max_state_frame_len = max_state_frame_len;
+        self
+    }
+
+    /// Share `limits` with the transport, e.g. a [`LiteCodec`](crate::codec::LiteCodec) which checks them before
+    /// buffering a frame. Replaces the limits set with [`LitePeer::with_max_frame_len`] and
+    /// [`LitePeer::with_max_state_frame_len`].
+    pub fn with_frame_limits(mut self, limits: FrameLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Take query ids and ping ids from `rng` instead of the thread-local generator.
+    pub fn with_rng(mut self, rng: SharedRng) -> Self {
+        self.rng = Some(rng);
+        self
+    }
+
+    fn fill_random(&self, dest: &mut [u8]) {
+        match &self.rng {
+            Some(rng) => rng.lock().unwrap().fill_bytes(dest),
+            None => rand::thread_rng().fill_bytes(dest),
+        }
+    }
+
+    fn random_query_id(&self) -> Int256 {
+        let mut query_id = Int256::default();
+        self.fill_random(&mut query_id.0);
+        query_id
+    }
+
+    fn random_id(&self) -> u64 {
+        let mut random_id = [0; 8];
+        self.fill_random(&mut random_id);
+        u64::from_le_bytes(random_id)
+    }
+}
+
+impl<T> From<tokio_tower::Error<LitePeer<T>, Message>> for LiteError
+where
+    LitePeer<T>: Sink<Message, Error = LiteError> + Stream<Item = Result<Message, LiteError>>,
+{
+    fn from(error: tokio_tower::Error<LitePeer<T>, Message>) -> Self {
+        match error {
+            tokio_tower::Error::BrokenTransportSend(e) | tokio_tower::Error::BrokenTransportRecv(Some(e)) => e,
+            // pending requests only learn that the worker stopped, not why
+            tokio_tower::Error::BrokenTransportRecv(None) | tokio_tower::Error::ClientDropped => LiteError::ConnectionClosed { during_query: true },
+            tokio_tower::Error::TransportFull => LiteError::TooManyQueries,
+            tokio_tower::Error::Desynchronized => LiteError::UnexpectedMessage,
+        }
+    }
+}
+
+impl<T, E> LitePeer<T> where T: Sink<Bytes, Error = E>, E: Into<LiteError> {
+    /// Close the transport if shutdown was aborted, `Poll::Ready` means the peer is done.
+    fn poll_aborted(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        let this = self.project();
+        if !*this.aborting {
+            match this.aborted {
+                Some(aborted) => ready!(aborted.as_mut().poll(cx)),
+                None => return Poll::Pending,
+            }
+            *this.aborting = true;
+        }
+        if let Err(e) = ready!(this.inner.poll_close(cx)) {
+            log::debug!("Error while closing transport: {:?}", e.into());
+        }
+        Poll::Ready(())
     }
 }
 
-impl<T> Sink<Message> for LitePeer<T> where T: Sink<Bytes, Error = AdnlError> {
+impl<T, E> Sink<Message> for LitePeer<T> where T: Sink<Bytes, Error = E>, E: Into<LiteError> {
     type Error = LiteError;
     
-    fn poll_ready(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
-        self.project().inner.poll_ready(cx).map_err(|e| LiteError::AdnlError(e.into()))
+    fn poll_ready(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+        if self.as_mut().poll_aborted(cx).is_ready() {
+            return Poll::Ready(Err(LiteError::Closed));
+        }
+        self.project().inner.poll_ready(cx).map_err(Into::into)
     }
     
     fn start_send(self: std::pin::Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
         log::debug!("Sending TL message: {:?}", item);
-        let data = tl_proto::serialize(item).into();
-        self.project().inner.start_send(data).map_err(|e| LiteError::AdnlError(e.into()))
+        let data: Bytes = tl_proto::serialize(&item).into();
+        if data.len() > self.limits.max_frame_len {
+            return Err(LiteError::FrameTooLong(Some(data.len())));
+        }
+        let this = self.project();
+        if let Message::Query { query_id, query } = &item {
+            if let Request::GetState(_) = query.wrapped_request.request {
+                this.limits.expect_state_answer(query_id.clone(), query.wrapped_request.context.caller.clone());
+            }
+            // slots nobody waits for anymore
+            this.raw_queries.retain(|_, raw| Arc::strong_count(raw) > 1);
+            if let Some(raw) = &query.wrapped_request.context.raw_answer {
+                this.raw_queries.insert(query_id.clone(), raw.clone());
+            }
+        }
+        this.inner.start_send(data).map_err(Into::into)
     }
     
     fn poll_flush(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
-        self.project().inner.poll_flush(cx).map_err(|e| LiteError::AdnlError(e.into()))
+        self.project().inner.poll_flush(cx).map_err(Into::into)
     }
     
     fn poll_close(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
-        self.project().inner.poll_close(cx).map_err(|e| LiteError::AdnlError(e.into()))
+        self.project().inner.poll_close(cx).map_err(Into::into)
     }
 }
 
-impl<T> Stream for LitePeer<T> where T: Stream<Item = Result<Bytes, AdnlError>> {
+impl<T, E> Stream for LitePeer<T> where T: Stream<Item = Result<Bytes, E>> + Sink<Bytes, Error = E>, E: Into<LiteError> {
     type Item = Result<Message, LiteError>;
 
-    fn poll_next(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Self::Item>> {
-        let inner = self.project().inner.poll_next(cx);
-        match inner {
+    fn poll_next(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Self::Item>> {
+        if self.as_mut().poll_aborted(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+        let this = self.project();
+        match this.inner.poll_next(cx) {
             Poll::Ready(Some(Ok(bytes))) => {
+                // a `LiteCodec` checked the frame already, other transports only hand out whole frames
+                if let Err(e) = this.limits.check(&bytes) {
+                    return Poll::Ready(Some(Err(e)));
+                }
                 let decoded = tl_proto::deserialize(&bytes);
                 log::debug!("Decoded to TL message:\n{:?}\n{:?}", bytes, decoded);
+                if let Ok(Message::Answer { query_id, .. }) = &decoded {
+                    this.limits.state_queries.lock().unwrap().remove(query_id);
+                    if let Some(raw) = this.raw_queries.remove(query_id) {
+                        // answer:bytes after the constructor and the query id
+                        let mut offset = ANSWER_PREFIX_LEN;
+                        *raw.lock().unwrap() = <&[u8]>::read_from(&bytes, &mut offset).ok().map(<[u8]>::to_vec);
+                    }
+                }
                 Poll::Ready(Some(decoded.map_err(|e| LiteError::TlError(e))))
             },
-            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(LiteError::AdnlError(e.into())))),
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e.into()))),
             Poll::Ready(None) => Poll::Ready(None),
             Poll::Pending => Poll::Pending,
         }
@@ -72,10 +306,10 @@ impl<T> TagStore<Message, Message> for LitePeer<T> {
 
     fn assign_tag(self: std::pin::Pin<&mut Self>, r: &mut Message) -> Self::Tag {
         match r {
-            Message::Answer { query_id, .. } => { *query_id = Int256::random(); LiteTag::Int256(query_id.clone()) },
-            Message::Query { query_id, .. } => { *query_id = Int256::random(); LiteTag::Int256(query_id.clone()) },
-            Message::Ping { random_id } => { *random_id = random(); LiteTag::Long(random_id.clone()) },
-            Message::Pong { random_id } => { *random_id = random(); LiteTag::Long(random_id.clone()) },
+            Message::Answer { query_id, .. } => { *query_id = self.random_query_id(); LiteTag::Int256(query_id.clone()) },
+            Message::Query { query_id, .. } => { *query_id = self.random_query_id(); LiteTag::Int256(query_id.clone()) },
+            Message::Ping { random_id } => { *random_id = self.random_id(); LiteTag::Long(random_id.clone()) },
+            Message::Pong { random_id } => { *random_id = self.random_id(); LiteTag::Long(random_id.clone()) },
         }
     }
 
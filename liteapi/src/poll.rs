@@ -0,0 +1,146 @@
+//! Pacing of the helpers which poll liteservers for new data.
+//!
+//! [`PollPolicy`] is taken by [`Indexer`](crate::indexer::Indexer) and
+//! [`LiteClient::find_transaction_by_message`](crate::client::LiteClient::find_transaction_by_message),
+//! and can pace own loops around monitors such as [`ValidatorMonitor`](crate::monitor::ValidatorMonitor):
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use ton_liteapi::{client::LiteClient, monitor::ConfigWatcher, poll::PollPolicy, types::LiteError};
+//! # async fn f(client: &mut LiteClient) -> Result<(), LiteError> {
+//! let policy = PollPolicy::new(Duration::from_secs(5))
+//!     .with_jitter(Duration::from_secs(1))
+//!     .with_error_backoff(Duration::from_secs(60));
+//! let mut watcher = ConfigWatcher::default();
+//! let mut errors = 0;
+//! loop {
+//!     match watcher.poll(client).await {
+//!         Ok(changes) => {
+//!             errors = 0;
+//!             println!("{:?}", changes);
+//!             policy.wait().await;
+//!         }
+//!         Err(e) => policy.backoff(&mut errors, e).await?,
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::fmt::{Debug, Display};
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Interval between polls, random jitter added to it, retries of failed polls and the lag at which
+/// a watcher warns that it doesn't keep up.
+///
+/// The default polls every second without jitter and fails on the first error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollPolicy {
+    interval: Duration,
+    jitter: Duration,
+    max_backoff: Option<Duration>,
+    max_lag: Option<Duration>,
+}
+
+impl PollPolicy {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, jitter: Duration::ZERO, max_backoff: None, max_lag: None }
+    }
+
+    /// Add a random delay up to `jitter` to every interval, so that many watchers don't poll at once.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Retry failed polls instead of failing, after the interval doubled with every consecutive error
+    /// up to `max_backoff`.
+    pub fn with_error_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = Some(max_backoff);
+        self
+    }
+
+    /// Log a warning whenever the data seen by a watcher is older than `max_lag`.
+    pub fn with_max_lag(mut self, max_lag: Duration) -> Self {
+        self.max_lag = Some(max_lag);
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Delay before the next poll: the interval and a random part of the jitter.
+    pub fn delay(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return self.interval;
+        }
+        self.interval + rand::thread_rng().gen_range(Duration::ZERO..=self.jitter)
+    }
+
+    /// Delay before retrying after `errors` consecutive failed polls, `None` if errors aren't retried.
+    pub fn retry_delay(&self, errors: u32) -> Option<Duration> {
+        let max_backoff = self.max_backoff?;
+        let backoff = self.interval.saturating_mul(1 << errors.saturating_sub(1).min(16));
+        Some(backoff.min(max_backoff) + self.delay() - self.interval)
+    }
+
+    /// Wait for the next poll.
+    pub async fn wait(&self) {
+        tokio::time::sleep(self.delay()).await;
+    }
+
+    /// Count the failed poll in `errors` and wait before retrying it, or return the error if it isn't retried.
+    pub async fn backoff<E: Debug>(&self, errors: &mut u32, error: E) -> Result<(), E> {
+        *errors += 1;
+        let Some(delay) = self.retry_delay(*errors) else {
+            return Err(error);
+        };
+        log::warn!("Poll failed {} times in a row, retrying in {:?}: {:?}", errors, delay, error);
+        tokio::time::sleep(delay).await;
+        Ok(())
+    }
+
+    /// Whether `lag` of the data seen by `watcher` exceeds the maximum, logging a warning if it does.
+    pub fn check_lag(&self, watcher: impl Display, lag: Duration) -> bool {
+        let lagging = self.max_lag.is_some_and(|max_lag| lag > max_lag);
+        if lagging {
+            log::warn!("{} lags {:?} behind", watcher, lag);
+        }
+        lagging
+    }
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        let secs = Duration::from_secs;
+        assert_eq!(PollPolicy::new(secs(5)).retry_delay(1), None);
+
+        let policy = PollPolicy::new(secs(5)).with_error_backoff(secs(60));
+        // the interval doubles with every consecutive error up to the maximum
+        let delays: Vec<_> = (1..=6).map(|errors| policy.retry_delay(errors)).collect();
+        assert_eq!(delays, [secs(5), secs(10), secs(20), secs(40), secs(60), secs(60)].map(Some));
+        assert_eq!(policy.retry_delay(u32::MAX), Some(secs(60)));
+
+        // jitter is added on top of the backoff
+        let policy = policy.with_jitter(secs(1));
+        for errors in 1..=6 {
+            let delay = policy.retry_delay(errors).unwrap();
+            let backoff = delays[errors as usize - 1].unwrap();
+            assert!(delay >= backoff && delay <= backoff + secs(1), "{:?} for {} errors", delay, errors);
+        }
+    }
+}
@@ -0,0 +1,1199 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+
+use derivative::Derivative;
+use futures::future::{self, BoxFuture};
+use futures::{stream, StreamExt as _};
+use rand::Rng as _;
+use tl_proto::TlWrite;
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+use tower::retry::budget::Budget;
+use tower::{Service, ServiceExt as _};
+
+use crate::cache::MasterchainInfoCache;
+use crate::client::{LiteClient, LiteClientBuilder};
+use crate::correlation;
+use crate::handle::LiteHandle;
+use crate::layers::{is_verifiable, verify_response, Verification};
+use crate::peer::RawAnswer;
+use crate::tl::request::{Request, SendMessage, WrappedRequest};
+use crate::tl::response::{Response, SendMsgStatus};
+use crate::tl::utils::FromResponse;
+use crate::types::{LiteError, LiteServer, PeerInfo};
+
+type Result<T> = std::result::Result<T, LiteError>;
+
+/// Number of most recent round trips used to calculate latency percentiles.
+const RTT_SAMPLES: usize = 256;
+/// Time a liteserver has to answer a health check.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Cooldown after the first failure of a liteserver, doubled with every consecutive failure.
+const MIN_COOLDOWN: Duration = Duration::from_secs(5);
+const MAX_COOLDOWN: Duration = Duration::from_secs(600);
+/// Window over which requests earn retries for [`LitePool::with_retry_budget`].
+const RETRY_BUDGET_TTL: Duration = Duration::from_secs(10);
+
+/// Source of session ids for [`LitePool::client`].
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+tokio::task_local! {
+    static RETRYABLE: bool;
+    static ROLE: Role;
+    static PRIORITY: Priority;
+}
+
+/// Run `future` with its queries marked as retryable or not, overriding [`Request::is_idempotent`].
+///
+/// A [`LitePool`] resubmits a retryable query on another server when its connection drops before the
+/// answer arrives. E.g. mark a `sendMessage` of a wallet with seqno replay protection as retryable, or
+/// a raw read query which the pool can't classify:
+///
+/// ```ignore
+/// let answer = pool::retryable(true, client.lite_query_raw(&bytes)).await?;
+/// ```
+pub async fn retryable<F: Future>(retryable: bool, future: F) -> F::Output {
+    RETRYABLE.scope(retryable, future).await
+}
+
+/// Workload of a request, which [`LitePool::with_roles`] routes to the liteservers assigned to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// Requests without a more specific role
+    General,
+    /// `sendMessage`
+    SendMessage,
+    /// Requests about accounts or blocks of a workchain, masterchain info belongs to the masterchain, `-1`
+    Workchain(i32),
+    /// Requests which need the full history, only ever set with [`route`]
+    Archival,
+}
+
+impl Role {
+    /// Role of `request` when none is set with [`route`].
+    pub fn of(request: &Request) -> Self {
+        match request {
+            Request::SendMessage(_) => Role::SendMessage,
+            request => request.workchain().map_or(Role::General, Role::Workchain),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::General => write!(f, "general"),
+            Role::SendMessage => write!(f, "send-message"),
+            Role::Workchain(workchain) => write!(f, "workchain:{}", workchain),
+            Role::Archival => write!(f, "archival"),
+        }
+    }
+}
+
+/// Parses the [`Display`](fmt::Display) form, e.g. `send-message` or `workchain:-1`.
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "general" => Ok(Role::General),
+            "send-message" => Ok(Role::SendMessage),
+            "archival" => Ok(Role::Archival),
+            s => s.strip_prefix("workchain:").and_then(|workchain| workchain.parse().ok()).map(Role::Workchain)
+                .ok_or_else(|| format!("unknown role {}, expected general, send-message, archival or workchain:N", s)),
+        }
+    }
+}
+
+/// Run `future` with the role of its requests set to `role`, e.g. [`Role::Archival`] for requests about old blocks.
+pub async fn route<F: Future>(role: Role, future: F) -> F::Output {
+    ROLE.scope(role, future).await
+}
+
+/// Importance of a request, which [`LitePool::with_priorities`] uses to schedule and shed requests under load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Priority {
+    /// Requests someone waits for, e.g. of a wallet, the default
+    #[default]
+    Interactive,
+    /// Bulk work which may be delayed, e.g. the backfill of an [`Indexer`](crate::indexer::Indexer)
+    Background,
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Priority::Interactive => write!(f, "interactive"),
+            Priority::Background => write!(f, "background"),
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "interactive" => Ok(Priority::Interactive),
+            "background" => Ok(Priority::Background),
+            s => Err(format!("unknown priority {}, expected interactive or background", s)),
+        }
+    }
+}
+
+/// Run `future` with the priority of its requests set to `priority`.
+pub async fn priority<F: Future>(priority: Priority, future: F) -> F::Output {
+    PRIORITY.scope(priority, future).await
+}
+
+/// Retryability, role and priority of a request, set with [`retryable`], [`route`] and [`priority`].
+///
+/// Captured from the scopes of the task which creates the request and carried in
+/// [`WrappedRequest::context`], so that they still apply when the request reaches the pool in another task,
+/// e.g. through a [`LiteHandle`] in front of it.
+#[derive(Derivative)]
+#[derivative(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    pub retryable: Option<bool>,
+    pub role: Option<Role>,
+    pub priority: Option<Priority>,
+    /// Alive while the caller waits for the answer, so that a peer stops expecting a long answer once it gives up
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    pub(crate) caller: Option<Weak<()>>,
+    /// Filled with the answer as received, see [`LiteClient::query_with_raw`]
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    pub(crate) raw_answer: Option<RawAnswer>,
+}
+
+impl RequestContext {
+    /// Context set by the scopes around the current task.
+    pub fn current() -> Self {
+        Self {
+            retryable: RETRYABLE.try_with(|r| *r).ok(),
+            role: ROLE.try_with(|role| *role).ok(),
+            priority: PRIORITY.try_with(|p| *p).ok(),
+            caller: None,
+            raw_answer: None,
+        }
+    }
+}
+
+/// Slots for requests in flight of a pool with [`LitePool::with_priorities`].
+struct Scheduler {
+    all: Arc<Semaphore>,
+    /// Slots background requests may take, the rest is reserved for interactive ones
+    background: Arc<Semaphore>,
+    max_queued: Option<usize>,
+    /// Background requests waiting for a slot
+    queued: AtomicUsize,
+}
+
+impl Scheduler {
+    /// Wait for a slot for a request of `priority`, background requests are shed if too many are waiting.
+    async fn acquire(&self, priority: Priority) -> Result<Vec<OwnedSemaphorePermit>> {
+        let mut permits = Vec::new();
+        if priority == Priority::Background {
+            let queued = Queued::new(&self.queued);
+            if self.max_queued.is_some_and(|max| queued.ahead >= max) && self.background.available_permits() == 0 {
+                return Err(LiteError::Overloaded);
+            }
+            permits.push(self.background.clone().acquire_owned().await.expect("semaphore is never closed"));
+        }
+        permits.push(self.all.clone().acquire_owned().await.expect("semaphore is never closed"));
+        Ok(permits)
+    }
+}
+
+/// Counts a waiting request until dropped.
+struct Queued<'a> {
+    counter: &'a AtomicUsize,
+    /// Requests which were already waiting
+    ahead: usize,
+}
+
+impl<'a> Queued<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        Self { counter, ahead: counter.fetch_add(1, Ordering::AcqRel) }
+    }
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Runtime statistics of a single liteserver in a [`LitePool`].
+#[derive(Debug, Clone)]
+pub struct ServerStats {
+    pub server: LiteServer,
+    /// Number of requests sent to this server
+    pub requests: u64,
+    /// Requests which failed on the transport or protocol level
+    pub errors: u64,
+    /// Requests answered with `liteServer.error`
+    pub server_errors: u64,
+    pub rtt_p50: Option<Duration>,
+    pub rtt_p90: Option<Duration>,
+    pub rtt_p99: Option<Duration>,
+    pub last_success: Option<SystemTime>,
+    /// Last masterchain seqno seen in `getMasterchainInfo(Ext)` answers of this server
+    pub last_seqno: Option<u32>,
+    /// How far `last_seqno` is behind the freshest server in the pool
+    pub seqno_lag: Option<u32>,
+    /// Size of the TL-serialized requests sent to this server, without the transport overhead
+    pub bytes_sent: u64,
+    /// Size of the TL-serialized answers received from this server, without the transport overhead
+    pub bytes_received: u64,
+    /// Traffic by function name, see [`Request::name`]
+    pub methods: HashMap<&'static str, MethodStats>,
+    /// Set while the server is evicted from the pool, the time it is reconnected at
+    pub cooldown_until: Option<Instant>,
+    /// Until when the circuit breaker keeps requests away from the server, see [`LitePool::with_circuit_breaker`]
+    ///
+    /// A time in the past means that the next request probes the server.
+    pub breaker_open_until: Option<Instant>,
+}
+
+/// Traffic of a single liteserver function, see [`ServerStats::methods`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodStats {
+    pub requests: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+#[derive(Default)]
+struct StatsRecorder {
+    requests: u64,
+    errors: u64,
+    server_errors: u64,
+    rtt: VecDeque<Duration>,
+    last_success: Option<SystemTime>,
+    last_seqno: Option<u32>,
+    methods: HashMap<&'static str, MethodStats>,
+    /// Moving average of the round trip time
+    latency: Option<Duration>,
+}
+
+impl StatsRecorder {
+    fn record(&mut self, method: &'static str, sent: usize, rtt: Duration, result: &Result<Response>) {
+        self.requests += 1;
+        let received = result.as_ref().map_or(0, |response| response.max_size_hint());
+        let method = self.methods.entry(method).or_default();
+        method.requests += 1;
+        method.bytes_sent += sent as u64;
+        method.bytes_received += received as u64;
+        match result {
+            Ok(response) => {
+                if self.rtt.len() == RTT_SAMPLES {
+                    self.rtt.pop_front();
+                }
+                self.rtt.push_back(rtt);
+                self.latency = Some(self.latency.map_or(rtt, |latency| (latency * 7 + rtt) / 8));
+                self.last_success = Some(SystemTime::now());
+                let seqno = match response {
+                    Response::MasterchainInfo(info) => Some(info.last.seqno),
+                    Response::MasterchainInfoExt(info) => Some(info.last.seqno),
+                    _ => None,
+                };
+                if seqno.is_some() && seqno > self.last_seqno {
+                    self.last_seqno = seqno;
+                }
+            }
+            Err(LiteError::ServerError(_)) => self.server_errors += 1,
+            Err(_) => self.errors += 1,
+        }
+    }
+
+    fn percentile(sorted: &[Duration], p: usize) -> Option<Duration> {
+        if sorted.is_empty() {
+            return None;
+        }
+        Some(sorted[(sorted.len() - 1) * p / 100])
+    }
+
+    fn snapshot(&self, server: &LiteServer, health: &Health, breaker: &Breaker, max_seqno: Option<u32>) -> ServerStats {
+        let mut rtt: Vec<_> = self.rtt.iter().copied().collect();
+        rtt.sort();
+        ServerStats {
+            server: server.clone(),
+            requests: self.requests,
+            errors: self.errors,
+            server_errors: self.server_errors,
+            rtt_p50: Self::percentile(&rtt, 50),
+            rtt_p90: Self::percentile(&rtt, 90),
+            rtt_p99: Self::percentile(&rtt, 99),
+            last_success: self.last_success,
+            last_seqno: self.last_seqno,
+            seqno_lag: self.last_seqno.zip(max_seqno).map(|(seqno, max)| max - seqno),
+            bytes_sent: self.methods.values().map(|m| m.bytes_sent).sum(),
+            bytes_received: self.methods.values().map(|m| m.bytes_received).sum(),
+            methods: self.methods.clone(),
+            cooldown_until: health.cooldown_until,
+            breaker_open_until: breaker.open_until,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Health {
+    /// Consecutive failed health checks and reconnects
+    failures: u32,
+    cooldown_until: Option<Instant>,
+}
+
+/// Options of [`LitePool::with_circuit_breaker`].
+#[derive(Debug, Clone, Copy)]
+struct BreakerConfig {
+    failures: u32,
+    open_for: Duration,
+}
+
+/// Circuit breaker of a server: closed while `open_until` is `None`, half-open once it passed.
+#[derive(Default)]
+struct Breaker {
+    /// Consecutive requests which failed on the transport level
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Breaker {
+    fn is_closed_or_due(&self) -> bool {
+        self.open_until.is_none_or(|until| until <= Instant::now())
+    }
+
+    /// Whether a request may be sent now. Once the breaker is open for long enough, a single request
+    /// probes the server, and the breaker stays open for others until the probe succeeds.
+    fn try_acquire(&mut self, config: &BreakerConfig) -> bool {
+        match self.open_until {
+            None => true,
+            Some(until) if until <= Instant::now() => {
+                self.open_until = Some(Instant::now() + config.open_for);
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    /// Returns `true` if the failure opened the breaker.
+    fn record(&mut self, config: &BreakerConfig, success: bool) -> bool {
+        if success {
+            *self = Self::default();
+            return false;
+        }
+        self.failures += 1;
+        let opened = self.failures == config.failures;
+        if self.failures >= config.failures {
+            self.open_until = Some(Instant::now() + config.open_for);
+        }
+        opened
+    }
+}
+
+struct PoolServer {
+    server: LiteServer,
+    /// `None` until the first connection attempt succeeds
+    handle: Mutex<Option<LiteHandle>>,
+    /// Whether the first connection attempt is still running
+    connecting: AtomicBool,
+    stats: Arc<Mutex<StatsRecorder>>,
+    health: Mutex<Health>,
+    breaker: Mutex<Breaker>,
+}
+
+impl PoolServer {
+    fn new(server: LiteServer, handle: Option<LiteHandle>) -> Self {
+        Self {
+            server,
+            handle: Mutex::new(handle),
+            connecting: AtomicBool::new(false),
+            stats: Default::default(),
+            health: Default::default(),
+            breaker: Default::default(),
+        }
+    }
+
+    fn handle(&self) -> Option<LiteHandle> {
+        self.handle.lock().unwrap().clone()
+    }
+
+    fn is_open(&self) -> bool {
+        self.handle().is_some_and(|handle| !handle.is_closed())
+    }
+
+    /// First connection attempt of a pool connected in the background.
+    async fn connect(&self, builder: LiteClientBuilder) {
+        match builder.connect(self.server.address, self.server.public_key).await {
+            Ok(client) => *self.handle.lock().unwrap() = Some(LiteHandle::new(client)),
+            Err(e) => {
+                log::warn!("Can't connect to liteserver {}: {:?}", self.server, e);
+                self.failed();
+            }
+        }
+        self.connecting.store(false, Ordering::Release);
+    }
+
+    /// Evict the server if its connection is dead or doesn't answer, reconnect it once the cooldown passed.
+    async fn check_health(&self, builder: &LiteClientBuilder) {
+        if self.connecting.load(Ordering::Acquire) {
+            return;
+        }
+        if let Some(handle) = self.handle().filter(|handle| !handle.is_closed()) {
+            match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, handle.get_time()).await {
+                Ok(Ok(_)) => self.health.lock().unwrap().failures = 0,
+                Ok(Err(e)) if matches!(e.kind(), LiteError::ServerError(_)) => self.health.lock().unwrap().failures = 0,
+                Ok(Err(e)) => {
+                    log::warn!("Liteserver {} failed the health check: {:?}", self.server, e);
+                    handle.close().await;
+                    self.failed();
+                }
+                Err(_) => {
+                    log::warn!("Liteserver {} didn't answer the health check in time", self.server);
+                    handle.close().await;
+                    self.failed();
+                }
+            }
+            return;
+        }
+        let cooldown_until = self.health.lock().unwrap().cooldown_until;
+        match cooldown_until {
+            // the connection was closed since the last check
+            None => return self.failed(),
+            Some(until) if until > Instant::now() => return,
+            Some(_) => {}
+        }
+        match builder.clone().connect(self.server.address, self.server.public_key).await {
+            Ok(client) => {
+                log::info!("Reconnected to liteserver {}", self.server);
+                *self.handle.lock().unwrap() = Some(LiteHandle::new(client));
+                // failures are reset only by a successful health check, so a flapping server stays out longer each time
+                self.health.lock().unwrap().cooldown_until = None;
+            }
+            Err(e) => {
+                log::warn!("Can't reconnect to liteserver {}: {:?}", self.server, e);
+                self.failed();
+            }
+        }
+    }
+
+    fn failed(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.failures += 1;
+        let cooldown = MIN_COOLDOWN.saturating_mul(1 << (health.failures - 1).min(16)).min(MAX_COOLDOWN);
+        health.cooldown_until = Some(Instant::now() + cooldown);
+        log::debug!("Liteserver {} is evicted for {:?}", self.server, cooldown);
+    }
+
+    /// Count the result of a request in the circuit breaker, if the pool has one.
+    fn record(&self, breaker: Option<&BreakerConfig>, result: &Result<Response>) {
+        let Some(config) = breaker else {
+            return;
+        };
+        let success = matches!(result, Ok(_) | Err(LiteError::ServerError(_)));
+        if self.breaker.lock().unwrap().record(config, success) {
+            log::warn!("Circuit breaker of liteserver {} opened for {:?} after {} failures", self.server, config.open_for, config.failures);
+        }
+    }
+
+    fn call(&self, request: WrappedRequest) -> BoxFuture<'static, Result<Response>> {
+        let handle = match self.handle() {
+            Some(handle) => handle,
+            None => return Box::pin(future::err(LiteError::Closed)),
+        };
+        let stats = self.stats.clone();
+        let method = request.request.name();
+        let sent = request.max_size_hint();
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = handle.oneshot(request).await;
+            stats.lock().unwrap().record(method, sent, started.elapsed(), &result);
+            result
+        })
+    }
+}
+
+/// Open server of a [`LitePool`] which a [`SelectionPolicy`] may choose.
+#[derive(Debug, Clone)]
+pub struct Candidate<'a> {
+    /// Position of the server in [`LitePool::servers`]
+    pub index: usize,
+    pub server: &'a LiteServer,
+    /// Moving average of the round trip time, `None` until the server answers a request
+    pub latency: Option<Duration>,
+    /// Weight of the server, see [`ServerPreference::weight`]
+    pub weight: u32,
+}
+
+/// Preference for a server of a [`LitePool`], see [`LitePool::with_preferences`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerPreference {
+    /// Share of the requests the server gets relative to the others with [`RoundRobin`] and [`WeightedRandom`],
+    /// 1 by default
+    pub weight: u32,
+    /// Servers of a lower priority only get requests while no server of a higher one is open, or when a request
+    /// fails over, 0 by default
+    pub priority: i32,
+}
+
+impl ServerPreference {
+    /// Weights and priorities of the servers of a global config which set them, see
+    /// [`ConfigLiteServer::weight`](ton_networkconfig::ConfigLiteServer::weight).
+    #[cfg(feature = "network-config")]
+    pub fn from_config(config: &ton_networkconfig::ConfigGlobal) -> Vec<(LiteServer, Self)> {
+        config.liteservers.iter()
+            .filter(|server| server.weight.is_some() || server.priority.is_some())
+            .map(|server| (LiteServer::from(server), Self {
+                weight: server.weight.unwrap_or(1),
+                priority: server.priority.unwrap_or(0),
+            }))
+            .collect()
+    }
+}
+
+impl Default for ServerPreference {
+    fn default() -> Self {
+        Self { weight: 1, priority: 0 }
+    }
+}
+
+/// Index into `candidates` of the point on the line of their weights laid one after another.
+fn weighted(candidates: &[Candidate], weight: impl Fn(&Candidate) -> u64, point: u64) -> usize {
+    let mut point = point;
+    for (i, candidate) in candidates.iter().enumerate() {
+        let weight = weight(candidate);
+        if point < weight {
+            return i;
+        }
+        point -= weight;
+    }
+    0
+}
+
+/// Strategy choosing the server of a [`LitePool`] for each request.
+pub trait SelectionPolicy: Send + Sync {
+    /// Pick the server for `request` among `candidates`, returns an index into `candidates`.
+    ///
+    /// `candidates` are never empty and ordered by [`Candidate::index`]. `session` identifies the
+    /// client the request comes from, see [`LitePool::client`].
+    fn select(&self, request: &WrappedRequest, session: u64, candidates: &[Candidate]) -> usize;
+}
+
+/// Use the servers one after another, each for as many requests in a row as its weight, the default policy.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl SelectionPolicy for RoundRobin {
+    fn select(&self, _request: &WrappedRequest, _session: u64, candidates: &[Candidate]) -> usize {
+        let next = self.next.fetch_add(1, Ordering::Relaxed) as u64;
+        match candidates.iter().map(|c| c.weight as u64).sum::<u64>() {
+            0 => (next % candidates.len() as u64) as usize,
+            total => weighted(candidates, |c| c.weight as u64, next % total),
+        }
+    }
+}
+
+/// Use the server with the lowest latency, servers which haven't answered yet are tried first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LeastLatency;
+
+impl SelectionPolicy for LeastLatency {
+    fn select(&self, _request: &WrappedRequest, _session: u64, candidates: &[Candidate]) -> usize {
+        (0..candidates.len()).min_by_key(|&i| candidates[i].latency.unwrap_or_default()).unwrap_or(0)
+    }
+}
+
+/// Send all requests of a client to the same server while it is open, e.g. so that the client
+/// never sees the chain going back in time because of lagging servers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sticky;
+
+impl SelectionPolicy for Sticky {
+    fn select(&self, _request: &WrappedRequest, session: u64, candidates: &[Candidate]) -> usize {
+        // rendezvous hashing, so closing a server only moves the clients which used it
+        (0..candidates.len()).max_by_key(|&i| {
+            let mut hasher = DefaultHasher::new();
+            (session, candidates[i].index).hash(&mut hasher);
+            hasher.finish()
+        }).unwrap_or(0)
+    }
+}
+
+/// Choose servers randomly in proportion to their weights, the weights given here override the ones of
+/// [`LitePool::with_preferences`].
+#[derive(Debug, Clone, Default)]
+pub struct WeightedRandom {
+    weights: HashMap<LiteServer, u32>,
+}
+
+impl WeightedRandom {
+    pub fn new(weights: impl IntoIterator<Item = (LiteServer, u32)>) -> Self {
+        Self { weights: weights.into_iter().collect() }
+    }
+
+    fn weight(&self, candidate: &Candidate) -> u64 {
+        self.weights.get(candidate.server).copied().unwrap_or(candidate.weight) as u64
+    }
+}
+
+impl SelectionPolicy for WeightedRandom {
+    fn select(&self, _request: &WrappedRequest, _session: u64, candidates: &[Candidate]) -> usize {
+        let total: u64 = candidates.iter().map(|c| self.weight(c)).sum();
+        if total == 0 {
+            return 0;
+        }
+        weighted(candidates, |c| self.weight(c), rand::thread_rng().gen_range(0..total))
+    }
+}
+
+/// Pool of connections to several liteservers, by default requests are distributed in round-robin order,
+/// see [`LitePool::with_policy`].
+///
+/// Closed connections are skipped, and with [`LitePool::with_failover`] requests failing on the transport
+/// level are retried on other servers. [`LitePool::check_health`] evicts servers which don't answer and
+/// reconnects evicted ones after a cooldown, which grows exponentially while the server keeps failing.
+/// Servers which keep failing requests without being evicted can be skipped with [`LitePool::with_circuit_breaker`],
+/// and [`LitePool::with_retry_budget`] bounds the extra load of retries. Under load, [`LitePool::with_priorities`]
+/// lets interactive requests go ahead of background ones.
+///
+/// The pool is itself a lite service, use [`LitePool::client`] for the typed API.
+#[derive(Clone)]
+pub struct LitePool {
+    servers: Arc<Vec<PoolServer>>,
+    policy: Arc<dyn SelectionPolicy>,
+    session: u64,
+    /// Number of servers a request is sent to before its transport error is returned
+    attempts: usize,
+    verification: Verification,
+    /// Roles of the servers with [`LitePool::with_roles`], servers without roles are general
+    roles: Arc<HashMap<LiteServer, HashSet<Role>>>,
+    preferences: Arc<HashMap<LiteServer, ServerPreference>>,
+    builder: LiteClientBuilder,
+    masterchain_info_cache: Option<Arc<MasterchainInfoCache>>,
+    breaker: Option<BreakerConfig>,
+    /// Shared by all clones of the pool, see [`LitePool::with_retry_budget`]
+    retry_budget: Option<Arc<Budget>>,
+    scheduler: Option<Arc<Scheduler>>,
+    /// Notified whenever a background connection attempt finishes
+    connected: Arc<watch::Sender<()>>,
+}
+
+impl LitePool {
+    /// Connect to all `servers` concurrently, servers which fail to connect are skipped.
+    pub async fn connect(servers: impl IntoIterator<Item = LiteServer>) -> Result<Self> {
+        Self::connect_with(servers, LiteClient::builder()).await
+    }
+
+    /// Same as [`LitePool::connect`], with the connection options of `builder`, e.g. a persistent client key.
+    pub async fn connect_with(servers: impl IntoIterator<Item = LiteServer>, builder: LiteClientBuilder) -> Result<Self> {
+        let connections = future::join_all(servers.into_iter().map(|server| {
+            let builder = builder.clone();
+            async move {
+                let result = builder.connect(server.address, server.public_key).await.map(LiteHandle::new);
+                (server, result)
+            }
+        })).await;
+        let mut handles = Vec::new();
+        for (server, result) in connections {
+            match result {
+                Ok(handle) => handles.push((server, handle)),
+                Err(e) => log::warn!("Can't connect to liteserver {}: {:?}", server, e),
+            }
+        }
+        Self::with_builder(handles, builder)
+    }
+
+    /// Pool of existing connections, evicted servers are reconnected with the default options.
+    pub fn new(handles: impl IntoIterator<Item = (LiteServer, LiteHandle)>) -> Result<Self> {
+        Self::with_builder(handles, LiteClient::builder())
+    }
+
+    /// Start connecting to all `servers` in the background and return immediately, e.g. to overlap the handshakes
+    /// with other initialization of a short-lived process. Must be called from within a tokio runtime.
+    ///
+    /// Use [`LitePool::ready`] to wait for the connections. Requests made before fail with
+    /// [`LiteError::NoServers`] if no connection is established yet, servers which fail to connect are
+    /// retried by [`LitePool::check_health`].
+    pub fn connect_in_background(servers: impl IntoIterator<Item = LiteServer>, builder: LiteClientBuilder) -> Result<Self> {
+        let pool = Self::from_servers(servers.into_iter().map(|server| PoolServer::new(server, None)).collect(), builder)?;
+        for i in 0..pool.servers.len() {
+            pool.servers[i].connecting.store(true, Ordering::Release);
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                pool.servers[i].connect(pool.builder.clone()).await;
+                pool.connected.send_replace(());
+            });
+        }
+        Ok(pool)
+    }
+
+    fn with_builder(handles: impl IntoIterator<Item = (LiteServer, LiteHandle)>, builder: LiteClientBuilder) -> Result<Self> {
+        Self::from_servers(handles.into_iter().map(|(server, handle)| PoolServer::new(server, Some(handle))).collect(), builder)
+    }
+
+    fn from_servers(servers: Vec<PoolServer>, builder: LiteClientBuilder) -> Result<Self> {
+        if servers.is_empty() {
+            return Err(LiteError::NoServers);
+        }
+        Ok(Self {
+            servers: Arc::new(servers),
+            policy: Arc::new(RoundRobin::default()),
+            session: 0,
+            attempts: 1,
+            verification: Verification::None,
+            roles: Default::default(),
+            preferences: Default::default(),
+            builder,
+            masterchain_info_cache: None,
+            breaker: None,
+            retry_budget: None,
+            scheduler: None,
+            connected: Arc::new(watch::channel(()).0),
+        })
+    }
+
+    /// Wait until at least `n` connections are open, or all connection attempts of
+    /// [`LitePool::connect_in_background`] finished. Returns the number of open connections.
+    ///
+    /// Fails with [`LiteError::NoServers`] if no connection could be established.
+    pub async fn ready(&self, n: usize) -> Result<usize> {
+        let mut connected = self.connected.subscribe();
+        loop {
+            let open = self.servers.iter().filter(|s| s.is_open()).count();
+            let pending = self.servers.iter().any(|s| s.connecting.load(Ordering::Acquire));
+            if open >= n || !pending {
+                return match open {
+                    0 => Err(LiteError::NoServers),
+                    open => Ok(open),
+                };
+            }
+            // the sender lives in the pool, so this can't fail
+            let _ = connected.changed().await;
+        }
+    }
+
+    /// Choose servers with `policy`, e.g. [`LeastLatency`], instead of round-robin.
+    pub fn with_policy(mut self, policy: impl SelectionPolicy + 'static) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    /// Retry requests which fail on the transport level, e.g. because the connection was closed or the
+    /// server answered garbage, on up to `attempts - 1` other servers. Errors returned by a liteserver
+    /// itself are not retried.
+    ///
+    /// A request which already reached the server is retried only if it's retryable, see [`retryable`],
+    /// so `sendMessage` is never sent twice. Retryable requests whose connection dropped while they were
+    /// in flight are resubmitted on another server once even without failover.
+    pub fn with_failover(mut self, attempts: usize) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Assign servers to roles, e.g. dedicate a server to [`Role::SendMessage`], or keep the masterchain
+    /// watchers on `Role::Workchain(-1)`. Servers without roles are general.
+    ///
+    /// A request goes to the servers assigned to its role, which is [`Role::of`] the request unless set with
+    /// [`route`]. If no open server has the role, it goes to the general servers, the ones without roles or
+    /// with [`Role::General`]. So a server assigned only [`Role::SendMessage`] never gets other requests.
+    pub fn with_roles(mut self, roles: impl IntoIterator<Item = (LiteServer, Role)>) -> Self {
+        let mut map = HashMap::<_, HashSet<_>>::new();
+        for (server, role) in roles {
+            map.entry(server).or_default().insert(role);
+        }
+        self.roles = Arc::new(map);
+        self
+    }
+
+    /// Set weights and priorities of servers, e.g. to prefer own servers and keep public ones as a fallback.
+    /// Servers without a preference have the [default](ServerPreference::default) one.
+    ///
+    /// Repeated calls override the preferences of the same servers, e.g. the ones of
+    /// [`ServerPreference::from_config`].
+    pub fn with_preferences(mut self, preferences: impl IntoIterator<Item = (LiteServer, ServerPreference)>) -> Self {
+        Arc::make_mut(&mut self.preferences).extend(preferences);
+        self
+    }
+
+    fn preference(&self, server: &LiteServer) -> ServerPreference {
+        self.preferences.get(server).copied().unwrap_or_default()
+    }
+
+    /// Check the proofs of all answers, see [`Verification::Strict`]. An answer failing the check is
+    /// treated like a transport error, so with [`LitePool::with_failover`] the request is retried on
+    /// another server. Requests whose answers can't be checked fail with [`LiteError::Unverifiable`].
+    pub fn with_verification(mut self, verification: Verification) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    /// Stop sending requests to a server after `failures` consecutive requests failed on the transport level,
+    /// e.g. timed out or failed verification, even while its connection is open.
+    ///
+    /// After `open_for`, a single request probes the server: its success lets the traffic back, its failure
+    /// keeps the server out for another `open_for`. Errors returned by a liteserver itself are not failures.
+    pub fn with_circuit_breaker(mut self, failures: u32, open_for: Duration) -> Self {
+        self.breaker = Some(BreakerConfig { failures: failures.max(1), open_for });
+        self
+    }
+
+    /// Limit retries of [`LitePool::with_failover`] and resubmissions to `retry_percent` of the requests made
+    /// over the last few seconds plus `min_per_sec` retries per second, for all clones of the pool together.
+    ///
+    /// Once the budget is spent, requests fail with their first error instead of multiplying the load when
+    /// many servers fail at once.
+    pub fn with_retry_budget(mut self, min_per_sec: u32, retry_percent: f32) -> Self {
+        self.retry_budget = Some(Arc::new(Budget::new(RETRY_BUDGET_TTL, min_per_sec, retry_percent)));
+        self
+    }
+
+    /// Send at most `max_in_flight` requests at once over the whole pool, keeping `reserved` of the slots for
+    /// [`Priority::Interactive`] requests, so that background work never starves them. Requests wait for a slot
+    /// in the order they were made.
+    ///
+    /// If `max_queued` is set and that many [`Priority::Background`] requests already wait for a slot, further
+    /// ones fail with [`LiteError::Overloaded`] instead of queueing. The priority of requests is set with
+    /// [`priority`].
+    pub fn with_priorities(mut self, max_in_flight: usize, reserved: usize, max_queued: Option<usize>) -> Self {
+        let max_in_flight = max_in_flight.max(1);
+        self.scheduler = Some(Arc::new(Scheduler {
+            all: Arc::new(Semaphore::new(max_in_flight)),
+            background: Arc::new(Semaphore::new(max_in_flight.saturating_sub(reserved).max(1))),
+            max_queued,
+            queued: AtomicUsize::new(0),
+        }));
+        self
+    }
+
+    /// Share `cache` between the clients of the pool, see [`LiteClient::with_masterchain_info_cache`].
+    ///
+    /// Every `getMasterchainInfo` answer of any server updates the cache, so it holds the freshest block seen
+    /// in the pool, and [`ServerStats::seqno_lag`] is measured against it.
+    pub fn with_masterchain_info_cache(mut self, cache: Arc<MasterchainInfoCache>) -> Self {
+        self.masterchain_info_cache = Some(cache);
+        self
+    }
+
+    /// Typed client whose requests are distributed over this pool.
+    ///
+    /// Every client is a separate session for the [`SelectionPolicy`], requests made through the pool
+    /// service directly belong to session 0.
+    pub fn client(&self) -> LiteClient {
+        let mut pool = self.clone();
+        pool.session = NEXT_SESSION.fetch_add(1, Ordering::Relaxed);
+        let cache = pool.masterchain_info_cache.clone();
+        let client = LiteClient::new(pool);
+        match cache {
+            Some(cache) => client.with_masterchain_info_cache(cache),
+            None => client,
+        }
+    }
+
+    pub fn servers(&self) -> impl Iterator<Item = &LiteServer> {
+        self.servers.iter().map(|s| &s.server)
+    }
+
+    /// Connection details of the servers, see [`LiteHandle::peer_info`].
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        self.servers.iter().filter_map(|s| s.handle()?.peer_info().cloned()).collect()
+    }
+
+    pub fn stats(&self) -> Vec<ServerStats> {
+        let recorders: Vec<_> = self.servers.iter().map(|s| s.stats.lock().unwrap()).collect();
+        let cached_seqno = self.masterchain_info_cache.as_ref().and_then(|cache| cache.last_seqno());
+        let max_seqno = recorders.iter().filter_map(|r| r.last_seqno).chain(cached_seqno).max();
+        self.servers.iter().zip(recorders.iter())
+            .map(|(s, r)| r.snapshot(&s.server, &s.health.lock().unwrap(), &s.breaker.lock().unwrap(), max_seqno))
+            .collect()
+    }
+
+    /// Check all servers concurrently: evict the ones whose connection is closed or which don't answer
+    /// `getTime`, and reconnect evicted servers whose cooldown passed.
+    pub async fn check_health(&self) {
+        future::join_all(self.servers.iter().map(|s| s.check_health(&self.builder))).await;
+    }
+
+    /// Run [`LitePool::check_health`] every `interval`, never returns.
+    pub async fn run_health_checks(&self, interval: Duration) {
+        loop {
+            self.check_health().await;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Gracefully shut down all connections, see [`LiteHandle::shutdown`].
+    ///
+    /// Running health checks reconnect the servers, stop them first.
+    pub async fn shutdown(&self) {
+        let handles: Vec<_> = self.servers.iter().filter_map(|s| s.handle()).collect();
+        future::join_all(handles.iter().map(|h| h.shutdown())).await;
+    }
+
+    /// Close all connections immediately, see [`LiteHandle::close`].
+    pub async fn close(&self) {
+        let handles: Vec<_> = self.servers.iter().filter_map(|s| s.handle()).collect();
+        future::join_all(handles.iter().map(|h| h.close())).await;
+    }
+
+    /// Send an external message to up to `n` servers concurrently.
+    ///
+    /// Returns the status reported by each server the message was submitted to, fails only if
+    /// there are no open connections at all.
+    pub async fn send_message_broadcast(&self, body: Vec<u8>, n: usize) -> Result<Vec<(LiteServer, Result<u32>)>> {
+        let request = WrappedRequest::new(Request::SendMessage(SendMessage { body }));
+        let servers: Vec<_> = self.candidates(&request).into_iter().take(n).map(|i| &self.servers[i]).collect();
+        if servers.is_empty() {
+            return Err(LiteError::NoServers);
+        }
+        let results = future::join_all(servers.iter().map(|server| {
+            let fut = server.call(request.clone());
+            async move {
+                fut.await.and_then(SendMsgStatus::from_response).map(|status| status.status)
+            }
+        })).await;
+        Ok(servers.iter().map(|s| s.server.clone()).zip(results).collect())
+    }
+
+    /// Send `requests` spreading them over the pool, with at most `concurrency` of them in flight.
+    ///
+    /// Results are returned in the order of `requests`, a failed request doesn't affect the others.
+    pub async fn batch(&self, requests: impl IntoIterator<Item = Request>, concurrency: usize) -> Vec<Result<Response>> {
+        stream::iter(requests)
+            .map(|request| self.clone().oneshot(WrappedRequest::new(request)))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Whether requests of `role` go to `server`, see [`LitePool::with_roles`].
+    fn serves(&self, server: &LiteServer, role: Role) -> bool {
+        match self.roles.get(server) {
+            Some(roles) => roles.contains(&role),
+            None => role == Role::General,
+        }
+    }
+
+    /// Open connections in the order they should be tried: the one chosen by the policy, then the
+    /// following ones in the pool order.
+    fn candidates(&self, request: &WrappedRequest) -> Vec<usize> {
+        let role = request.context.role.unwrap_or_else(|| Role::of(&request.request));
+        let serving = |role| -> Vec<_> {
+            self.servers.iter().enumerate()
+                .filter(|(_, s)| s.is_open() && self.serves(&s.server, role))
+                .filter(|(_, s)| self.breaker.is_none() || s.breaker.lock().unwrap().is_closed_or_due())
+                .collect()
+        };
+        let mut open = serving(role);
+        if open.is_empty() && role != Role::General {
+            open = serving(Role::General);
+        }
+        let Some(top) = open.iter().map(|(_, s)| self.preference(&s.server).priority).max() else {
+            return Vec::new();
+        };
+        // the policy chooses among the servers of the highest priority, the others are only tried on failover
+        let (mut open, mut fallback): (Vec<_>, Vec<_>) = open.into_iter()
+            .partition(|(_, s)| self.preference(&s.server).priority == top);
+        fallback.sort_by_key(|(_, s)| std::cmp::Reverse(self.preference(&s.server).priority));
+        let candidates: Vec<_> = open.iter().map(|&(index, s)| Candidate {
+            index,
+            server: &s.server,
+            latency: s.stats.lock().unwrap().latency,
+            weight: self.preference(&s.server).weight,
+        }).collect();
+        let selected = self.policy.select(request, self.session, &candidates).min(open.len() - 1);
+        open.rotate_left(selected);
+        open.into_iter().chain(fallback).map(|(index, _)| index).collect()
+    }
+}
+
+impl Service<WrappedRequest> for LitePool {
+    type Response = Response;
+    type Error = LiteError;
+    type Future = BoxFuture<'static, Result<Response>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: WrappedRequest) -> Self::Future {
+        if self.verification == Verification::Strict && !is_verifiable(&request.request) {
+            return Box::pin(future::err(LiteError::Unverifiable(request.request.name())));
+        }
+        let candidates = self.candidates(&request);
+        if candidates.is_empty() {
+            return Box::pin(future::err(LiteError::NoServers));
+        }
+        let servers = self.servers.clone();
+        let attempts = self.attempts;
+        let verification = self.verification;
+        let cache = self.masterchain_info_cache.clone();
+        let breaker = self.breaker;
+        let budget = self.retry_budget.clone();
+        let scheduler = self.scheduler.clone();
+        let retryable = request.context.retryable.unwrap_or_else(|| request.request.is_idempotent());
+        let priority = request.context.priority.unwrap_or_default();
+        if let Some(budget) = &budget {
+            budget.deposit();
+        }
+        Box::pin(async move {
+            let _permits = match &scheduler {
+                Some(scheduler) => scheduler.acquire(priority).await?,
+                None => Vec::new(),
+            };
+            let mut result = Err(LiteError::NoServers);
+            let mut last = None;
+            let candidates = candidates.into_iter()
+                .filter(|&index| breaker.is_none_or(|config| servers[index].breaker.lock().unwrap().try_acquire(&config)));
+            for (tried, index) in (1..).zip(candidates) {
+                if tried > 1 && budget.as_ref().is_some_and(|budget| budget.withdraw().is_err()) {
+                    log::debug!("{}Retry budget of the pool is spent, not retrying {}", correlation::prefix(), request.request.name());
+                    break;
+                }
+                result = servers[index].call(request.clone()).await;
+                last = Some(index);
+                if let (Verification::Strict, Ok(response)) = (verification, &result) {
+                    if let Err(e) = verify_response(&request.request, response) {
+                        log::warn!("{}Liteserver {} sent an answer failing verification: {:?}", correlation::prefix(), servers[index].server, e);
+                        result = Err(e);
+                    }
+                }
+                servers[index].record(breaker.as_ref(), &result);
+                let retry = match &result {
+                    Ok(_) | Err(LiteError::ServerError(_)) => break,
+                    // never sent, so safe to send elsewhere
+                    Err(LiteError::Closed | LiteError::NoServers | LiteError::TooManyQueries | LiteError::ConnectionClosed { during_query: false }) => tried < attempts,
+                    Err(LiteError::ConnectionClosed { during_query: true }) => retryable && tried < attempts.max(2),
+                    Err(_) => retryable && tried < attempts,
+                };
+                log::debug!("{}Request to liteserver {} failed: {:?}", correlation::prefix(), servers[index].server, result.as_ref().unwrap_err());
+                if !retry {
+                    break;
+                }
+                if let Err(LiteError::ConnectionClosed { during_query: true }) = &result {
+                    log::info!("{}Resubmitting {} after the connection to {} dropped", correlation::prefix(), request.request.name(), servers[index].server);
+                }
+            }
+            if let (Some(cache), Ok(Response::MasterchainInfo(info))) = (&cache, &result) {
+                cache.insert(info);
+            }
+            result.map_err(|e| match last {
+                Some(index) => e.in_query(request.request.name(), Some(&servers[index].server)),
+                None => e,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tl::common::{AccountId, BlockIdExt, Int256};
+    use crate::tl::request::GetAccountState;
+    use crate::tl::response::CurrentTime;
+
+    use super::*;
+
+    fn server(n: u8) -> LiteServer {
+        LiteServer::new(([127, 0, 0, 1], n as u16).into(), [n; 32])
+    }
+
+    /// Connection to a fake server which answers every request with its number as the time.
+    fn handle(n: u8) -> LiteHandle {
+        let service = tower::service_fn(move |_: WrappedRequest| future::ok(Response::CurrentTime(CurrentTime { now: n as u32 })));
+        LiteHandle::new(LiteClient::new(service))
+    }
+
+    /// Number of the server which answered `request`.
+    async fn answered_by(pool: &LitePool, request: Request) -> u32 {
+        match pool.clone().oneshot(WrappedRequest::new(request)).await {
+            Ok(Response::CurrentTime(time)) => time.now,
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_breaker() {
+        let config = BreakerConfig { failures: 2, open_for: Duration::from_millis(50) };
+        let mut breaker = Breaker::default();
+        assert!(!breaker.record(&config, false));
+        assert!(breaker.try_acquire(&config));
+        // the second consecutive failure opens the breaker
+        assert!(breaker.record(&config, false));
+        assert!(!breaker.is_closed_or_due());
+        assert!(!breaker.try_acquire(&config));
+
+        // half-open: a single request probes the server
+        std::thread::sleep(config.open_for);
+        assert!(breaker.is_closed_or_due());
+        assert!(breaker.try_acquire(&config));
+        assert!(!breaker.try_acquire(&config));
+        // a failed probe keeps it open without reporting it opened again
+        assert!(!breaker.record(&config, false));
+        assert!(!breaker.try_acquire(&config));
+
+        std::thread::sleep(config.open_for);
+        assert!(breaker.try_acquire(&config));
+        assert!(!breaker.record(&config, true));
+        assert!(breaker.is_closed_or_due());
+        assert!(breaker.try_acquire(&config) && breaker.try_acquire(&config));
+        // failures are counted anew after a success
+        assert!(!breaker.record(&config, false));
+        assert!(breaker.try_acquire(&config));
+    }
+
+    #[test]
+    fn test_role() {
+        for role in [Role::General, Role::SendMessage, Role::Archival, Role::Workchain(-1), Role::Workchain(0)] {
+            assert_eq!(role.to_string().parse(), Ok(role));
+        }
+        assert_eq!("workchain:-1".parse(), Ok(Role::Workchain(-1)));
+        assert!("workchain:x".parse::<Role>().is_err());
+        assert!("masterchain".parse::<Role>().is_err());
+
+        let account = AccountId { workchain: 0, id: Int256::default() };
+        let id = BlockIdExt { workchain: -1, shard: 0x8000000000000000, seqno: 1, root_hash: Int256::default(), file_hash: Int256::default() };
+        assert_eq!(Role::of(&Request::GetAccountState(GetAccountState { id, account })), Role::Workchain(0));
+        assert_eq!(Role::of(&Request::GetMasterchainInfo), Role::Workchain(-1));
+        assert_eq!(Role::of(&Request::SendMessage(SendMessage { body: vec![] })), Role::SendMessage);
+        assert_eq!(Role::of(&Request::GetTime), Role::General);
+    }
+
+    #[tokio::test]
+    async fn test_routing() -> Result<()> {
+        let pool = LitePool::new((1..=3).map(|n| (server(n), handle(n))))?
+            .with_roles([(server(1), Role::SendMessage), (server(2), Role::Workchain(-1)), (server(2), Role::Archival)]);
+        // server 3 has no roles, so it is the only general one
+        assert_eq!(answered_by(&pool, Request::GetTime).await, 3);
+        assert_eq!(answered_by(&pool, Request::SendMessage(SendMessage { body: vec![] })).await, 1);
+        assert_eq!(answered_by(&pool, Request::GetMasterchainInfo).await, 2);
+        assert_eq!(route(Role::Archival, answered_by(&pool, Request::GetTime)).await, 2);
+        assert_eq!(route(Role::SendMessage, answered_by(&pool, Request::GetMasterchainInfo)).await, 1);
+        // requests of a role no server has go to the general servers
+        assert_eq!(route(Role::Workchain(0), answered_by(&pool, Request::GetMasterchainInfo)).await, 3);
+
+        // and so do requests whose servers are closed
+        pool.servers[1].handle().unwrap().close().await;
+        assert_eq!(answered_by(&pool, Request::GetMasterchainInfo).await, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_routing_through_handle() -> Result<()> {
+        let pool = LitePool::new((1..=2).map(|n| (server(n), handle(n))))?
+            .with_roles([(server(1), Role::Archival)]);
+        // the pool runs in the task of the handle, the role is carried by the request
+        let handle = LiteHandle::new(LiteClient::new(pool));
+        assert_eq!(handle.get_time().await?, 2);
+        assert_eq!(route(Role::Archival, handle.get_time()).await?, 1);
+        assert_eq!(route(Role::Archival, async { handle.client().get_time().await }).await?, 1);
+        Ok(())
+    }
+}
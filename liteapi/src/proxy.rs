@@ -0,0 +1,356 @@
+//! Caching liteserver proxy, see the `lite-proxy` binary.
+//!
+//! [`LiteProxy`] is a make-service for [`crate::server::serve`]. Every accepted connection forwards its
+//! queries to an upstream lite service, usually a [`crate::pool::LitePool`]. Answers which can't change,
+//! i.e. those about a specific block, are kept in an [`AnswerCache`] shared by all connections, and
+//! clients may be limited to a number of queries per second by their IP address.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures::future::{self, BoxFuture};
+use tl_proto::TlWrite;
+use tower::{Layer, Service, ServiceExt as _};
+
+use crate::layers::{RequestLog, RequestLogLayer, RequestLogService, UnwrapMessagesLayer, UnwrapService, WrapErrorLayer, WrapErrorService};
+use crate::tl::request::{Request, WrappedRequest};
+use crate::tl::response::{Error, Response};
+use crate::types::LiteError;
+
+type Result<T> = std::result::Result<T, LiteError>;
+
+/// Error code returned to clients exceeding the rate limit.
+pub const RATE_LIMIT_ERROR_CODE: i32 = 429;
+/// Number of clients above which the buckets of idle clients are dropped.
+const MAX_IDLE_BUCKETS: usize = 4096;
+
+/// Whether the answer to `request` never changes, so it may be cached.
+///
+/// These are the queries about a block given by its full id, including the hashes.
+pub fn is_immutable(request: &Request) -> bool {
+    match request {
+        Request::GetBlock(_)
+        | Request::GetState(_)
+        | Request::GetBlockHeader(_)
+        | Request::GetAccountState(_)
+        | Request::GetAccountStatePrunned(_)
+        | Request::RunSmcMethod(_)
+        | Request::GetShardInfo(_)
+        | Request::GetAllShardsInfo(_)
+        | Request::GetOneTransaction(_)
+        | Request::GetTransactions(_)
+        | Request::ListBlockTransactions(_)
+        | Request::ListBlockTransactionsExt(_)
+        | Request::GetConfigAll(_)
+        | Request::GetConfigParams(_)
+        | Request::GetShardBlockProof(_) => true,
+        Request::GetBlockProof(proof) => proof.target_block.is_some(),
+        _ => false,
+    }
+}
+
+/// Answers to [immutable](is_immutable) queries, the oldest ones are evicted first once the cache is full.
+pub struct AnswerCache {
+    capacity: usize,
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    answers: HashMap<Vec<u8>, Response>,
+    order: VecDeque<(Vec<u8>, usize)>,
+    size: usize,
+}
+
+impl AnswerCache {
+    /// Cache holding up to `capacity` bytes of serialized queries and answers.
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, inner: Default::default() }
+    }
+
+    pub fn get(&self, request: &Request) -> Option<Response> {
+        if !is_immutable(request) {
+            return None;
+        }
+        self.inner.lock().unwrap().answers.get(&tl_proto::serialize(request)).cloned()
+    }
+
+    /// Remember `response` if `request` is immutable and the answer fits into the cache.
+    pub fn insert(&self, request: &Request, response: &Response) {
+        if !is_immutable(request) || matches!(response, Response::Error(_)) {
+            return;
+        }
+        let key = tl_proto::serialize(request);
+        let size = key.len() + response.max_size_hint();
+        if size > self.capacity {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if inner.answers.contains_key(&key) {
+            return;
+        }
+        while inner.size + size > self.capacity {
+            match inner.order.pop_front() {
+                Some((evicted, evicted_size)) => {
+                    inner.answers.remove(&evicted);
+                    inner.size -= evicted_size;
+                }
+                None => break,
+            }
+        }
+        inner.answers.insert(key.clone(), response.clone());
+        inner.order.push_back((key, size));
+        inner.size += size;
+    }
+
+    /// Number of cached answers.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().answers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Approximate memory used by the cached queries and answers, in bytes.
+    pub fn size(&self) -> usize {
+        self.inner.lock().unwrap().size
+    }
+}
+
+/// Token bucket rate limit per client IP address.
+pub struct RateLimit {
+    per_second: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimit {
+    /// Allow `per_second` queries per second on average, and up to `burst` queries at once.
+    pub fn new(per_second: u32, burst: u32) -> Self {
+        Self { per_second: per_second as f64, burst: burst.max(1) as f64, buckets: Default::default() }
+    }
+
+    /// Take a token for a query of `client`, returns `false` if it exceeded the limit.
+    pub fn try_acquire(&self, client: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_IDLE_BUCKETS {
+            buckets.retain(|_, bucket| bucket.refill(now, self.per_second, self.burst) < self.burst);
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket { tokens: self.burst, updated: now });
+        if bucket.refill(now, self.per_second, self.burst) < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant, per_second: f64, burst: f64) -> f64 {
+        self.tokens = (self.tokens + now.duration_since(self.updated).as_secs_f64() * per_second).min(burst);
+        self.updated = now;
+        self.tokens
+    }
+}
+
+/// Make-service of the proxy, see the [module docs](self).
+#[derive(Clone)]
+pub struct LiteProxy<S> {
+    upstream: S,
+    cache: Option<Arc<AnswerCache>>,
+    rate_limit: Option<Arc<RateLimit>>,
+    request_log: RequestLog,
+}
+
+impl<S> LiteProxy<S> {
+    pub fn new(upstream: S) -> Self {
+        Self { upstream, cache: None, rate_limit: None, request_log: RequestLog::new() }
+    }
+
+    /// Queries of all clients, including the ones answered from the cache or rejected by the rate limit.
+    pub fn request_log(&self) -> &RequestLog {
+        &self.request_log
+    }
+
+    pub fn with_cache(mut self, cache: Arc<AnswerCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: Arc<RateLimit>) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+}
+
+impl<S: Clone> Service<SocketAddr> for LiteProxy<S> {
+    type Response = UnwrapService<RequestLogService<WrapErrorService<ProxyConnection<S>>>>;
+    type Error = Infallible;
+    type Future = future::Ready<std::result::Result<Self::Response, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<std::result::Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, client: SocketAddr) -> Self::Future {
+        let connection = ProxyConnection {
+            client,
+            upstream: self.upstream.clone(),
+            cache: self.cache.clone(),
+            rate_limit: self.rate_limit.clone(),
+        };
+        let log = RequestLogLayer::new(self.request_log.clone());
+        future::ok(UnwrapMessagesLayer.layer(log.layer(WrapErrorLayer.layer(connection))))
+    }
+}
+
+/// Queries of a single client connection, see [`LiteProxy`].
+pub struct ProxyConnection<S> {
+    client: SocketAddr,
+    upstream: S,
+    cache: Option<Arc<AnswerCache>>,
+    rate_limit: Option<Arc<RateLimit>>,
+}
+
+impl<S> Service<WrappedRequest> for ProxyConnection<S>
+where
+    S: Service<WrappedRequest, Response = Response, Error = LiteError> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = LiteError;
+    type Future = BoxFuture<'static, Result<Response>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: WrappedRequest) -> Self::Future {
+        if let Some(rate_limit) = &self.rate_limit {
+            if !rate_limit.try_acquire(self.client.ip()) {
+                log::debug!("[{}] Rate limit exceeded", self.client);
+                return Box::pin(future::ok(Response::Error(Error {
+                    code: RATE_LIMIT_ERROR_CODE,
+                    message: "rate limit exceeded".into(),
+                })));
+            }
+        }
+        if let Some(response) = self.cache.as_ref().and_then(|cache| cache.get(&request.request)) {
+            return Box::pin(future::ok(response));
+        }
+        let cache = self.cache.clone();
+        let upstream = self.upstream.clone();
+        Box::pin(async move {
+            let query = request.request.clone();
+            match upstream.oneshot(request).await {
+                Ok(response) => {
+                    if let Some(cache) = cache {
+                        cache.insert(&query, &response);
+                    }
+                    Ok(response)
+                }
+                // pass errors of the upstream server to the client as they are
+                Err(e) => match e.kind() {
+                    LiteError::ServerError(error) => Ok(Response::Error(error.clone())),
+                    _ => Err(e),
+                },
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::tl::common::{BlockIdExt, Int256};
+    use crate::tl::request::{GetBlock, GetBlockProof};
+    use crate::tl::response::BlockData;
+
+    use super::*;
+
+    fn block_id(seqno: u32) -> BlockIdExt {
+        BlockIdExt { workchain: -1, shard: 0x8000000000000000, seqno, root_hash: Int256::default(), file_hash: Int256::default() }
+    }
+
+    fn get_block(seqno: u32) -> (Request, Response) {
+        let request = Request::GetBlock(GetBlock { id: block_id(seqno) });
+        (request, Response::BlockData(BlockData { id: block_id(seqno), data: vec![0; 100] }))
+    }
+
+    #[test]
+    fn test_answer_cache() {
+        let (request, response) = get_block(1);
+        let size = tl_proto::serialize(&request).len() + response.max_size_hint();
+        let cache = AnswerCache::new(2 * size);
+        cache.insert(&request, &response);
+        assert_eq!(cache.get(&request), Some(response.clone()));
+        assert_eq!((cache.len(), cache.size()), (1, size));
+
+        // answers which may change and errors aren't cached
+        let time = Response::CurrentTime(crate::tl::response::CurrentTime { now: 1 });
+        cache.insert(&Request::GetTime, &time);
+        assert_eq!(cache.get(&Request::GetTime), None);
+        let latest_proof = Request::GetBlockProof(GetBlockProof {
+            mode: (),
+            known_block: block_id(1),
+            target_block: None,
+            allow_weak_target: None,
+            base_block_from_request: None,
+        });
+        cache.insert(&latest_proof, &response);
+        assert_eq!(cache.get(&latest_proof), None);
+        let (failed, _) = get_block(2);
+        cache.insert(&failed, &Response::Error(Error { code: 651, message: "not found".into() }));
+        assert_eq!(cache.get(&failed), None);
+        assert_eq!(cache.len(), 1);
+
+        // the oldest answer is evicted first
+        let (second, second_response) = get_block(2);
+        let (third, third_response) = get_block(3);
+        cache.insert(&second, &second_response);
+        cache.insert(&third, &third_response);
+        assert_eq!(cache.get(&request), None);
+        assert_eq!(cache.get(&second), Some(second_response));
+        assert_eq!(cache.get(&third), Some(third_response));
+        assert_eq!((cache.len(), cache.size()), (2, 2 * size));
+
+        // an answer larger than the cache doesn't evict anything
+        let (large, _) = get_block(4);
+        cache.insert(&large, &Response::BlockData(BlockData { id: block_id(4), data: vec![0; 3 * size] }));
+        assert_eq!(cache.get(&large), None);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let client: IpAddr = [10, 0, 0, 1].into();
+        let limit = RateLimit::new(20, 2);
+        assert!(limit.try_acquire(client));
+        assert!(limit.try_acquire(client));
+        assert!(!limit.try_acquire(client));
+        // every client has its own bucket
+        assert!(limit.try_acquire([10, 0, 0, 2].into()));
+
+        // a token is added every 50 ms
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limit.try_acquire(client));
+        assert!(!limit.try_acquire(client));
+
+        // no burst still allows single queries
+        let limit = RateLimit::new(1, 0);
+        assert!(limit.try_acquire(client));
+        assert!(!limit.try_acquire(client));
+    }
+}
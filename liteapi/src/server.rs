@@ -5,14 +5,14 @@ use std::net::SocketAddr;
 use std::time::Duration;
 
 use adnl::crypto::KeyPair;
-use adnl::AdnlPeer;
 use tokio::net::TcpListener;
 use tokio::net::ToSocketAddrs;
 use tokio_tower::multiplex::Server;
 use tower::MakeService;
 use tower::Service;
 
-use crate::peer::LitePeer;
+use crate::codec::server_handshake;
+use crate::peer::{FrameLimits, LitePeer};
 use crate::tl::adnl::Message;
 
 pub async fn serve<A, M>(addr: &A, private_key: KeyPair, mut service_maker: M) -> Result<(), Box<dyn std::error::Error>> 
@@ -58,7 +58,8 @@ pub async fn serve<A, M>(addr: &A, private_key: KeyPair, mut service_maker: M) -
         };
         let private_key = private_key.clone();
         tokio::spawn(async move {
-            let adnl = match AdnlPeer::handle_handshake(socket, |_| Some(private_key.clone())).await {
+            let limits = FrameLimits::default();
+            let transport = match server_handshake(socket, &private_key, limits.clone()).await {
                 Ok(x) => x,
                 Err(e) => {
                     log::error!("[{addr:?}] Handshake failed: {:?}", e);
@@ -66,7 +67,7 @@ pub async fn serve<A, M>(addr: &A, private_key: KeyPair, mut service_maker: M) -
                 }
             };
             log::debug!("[{addr:?}] Handshake performed");
-            let lite = LitePeer::new(adnl);
+            let lite = LitePeer::new(transport).with_frame_limits(limits);
             if let Err(e) = Server::new(lite, service).await {
                 log::error!("[{addr:?}] Server failed: {:?}", e);
             }
@@ -0,0 +1,674 @@
+//! Destinations of the data produced by the [`Indexer`](crate::indexer::Indexer).
+//!
+//! Sinks may buffer written data until [`BlockSink::flush`]. The indexer saves its checkpoint only after
+//! a flush, so after a restart it repeats the blocks which weren't flushed: every block is delivered at
+//! least once, and sinks should tolerate duplicates.
+//!
+//! Besides the in-memory sink, there are sinks writing JSON lines and CSV files, and with the `parquet`
+//! feature Parquet files, which can be loaded directly into pandas or DuckDB.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+#[cfg(feature = "parquet")]
+use std::path::PathBuf;
+
+use crate::indexer::Reorg;
+use crate::tl::common::BlockIdExt;
+use crate::tlb::{Account, AccountStatus, AccountStatusTag, Transaction};
+use crate::types::{BlockFull, LiteError};
+
+type Result<T> = std::result::Result<T, LiteError>;
+
+/// Receiver of indexed blocks, shard blocks come before the masterchain block which registered them.
+///
+/// Implemented for closures, which have nothing to flush.
+pub trait BlockSink {
+    fn write(&mut self, block: &BlockFull) -> Result<()>;
+
+    /// Make the blocks written so far durable.
+    fn flush(&mut self) -> Result<()>;
+
+    /// Handle a switch to another fork, called before the first block of the new fork is written.
+    ///
+    /// Fails with [`LiteError::Reorg`] by default, so indexing stops instead of mixing blocks of both forks.
+    fn reorg(&mut self, reorg: &Reorg) -> Result<()> {
+        Err(LiteError::Reorg(Box::new(reorg.clone())))
+    }
+}
+
+impl<F> BlockSink for F where F: FnMut(&BlockFull) -> Result<()> {
+    fn write(&mut self, block: &BlockFull) -> Result<()> {
+        self(block)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Receiver of indexed transactions, use [`Transactions`] to pass it to the indexer.
+pub trait TxSink {
+    fn write(&mut self, block: &BlockIdExt, transaction: &Transaction) -> Result<()>;
+
+    /// Make the transactions written so far durable.
+    fn flush(&mut self) -> Result<()>;
+}
+
+/// Block sink writing all transactions of the blocks into a [`TxSink`].
+#[derive(Debug, Clone, Default)]
+pub struct Transactions<S>(pub S);
+
+impl<S: TxSink> BlockSink for Transactions<S> {
+    fn write(&mut self, block: &BlockFull) -> Result<()> {
+        block.transactions.iter().try_for_each(|tx| self.0.write(&block.header.id, tx))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Sink keeping everything in memory, mostly useful for tests and small ranges.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    pub blocks: Vec<BlockFull>,
+    pub transactions: Vec<(BlockIdExt, Transaction)>,
+    pub reorgs: Vec<Reorg>,
+}
+
+impl BlockSink for MemorySink {
+    fn write(&mut self, block: &BlockFull) -> Result<()> {
+        self.blocks.push(block.clone());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn reorg(&mut self, reorg: &Reorg) -> Result<()> {
+        self.reorgs.push(reorg.clone());
+        Ok(())
+    }
+}
+
+impl TxSink for MemorySink {
+    fn write(&mut self, block: &BlockIdExt, transaction: &Transaction) -> Result<()> {
+        self.transactions.push((block.clone(), transaction.clone()));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink writing one JSON object per transaction or block into a file, hashes are hex encoded.
+///
+/// Transactions have the fields `workchain`, `shard`, `seqno`, `account`, `lt`, `hash`, `prev_trans_lt`,
+/// `now`, `in_msg`, `out_msgs` and `total_fees`, where `shard` is hex and `in_msg` is the inbound
+/// message hash or `null`. Blocks have the block id fields, `root_hash`, `file_hash` and `transactions`.
+pub struct JsonlSink<W: Write> {
+    writer: W,
+}
+
+impl JsonlSink<BufWriter<File>> {
+    /// Append to the file at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> JsonlSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> BlockSink for JsonlSink<W> {
+    fn write(&mut self, block: &BlockFull) -> Result<()> {
+        let id = &block.header.id;
+        writeln!(
+            self.writer,
+            r#"{{"workchain":{},"shard":"{:016x}","seqno":{},"root_hash":"{}","file_hash":"{}","transactions":{}}}"#,
+            id.workchain, id.shard, id.seqno, id.root_hash, id.file_hash, block.transactions.len(),
+        )?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> TxSink for JsonlSink<W> {
+    fn write(&mut self, block: &BlockIdExt, transaction: &Transaction) -> Result<()> {
+        let in_msg = match &transaction.in_msg {
+            Some(message) => format!(r#""{}""#, hex::encode(message.repr_hash())),
+            None => "null".to_owned(),
+        };
+        writeln!(
+            self.writer,
+            r#"{{"workchain":{},"shard":"{:016x}","seqno":{},"account":"{}","lt":{},"hash":"{}","prev_trans_lt":{},"now":{},"in_msg":{},"out_msgs":{},"total_fees":{}}}"#,
+            block.workchain, block.shard, block.seqno, hex::encode(transaction.account_addr), transaction.lt,
+            hex::encode(transaction.hash), transaction.prev_trans_lt, transaction.now, in_msg,
+            transaction.out_msgs.len(), transaction.total_fees.grams.nanotons(),
+        )?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Columns of [`CsvSink`] for transactions, `in_msg` is empty if there is no inbound message.
+pub const TRANSACTION_CSV_HEADER: &str = "workchain,shard,seqno,account,lt,hash,prev_trans_lt,now,orig_status,end_status,in_msg,out_msgs,total_fees";
+/// Columns of [`CsvSink::write_account`], `code_hash` and `data_hash` are empty unless the account is active.
+pub const ACCOUNT_CSV_HEADER: &str = "workchain,shard,seqno,address,last_trans_lt,balance,status,code_hash,data_hash";
+
+/// Sink writing transactions, or account state snapshots, as CSV rows with a stable set of columns.
+///
+/// Hashes and the shard are hex encoded, amounts are in nanotons. The header is written before the first
+/// row unless appending to a non-empty file, so one sink should be used for one kind of rows only.
+pub struct CsvSink<W: Write> {
+    writer: W,
+    header_written: bool,
+}
+
+impl CsvSink<BufWriter<File>> {
+    /// Append to the file at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let header_written = file.metadata()?.len() > 0;
+        Ok(Self { writer: BufWriter::new(file), header_written })
+    }
+}
+
+impl<W: Write> CsvSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, header_written: false }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Write a snapshot of `account` taken at `block`.
+    pub fn write_account(&mut self, block: &BlockIdExt, account: &Account) -> Result<()> {
+        let (code_hash, data_hash) = match &account.state {
+            AccountStatus::Active(init) => (
+                init.code.as_ref().map(|c| hex::encode(c.repr_hash())).unwrap_or_default(),
+                init.data.as_ref().map(|c| hex::encode(c.repr_hash())).unwrap_or_default(),
+            ),
+            _ => (String::new(), String::new()),
+        };
+        self.header(ACCOUNT_CSV_HEADER)?;
+        writeln!(
+            self.writer,
+            "{},{:016x},{},{},{},{},{},{},{}",
+            block.workchain, block.shard, block.seqno, account.address, account.last_trans_lt,
+            account.balance.grams.nanotons(), account_status_name(&account.state), code_hash, data_hash,
+        )?;
+        Ok(())
+    }
+
+    fn header(&mut self, header: &str) -> Result<()> {
+        if !self.header_written {
+            writeln!(self.writer, "{}", header)?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> TxSink for CsvSink<W> {
+    fn write(&mut self, block: &BlockIdExt, transaction: &Transaction) -> Result<()> {
+        let in_msg = transaction.in_msg.as_ref().map(|m| hex::encode(m.repr_hash())).unwrap_or_default();
+        self.header(TRANSACTION_CSV_HEADER)?;
+        writeln!(
+            self.writer,
+            "{},{:016x},{},{},{},{},{},{},{},{},{},{},{}",
+            block.workchain, block.shard, block.seqno, hex::encode(transaction.account_addr), transaction.lt,
+            hex::encode(transaction.hash), transaction.prev_trans_lt, transaction.now,
+            status_name(transaction.orig_status), status_name(transaction.end_status), in_msg,
+            transaction.out_msgs.len(), transaction.total_fees.grams.nanotons(),
+        )?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Message type of the transaction files of [`ParquetSink`], the columns of [`TRANSACTION_CSV_HEADER`].
+#[cfg(feature = "parquet")]
+pub const TRANSACTION_PARQUET_SCHEMA: &str = "message transaction {
+    required int32 workchain;
+    required binary shard (UTF8);
+    required int32 seqno (INTEGER(32, false));
+    required binary account (UTF8);
+    required int64 lt (INTEGER(64, false));
+    required binary hash (UTF8);
+    required int64 prev_trans_lt (INTEGER(64, false));
+    required int32 now (INTEGER(32, false));
+    required binary orig_status (UTF8);
+    required binary end_status (UTF8);
+    optional binary in_msg (UTF8);
+    required int32 out_msgs;
+    required fixed_len_byte_array(16) total_fees (DECIMAL(38, 0));
+}";
+/// Message type of the account files of [`ParquetSink`], the columns of [`ACCOUNT_CSV_HEADER`].
+#[cfg(feature = "parquet")]
+pub const ACCOUNT_PARQUET_SCHEMA: &str = "message account {
+    required int32 workchain;
+    required binary shard (UTF8);
+    required int32 seqno (INTEGER(32, false));
+    required binary address (UTF8);
+    required int64 last_trans_lt (INTEGER(64, false));
+    required fixed_len_byte_array(16) balance (DECIMAL(38, 0));
+    required binary status (UTF8);
+    optional binary code_hash (UTF8);
+    optional binary data_hash (UTF8);
+}";
+
+/// Sink writing transactions and account state snapshots as Parquet files into a directory.
+///
+/// The columns and values are those of [`CsvSink`] with their types, see [`TRANSACTION_PARQUET_SCHEMA`] and
+/// [`ACCOUNT_PARQUET_SCHEMA`]; amounts are decimals since they may not fit 64 bits. A Parquet file can't be
+/// appended to, so every flush writes the buffered rows into new files `transactions-NNNNNN.parquet` and
+/// `accounts-NNNNNN.parquet`, numbered on from the files already in the directory. Read them all at once,
+/// e.g. with `read_parquet('dir/transactions-*.parquet')` in DuckDB.
+#[cfg(feature = "parquet")]
+pub struct ParquetSink {
+    dir: PathBuf,
+    next_part: u32,
+    transactions: Vec<(BlockIdExt, Transaction)>,
+    accounts: Vec<(BlockIdExt, Account)>,
+}
+
+#[cfg(feature = "parquet")]
+impl ParquetSink {
+    /// Write into the directory at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let dir = path.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let mut next_part = 0;
+        for entry in std::fs::read_dir(&dir)? {
+            let name = entry?.file_name();
+            let part = name.to_str()
+                .and_then(|name| name.strip_prefix("transactions-").or_else(|| name.strip_prefix("accounts-")))
+                .and_then(|name| name.strip_suffix(".parquet"))
+                .and_then(|part| part.parse::<u32>().ok());
+            if let Some(part) = part {
+                next_part = next_part.max(part + 1);
+            }
+        }
+        Ok(Self { dir, next_part, transactions: Vec::new(), accounts: Vec::new() })
+    }
+
+    /// Write a snapshot of `account` taken at `block`.
+    pub fn write_account(&mut self, block: &BlockIdExt, account: &Account) -> Result<()> {
+        self.accounts.push((block.clone(), account.clone()));
+        Ok(())
+    }
+
+    fn write_part(&self, kind: &str, schema: &str, columns: Vec<parquet_columns::Column>) -> Result<()> {
+        let path = self.dir.join(format!("{}-{:06}.parquet", kind, self.next_part));
+        let partial = path.with_extension("parquet.tmp");
+        parquet_columns::write_file(&partial, schema, columns)?;
+        // a file appears only once complete, so a crash can't leave a file without its footer
+        std::fs::rename(&partial, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl TxSink for ParquetSink {
+    fn write(&mut self, block: &BlockIdExt, transaction: &Transaction) -> Result<()> {
+        self.transactions.push((block.clone(), transaction.clone()));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        use parquet_columns::Column;
+
+        if !self.transactions.is_empty() {
+            let rows = &self.transactions;
+            let columns = vec![
+                Column::Int32(rows.iter().map(|(block, _)| block.workchain).collect()),
+                Column::String(rows.iter().map(|(block, _)| Some(format!("{:016x}", block.shard))).collect()),
+                Column::Int32(rows.iter().map(|(block, _)| block.seqno as i32).collect()),
+                Column::String(rows.iter().map(|(_, tx)| Some(hex::encode(tx.account_addr))).collect()),
+                Column::Int64(rows.iter().map(|(_, tx)| tx.lt as i64).collect()),
+                Column::String(rows.iter().map(|(_, tx)| Some(hex::encode(tx.hash))).collect()),
+                Column::Int64(rows.iter().map(|(_, tx)| tx.prev_trans_lt as i64).collect()),
+                Column::Int32(rows.iter().map(|(_, tx)| tx.now as i32).collect()),
+                Column::String(rows.iter().map(|(_, tx)| Some(status_name(tx.orig_status).to_owned())).collect()),
+                Column::String(rows.iter().map(|(_, tx)| Some(status_name(tx.end_status).to_owned())).collect()),
+                Column::String(rows.iter().map(|(_, tx)| tx.in_msg.as_ref().map(|m| hex::encode(m.repr_hash()))).collect()),
+                Column::Int32(rows.iter().map(|(_, tx)| tx.out_msgs.len() as i32).collect()),
+                Column::Decimal(rows.iter().map(|(_, tx)| tx.total_fees.grams.nanotons()).collect()),
+            ];
+            self.write_part("transactions", TRANSACTION_PARQUET_SCHEMA, columns)?;
+        }
+        if !self.accounts.is_empty() {
+            let rows = &self.accounts;
+            let hashes = |account: &Account| match &account.state {
+                AccountStatus::Active(init) => (
+                    init.code.as_ref().map(|c| hex::encode(c.repr_hash())),
+                    init.data.as_ref().map(|c| hex::encode(c.repr_hash())),
+                ),
+                _ => (None, None),
+            };
+            let columns = vec![
+                Column::Int32(rows.iter().map(|(block, _)| block.workchain).collect()),
+                Column::String(rows.iter().map(|(block, _)| Some(format!("{:016x}", block.shard))).collect()),
+                Column::Int32(rows.iter().map(|(block, _)| block.seqno as i32).collect()),
+                Column::String(rows.iter().map(|(_, account)| Some(account.address.to_string())).collect()),
+                Column::Int64(rows.iter().map(|(_, account)| account.last_trans_lt as i64).collect()),
+                Column::Decimal(rows.iter().map(|(_, account)| account.balance.grams.nanotons()).collect()),
+                Column::String(rows.iter().map(|(_, account)| Some(account_status_name(&account.state).to_owned())).collect()),
+                Column::String(rows.iter().map(|(_, account)| hashes(account).0).collect()),
+                Column::String(rows.iter().map(|(_, account)| hashes(account).1).collect()),
+            ];
+            self.write_part("accounts", ACCOUNT_PARQUET_SCHEMA, columns)?;
+        }
+        if !self.transactions.is_empty() || !self.accounts.is_empty() {
+            self.next_part += 1;
+        }
+        self.transactions.clear();
+        self.accounts.clear();
+        Ok(())
+    }
+}
+
+/// Writing of a Parquet file with one row group from its columns.
+#[cfg(feature = "parquet")]
+mod parquet_columns {
+    use std::fs::File;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use parquet::data_type::{ByteArray, ByteArrayType, FixedLenByteArray, FixedLenByteArrayType, Int32Type, Int64Type};
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    use super::Result;
+
+    /// Values of a column, `None` of a string is a null.
+    pub enum Column {
+        Int32(Vec<i32>),
+        Int64(Vec<i64>),
+        String(Vec<Option<String>>),
+        /// `DECIMAL(38, 0)` stored in 16 bytes
+        Decimal(Vec<u128>),
+    }
+
+    pub fn write_file(path: &Path, schema: &str, columns: Vec<Column>) -> Result<()> {
+        let schema = Arc::new(parse_message_type(schema)?);
+        let file = File::create(path)?;
+        let mut writer = SerializedFileWriter::new(file.try_clone()?, schema, Default::default())?;
+        let mut row_group = writer.next_row_group()?;
+        for column in columns {
+            let Some(mut writer) = row_group.next_column()? else {
+                unreachable!("more columns than in the schema");
+            };
+            match column {
+                Column::Int32(values) => writer.typed::<Int32Type>().write_batch(&values, None, None)?,
+                Column::Int64(values) => writer.typed::<Int64Type>().write_batch(&values, None, None)?,
+                Column::String(values) => {
+                    let levels: Vec<i16> = values.iter().map(|value| value.is_some() as i16).collect();
+                    let values: Vec<ByteArray> = values.into_iter().flatten().map(|value| ByteArray::from(value.into_bytes())).collect();
+                    let writer = writer.typed::<ByteArrayType>();
+                    let levels = (writer.get_descriptor().max_def_level() > 0).then_some(&levels[..]);
+                    writer.write_batch(&values, levels, None)?
+                },
+                Column::Decimal(values) => {
+                    let values: Vec<FixedLenByteArray> = values.into_iter().map(|value| FixedLenByteArray::from(value.to_be_bytes().to_vec())).collect();
+                    writer.typed::<FixedLenByteArrayType>().write_batch(&values, None, None)?
+                },
+            };
+            writer.close()?;
+        }
+        row_group.close()?;
+        writer.close()?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+fn status_name(status: AccountStatusTag) -> &'static str {
+    match status {
+        AccountStatusTag::Uninit => "uninit",
+        AccountStatusTag::Frozen => "frozen",
+        AccountStatusTag::Active => "active",
+        AccountStatusTag::NonExist => "nonexist",
+    }
+}
+
+fn account_status_name(status: &AccountStatus) -> &'static str {
+    match status {
+        AccountStatus::Uninit => "uninit",
+        AccountStatus::Frozen(_) => "frozen",
+        AccountStatus::Active(_) => "active",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cell::{ArcCell, CellBuilder};
+    use crate::tl::common::Int256;
+    use crate::tl::response::BlockHeader;
+    use crate::tlb::{Coins, CurrencyCollection, MsgAddressInt, StateInit};
+
+    use super::*;
+
+    fn block_id(seqno: u32) -> BlockIdExt {
+        BlockIdExt { workchain: 0, shard: 1 << 63, seqno, root_hash: Int256([1; 32]), file_hash: Int256([2; 32]) }
+    }
+
+    fn cell(value: u32) -> ArcCell {
+        CellBuilder::new().store_u32(value).unwrap().build().unwrap()
+    }
+
+    fn transaction(lt: u64, in_msg: Option<ArcCell>) -> Transaction {
+        Transaction {
+            hash: [lt as u8; 32],
+            account_addr: [0xaa; 32],
+            lt,
+            prev_trans_hash: [0; 32],
+            prev_trans_lt: lt - 1,
+            now: 1_700_000_000,
+            orig_status: AccountStatusTag::Uninit,
+            end_status: AccountStatusTag::Active,
+            in_msg,
+            out_msgs: vec![cell(1), cell(2)],
+            total_fees: CurrencyCollection { grams: Coins(1234), other: None },
+            state_update: cell(3),
+            description: cell(4),
+        }
+    }
+
+    fn account(state: AccountStatus) -> Account {
+        Account {
+            address: MsgAddressInt::std(0, [0xbb; 32]),
+            last_paid: 0,
+            due_payment: None,
+            last_trans_lt: 42,
+            balance: CurrencyCollection { grams: Coins(1 << 100), other: None },
+            state,
+        }
+    }
+
+    fn active() -> AccountStatus {
+        AccountStatus::Active(StateInit { fixed_prefix_length: None, special: None, code: Some(cell(5)), data: None, library: None })
+    }
+
+    fn block(seqno: u32, transactions: Vec<Transaction>) -> BlockFull {
+        let header = BlockHeader {
+            id: block_id(seqno),
+            mode: (),
+            with_state_update: None,
+            with_value_flow: None,
+            with_extra: None,
+            with_shard_hashes: None,
+            with_prev_blk_signatures: None,
+            header_proof: Vec::new(),
+        };
+        BlockFull { header, transactions, shards: None }
+    }
+
+    #[test]
+    fn test_memory_sink() {
+        let mut sink = Transactions(MemorySink::default());
+        BlockSink::write(&mut sink, &block(7, vec![transaction(10, None), transaction(11, None)])).unwrap();
+        BlockSink::flush(&mut sink).unwrap();
+        let lts: Vec<_> = sink.0.transactions.iter().map(|(block, tx)| (block.seqno, tx.lt)).collect();
+        assert_eq!(lts, [(7, 10), (7, 11)]);
+
+        let reorg = Reorg { from: block_id(7), to: block_id(8) };
+        let mut sink = MemorySink::default();
+        BlockSink::write(&mut sink, &block(7, Vec::new())).unwrap();
+        sink.reorg(&reorg).unwrap();
+        assert_eq!(sink.blocks.len(), 1);
+        assert_eq!(sink.reorgs, std::slice::from_ref(&reorg));
+
+        // closures don't handle reorgs
+        let mut seqnos = Vec::new();
+        let mut sink = |block: &BlockFull| {
+            seqnos.push(block.header.id.seqno);
+            Ok(())
+        };
+        BlockSink::write(&mut sink, &block(9, Vec::new())).unwrap();
+        assert!(matches!(sink.reorg(&reorg), Err(LiteError::Reorg(r)) if *r == reorg));
+        assert_eq!(seqnos, [9]);
+    }
+
+    #[test]
+    fn test_jsonl_sink() {
+        let in_msg = cell(6);
+        let mut sink = JsonlSink::new(Vec::new());
+        BlockSink::write(&mut sink, &block(7, vec![transaction(10, None)])).unwrap();
+        TxSink::write(&mut sink, &block_id(7), &transaction(10, Some(in_msg.clone()))).unwrap();
+        TxSink::write(&mut sink, &block_id(7), &transaction(11, None)).unwrap();
+        TxSink::flush(&mut sink).unwrap();
+
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], format!(r#"{{"workchain":0,"shard":"8000000000000000","seqno":7,"root_hash":"{}","file_hash":"{}","transactions":1}}"#, Int256([1; 32]), Int256([2; 32])));
+        assert_eq!(lines[1], format!(
+            r#"{{"workchain":0,"shard":"8000000000000000","seqno":7,"account":"{}","lt":10,"hash":"{}","prev_trans_lt":9,"now":1700000000,"in_msg":"{}","out_msgs":2,"total_fees":1234}}"#,
+            "aa".repeat(32), "0a".repeat(32), hex::encode(in_msg.repr_hash()),
+        ));
+        assert!(lines[2].contains(r#""lt":11,"#) && lines[2].contains(r#""in_msg":null,"#));
+    }
+
+    #[test]
+    fn test_csv_sink() {
+        let mut sink = CsvSink::new(Vec::new());
+        TxSink::write(&mut sink, &block_id(7), &transaction(10, None)).unwrap();
+        TxSink::write(&mut sink, &block_id(7), &transaction(11, Some(cell(6)))).unwrap();
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], TRANSACTION_CSV_HEADER);
+        assert_eq!(lines[1], format!("0,8000000000000000,7,{},10,{},9,1700000000,uninit,active,,2,1234", "aa".repeat(32), "0a".repeat(32)));
+        assert_eq!(lines[2].split(',').nth(10), Some(hex::encode(cell(6).repr_hash()).as_str()));
+        for line in &lines {
+            assert_eq!(line.split(',').count(), TRANSACTION_CSV_HEADER.split(',').count());
+        }
+
+        let mut sink = CsvSink::new(Vec::new());
+        sink.write_account(&block_id(7), &account(active())).unwrap();
+        sink.write_account(&block_id(7), &account(AccountStatus::Frozen([0; 32]))).unwrap();
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let address = format!("0:{}", "bb".repeat(32));
+        assert_eq!(output, format!(
+            "{}\n0,8000000000000000,7,{},42,{},active,{},\n0,8000000000000000,7,{},42,{},frozen,,\n",
+            ACCOUNT_CSV_HEADER, address, 1u128 << 100, hex::encode(cell(5).repr_hash()), address, 1u128 << 100,
+        ));
+    }
+
+    #[test]
+    fn test_csv_sink_append() {
+        let path = std::env::temp_dir().join(format!("ton-liteapi-csv-{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        for lt in [10, 11] {
+            let mut sink = CsvSink::open(&path).unwrap();
+            TxSink::write(&mut sink, &block_id(7), &transaction(lt, None)).unwrap();
+            TxSink::flush(&mut sink).unwrap();
+        }
+        let output = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // the header isn't repeated when appending
+        assert_eq!(output.lines().count(), 3);
+        assert_eq!(output.matches(TRANSACTION_CSV_HEADER).count(), 1);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_sink() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::RowAccessor;
+
+        let dir = std::env::temp_dir().join(format!("ton-liteapi-parquet-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let read = |name: &str| {
+            let reader = SerializedFileReader::new(File::open(dir.join(name)).unwrap()).unwrap();
+            reader.get_row_iter(None).unwrap().map(|row| row.unwrap()).collect::<Vec<_>>()
+        };
+
+        let mut sink = ParquetSink::open(&dir).unwrap();
+        TxSink::write(&mut sink, &block_id(7), &transaction(10, None)).unwrap();
+        TxSink::write(&mut sink, &block_id(7), &transaction(11, Some(cell(6)))).unwrap();
+        sink.write_account(&block_id(7), &account(active())).unwrap();
+        // nothing is written before the flush
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        TxSink::flush(&mut sink).unwrap();
+        TxSink::flush(&mut sink).unwrap();
+
+        let rows = read("transactions-000000.parquet");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get_int(0).unwrap(), 0);
+        assert_eq!(rows[0].get_string(1).unwrap(), "8000000000000000");
+        assert_eq!(rows[0].get_uint(2).unwrap(), 7);
+        assert_eq!(rows[0].get_string(3).unwrap(), &"aa".repeat(32));
+        assert_eq!(rows[0].get_ulong(4).unwrap(), 10);
+        assert_eq!(rows[0].get_uint(7).unwrap(), 1_700_000_000);
+        assert_eq!(rows[0].get_string(9).unwrap(), "active");
+        assert!(rows[0].get_string(10).is_err());
+        assert_eq!(rows[1].get_string(10).unwrap(), &hex::encode(cell(6).repr_hash()));
+        assert_eq!(rows[1].get_int(11).unwrap(), 2);
+        assert_eq!(rows[1].get_decimal(12).unwrap().data(), 1234u128.to_be_bytes());
+
+        let rows = read("accounts-000000.parquet");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_string(3).unwrap(), &format!("0:{}", "bb".repeat(32)));
+        assert_eq!(rows[0].get_decimal(5).unwrap().data(), (1u128 << 100).to_be_bytes());
+        assert_eq!(rows[0].get_string(6).unwrap(), "active");
+        assert_eq!(rows[0].get_string(7).unwrap(), &hex::encode(cell(5).repr_hash()));
+        assert!(rows[0].get_string(8).is_err());
+
+        // a new sink numbers its files on instead of overwriting
+        let mut sink = ParquetSink::open(&dir).unwrap();
+        TxSink::write(&mut sink, &block_id(8), &transaction(12, None)).unwrap();
+        TxSink::flush(&mut sink).unwrap();
+        assert_eq!(read("transactions-000001.parquet")[0].get_uint(2).unwrap(), 8);
+        let mut names: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect();
+        names.sort();
+        assert_eq!(names, ["accounts-000000.parquet", "transactions-000000.parquet", "transactions-000001.parquet"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+//! Logical and unix time of blocks, transactions and messages.
+//!
+//! Logical time (lt) orders everything happening in the blockchain: a block covers the range
+//! `start_lt..end_lt`, and a transaction has a greater lt than the message it processes. Unix times of blocks
+//! and states, like `gen_utime`, are seconds stored as `u32`.
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Logical time of a transaction, message or block boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct LogicalTime(pub u64);
+
+impl LogicalTime {
+    /// Logical time `delta` after this one, saturating at `u64::MAX`.
+    pub fn advance(self, delta: u64) -> Self {
+        Self(self.0.saturating_add(delta))
+    }
+
+    /// The least logical time after this one.
+    pub fn next(self) -> Self {
+        self.advance(1)
+    }
+
+    /// Whether this logical time belongs to the block with the given `start_lt` and `end_lt`.
+    pub fn is_within(self, start_lt: u64, end_lt: u64) -> bool {
+        (start_lt..end_lt).contains(&self.0)
+    }
+}
+
+impl From<u64> for LogicalTime {
+    fn from(lt: u64) -> Self {
+        Self(lt)
+    }
+}
+
+impl From<LogicalTime> for u64 {
+    fn from(lt: LogicalTime) -> Self {
+        lt.0
+    }
+}
+
+impl fmt::Display for LogicalTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// `SystemTime` of a unix time such as `gen_utime`.
+pub fn system_time(utime: u32) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(utime as u64)
+}
+
+/// Unix time of `time`, clamped to the range of `u32`.
+pub fn unix_time(time: SystemTime) -> u32 {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    secs.min(u32::MAX as u64) as u32
+}
+
+/// Time passed since `utime`, zero if it's in the future.
+pub fn age(utime: u32) -> Duration {
+    SystemTime::now().duration_since(system_time(utime)).unwrap_or_default()
+}
+
+/// Whether more than `max_age` has passed since `utime`.
+pub fn is_older_than(utime: u32, max_age: Duration) -> bool {
+    age(utime) > max_age
+}
@@ -16,7 +16,7 @@ pub enum Message {
 
     /// adnl.message.answer query_id:int256 answer:bytes = adnl.Message;
     #[tl(id = 0x0fac8416)]
-    Answer { query_id: Int256, #[tl(with = "struct_as_bytes")] answer: Response },
+    Answer { query_id: Int256, #[tl(with = "response_as_bytes")] answer: Response },
 
     /// tcp.ping random_id:long = tcp.Pong;
     #[tl(id = 0x4d082b9a)]
@@ -25,4 +25,160 @@ pub enum Message {
     /// tcp.pong random_id:long = tcp.Pong;
     #[tl(id = 0xdc69fb03)]
     Pong { random_id: u64 },
-}
\ No newline at end of file
+}
+
+/// Key of an ADNL peer or channel, its ADNL id is the sha256 of the serialized key.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(
+    boxed,
+    scheme_inline = r##"pub.ed25519 key:int256 = PublicKey;
+        pub.aes key:int256 = PublicKey;"##
+)]
+pub enum PublicKey {
+    /// pub.ed25519 key:int256 = PublicKey;
+    #[tl(id = "pub.ed25519")]
+    Ed25519 { key: Int256 },
+    /// pub.aes key:int256 = PublicKey;
+    #[tl(id = "pub.aes")]
+    Aes { key: Int256 },
+}
+
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(
+    boxed,
+    scheme_inline = r##"adnl.address.udp ip:int port:int = adnl.Address;
+        adnl.address.udp6 ip:int128 port:int = adnl.Address;
+        adnl.address.tunnel to:int256 pubkey:PublicKey = adnl.Address;"##
+)]
+pub enum Address {
+    /// adnl.address.udp ip:int port:int = adnl.Address;
+    #[tl(id = "adnl.address.udp")]
+    Udp { ip: u32, port: u32 },
+    /// adnl.address.udp6 ip:int128 port:int = adnl.Address;
+    #[tl(id = "adnl.address.udp6")]
+    Udp6 { ip: [u8; 16], port: u32 },
+    /// adnl.address.tunnel to:int256 pubkey:PublicKey = adnl.Address;
+    #[tl(id = "adnl.address.tunnel")]
+    Tunnel { to: Int256, pubkey: PublicKey },
+}
+
+/// adnl.addressList addrs:(vector adnl.Address) version:int reinit_date:int priority:int expire_at:int = adnl.AddressList;
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct AddressList {
+    pub addrs: Vec<Address>,
+    pub version: u32,
+    pub reinit_date: u32,
+    pub priority: u32,
+    pub expire_at: u32,
+}
+
+/// Message of ADNL over UDP, where [`Message::Query`] and [`Message::Answer`] are the same objects with the
+/// payload kept as bytes.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(
+    boxed,
+    scheme_inline = r##"adnl.message.createChannel key:int256 date:int = adnl.Message;
+        adnl.message.confirmChannel key:int256 peer_key:int256 date:int = adnl.Message;
+        adnl.message.custom data:bytes = adnl.Message;
+        adnl.message.nop = adnl.Message;
+        adnl.message.reinit date:int = adnl.Message;
+        adnl.message.query query_id:int256 query:bytes = adnl.Message;
+        adnl.message.answer query_id:int256 answer:bytes = adnl.Message;
+        adnl.message.part hash:int256 total_size:int offset:int data:bytes = adnl.Message;"##
+)]
+pub enum UdpMessage {
+    /// adnl.message.createChannel key:int256 date:int = adnl.Message;
+    #[tl(id = "adnl.message.createChannel")]
+    CreateChannel { key: Int256, date: u32 },
+    /// adnl.message.confirmChannel key:int256 peer_key:int256 date:int = adnl.Message;
+    #[tl(id = "adnl.message.confirmChannel")]
+    ConfirmChannel { key: Int256, peer_key: Int256, date: u32 },
+    /// adnl.message.custom data:bytes = adnl.Message;
+    #[tl(id = "adnl.message.custom")]
+    Custom { #[derivative(Debug(format_with = "fmt_bytes"))] data: Vec<u8> },
+    /// adnl.message.nop = adnl.Message;
+    #[tl(id = "adnl.message.nop")]
+    Nop,
+    /// adnl.message.reinit date:int = adnl.Message;
+    #[tl(id = "adnl.message.reinit")]
+    Reinit { date: u32 },
+    /// adnl.message.query query_id:int256 query:bytes = adnl.Message;
+    #[tl(id = "adnl.message.query")]
+    Query { query_id: Int256, #[derivative(Debug(format_with = "fmt_bytes"))] query: Vec<u8> },
+    /// adnl.message.answer query_id:int256 answer:bytes = adnl.Message;
+    #[tl(id = "adnl.message.answer")]
+    Answer { query_id: Int256, #[derivative(Debug(format_with = "fmt_bytes"))] answer: Vec<u8> },
+    /// Piece of a message too long for one packet, `hash` is the sha256 of the serialized message
+    ///
+    /// adnl.message.part hash:int256 total_size:int offset:int data:bytes = adnl.Message;
+    #[tl(id = "adnl.message.part")]
+    Part { hash: Int256, total_size: u32, offset: u32, #[derivative(Debug(format_with = "fmt_bytes"))] data: Vec<u8> },
+}
+
+/// Datagram of ADNL over UDP once decrypted. Packets outside a channel carry the key of the sender and are
+/// signed by it, the signature covering the packet without it.
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+#[tl(
+    boxed,
+    id = "adnl.packetContents",
+    scheme_inline = r##"adnl.packetContents
+        rand1:bytes
+        flags:#
+        from:flags.0?PublicKey
+        from_short:flags.1?adnl.id.short
+        message:flags.2?adnl.Message
+        messages:flags.3?(vector adnl.Message)
+        address:flags.4?adnl.addressList
+        priority_address:flags.5?adnl.addressList
+        seqno:flags.6?long
+        confirm_seqno:flags.7?long
+        recv_addr_list_version:flags.8?int
+        recv_priority_addr_list_version:flags.9?int
+        reinit_date:flags.10?int
+        dst_reinit_date:flags.10?int
+        signature:flags.11?bytes
+        rand2:bytes
+        = adnl.PacketContents;"##
+)]
+pub struct PacketContents {
+    #[derivative(Debug = "ignore")]
+    pub rand1: Vec<u8>,
+    #[tl(flags)]
+    pub flags: (),
+    #[tl(flags_bit = "flags.0")]
+    pub from: Option<PublicKey>,
+    /// adnl.id.short id:int256 = adnl.id.Short;
+    #[tl(flags_bit = "flags.1")]
+    pub from_short: Option<Int256>,
+    #[tl(flags_bit = "flags.2")]
+    pub message: Option<UdpMessage>,
+    #[tl(flags_bit = "flags.3")]
+    pub messages: Option<Vec<UdpMessage>>,
+    #[tl(flags_bit = "flags.4")]
+    pub address: Option<AddressList>,
+    #[tl(flags_bit = "flags.5")]
+    pub priority_address: Option<AddressList>,
+    #[tl(flags_bit = "flags.6")]
+    pub seqno: Option<u64>,
+    #[tl(flags_bit = "flags.7")]
+    pub confirm_seqno: Option<u64>,
+    #[tl(flags_bit = "flags.8")]
+    pub recv_addr_list_version: Option<u32>,
+    #[tl(flags_bit = "flags.9")]
+    pub recv_priority_addr_list_version: Option<u32>,
+    /// Set together with `dst_reinit_date`
+    #[tl(flags_bit = "flags.10")]
+    pub reinit_date: Option<u32>,
+    #[tl(flags_bit = "flags.10")]
+    pub dst_reinit_date: Option<u32>,
+    #[tl(flags_bit = "flags.11")]
+    #[derivative(Debug(format_with = "fmt_opt_bytes"))]
+    pub signature: Option<Vec<u8>>,
+    #[derivative(Debug = "ignore")]
+    pub rand2: Vec<u8>,
+}
@@ -1,11 +1,17 @@
 use core::fmt;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::{fmt::Display, str::FromStr};
 
 use derivative::Derivative;
 use hex::FromHex;
 use tl_proto::{TlRead, TlWrite};
+use crate::tlb::ShardIdent;
 use super::utils::*;
 
+/// Workchain of the masterchain
+pub const MASTERCHAIN_WORKCHAIN: i32 = -1;
+
 /// true = True;
 #[derive(TlRead, TlWrite, Derivative)]
 #[derivative(Debug, Clone, PartialEq)]
@@ -90,6 +96,73 @@ pub struct BlockIdExt {
     pub file_hash: Int256,
 }
 
+impl BlockId {
+    pub fn is_masterchain(&self) -> bool {
+        self.workchain == MASTERCHAIN_WORKCHAIN
+    }
+
+    pub fn shard_ident(&self) -> ShardIdent {
+        ShardIdent::from(self)
+    }
+}
+
+impl BlockIdExt {
+    pub fn is_masterchain(&self) -> bool {
+        self.workchain == MASTERCHAIN_WORKCHAIN
+    }
+
+    pub fn shard_ident(&self) -> ShardIdent {
+        ShardIdent::from(self)
+    }
+
+    /// The id without hashes.
+    pub fn block_id(&self) -> BlockId {
+        BlockId { workchain: self.workchain, shard: self.shard, seqno: self.seqno }
+    }
+
+    /// Whether both blocks belong to the same shard chain, i.e. have the same workchain and shard.
+    pub fn is_same_chain(&self, other: &BlockIdExt) -> bool {
+        self.workchain == other.workchain && self.shard == other.shard
+    }
+
+    /// Whether this block precedes `block` following the `prev` links of blocks, as returned by [`prev_blocks`](crate::tlb::prev_blocks).
+    ///
+    /// A block isn't its own ancestor. `prev` returns an empty list for blocks whose links are unknown,
+    /// so the check fails rather than guessing when the chain between the blocks is incomplete.
+    pub fn is_ancestor_of(&self, block: &BlockIdExt, mut prev: impl FnMut(&BlockIdExt) -> Vec<BlockIdExt>) -> bool {
+        let mut visited = HashSet::new();
+        let mut queue = vec![block.clone()];
+        while let Some(id) = queue.pop() {
+            // seqno strictly grows along prev links, also across splits and merges
+            if id.workchain != self.workchain || id.seqno <= self.seqno || !visited.insert(id.clone()) {
+                continue;
+            }
+            for prev in prev(&id) {
+                if prev == *self {
+                    return true;
+                }
+                queue.push(prev);
+            }
+        }
+        false
+    }
+}
+
+/// Blocks of the same shard chain are ordered by seqno, other pairs aren't comparable.
+///
+/// Distinct blocks with the same seqno, i.e. forks, aren't comparable either.
+impl PartialOrd for BlockIdExt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if !self.is_same_chain(other) {
+            return None;
+        }
+        match self.seqno.cmp(&other.seqno) {
+            Ordering::Equal if self != other => None,
+            ordering => Some(ordering),
+        }
+    }
+}
+
 impl fmt::Display for BlockIdExt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "({},{:X},{}):{}:{}", self.workchain, self.shard, self.seqno, self.root_hash.to_string(), self.file_hash.to_string())
@@ -172,6 +245,32 @@ pub enum BlockLink {
     },
 }
 
+/// Object of a constructor unknown to the scheme.
+///
+/// TL objects are not length-prefixed, so nothing after an unknown constructor can be parsed: reading takes the
+/// rest of the packet as `data`, which for a vector are `count` objects including this one. There is no `TlWrite`,
+/// the bytes are only written back by the answer holding them.
+#[derive(Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct Unsupported {
+    pub constructor: u32,
+    /// 1 when read on its own, set by the reader of a vector to the number of its remaining objects
+    pub count: u32,
+    #[derivative(Debug(format_with = "fmt_bytes"))]
+    pub data: Vec<u8>,
+}
+
+impl<'a> TlRead<'a> for Unsupported {
+    type Repr = tl_proto::Boxed;
+
+    fn read_from(packet: &'a [u8], offset: &mut usize) -> tl_proto::TlResult<Self> {
+        let constructor = u32::read_from(packet, offset)?;
+        let data = packet[*offset..].to_vec();
+        *offset = packet.len();
+        Ok(Self { constructor, count: 1, data })
+    }
+}
+
 /// tonNode.zeroStateIdExt workchain:int root_hash:int256 file_hash:int256 = tonNode.ZeroStateIdExt;
 #[derive(TlRead, TlWrite, Derivative)]
 #[derivative(Debug, Clone, PartialEq)]
@@ -4,5 +4,16 @@ pub mod request;
 pub mod response;
 pub mod utils;
 
+/// Version of `lite_api.tl` the types follow, as reported in `liteServer.version` by liteservers of the same release.
+///
+/// Liteservers with a newer scheme may send constructors unknown to this crate: unknown answers are kept as
+/// [`Response::Raw`](response::Response::Raw), unknown block links in [`PartialBlockProof::unsupported`](response::PartialBlockProof::unsupported),
+/// and fields appended to known constructors are ignored. `liteServer.BlockLink` is the only type with several
+/// constructors inside the answers, other objects are bare and can't have unknown ones; a new polymorphic
+/// type is read with [`read_known`](utils::read_known) the same way.
+pub const SCHEME_VERSION: u32 = 0x101;
+/// Capabilities of a liteserver implementing [`SCHEME_VERSION`], the bits of `liteServer.version`.
+pub const SCHEME_CAPABILITIES: u64 = 7;
+
 #[cfg(test)]
 mod tests;
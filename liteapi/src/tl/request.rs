@@ -1,7 +1,15 @@
 use derivative::Derivative;
 use tl_proto::{TlRead, TlWrite};
 
+use crate::pool::RequestContext;
+
 use super::common::*;
+use super::response::{
+    AccountState, AllShardsInfo, BlockData, BlockHeader, BlockOutMsgQueueSize, BlockState, BlockTransactions, ConfigInfo,
+    CurrentTime, DispatchQueueInfo, DispatchQueueMessages, LibraryResult, LibraryResultWithProof, LookupBlockResult,
+    MasterchainInfo, MasterchainInfoExt, OutMsgQueueSizes, PartialBlockProof, RunMethodResult, SendMsgStatus, ShardBlockProof,
+    ShardInfo, TransactionInfo, TransactionList, ValidatorStats, Version,
+};
 use super::utils::*;
 
 /// liteServer.query data:bytes = Object;
@@ -22,7 +30,19 @@ pub struct LiteQuery {
 pub struct WrappedRequest {
     #[tl(read_with = "lossy_read")]
     pub wait_masterchain_seqno: Option<WaitMasterchainSeqno>,
+    #[tl(with = "request_or_raw")]
     pub request: Request,
+    /// Routing of the request set by the task which sent it, not part of the scheme
+    #[tl(skip)]
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    pub context: RequestContext,
+}
+
+impl WrappedRequest {
+    /// Request without waiting for a masterchain block, with the context of the current task.
+    pub fn new(request: Request) -> Self {
+        Self { wait_masterchain_seqno: None, request, context: RequestContext::current() }
+    }
 }
 
 /// liteServer.query data:bytes = Object;
@@ -38,6 +58,18 @@ pub struct WaitMasterchainSeqno {
     pub timeout_ms: u32,
 }
 
+/// liteServer.getMasterchainInfo = liteServer.MasterchainInfo;
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetMasterchainInfo;
+
+/// liteServer.getTime = liteServer.CurrentTime;
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetTime;
+
+/// liteServer.getVersion = liteServer.Version;
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetVersion;
+
 #[derive(TlRead, TlWrite, Derivative)]
 #[derivative(Debug, Clone, PartialEq)]
 pub struct GetMasterchainInfoExt {
@@ -474,4 +506,170 @@ pub enum Request {
     /// liteServer.getDispatchQueueMessages mode:# id:tonNode.blockIdExt addr:int256 after_lt:long max_messages:int want_proof:mode.0?true one_account:mode.1?true messages_boc:mode.2?true = liteServer.DispatchQueueMessages;
     #[tl(id = 0xbbfd6439)]
     GetDispatchQueueMessages(GetDispatchQueueMessages),
+
+    /// Pre-serialized function unknown to the scheme, including its constructor id.
+    ///
+    /// Written as is inside [`WrappedRequest`], the id of this variant never appears on the wire.
+    #[tl(id = 0x00000000)]
+    Raw(#[derivative(Debug(format_with="fmt_bytes"))] Vec<u8>),
+}
+
+impl Request {
+    /// TL name of the function, e.g. `liteServer.getTime`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Request::GetMasterchainInfo => "liteServer.getMasterchainInfo",
+            Request::GetMasterchainInfoExt(_) => "liteServer.getMasterchainInfoExt",
+            Request::GetTime => "liteServer.getTime",
+            Request::GetVersion => "liteServer.getVersion",
+            Request::GetBlock(_) => "liteServer.getBlock",
+            Request::GetState(_) => "liteServer.getState",
+            Request::GetBlockHeader(_) => "liteServer.getBlockHeader",
+            Request::SendMessage(_) => "liteServer.sendMessage",
+            Request::GetAccountState(_) => "liteServer.getAccountState",
+            Request::GetAccountStatePrunned(_) => "liteServer.getAccountStatePrunned",
+            Request::RunSmcMethod(_) => "liteServer.runSmcMethod",
+            Request::GetShardInfo(_) => "liteServer.getShardInfo",
+            Request::GetAllShardsInfo(_) => "liteServer.getAllShardsInfo",
+            Request::GetOneTransaction(_) => "liteServer.getOneTransaction",
+            Request::GetTransactions(_) => "liteServer.getTransactions",
+            Request::LookupBlock(_) => "liteServer.lookupBlock",
+            Request::LookupBlockWithProof(_) => "liteServer.lookupBlockWithProof",
+            Request::ListBlockTransactions(_) => "liteServer.listBlockTransactions",
+            Request::ListBlockTransactionsExt(_) => "liteServer.listBlockTransactionsExt",
+            Request::GetBlockProof(_) => "liteServer.getBlockProof",
+            Request::GetConfigAll(_) => "liteServer.getConfigAll",
+            Request::GetConfigParams(_) => "liteServer.getConfigParams",
+            Request::GetValidatorStats(_) => "liteServer.getValidatorStats",
+            Request::GetLibraries(_) => "liteServer.getLibraries",
+            Request::GetLibrariesWithProof(_) => "liteServer.getLibrariesWithProof",
+            Request::GetShardBlockProof(_) => "liteServer.getShardBlockProof",
+            Request::GetOutMsgQueueSizes(_) => "liteServer.getOutMsgQueueSizes",
+            Request::GetBlockOutMsgQueueSize(_) => "liteServer.getBlockOutMsgQueueSize",
+            Request::GetDispatchQueueInfo(_) => "liteServer.getDispatchQueueInfo",
+            Request::GetDispatchQueueMessages(_) => "liteServer.getDispatchQueueMessages",
+            Request::Raw(_) => "raw",
+        }
+    }
+
+    /// Whether sending the function twice has the same effect as sending it once, so it can be resubmitted
+    /// when the connection drops before the answer arrives. Only `sendMessage` and [`Request::Raw`],
+    /// whose effect is unknown, are not.
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(self, Request::SendMessage(_) | Request::Raw(_))
+    }
+
+    /// Workchain the function is about: of the account for account queries, of the block for block queries.
+    /// Masterchain info belongs to the masterchain, `None` for functions without a workchain.
+    pub fn workchain(&self) -> Option<i32> {
+        match self {
+            Request::GetMasterchainInfo | Request::GetMasterchainInfoExt(_) => Some(MASTERCHAIN_WORKCHAIN),
+            Request::GetAccountState(req) | Request::GetAccountStatePrunned(req) => Some(req.account.workchain),
+            Request::RunSmcMethod(req) => Some(req.account.workchain),
+            Request::GetOneTransaction(req) => Some(req.account.workchain),
+            Request::GetTransactions(req) => Some(req.account.workchain),
+            Request::GetShardInfo(req) => Some(req.workchain),
+            Request::LookupBlock(req) => Some(req.id.workchain),
+            Request::LookupBlockWithProof(req) => Some(req.id.workchain),
+            Request::GetBlock(req) => Some(req.id.workchain),
+            Request::GetState(req) => Some(req.id.workchain),
+            Request::GetBlockHeader(req) => Some(req.id.workchain),
+            Request::GetAllShardsInfo(req) => Some(req.id.workchain),
+            Request::ListBlockTransactions(req) | Request::ListBlockTransactionsExt(req) => Some(req.id.workchain),
+            Request::GetBlockProof(req) => Some(req.known_block.workchain),
+            Request::GetConfigAll(req) => Some(req.id.workchain),
+            Request::GetConfigParams(req) => Some(req.id.workchain),
+            Request::GetValidatorStats(req) => Some(req.id.workchain),
+            Request::GetLibrariesWithProof(req) => Some(req.id.workchain),
+            Request::GetShardBlockProof(req) => Some(req.id.workchain),
+            Request::GetBlockOutMsgQueueSize(req) => Some(req.id.workchain),
+            Request::GetDispatchQueueInfo(req) => Some(req.id.workchain),
+            Request::GetDispatchQueueMessages(req) => Some(req.id.workchain),
+            Request::GetOutMsgQueueSizes(req) => req.shard_id.map(|(workchain, _)| workchain as i32),
+            Request::GetTime | Request::GetVersion | Request::SendMessage(_) | Request::GetLibraries(_) | Request::Raw(_) => None,
+        }
+    }
+}
+
+/// Function of the scheme together with the type of its answer, see [`LiteClient::query`](crate::client::LiteClient::query).
+///
+/// Functions sharing their arguments with another one, `getAccountStatePrunned` and `listBlockTransactionsExt`,
+/// are sent as `getAccountState` and `listBlockTransactions`.
+pub trait LiteFunction: Into<Request> {
+    type Response: FromResponse;
 }
+
+macro_rules! lite_functions {
+    ($($function:ident => $response:ident),* $(,)?) => {$(
+        impl LiteFunction for $function {
+            type Response = $response;
+        }
+    )*};
+}
+
+macro_rules! impl_from_function {
+    ($($function:ident),* $(,)?) => {$(
+        impl From<$function> for Request {
+            fn from(function: $function) -> Self {
+                Request::$function(function)
+            }
+        }
+    )*};
+}
+
+impl From<GetMasterchainInfo> for Request {
+    fn from(_: GetMasterchainInfo) -> Self {
+        Request::GetMasterchainInfo
+    }
+}
+
+impl From<GetTime> for Request {
+    fn from(_: GetTime) -> Self {
+        Request::GetTime
+    }
+}
+
+impl From<GetVersion> for Request {
+    fn from(_: GetVersion) -> Self {
+        Request::GetVersion
+    }
+}
+
+impl_from_function!(
+    GetMasterchainInfoExt, GetBlock, GetState, GetBlockHeader, SendMessage, GetAccountState, RunSmcMethod,
+    GetShardInfo, GetAllShardsInfo, GetOneTransaction, GetTransactions, LookupBlock, LookupBlockWithProof,
+    ListBlockTransactions, GetBlockProof, GetConfigAll, GetConfigParams, GetValidatorStats, GetLibraries,
+    GetLibrariesWithProof, GetShardBlockProof, GetOutMsgQueueSizes, GetBlockOutMsgQueueSize, GetDispatchQueueInfo,
+    GetDispatchQueueMessages,
+);
+
+lite_functions!(
+    GetMasterchainInfo => MasterchainInfo,
+    GetMasterchainInfoExt => MasterchainInfoExt,
+    GetTime => CurrentTime,
+    GetVersion => Version,
+    GetBlock => BlockData,
+    GetState => BlockState,
+    GetBlockHeader => BlockHeader,
+    SendMessage => SendMsgStatus,
+    GetAccountState => AccountState,
+    RunSmcMethod => RunMethodResult,
+    GetShardInfo => ShardInfo,
+    GetAllShardsInfo => AllShardsInfo,
+    GetOneTransaction => TransactionInfo,
+    GetTransactions => TransactionList,
+    LookupBlock => BlockHeader,
+    LookupBlockWithProof => LookupBlockResult,
+    ListBlockTransactions => BlockTransactions,
+    GetBlockProof => PartialBlockProof,
+    GetConfigAll => ConfigInfo,
+    GetConfigParams => ConfigInfo,
+    GetValidatorStats => ValidatorStats,
+    GetLibraries => LibraryResult,
+    GetLibrariesWithProof => LibraryResultWithProof,
+    GetShardBlockProof => ShardBlockProof,
+    GetOutMsgQueueSizes => OutMsgQueueSizes,
+    GetBlockOutMsgQueueSize => BlockOutMsgQueueSize,
+    GetDispatchQueueInfo => DispatchQueueInfo,
+    GetDispatchQueueMessages => DispatchQueueMessages,
+);
@@ -1,5 +1,12 @@
+use std::time::Duration;
+
 use derivative::Derivative;
-use tl_proto::{TlRead, TlWrite};
+use sha2::{Digest, Sha256};
+use tl_proto::{TlPacket, TlRead, TlResult, TlWrite};
+
+use crate::cell::{deserialize_boc, ArcCell, Cell, CellError, CellType};
+use crate::types::LiteError;
+use crate::tlb::{block_hash, block_state_hash, lookup_block_transaction, shard_hashes_root, Account, BlockInfo, CreatorStats, ExitCode, McStateConfig, ProvenAccount, ProvenTransaction, ShardAccount, ShardDescr, ShardHashes, Transaction, ValueFlow, VmStack};
 
 use super::common::*;
 use super::utils::*;
@@ -213,13 +220,53 @@ pub struct BlockTransactionsExt {
     pub proof: Vec<u8>,
 }
 
-#[derive(TlRead, TlWrite, Derivative)]
+#[derive(Derivative)]
 #[derivative(Debug, Clone, PartialEq)]
 pub struct PartialBlockProof {
     pub complete: bool,
     pub from: BlockIdExt,
     pub to: BlockIdExt,
+    /// Links up to the first one of a constructor unknown to the scheme
     pub steps: Vec<BlockLink>,
+    /// The first link of an unknown constructor with the links following it, `None` if all links are known
+    pub unsupported: Option<Unsupported>,
+}
+
+impl<'a> TlRead<'a> for PartialBlockProof {
+    type Repr = tl_proto::Bare;
+
+    fn read_from(packet: &'a [u8], offset: &mut usize) -> TlResult<Self> {
+        let complete = bool::read_from(packet, offset)?;
+        let from = BlockIdExt::read_from(packet, offset)?;
+        let to = BlockIdExt::read_from(packet, offset)?;
+        let (steps, unsupported) = read_known(packet, offset)?;
+        Ok(Self { complete, from, to, steps, unsupported })
+    }
+}
+
+impl TlWrite for PartialBlockProof {
+    type Repr = tl_proto::Bare;
+
+    fn max_size_hint(&self) -> usize {
+        self.complete.max_size_hint() + self.from.max_size_hint() + self.to.max_size_hint() + 4
+            + self.steps.iter().map(TlWrite::max_size_hint).sum::<usize>()
+            + self.unsupported.as_ref().map_or(0, |unsupported| 4 + unsupported.data.len())
+    }
+
+    fn write_to<P: TlPacket>(&self, packet: &mut P) {
+        self.complete.write_to(packet);
+        self.from.write_to(packet);
+        self.to.write_to(packet);
+        let count = self.steps.len() as u32 + self.unsupported.as_ref().map_or(0, |unsupported| unsupported.count);
+        count.write_to(packet);
+        for link in &self.steps {
+            link.write_to(packet);
+        }
+        if let Some(unsupported) = &self.unsupported {
+            unsupported.constructor.write_to(packet);
+            packet.write_raw_slice(&unsupported.data);
+        }
+    }
 }
 
 #[derive(TlRead, TlWrite, Derivative)]
@@ -395,6 +442,12 @@ pub struct DispatchQueueMessages {
     pub messages_boc: Option<Vec<u8>>,
 }
 
+#[derive(TlRead, TlWrite, Derivative)]
+#[derivative(Debug, Clone, PartialEq)]
+pub struct DebugVerbosity {
+    pub value: u32,
+}
+
 #[derive(TlRead, TlWrite, Derivative)]
 #[derivative(Debug, Clone, PartialEq)]
 pub struct Error {
@@ -471,6 +524,10 @@ pub enum Response {
     #[tl(id = 0xbd8cad2b)]
     BlockTransactions(BlockTransactions),
 
+    /// liteServer.blockTransactionsExt id:tonNode.blockIdExt req_count:# incomplete:Bool transactions:bytes proof:bytes = liteServer.BlockTransactionsExt;
+    #[tl(id = 0xfb8ffce4)]
+    BlockTransactionsExt(BlockTransactionsExt),
+
     /// liteServer.partialBlockProof complete:Bool from:tonNode.blockIdExt to:tonNode.blockIdExt steps:(vector liteServer.BlockLink) = liteServer.PartialBlockProof;
     #[tl(id = 0x8ed0d2c1)]
     PartialBlockProof(PartialBlockProof),
@@ -487,7 +544,7 @@ pub enum Response {
     #[tl(id = 0x117ab96b)]
     LibraryResult(LibraryResult),
 
-    /// liteServer.libraryResult result:(vector liteServer.libraryEntry) = liteServer.LibraryResult;
+    /// liteServer.libraryResultWithProof id:tonNode.blockIdExt mode:# result:(vector liteServer.libraryEntry) state_proof:bytes data_proof:bytes = liteServer.LibraryResultWithProof;
     #[tl(id = 0x10a927bf)]
     LibraryResultWithProof(LibraryResultWithProof),
 
@@ -515,7 +572,355 @@ pub enum Response {
     #[tl(id = 0x4b407931)]
     DispatchQueueMessages(DispatchQueueMessages),
 
+    /// liteServer.debug.verbosity value:int = liteServer.debug.Verbosity;
+    #[tl(id = 0x5d404733)]
+    DebugVerbosity(DebugVerbosity),
+
     /// liteServer.error code:int message:string = liteServer.Error;
     #[tl(id = 0xbba9e148)]
     Error(Error),
-}
\ No newline at end of file
+
+    /// Answer unknown to the scheme, including its constructor id.
+    ///
+    /// Read and written as is inside [`Message::Answer`](super::adnl::Message::Answer), the id of this variant never appears on the wire.
+    #[tl(id = 0x00000000)]
+    Raw(#[derivative(Debug(format_with="fmt_bytes"))] Vec<u8>),
+}
+impl Version {
+    /// Whether the liteserver implements a newer scheme than [`SCHEME_VERSION`](super::SCHEME_VERSION), so some
+    /// of its answers may be [`Unsupported`].
+    pub fn is_newer_scheme(&self) -> bool {
+        self.version > super::SCHEME_VERSION
+    }
+}
+
+impl MasterchainInfoExt {
+    /// How far the last masterchain block lags behind the clock of the liteserver.
+    pub fn lag(&self) -> Duration {
+        Duration::from_secs(self.now.saturating_sub(self.last_utime) as u64)
+    }
+}
+
+impl BlockData {
+    /// Root cell of the block.
+    pub fn root(&self) -> Result<ArcCell, CellError> {
+        Cell::from_boc(&self.data)
+    }
+
+    pub fn value_flow(&self) -> Result<ValueFlow, CellError> {
+        ValueFlow::from_proof(&*self.root()?)
+    }
+
+    /// Check that this is the block `id`: its file hash and the hash of its root cell must match the requested ones.
+    pub fn verify(&self, id: &BlockIdExt) -> Result<(), LiteError> {
+        let file_hash: [u8; 32] = Sha256::digest(&self.data).into();
+        if self.id != *id || file_hash != id.file_hash.0 || self.root()?.repr_hash() != id.root_hash.0 {
+            return Err(LiteError::HashMismatch);
+        }
+        Ok(())
+    }
+}
+
+impl BlockState {
+    /// Check that this is the state after block `id`, using the header proof of that block with the state update.
+    ///
+    /// Both the hashes reported by the liteserver and the hashes of `data` itself must match the proof.
+    pub fn verify(&self, id: &BlockIdExt, header_proof: &[u8]) -> Result<(), LiteError> {
+        let (block_hash, state_hash) = block_state_hash(&*Cell::from_boc(header_proof)?)?;
+        let file_hash: [u8; 32] = Sha256::digest(&self.data).into();
+        if self.id != *id || block_hash != id.root_hash.0 || self.root_hash.0 != state_hash
+            || self.file_hash.0 != file_hash || Cell::from_boc(&self.data)?.repr_hash() != state_hash {
+            return Err(LiteError::HashMismatch);
+        }
+        Ok(())
+    }
+}
+
+impl BlockHeader {
+    /// Header fields from `header_proof`.
+    pub fn info(&self) -> Result<BlockInfo, CellError> {
+        BlockInfo::from_proof(&*Cell::from_boc(&self.header_proof)?)
+    }
+
+    /// Value flow of the block, present when requested `with_value_flow`.
+    pub fn value_flow(&self) -> Result<ValueFlow, CellError> {
+        ValueFlow::from_proof(&*Cell::from_boc(&self.header_proof)?)
+    }
+
+    /// Check that `header_proof` is a proof of the block `id`.
+    pub fn verify(&self, id: &BlockIdExt) -> Result<(), LiteError> {
+        if self.id != *id || block_hash(&*Cell::from_boc(&self.header_proof)?)? != id.root_hash.0 {
+            return Err(LiteError::HashMismatch);
+        }
+        Ok(())
+    }
+}
+
+impl BlockState {
+    /// Root cell of the shard state.
+    pub fn root(&self) -> Result<ArcCell, CellError> {
+        Cell::from_boc(&self.data)
+    }
+}
+
+impl AccountState {
+    /// Root cell of the `Account`, `None` if the account doesn't exist.
+    pub fn state_root(&self) -> Result<Option<ArcCell>, CellError> {
+        if self.state.is_empty() {
+            return Ok(None);
+        }
+        Cell::from_boc(&self.state).map(Some)
+    }
+
+    /// Parsed `Account`, `None` if the account doesn't exist.
+    pub fn account(&self) -> Result<Option<Account>, CellError> {
+        match self.state_root()? {
+            Some(root) => Account::load(&root),
+            None => Ok(None),
+        }
+    }
+
+    /// `ShardAccount` with the last transaction id of `account`, taken from the state proof.
+    pub fn shard_account(&self, account: &[u8; 32]) -> Result<Option<ShardAccount>, CellError> {
+        let roots = deserialize_boc(&self.proof)?;
+        let proof = roots.get(1).ok_or(CellError::InvalidBoc("missing state proof"))?;
+        ShardAccount::from_state_proof(proof, account)
+    }
+
+    /// Check the answer against the masterchain block `id`: `shardblk` must be registered in it, and `state`
+    /// must be the state of `account` after `shardblk`. Only for masterchain accounts `shardblk` may be `id` itself.
+    ///
+    /// An empty `state` is only accepted if the state proof shows the account is absent from the shard state.
+    pub fn verify(&self, id: &BlockIdExt, account: &AccountId) -> Result<(), LiteError> {
+        self.proven_account(id, account).map(|_| ())
+    }
+
+    /// Same as [`AccountState::verify`], returning the proven state of `account`: either
+    /// [`ProvenAccount::Exists`] with the root of `state` as its `account`, or [`ProvenAccount::Nonexistent`].
+    pub fn proven_account(&self, id: &BlockIdExt, account: &AccountId) -> Result<ProvenAccount, LiteError> {
+        if self.id != *id {
+            return Err(LiteError::HashMismatch);
+        }
+        if self.shardblk == self.id {
+            // only masterchain accounts are proven against the masterchain block itself
+            if !id.is_masterchain() || account.workchain != id.workchain {
+                return Err(LiteError::InvalidProof("shard proof is missing"));
+            }
+        } else {
+            let shards = proven_shards(&self.shard_proof, id)?;
+            let shard = shards.find(account.workchain, &account.id.0).ok_or(LiteError::InvalidProof("shard is missing from the proof"))?;
+            if shard.block_id() != self.shardblk {
+                return Err(LiteError::HashMismatch);
+            }
+        }
+        let (block_proof, state_proof) = proof_pair(&self.proof)?;
+        let state_hash = check_block_proof(&block_proof, &self.shardblk)?;
+        check_state_proof(&state_proof, &state_hash)?;
+        match (ShardAccount::lookup_state_proof(&state_proof, &account.id.0)?, self.state_root()?) {
+            (ProvenAccount::Exists(proven), Some(state)) if proven.account.hash(0) == state.hash(0) => {
+                Ok(ProvenAccount::Exists(ShardAccount { account: state, ..proven }))
+            }
+            (ProvenAccount::Nonexistent, None) => Ok(ProvenAccount::Nonexistent),
+            (ProvenAccount::Unknown, _) => Err(LiteError::InvalidProof("account is pruned from the proof")),
+            _ => Err(LiteError::HashMismatch),
+        }
+    }
+}
+
+impl RunMethodResult {
+    /// Parsed `result`, `None` if it wasn't requested with mode bit 2.
+    pub fn stack(&self) -> Result<Option<VmStack>, CellError> {
+        self.result.as_deref().map(VmStack::from_boc).transpose()
+    }
+
+    /// `exit_code` with its description, e.g. `method not found (11)`.
+    pub fn exit(&self) -> ExitCode {
+        ExitCode::from(self.exit_code)
+    }
+}
+
+impl ShardInfo {
+    /// Parsed `shard_descr` of `shardblk`.
+    pub fn descr(&self) -> Result<ShardDescr, CellError> {
+        ShardDescr::from_cell(&*Cell::from_boc(&self.shard_descr)?)
+    }
+
+    /// Check that `shardblk` is registered in the masterchain block `id` with the description `shard_descr`.
+    pub fn verify(&self, id: &BlockIdExt) -> Result<(), LiteError> {
+        if self.id != *id {
+            return Err(LiteError::HashMismatch);
+        }
+        let shards = proven_shards(&self.shard_proof, id)?;
+        let proven = shards.shards.into_iter().find(|shard| shard.block_id() == self.shardblk)
+            .ok_or(LiteError::InvalidProof("shard is missing from the proof"))?;
+        let mut descr = self.descr()?;
+        if proven.descr.fees_collected.is_none() {
+            // pruned from the proof, so the ones of the answer can't be checked
+            descr.fees_collected = None;
+            descr.funds_created = None;
+        }
+        if descr != proven.descr {
+            return Err(LiteError::HashMismatch);
+        }
+        Ok(())
+    }
+}
+
+impl AllShardsInfo {
+    /// Shard blocks registered in the masterchain block.
+    pub fn shard_hashes(&self) -> Result<ShardHashes, CellError> {
+        ShardHashes::from_cell(&*Cell::from_boc(&self.data)?)
+    }
+
+    /// Check that `data` are the shard hashes of the masterchain block `id`.
+    pub fn verify(&self, id: &BlockIdExt) -> Result<(), LiteError> {
+        let (block_proof, state_proof) = proof_pair(&self.proof)?;
+        let state_hash = check_block_proof(&block_proof, id)?;
+        check_state_proof(&state_proof, &state_hash)?;
+        let data = Cell::from_boc(&self.data)?;
+        let data_root = data.parser()?.load_maybe_ref()?.map(|root| root.hash(0));
+        if self.id != *id || shard_hashes_root(&state_proof)?.map(|root| root.hash(0)) != data_root {
+            return Err(LiteError::HashMismatch);
+        }
+        Ok(())
+    }
+}
+
+impl TransactionInfo {
+    /// Root cell of the `Transaction`.
+    pub fn transaction_root(&self) -> Result<ArcCell, CellError> {
+        Cell::from_boc(&self.transaction)
+    }
+
+    /// Check that `transaction` is the transaction `lt` of `account` in the block `id`.
+    ///
+    /// An empty `transaction`, returned when there is no such transaction, is only accepted if the block proof
+    /// shows the block has no such transaction, and fails with [`LiteError::Unverifiable`] if it shows neither.
+    pub fn verify(&self, id: &BlockIdExt, account: &AccountId, lt: u64) -> Result<(), LiteError> {
+        if self.id != *id {
+            return Err(LiteError::HashMismatch);
+        }
+        if self.transaction.is_empty() && self.proof.is_empty() {
+            return Err(LiteError::Unverifiable("liteServer.getOneTransaction"));
+        }
+        let proof = Cell::from_boc(&self.proof)?;
+        if block_hash(&proof)? != id.root_hash.0 {
+            return Err(LiteError::HashMismatch);
+        }
+        match (lookup_block_transaction(&proof, &account.id.0, lt)?, self.transaction.is_empty()) {
+            (ProvenTransaction::Exists(proven), false) if proven.hash(0) == self.transaction_root()?.hash(0) => Ok(()),
+            (ProvenTransaction::Nonexistent, true) => Ok(()),
+            (ProvenTransaction::Unknown, true) => Err(LiteError::Unverifiable("liteServer.getOneTransaction")),
+            (ProvenTransaction::Unknown, false) => Err(LiteError::InvalidProof("transaction is missing from the proof")),
+            _ => Err(LiteError::HashMismatch),
+        }
+    }
+}
+
+impl TransactionList {
+    /// Root cells of the listed transactions, in the order of `ids`.
+    pub fn transaction_roots(&self) -> Result<Vec<ArcCell>, CellError> {
+        deserialize_boc(&self.transactions)
+    }
+
+    /// Check that the transactions are a chain of the account going back from the transaction with the given
+    /// logical time and hash, each one referencing the next one as its previous transaction, of at most `count`.
+    ///
+    /// The chain isn't proven to be complete, a shorter one than requested is accepted, but an empty one is only
+    /// accepted if `count` is 0.
+    pub fn verify(&self, count: u32, mut lt: u64, mut hash: [u8; 32]) -> Result<(), LiteError> {
+        if self.ids.is_empty() {
+            if count > 0 {
+                return Err(LiteError::InvalidProof("no transactions"));
+            }
+            return Ok(());
+        }
+        let roots = self.transaction_roots()?;
+        if roots.len() > count as usize {
+            return Err(LiteError::InvalidProof("more transactions than requested"));
+        }
+        if roots.len() != self.ids.len() {
+            return Err(LiteError::InvalidProof("number of transactions doesn't match the block ids"));
+        }
+        for root in roots {
+            let transaction = Transaction::load(&root)?;
+            if transaction.hash != hash || transaction.lt != lt {
+                return Err(LiteError::HashMismatch);
+            }
+            (lt, hash) = (transaction.prev_trans_lt, transaction.prev_trans_hash);
+        }
+        Ok(())
+    }
+}
+
+impl BlockTransactionsExt {
+    /// Root cells of the listed transactions.
+    pub fn transaction_roots(&self) -> Result<Vec<ArcCell>, CellError> {
+        deserialize_boc(&self.transactions)
+    }
+}
+
+impl ConfigInfo {
+    /// Configuration and state time from `config_proof`.
+    pub fn config(&self) -> Result<McStateConfig, CellError> {
+        let proof = Cell::from_boc(&self.config_proof)?;
+        McStateConfig::from_proof(&proof)
+    }
+
+    /// Check that `config_proof` is a proof of the state after the masterchain block `id`.
+    ///
+    /// Configurations read from a key block can't be verified this way and are rejected.
+    pub fn verify(&self, id: &BlockIdExt) -> Result<(), LiteError> {
+        if self.extract_from_key_block.is_some() {
+            return Err(LiteError::InvalidProof("configuration of a key block"));
+        }
+        if self.id != *id {
+            return Err(LiteError::HashMismatch);
+        }
+        let state_hash = check_block_proof(&*Cell::from_boc(&self.state_proof)?, id)?;
+        check_state_proof(&*Cell::from_boc(&self.config_proof)?, &state_hash)
+    }
+}
+
+impl ValidatorStats {
+    /// Validator entries present in `data_proof`, ordered by public key.
+    pub fn creator_stats(&self) -> Result<Vec<CreatorStats>, CellError> {
+        CreatorStats::from_state_proof(&*Cell::from_boc(&self.data_proof)?)
+    }
+}
+
+/// Block proof and state proof stored together in a single bag of cells.
+fn proof_pair(boc: &[u8]) -> Result<(ArcCell, ArcCell), LiteError> {
+    let mut roots = deserialize_boc(boc)?.into_iter();
+    match (roots.next(), roots.next()) {
+        (Some(block_proof), Some(state_proof)) => Ok((block_proof, state_proof)),
+        _ => Err(LiteError::InvalidProof("expected block and state proofs")),
+    }
+}
+
+/// Check that `proof` is a proof of the block `id` with its state update, returns the hash of the state after it.
+fn check_block_proof(proof: &Cell, id: &BlockIdExt) -> Result<[u8; 32], LiteError> {
+    let (block_hash, state_hash) = block_state_hash(proof)?;
+    if block_hash != id.root_hash.0 {
+        return Err(LiteError::HashMismatch);
+    }
+    Ok(state_hash)
+}
+
+fn check_state_proof(proof: &Cell, state_hash: &[u8; 32]) -> Result<(), LiteError> {
+    if proof.cell_type() != CellType::MerkleProof {
+        return Err(LiteError::InvalidProof("expected merkle proof"));
+    }
+    if proof.reference(0)?.hash(0) != *state_hash {
+        return Err(LiteError::HashMismatch);
+    }
+    Ok(())
+}
+
+/// Shard blocks registered in the masterchain block `id`, from a `shard_proof`.
+fn proven_shards(shard_proof: &[u8], id: &BlockIdExt) -> Result<ShardHashes, LiteError> {
+    let (block_proof, state_proof) = proof_pair(shard_proof)?;
+    let state_hash = check_block_proof(&block_proof, id)?;
+    check_state_proof(&state_proof, &state_hash)?;
+    Ok(ShardHashes::from_state_proof(&state_proof)?)
+}
@@ -15,6 +15,7 @@ fn test_time() -> Result<(), Box<dyn Error>> {
             wrapped_request: WrappedRequest { 
                 request: Request::GetTime,
                 wait_masterchain_seqno: None, 
+                context: Default::default(),
             } 
         }
     };
@@ -23,4 +24,121 @@ fn test_time() -> Result<(), Box<dyn Error>> {
     let deserialized = tl_proto::deserialize::<Message>(raw.as_slice())?;
     assert_eq!(deserialized, message);
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn test_raw() -> Result<(), Box<dyn Error>> {
+    let raw = hex::decode("7af98bb435263e6c95d6fecb497dfd0aa5f031e7d412986b5ce720496db512052e8f2d100cdf068c7904345aad16000000000000")?;
+    let query_id = Int256::from_hex("35263e6c95d6fecb497dfd0aa5f031e7d412986b5ce720496db512052e8f2d10")?;
+    let message = Message::Query {
+        query_id: query_id.clone(),
+        query: request::LiteQuery {
+            wrapped_request: WrappedRequest {
+                request: Request::Raw(hex::decode("345aad16")?),
+                wait_masterchain_seqno: None,
+                context: Default::default(),
+            }
+        }
+    };
+    assert_eq!(raw, tl_proto::serialize(message));
+
+    let unknown = Message::Query {
+        query_id: query_id.clone(),
+        query: request::LiteQuery {
+            wrapped_request: WrappedRequest {
+                request: Request::Raw(hex::decode("efbeadde2a000000")?),
+                wait_masterchain_seqno: None,
+                context: Default::default(),
+            }
+        }
+    };
+    let deserialized = tl_proto::deserialize::<Message>(&tl_proto::serialize(unknown.clone()))?;
+    assert_eq!(deserialized, unknown);
+
+    let answer = Message::Answer { query_id, answer: response::Response::Raw(hex::decode("efbeadde2a000000")?) };
+    let deserialized = tl_proto::deserialize::<Message>(&tl_proto::serialize(answer.clone()))?;
+    assert_eq!(deserialized, answer);
+    Ok(())
+}
+
+#[test]
+fn test_block_id_relations() {
+    use std::collections::HashMap;
+    use common::BlockIdExt;
+
+    let id = |workchain, shard, seqno, hash: u8| BlockIdExt {
+        workchain, shard, seqno, root_hash: Int256([hash; 32]), file_hash: Int256([hash; 32]),
+    };
+    let mc = id(-1, 1 << 63, 10, 1);
+    let mc_next = id(-1, 1 << 63, 11, 2);
+    let mc_fork = id(-1, 1 << 63, 11, 3);
+    assert!(mc.is_masterchain() && !id(0, 1 << 63, 10, 1).is_masterchain());
+    assert!(mc < mc_next);
+    assert_eq!(mc_next.partial_cmp(&mc_fork), None);
+    assert_eq!(mc.partial_cmp(&id(0, 1 << 63, 11, 1)), None);
+    assert_eq!(mc.block_id().seqno, 10);
+
+    // a shard splitting into two and merging back
+    let parent = id(0, 1 << 63, 5, 1);
+    let left = id(0, 1 << 62, 6, 2);
+    let right = id(0, 3 << 62, 6, 3);
+    let merged = id(0, 1 << 63, 7, 4);
+    let links = HashMap::from([
+        (left.clone(), vec![parent.clone()]),
+        (right.clone(), vec![parent.clone()]),
+        (merged.clone(), vec![left.clone(), right.clone()]),
+    ]);
+    let prev = |id: &BlockIdExt| links.get(id).cloned().unwrap_or_default();
+    assert!(parent.is_ancestor_of(&merged, prev));
+    assert!(right.is_ancestor_of(&merged, prev));
+    assert!(!merged.is_ancestor_of(&parent, prev));
+    assert!(!merged.is_ancestor_of(&merged, prev));
+    assert!(!id(0, 1 << 63, 5, 9).is_ancestor_of(&merged, prev));
+}
+
+#[test]
+fn test_unsupported_block_link() -> Result<(), Box<dyn Error>> {
+    use common::{BlockIdExt, BlockLink, Unsupported};
+    use response::{PartialBlockProof, Response};
+
+    let id = BlockIdExt { workchain: -1, shard: 1 << 63, seqno: 1, root_hash: Int256([1; 32]), file_hash: Int256([2; 32]) };
+    let back = BlockLink::BlockLinkBack {
+        to_key_block: false, from: id.clone(), to: id.clone(), dest_proof: vec![1], proof: vec![2], state_proof: vec![3],
+    };
+    let proof = Response::PartialBlockProof(PartialBlockProof {
+        complete: true, from: id.clone(), to: id.clone(),
+        steps: vec![back.clone()],
+        unsupported: Some(Unsupported { constructor: 0xdeadbeef, count: 2, data: hex::decode("2a0000002b000000")? }),
+    });
+    let raw = tl_proto::serialize(proof.clone());
+    assert_eq!(tl_proto::deserialize::<Response>(&raw)?, proof);
+    let Response::PartialBlockProof(parsed) = tl_proto::deserialize::<Response>(&raw)? else { unreachable!() };
+    assert_eq!(parsed.steps[0], back);
+    assert_eq!(tl_proto::serialize(back.clone())[..4], 0xef7e1befu32.to_le_bytes());
+    let unsupported = tl_proto::deserialize::<Unsupported>(&hex::decode("efbeadde2a000000")?)?;
+    assert_eq!(unsupported, Unsupported { constructor: 0xdeadbeef, count: 1, data: vec![0x2a, 0, 0, 0] });
+
+    let known = Response::PartialBlockProof(PartialBlockProof { complete: true, from: id.clone(), to: id, steps: vec![back], unsupported: None });
+    let mut raw = tl_proto::serialize(known.clone());
+    raw.extend_from_slice(&[0; 8]);
+    assert_eq!(tl_proto::deserialize::<Response>(&raw)?, known);
+    Ok(())
+}
+
+#[test]
+fn test_account_state_without_shard_proof() {
+    use common::{AccountId, BlockIdExt};
+    use response::AccountState;
+    use crate::types::LiteError;
+
+    let mc = BlockIdExt { workchain: -1, shard: 1 << 63, seqno: 1, root_hash: Int256([1; 32]), file_hash: Int256([2; 32]) };
+    // a basechain account answered against the masterchain block, whose state can't contain it
+    let state = AccountState { id: mc.clone(), shardblk: mc.clone(), shard_proof: Vec::new(), proof: vec![1, 2, 3], state: Vec::new() };
+    let account = AccountId { workchain: 0, id: Int256([3; 32]) };
+    assert!(matches!(state.proven_account(&mc, &account), Err(LiteError::InvalidProof(_))));
+    assert!(matches!(state.verify(&mc, &account), Err(LiteError::InvalidProof(_))));
+
+    // a shard block is never the block a state is checked against
+    let shard = BlockIdExt { workchain: 0, ..mc.clone() };
+    let state = AccountState { id: shard.clone(), shardblk: shard.clone(), ..state };
+    assert!(matches!(state.proven_account(&shard, &account), Err(LiteError::InvalidProof(_))));
+}
@@ -1,7 +1,9 @@
-use tl_proto::{TlRead, TlResult};
+use tl_proto::{TlError, TlRead, TlResult};
 
 use crate::types::LiteError;
 
+use super::common::Unsupported;
+use super::request::Request;
 use super::response::*;
 
 pub fn lossy_read<'tl, T: TlRead<'tl>>(packet: &'tl [u8], offset: &mut usize) -> TlResult<Option<T>> {
@@ -15,6 +17,25 @@ pub fn lossy_read<'tl, T: TlRead<'tl>>(packet: &'tl [u8], offset: &mut usize) ->
     }
 }
 
+/// Read a vector of boxed objects up to the first one of a constructor unknown to the scheme, which is returned
+/// as [`Unsupported`] with the objects following it. The vector must be the last field of the answer.
+pub fn read_known<'tl, T: TlRead<'tl>>(packet: &'tl [u8], offset: &mut usize) -> TlResult<(Vec<T>, Option<Unsupported>)> {
+    let count = u32::read_from(packet, offset)?;
+    let mut known = Vec::with_capacity(count.min(16) as usize);
+    for i in 0..count {
+        let orig_offset = *offset;
+        match T::read_from(packet, offset) {
+            Err(TlError::UnknownConstructor) => {
+                *offset = orig_offset;
+                let unsupported = Unsupported { count: count - i, ..Unsupported::read_from(packet, offset)? };
+                return Ok((known, Some(unsupported)));
+            },
+            object => known.push(object?),
+        }
+    }
+    Ok((known, None))
+}
+
 pub fn fmt_string(bytes: &[u8], f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
     write!(
         f,
@@ -51,6 +72,67 @@ pub mod struct_as_bytes {
     }
 }
 
+/// Keep functions which are not in the scheme as [`Request::Raw`].
+pub mod request_or_raw {
+    use tl_proto::{TlError, TlPacket, TlRead, TlResult, TlWrite};
+
+    use super::Request;
+
+    pub fn size_hint(v: &Request) -> usize {
+        match v {
+            Request::Raw(data) => data.len(),
+            v => v.max_size_hint(),
+        }
+    }
+
+    pub fn write<P: TlPacket>(v: &Request, packet: &mut P) {
+        match v {
+            Request::Raw(data) => packet.write_raw_slice(data),
+            v => v.write_to(packet),
+        }
+    }
+
+    pub fn read(packet: &[u8], offset: &mut usize) -> TlResult<Request> {
+        let orig_offset = *offset;
+        match Request::read_from(packet, offset) {
+            Err(TlError::UnknownConstructor) => {
+                *offset = packet.len();
+                Ok(Request::Raw(packet[orig_offset..].to_vec()))
+            },
+            result => result,
+        }
+    }
+}
+
+/// Same as [`struct_as_bytes`], but keeps answers which are not in the scheme as [`Response::Raw`].
+pub mod response_as_bytes {
+    use tl_proto::{TlError, TlPacket, TlRead, TlResult, TlWrite};
+
+    use super::{struct_as_bytes, Response};
+
+    pub fn size_hint(v: &Response) -> usize {
+        match v {
+            Response::Raw(data) => data.as_slice().max_size_hint(),
+            v => struct_as_bytes::size_hint(v),
+        }
+    }
+
+    pub fn write<P: TlPacket>(v: &Response, packet: &mut P) {
+        match v {
+            Response::Raw(data) => data.as_slice().write_to(packet),
+            v => struct_as_bytes::write(v, packet),
+        }
+    }
+
+    pub fn read(packet: &[u8], offset: &mut usize) -> TlResult<Response> {
+        let data = <&[u8]>::read_from(packet, offset)?;
+        match tl_proto::deserialize(data) {
+            Err(TlError::UnknownConstructor) => Ok(Response::Raw(data.to_vec())),
+            result => result,
+        }
+    }
+}
+
 pub trait FromResponse: Sized {
     fn from_response(response: Response) -> Result<Self, LiteError>;
 }
@@ -199,6 +281,15 @@ impl FromResponse for BlockTransactions {
     }
 }
 
+impl FromResponse for BlockTransactionsExt {
+    fn from_response(response: Response) -> Result<Self, LiteError> {
+        match response {
+            Response::BlockTransactionsExt(s) => Ok(s),
+            _ => Err(LiteError::UnexpectedMessage)
+        }
+    }
+}
+
 impl FromResponse for PartialBlockProof {
     fn from_response(response: Response) -> Result<Self, LiteError> {
         match response {
@@ -235,6 +326,78 @@ impl FromResponse for LibraryResult {
     }
 }
 
+impl FromResponse for LibraryResultWithProof {
+    fn from_response(response: Response) -> Result<Self, LiteError> {
+        match response {
+            Response::LibraryResultWithProof(s) => Ok(s),
+            _ => Err(LiteError::UnexpectedMessage)
+        }
+    }
+}
+
+impl FromResponse for ShardBlockProof {
+    fn from_response(response: Response) -> Result<Self, LiteError> {
+        match response {
+            Response::ShardBlockProof(s) => Ok(s),
+            _ => Err(LiteError::UnexpectedMessage)
+        }
+    }
+}
+
+impl FromResponse for LookupBlockResult {
+    fn from_response(response: Response) -> Result<Self, LiteError> {
+        match response {
+            Response::LookupBlockResult(s) => Ok(s),
+            _ => Err(LiteError::UnexpectedMessage)
+        }
+    }
+}
+
+impl FromResponse for OutMsgQueueSizes {
+    fn from_response(response: Response) -> Result<Self, LiteError> {
+        match response {
+            Response::OutMsgQueueSizes(s) => Ok(s),
+            _ => Err(LiteError::UnexpectedMessage)
+        }
+    }
+}
+
+impl FromResponse for BlockOutMsgQueueSize {
+    fn from_response(response: Response) -> Result<Self, LiteError> {
+        match response {
+            Response::BlockOutMsgQueueSize(s) => Ok(s),
+            _ => Err(LiteError::UnexpectedMessage)
+        }
+    }
+}
+
+impl FromResponse for DispatchQueueInfo {
+    fn from_response(response: Response) -> Result<Self, LiteError> {
+        match response {
+            Response::DispatchQueueInfo(s) => Ok(s),
+            _ => Err(LiteError::UnexpectedMessage)
+        }
+    }
+}
+
+impl FromResponse for DispatchQueueMessages {
+    fn from_response(response: Response) -> Result<Self, LiteError> {
+        match response {
+            Response::DispatchQueueMessages(s) => Ok(s),
+            _ => Err(LiteError::UnexpectedMessage)
+        }
+    }
+}
+
+impl FromResponse for DebugVerbosity {
+    fn from_response(response: Response) -> Result<Self, LiteError> {
+        match response {
+            Response::DebugVerbosity(s) => Ok(s),
+            _ => Err(LiteError::UnexpectedMessage)
+        }
+    }
+}
+
 impl FromResponse for Error {
     fn from_response(response: Response) -> Result<Self, LiteError> {
         match response {
@@ -242,4 +405,38 @@ impl FromResponse for Error {
             _ => Err(LiteError::UnexpectedMessage)
         }
     }
-}
\ No newline at end of file
+}
+
+/// `TryFrom<Response>` for answer types, `liteServer.error` is returned as [`LiteError::ServerError`].
+///
+/// Only conversions of a [`Response`] return it bare, queries wrap it in [`LiteError::Query`].
+macro_rules! impl_try_from_response {
+    ($($ty:ident),* $(,)?) => {$(
+        impl TryFrom<Response> for $ty {
+            type Error = LiteError;
+
+            fn try_from(response: Response) -> Result<Self, LiteError> {
+                match response {
+                    Response::Error(e) => Err(LiteError::ServerError(e)),
+                    response => Self::from_response(response),
+                }
+            }
+        }
+    )*};
+}
+
+impl_try_from_response!(
+    MasterchainInfo, MasterchainInfoExt, CurrentTime, Version, BlockData, BlockState, BlockHeader,
+    SendMsgStatus, AccountState, RunMethodResult, ShardInfo, AllShardsInfo, TransactionInfo, TransactionList,
+    TransactionId, BlockTransactions, BlockTransactionsExt, PartialBlockProof, ConfigInfo, ValidatorStats,
+    LibraryResult, LibraryResultWithProof, ShardBlockProof, LookupBlockResult, OutMsgQueueSizes,
+    BlockOutMsgQueueSize, DispatchQueueInfo, DispatchQueueMessages, DebugVerbosity,
+);
+
+impl TryFrom<Response> for Error {
+    type Error = LiteError;
+
+    fn try_from(response: Response) -> Result<Self, LiteError> {
+        Self::from_response(response)
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use crate::cell::{ArcCell, Cell, CellError, CellSlice, CellType};
+
+use super::{hashmap_entries, hashmap_get, Coins, HashmapEntry, MsgAddressInt, StateInit};
+
+/// ```tlb
+/// currencies$_ grams:Grams other:ExtraCurrencyCollection = CurrencyCollection;
+/// extra_currencies$_ dict:(HashmapE 32 (VarUInteger 32)) = ExtraCurrencyCollection;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CurrencyCollection {
+    pub grams: Coins,
+    /// Root of the extra currencies dictionary, see [`CurrencyCollection::extra_currencies`]
+    pub other: Option<ArcCell>,
+}
+
+impl CurrencyCollection {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let grams = Coins::load(slice)?;
+        let other = slice.load_maybe_ref()?.cloned();
+        Ok(Self { grams, other })
+    }
+
+    /// Amounts of extra currencies by currency id, amounts above `u128::MAX` are rejected with `Overflow`.
+    ///
+    /// `var_uint$_ {n:#} len:(#< n) value:(uint (len * 8)) = VarUInteger n;`
+    pub fn extra_currencies(&self) -> Result<BTreeMap<u32, u128>, CellError> {
+        let Some(root) = &self.other else {
+            return Ok(BTreeMap::new());
+        };
+        let mut currencies = BTreeMap::new();
+        for (key, mut value) in hashmap_entries(root, 32)? {
+            let id = u32::from_be_bytes(key.try_into().unwrap());
+            let len = value.load_uint(5)? as usize;
+            if len > 16 {
+                return Err(CellError::Overflow);
+            }
+            let mut amount = 0u128;
+            for _ in 0..len {
+                amount = (amount << 8) | value.load_u8()? as u128;
+            }
+            currencies.insert(id, amount);
+        }
+        Ok(currencies)
+    }
+}
+
+/// ```tlb
+/// account_uninit$00 = AccountState;
+/// account_active$1 _:StateInit = AccountState;
+/// account_frozen$01 state_hash:bits256 = AccountState;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountStatus {
+    Uninit,
+    /// Deployed account with its code and data
+    Active(StateInit),
+    /// Account frozen for unpaid storage, with the hash of its last `StateInit`
+    Frozen([u8; 32]),
+}
+
+impl AccountStatus {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        if slice.load_bit()? {
+            return Ok(Self::Active(StateInit::load(slice)?));
+        }
+        if slice.load_bit()? {
+            Ok(Self::Frozen(slice.load_u256()?))
+        } else {
+            Ok(Self::Uninit)
+        }
+    }
+
+    /// Whether the account is frozen with the hash of `init`, so it can be unfrozen by sending a message
+    /// with this `StateInit`. Returns `false` for accounts which aren't frozen.
+    pub fn can_unfreeze_with(&self, init: &StateInit) -> Result<bool, CellError> {
+        match self {
+            Self::Frozen(state_hash) => Ok(init.to_cell()?.repr_hash() == *state_hash),
+            _ => Ok(false),
+        }
+    }
+}
+
+/// ```tlb
+/// account$1 addr:MsgAddressInt storage_stat:StorageInfo storage:AccountStorage = Account;
+/// storage_info$_ used:StorageUsed storage_extra:StorageExtraInfo last_paid:uint32 due_payment:(Maybe Grams) = StorageInfo;
+/// account_storage$_ last_trans_lt:uint64 balance:CurrencyCollection state:AccountState = AccountStorage;
+/// ```
+///
+/// Only the fields needed by the client are kept, storage statistics are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub address: MsgAddressInt,
+    pub last_paid: u32,
+    pub due_payment: Option<Coins>,
+    pub last_trans_lt: u64,
+    pub balance: CurrencyCollection,
+    pub state: AccountStatus,
+}
+
+impl Account {
+    /// Parse `Account`, returning `None` for `account_none$0`.
+    pub fn load(cell: &Cell) -> Result<Option<Self>, CellError> {
+        let mut slice = cell.parser()?;
+        if !slice.load_bit()? {
+            return Ok(None);
+        }
+        let address = MsgAddressInt::load(&mut slice)?;
+        // storage_used$_ cells:(VarUInteger 7) bits:(VarUInteger 7)
+        for _ in 0..2 {
+            let len = slice.load_uint(3)? as usize;
+            slice.skip_bits(len * 8)?;
+        }
+        // storage_extra_none$000 or storage_extra_info$001 dict_hash:uint256, which also matches
+        // the zero `public_cells:(VarUInteger 7)` of the older StorageUsed layout
+        match slice.load_uint(3)? {
+            0b000 => {}
+            0b001 => slice.skip_bits(256)?,
+            tag => return Err(CellError::UnexpectedTag(tag)),
+        }
+        let last_paid = slice.load_u32()?;
+        let due_payment = if slice.load_bit()? { Some(Coins::load(&mut slice)?) } else { None };
+        let last_trans_lt = slice.load_u64()?;
+        let balance = CurrencyCollection::load(&mut slice)?;
+        let state = AccountStatus::load(&mut slice)?;
+        Ok(Some(Self { address, last_paid, due_payment, last_trans_lt, balance, state }))
+    }
+}
+
+/// `account_descr$_ account:^Account last_trans_hash:bits256 last_trans_lt:uint64 = ShardAccount;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardAccount {
+    pub account: ArcCell,
+    pub last_trans_hash: [u8; 32],
+    pub last_trans_lt: u64,
+}
+
+/// Account looked up in a merkle proof of the shard state, see [`ShardAccount::lookup_state_proof`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProvenAccount {
+    Exists(ShardAccount),
+    /// The proof shows that the shard state has no account with this address
+    Nonexistent,
+    /// The account was pruned from the proof, so the proof says nothing about it
+    Unknown,
+}
+
+impl ShardAccount {
+    /// Find the account in a merkle proof of `ShardStateUnsplit`, `None` if it's absent or pruned from the proof.
+    pub fn from_state_proof(proof: &Cell, account: &[u8; 32]) -> Result<Option<Self>, CellError> {
+        match Self::lookup_state_proof(proof, account)? {
+            ProvenAccount::Exists(shard_account) => Ok(Some(shard_account)),
+            ProvenAccount::Nonexistent | ProvenAccount::Unknown => Ok(None),
+        }
+    }
+
+    /// Look the account up in a merkle proof of `ShardStateUnsplit`, such as the one returned by `getAccountState`.
+    ///
+    /// ```tlb
+    /// _ (HashmapAugE 256 ShardAccount DepthBalanceInfo) = ShardAccounts;
+    /// depth_balance$_ split_depth:(#<= 30) balance:CurrencyCollection = DepthBalanceInfo;
+    /// ```
+    pub fn lookup_state_proof(proof: &Cell, account: &[u8; 32]) -> Result<ProvenAccount, CellError> {
+        if proof.cell_type() != CellType::MerkleProof {
+            return Err(CellError::InvalidExotic("expected merkle proof"));
+        }
+        let mut state = proof.reference(0)?.parser()?;
+        let tag = state.load_u32()?;
+        if tag != 0x9023afe2 {
+            return Err(CellError::UnexpectedTag(tag as u64));
+        }
+        state.load_ref()?;
+        let accounts = state.load_ref()?;
+        if accounts.cell_type() == CellType::PrunedBranch {
+            return Ok(ProvenAccount::Unknown);
+        }
+        // ahme_empty$0 or ahme_root$1 root:^(HashmapAug n X Y), followed by the extra
+        let Some(root) = accounts.parser()?.load_maybe_ref()? else {
+            return Ok(ProvenAccount::Nonexistent);
+        };
+        let mut value = match hashmap_get(root, account, 256)? {
+            HashmapEntry::Found(value) => value,
+            HashmapEntry::Absent => return Ok(ProvenAccount::Nonexistent),
+            HashmapEntry::Pruned => return Ok(ProvenAccount::Unknown),
+        };
+        value.skip_bits(5)?;
+        CurrencyCollection::load(&mut value)?;
+        let account = value.load_ref()?.clone();
+        let last_trans_hash = value.load_u256()?;
+        let last_trans_lt = value.load_u64()?;
+        Ok(ProvenAccount::Exists(Self { account, last_trans_hash, last_trans_lt }))
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cell::{CellBuilder, CellError, CellSlice};
+
+use super::MsgAddressExt;
+
+/// `anycast_info$_ depth:(#<= 30) { depth >= 1 } rewrite_pfx:(bits depth) = Anycast;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anycast {
+    pub depth: u8,
+    pub rewrite_pfx: Vec<u8>,
+}
+
+impl Anycast {
+    fn load(slice: &mut CellSlice) -> Result<Option<Self>, CellError> {
+        if !slice.load_bit()? {
+            return Ok(None);
+        }
+        let depth = slice.load_uint(5)? as u8;
+        if depth == 0 || depth > 30 {
+            return Err(CellError::UnexpectedTag(depth as u64));
+        }
+        let rewrite_pfx = slice.load_bits(depth as usize)?;
+        Ok(Some(Self { depth, rewrite_pfx }))
+    }
+
+    fn store(anycast: &Option<Self>, builder: &mut CellBuilder) -> Result<(), CellError> {
+        match anycast {
+            Some(anycast) => {
+                builder.store_bit(true)?.store_uint(5, anycast.depth as u64)?;
+                builder.store_bits(&anycast.rewrite_pfx, anycast.depth as usize)?;
+            }
+            None => {
+                builder.store_bit(false)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ```tlb
+/// addr_std$10 anycast:(Maybe Anycast) workchain_id:int8 address:bits256 = MsgAddressInt;
+/// addr_var$11 anycast:(Maybe Anycast) addr_len:(## 9) workchain_id:int32 address:(bits addr_len) = MsgAddressInt;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MsgAddressInt {
+    Std { anycast: Option<Anycast>, workchain: i8, address: [u8; 32] },
+    Var { anycast: Option<Anycast>, address_len: u16, workchain: i32, address: Vec<u8> },
+}
+
+impl MsgAddressInt {
+    /// Standard address without anycast.
+    pub fn std(workchain: i8, address: [u8; 32]) -> Self {
+        Self::Std { anycast: None, workchain, address }
+    }
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_uint(2)?;
+        match tag {
+            0b10 => {
+                let anycast = Anycast::load(slice)?;
+                let workchain = slice.load_int(8)? as i8;
+                let address = slice.load_u256()?;
+                Ok(Self::Std { anycast, workchain, address })
+            }
+            0b11 => {
+                let anycast = Anycast::load(slice)?;
+                let address_len = slice.load_uint(9)? as u16;
+                let workchain = slice.load_int(32)? as i32;
+                let address = slice.load_bits(address_len as usize)?;
+                Ok(Self::Var { anycast, address_len, workchain, address })
+            }
+            _ => Err(CellError::UnexpectedTag(tag)),
+        }
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        match self {
+            Self::Std { anycast, workchain, address } => {
+                builder.store_uint(2, 0b10)?;
+                Anycast::store(anycast, builder)?;
+                builder.store_int(8, *workchain as i64)?.store_u256(address)?;
+            }
+            Self::Var { anycast, address_len, workchain, address } => {
+                builder.store_uint(2, 0b11)?;
+                Anycast::store(anycast, builder)?;
+                builder.store_uint(9, *address_len as u64)?.store_int(32, *workchain as i64)?;
+                builder.store_bits(address, *address_len as usize)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `MsgAddress` of message bodies, which is either internal or `addr_none$00` loaded as `None`.
+    pub fn load_maybe(slice: &mut CellSlice) -> Result<Option<Self>, CellError> {
+        let mut tag = slice.clone();
+        if tag.load_uint(2)? == 0b00 {
+            *slice = tag;
+            return Ok(None);
+        }
+        Self::load(slice).map(Some)
+    }
+
+    pub fn store_maybe(address: &Option<Self>, builder: &mut CellBuilder) -> Result<(), CellError> {
+        match address {
+            Some(address) => address.store(builder),
+            None => {
+                builder.store_uint(2, 0b00)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Raw form `workchain:hex`, e.g. `-1:3333333333333333333333333333333333333333333333333333333333333333`
+impl fmt::Display for MsgAddressInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Std { workchain, address, .. } => write!(f, "{}:{}", workchain, hex::encode(address)),
+            Self::Var { workchain, address, .. } => write!(f, "{}:{}", workchain, hex::encode(address)),
+        }
+    }
+}
+
+impl FromStr for MsgAddressInt {
+    type Err = CellError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (workchain, address) = s.split_once(':').ok_or(CellError::InvalidAddress)?;
+        let workchain = workchain.parse().map_err(|_| CellError::InvalidAddress)?;
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(address, &mut bytes).map_err(|_| CellError::InvalidAddress)?;
+        Ok(Self::std(workchain, bytes))
+    }
+}
+
+/// Source or destination of any message: internal, external or `addr_none`.
+///
+/// ```tlb
+/// _ _:MsgAddressInt = MsgAddress;
+/// _ _:MsgAddressExt = MsgAddress;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MsgAddress {
+    #[default]
+    None,
+    Extern { len: u16, address: Vec<u8> },
+    /// `addr_std` or `addr_var`
+    Int(MsgAddressInt),
+}
+
+impl MsgAddress {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let mut tag = slice.clone();
+        if tag.load_bit()? {
+            return MsgAddressInt::load(slice).map(Self::Int);
+        }
+        Ok(MsgAddressExt::load(slice)?.into())
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        match self {
+            Self::None => MsgAddressExt::None.store(builder),
+            Self::Extern { len, address } => MsgAddressExt::Extern { len: *len, address: address.clone() }.store(builder),
+            Self::Int(address) => address.store(builder),
+        }
+    }
+
+    pub fn as_int(&self) -> Option<&MsgAddressInt> {
+        match self {
+            Self::Int(address) => Some(address),
+            _ => None,
+        }
+    }
+}
+
+impl From<MsgAddressInt> for MsgAddress {
+    fn from(address: MsgAddressInt) -> Self {
+        Self::Int(address)
+    }
+}
+
+impl From<MsgAddressExt> for MsgAddress {
+    fn from(address: MsgAddressExt) -> Self {
+        match address {
+            MsgAddressExt::None => Self::None,
+            MsgAddressExt::Extern { len, address } => Self::Extern { len, address },
+        }
+    }
+}
+
+/// Internal addresses in the raw form, external ones as `ext:` with their bits in hex and `addr_none` as `none`.
+impl fmt::Display for MsgAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Extern { address, .. } => write!(f, "ext:{}", hex::encode(address)),
+            Self::Int(address) => address.fmt(f),
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use std::time::{Duration, SystemTime};
+
+use crate::cell::{ArcCell, Cell, CellError, CellType};
+use crate::time::{self, LogicalTime};
+use crate::tl::common::{BlockIdExt, Int256};
+
+use super::{hashmap_get, hashmap_slice_get, CurrencyCollection, HashmapEntry, ShardIdent};
+
+/// Block header fields, without the references to previous and masterchain blocks.
+///
+/// ```tlb
+/// block_info#9bc7a987 version:uint32 not_master:(## 1) after_merge:(## 1) before_split:(## 1) after_split:(## 1)
+///   want_split:Bool want_merge:Bool key_block:Bool vert_seqno_incr:(## 1) flags:(## 8) { flags <= 1 }
+///   seq_no:# vert_seq_no:# shard:ShardIdent gen_utime:uint32 start_lt:uint64 end_lt:uint64
+///   gen_validator_list_hash_short:uint32 gen_catchain_seqno:uint32 min_ref_mc_seqno:uint32
+///   prev_key_block_seqno:uint32 gen_software:flags . 0?GlobalVersion ... = BlockInfo;
+/// shard_ident$00 shard_pfx_bits:(#<= 60) workchain_id:int32 shard_prefix:uint64 = ShardIdent;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    pub version: u32,
+    pub not_master: bool,
+    pub after_merge: bool,
+    pub before_split: bool,
+    pub after_split: bool,
+    pub want_split: bool,
+    pub want_merge: bool,
+    pub key_block: bool,
+    pub vert_seqno_incr: bool,
+    pub flags: u8,
+    pub seq_no: u32,
+    pub vert_seq_no: u32,
+    pub workchain: i32,
+    /// Shard prefix with the tag bit, as in `tonNode.blockIdExt`
+    pub shard: u64,
+    pub gen_utime: u32,
+    pub start_lt: u64,
+    pub end_lt: u64,
+    pub gen_validator_list_hash_short: u32,
+    pub gen_catchain_seqno: u32,
+    pub min_ref_mc_seqno: u32,
+    pub prev_key_block_seqno: u32,
+}
+
+impl BlockInfo {
+    /// Time the block was generated at.
+    pub fn gen_time(&self) -> SystemTime {
+        time::system_time(self.gen_utime)
+    }
+
+    /// Whether the block was generated more than `max_age` ago.
+    pub fn is_older_than(&self, max_age: Duration) -> bool {
+        time::is_older_than(self.gen_utime, max_age)
+    }
+
+    /// Whether a transaction or message with logical time `lt` belongs to this block.
+    pub fn contains_lt(&self, lt: u64) -> bool {
+        LogicalTime(lt).is_within(self.start_lt, self.end_lt)
+    }
+
+    pub fn load(cell: &Cell) -> Result<Self, CellError> {
+        let mut slice = cell.parser()?;
+        let tag = slice.load_u32()?;
+        if tag != 0x9bc7a987 {
+            return Err(CellError::UnexpectedTag(tag as u64));
+        }
+        let version = slice.load_u32()?;
+        let not_master = slice.load_bit()?;
+        let after_merge = slice.load_bit()?;
+        let before_split = slice.load_bit()?;
+        let after_split = slice.load_bit()?;
+        let want_split = slice.load_bit()?;
+        let want_merge = slice.load_bit()?;
+        let key_block = slice.load_bit()?;
+        let vert_seqno_incr = slice.load_bit()?;
+        let flags = slice.load_u8()?;
+        let seq_no = slice.load_u32()?;
+        let vert_seq_no = slice.load_u32()?;
+        let tag = slice.load_uint(2)?;
+        if tag != 0 {
+            return Err(CellError::UnexpectedTag(tag));
+        }
+        let prefix_bits = slice.load_uint(6)?;
+        let workchain = slice.load_int(32)? as i32;
+        let prefix = slice.load_u64()?;
+        if prefix_bits > 60 {
+            return Err(CellError::InvalidExotic("invalid shard prefix length"));
+        }
+        let shard = prefix | (1u64 << (63 - prefix_bits));
+        Ok(Self {
+            version,
+            not_master,
+            after_merge,
+            before_split,
+            after_split,
+            want_split,
+            want_merge,
+            key_block,
+            vert_seqno_incr,
+            flags,
+            seq_no,
+            vert_seq_no,
+            workchain,
+            shard,
+            gen_utime: slice.load_u32()?,
+            start_lt: slice.load_u64()?,
+            end_lt: slice.load_u64()?,
+            gen_validator_list_hash_short: slice.load_u32()?,
+            gen_catchain_seqno: slice.load_u32()?,
+            min_ref_mc_seqno: slice.load_u32()?,
+            prev_key_block_seqno: slice.load_u32()?,
+        })
+    }
+
+    /// Extract the header from a merkle proof of `Block`, such as `liteServer.blockHeader`, or from the block itself.
+    ///
+    /// ```tlb
+    /// block#11ef55aa global_id:int32 info:^BlockInfo value_flow:^ValueFlow
+    ///   state_update:^(MERKLE_UPDATE ShardState) extra:^BlockExtra = Block;
+    /// ```
+    pub fn from_proof(proof: &Cell) -> Result<Self, CellError> {
+        Self::load(block_root(proof)?.reference(0)?)
+    }
+
+    /// Seqno of the last key block, which is this block itself for key blocks.
+    pub fn last_key_block_seqno(&self) -> u32 {
+        if self.key_block {
+            self.seq_no
+        } else {
+            self.prev_key_block_seqno
+        }
+    }
+}
+
+/// Movement of TON and extra currencies through a block, from its `value_flow`.
+///
+/// ```tlb
+/// value_flow#b8e48dfb ^[ from_prev_blk:CurrencyCollection to_next_blk:CurrencyCollection
+///   imported:CurrencyCollection exported:CurrencyCollection ] fees_collected:CurrencyCollection
+///   ^[ fees_imported:CurrencyCollection recovered:CurrencyCollection created:CurrencyCollection
+///   minted:CurrencyCollection ] = ValueFlow;
+/// value_flow_v2#3ebf98b7 ... fees_collected:CurrencyCollection burned:CurrencyCollection ^[ ... ] = ValueFlow;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueFlow {
+    /// Balance of all accounts of the shard before the block
+    pub from_prev_blk: CurrencyCollection,
+    /// Balance of all accounts of the shard after the block
+    pub to_next_blk: CurrencyCollection,
+    /// Value of the messages imported from other shards
+    pub imported: CurrencyCollection,
+    /// Value of the messages exported to other shards
+    pub exported: CurrencyCollection,
+    pub fees_collected: CurrencyCollection,
+    /// Fees burned since the burning of fees was enabled, zero in older blocks
+    pub burned: CurrencyCollection,
+    /// Fees collected in the shardchain blocks, in masterchain blocks
+    pub fees_imported: CurrencyCollection,
+    /// Fees recovered to the fee collector account, in masterchain blocks
+    pub recovered: CurrencyCollection,
+    /// Block creation reward
+    pub created: CurrencyCollection,
+    /// Extra currencies minted, in masterchain blocks
+    pub minted: CurrencyCollection,
+}
+
+impl ValueFlow {
+    pub fn load(cell: &Cell) -> Result<Self, CellError> {
+        let mut slice = cell.parser()?;
+        let tag = slice.load_u32()?;
+        if tag != 0xb8e48dfb && tag != 0x3ebf98b7 {
+            return Err(CellError::UnexpectedTag(tag as u64));
+        }
+        let mut balances = slice.load_ref()?.parser()?;
+        let from_prev_blk = CurrencyCollection::load(&mut balances)?;
+        let to_next_blk = CurrencyCollection::load(&mut balances)?;
+        let imported = CurrencyCollection::load(&mut balances)?;
+        let exported = CurrencyCollection::load(&mut balances)?;
+        let fees_collected = CurrencyCollection::load(&mut slice)?;
+        let burned = match tag {
+            0x3ebf98b7 => CurrencyCollection::load(&mut slice)?,
+            _ => CurrencyCollection::default(),
+        };
+        let mut created = slice.load_ref()?.parser()?;
+        Ok(Self {
+            from_prev_blk, to_next_blk, imported, exported, fees_collected, burned,
+            fees_imported: CurrencyCollection::load(&mut created)?,
+            recovered: CurrencyCollection::load(&mut created)?,
+            created: CurrencyCollection::load(&mut created)?,
+            minted: CurrencyCollection::load(&mut created)?,
+        })
+    }
+
+    /// Extract the value flow from a merkle proof of `Block` which includes it, such as `liteServer.blockHeader`
+    /// requested `with_value_flow`, or from the block itself.
+    pub fn from_proof(proof: &Cell) -> Result<Self, CellError> {
+        Self::load(block_root(proof)?.reference(1)?)
+    }
+}
+
+/// Root of `Block` in its merkle proof, or the block itself.
+fn block_root(proof: &Cell) -> Result<&Cell, CellError> {
+    let block = match proof.cell_type() {
+        CellType::MerkleProof => proof.reference(0)?,
+        _ => proof,
+    };
+    let tag = block.parser()?.load_u32()?;
+    if tag != 0x11ef55aa {
+        return Err(CellError::UnexpectedTag(tag as u64));
+    }
+    Ok(block)
+}
+
+/// `global_id` of the network which created the block, from a merkle proof of `Block` or from the block itself.
+pub fn block_global_id(proof: &Cell) -> Result<i32, CellError> {
+    let mut block = block_root(proof)?.parser()?;
+    block.skip_bits(32)?;
+    Ok(block.load_int(32)? as i32)
+}
+
+/// Hash of the block in a merkle proof of `Block`, such as `liteServer.blockHeader`, or of the block itself.
+pub fn block_hash(proof: &Cell) -> Result<[u8; 32], CellError> {
+    Ok(block_root(proof)?.hash(0))
+}
+
+/// Hash of the block and hash of the shard state after it, from a merkle proof of `Block` which includes
+/// the state update, such as `liteServer.blockHeader` requested `with_state_update`.
+pub fn block_state_hash(proof: &Cell) -> Result<([u8; 32], [u8; 32]), CellError> {
+    let block = block_root(proof)?;
+    let state_update = block.reference(2)?;
+    if state_update.cell_type() != CellType::MerkleUpdate {
+        return Err(CellError::InvalidExotic("state update is not a merkle update"));
+    }
+    Ok((block.hash(0), state_update.reference(1)?.hash(0)))
+}
+
+/// Ids of the blocks preceding the block in a merkle proof of `Block`, two of them after a merge.
+///
+/// ```tlb
+/// ext_blk_ref$_ end_lt:uint64 seq_no:uint32 root_hash:bits256 file_hash:bits256 = ExtBlkRef;
+/// prev_blk_info$_ prev:ExtBlkRef = BlkPrevInfo 0;
+/// prev_blks_info$_ prev1:^ExtBlkRef prev2:^ExtBlkRef = BlkPrevInfo 1;
+/// ```
+pub fn prev_blocks(proof: &Cell) -> Result<Vec<BlockIdExt>, CellError> {
+    let info_cell = block_root(proof)?.reference(0)?;
+    let info = BlockInfo::load(info_cell)?;
+    // master_ref goes before prev_ref in shardchain blocks
+    let prev_ref = info_cell.reference(info.not_master as usize)?;
+    let load = |cell: &Cell, shard: u64| -> Result<BlockIdExt, CellError> {
+        let mut slice = cell.parser()?;
+        slice.load_u64()?;
+        Ok(BlockIdExt {
+            workchain: info.workchain,
+            shard,
+            seqno: slice.load_u32()?,
+            root_hash: Int256(slice.load_u256()?),
+            file_hash: Int256(slice.load_u256()?),
+        })
+    };
+    let shard = ShardIdent::new(info.workchain, info.shard);
+    if info.after_merge {
+        let (left, right) = shard.split().ok_or(CellError::InvalidExotic("shard can't be split"))?;
+        Ok(vec![load(prev_ref.reference(0)?, left.shard)?, load(prev_ref.reference(1)?, right.shard)?])
+    } else if info.after_split {
+        let parent = shard.merge().ok_or(CellError::InvalidExotic("shard can't be merged"))?;
+        Ok(vec![load(prev_ref, parent.shard)?])
+    } else {
+        Ok(vec![load(prev_ref, info.shard)?])
+    }
+}
+
+/// Transaction of `account` with logical time `lt`, from a merkle proof of `Block` which includes it or from
+/// the block itself. `None` if the block has no such transaction or it was pruned from the proof.
+pub fn block_transaction(proof: &Cell, account: &[u8; 32], lt: u64) -> Result<Option<ArcCell>, CellError> {
+    match lookup_block_transaction(proof, account, lt)? {
+        ProvenTransaction::Exists(transaction) => Ok(Some(transaction)),
+        ProvenTransaction::Nonexistent | ProvenTransaction::Unknown => Ok(None),
+    }
+}
+
+/// Transaction looked up in a merkle proof of `Block`, see [`lookup_block_transaction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProvenTransaction {
+    Exists(ArcCell),
+    /// The proof shows that the block has no transaction of the account with this logical time
+    Nonexistent,
+    /// The transaction was pruned from the proof, so the proof says nothing about it
+    Unknown,
+}
+
+/// Look the transaction of `account` with logical time `lt` up in a merkle proof of `Block`, such as the one
+/// returned by `getOneTransaction`, or in the block itself.
+///
+/// ```tlb
+/// block_extra in_msg_descr:^InMsgDescr out_msg_descr:^OutMsgDescr account_blocks:^ShardAccountBlocks
+///   rand_seed:bits256 created_by:bits256 custom:(Maybe ^McBlockExtra) = BlockExtra;
+/// _ (HashmapAugE 256 AccountBlock CurrencyCollection) = ShardAccountBlocks;
+/// acc_trans#5 account_addr:bits256 transactions:(HashmapAug 64 ^Transaction CurrencyCollection)
+///   state_update:^(HASH_UPDATE Account) = AccountBlock;
+/// ```
+pub fn lookup_block_transaction(proof: &Cell, account: &[u8; 32], lt: u64) -> Result<ProvenTransaction, CellError> {
+    let extra = block_root(proof)?.reference(3)?;
+    if extra.cell_type() == CellType::PrunedBranch {
+        return Ok(ProvenTransaction::Unknown);
+    }
+    let account_blocks = extra.reference(2)?;
+    if account_blocks.cell_type() == CellType::PrunedBranch {
+        return Ok(ProvenTransaction::Unknown);
+    }
+    let Some(root) = account_blocks.parser()?.load_maybe_ref()? else {
+        return Ok(ProvenTransaction::Nonexistent);
+    };
+    let mut account_block = match hashmap_get(root, account, 256)? {
+        HashmapEntry::Found(value) => value,
+        HashmapEntry::Absent => return Ok(ProvenTransaction::Nonexistent),
+        HashmapEntry::Pruned => return Ok(ProvenTransaction::Unknown),
+    };
+    // the values of augmented dictionaries start with the extra
+    CurrencyCollection::load(&mut account_block)?;
+    let tag = account_block.load_uint(4)?;
+    if tag != 0x5 {
+        return Err(CellError::UnexpectedTag(tag));
+    }
+    account_block.skip_bits(256)?;
+    match hashmap_slice_get(account_block, &lt.to_be_bytes(), 64)? {
+        HashmapEntry::Found(mut value) => {
+            CurrencyCollection::load(&mut value)?;
+            Ok(ProvenTransaction::Exists(value.load_ref()?.clone()))
+        }
+        HashmapEntry::Absent => Ok(ProvenTransaction::Nonexistent),
+        HashmapEntry::Pruned => Ok(ProvenTransaction::Unknown),
+    }
+}
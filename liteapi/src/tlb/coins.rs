@@ -0,0 +1,132 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cell::{CellBuilder, CellError, CellSlice};
+
+/// Amount of nanotons, `nanograms$_ amount:(VarUInteger 16) = Grams;`.
+///
+/// Displayed and parsed as TON, e.g. `1.5 TON`. With the `serde` feature it's serialized as a string of
+/// nanotons, since JSON numbers can't hold every amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Coins(pub u128);
+
+impl Coins {
+    pub const ZERO: Coins = Coins(0);
+    /// Largest amount `Grams` can hold
+    pub const MAX: Coins = Coins((1 << 120) - 1);
+    pub const NANOTONS_PER_TON: u128 = 1_000_000_000;
+
+    pub fn from_nanotons(nanotons: u128) -> Self {
+        Self(nanotons)
+    }
+
+    pub fn from_ton(ton: u64) -> Self {
+        Self(ton as u128 * Self::NANOTONS_PER_TON)
+    }
+
+    pub fn nanotons(self) -> u128 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: Coins) -> Option<Coins> {
+        self.0.checked_add(other.0).map(Self).filter(|sum| *sum <= Self::MAX)
+    }
+
+    pub fn checked_sub(self, other: Coins) -> Option<Coins> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    pub fn checked_mul(self, factor: u128) -> Option<Coins> {
+        self.0.checked_mul(factor).map(Self).filter(|product| *product <= Self::MAX)
+    }
+
+    pub fn checked_div(self, divisor: u128) -> Option<Coins> {
+        self.0.checked_div(divisor).map(Self)
+    }
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        Ok(Self(slice.load_coins()?))
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        builder.store_coins(self.0)?;
+        Ok(())
+    }
+}
+
+impl From<u128> for Coins {
+    fn from(nanotons: u128) -> Self {
+        Self(nanotons)
+    }
+}
+
+impl From<Coins> for u128 {
+    fn from(coins: Coins) -> Self {
+        coins.0
+    }
+}
+
+impl fmt::Display for Coins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ton = self.0 / Self::NANOTONS_PER_TON;
+        let nanotons = self.0 % Self::NANOTONS_PER_TON;
+        if nanotons == 0 {
+            return write!(f, "{} TON", ton);
+        }
+        let fraction = format!("{:09}", nanotons);
+        write!(f, "{}.{} TON", ton, fraction.trim_end_matches('0'))
+    }
+}
+
+/// Parses amounts in TON with up to 9 decimals and an optional `TON` suffix, e.g. `1.5 TON` or `0.05`.
+impl FromStr for Coins {
+    type Err = CellError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_suffix("TON").unwrap_or(s).trim_end();
+        let (ton, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if ton.is_empty() || !is_digits(ton) || !is_digits(fraction) || fraction.len() > 9 {
+            return Err(CellError::InvalidAmount);
+        }
+        let ton: u128 = ton.parse().map_err(|_| CellError::InvalidAmount)?;
+        let nanotons: u128 = format!("{:0<9}", fraction).parse().map_err(|_| CellError::InvalidAmount)?;
+        ton.checked_mul(Self::NANOTONS_PER_TON)
+            .and_then(|ton| Self(ton).checked_add(Self(nanotons)))
+            .ok_or(CellError::InvalidAmount)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Coins {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Coins {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Coins;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an amount of nanotons as a string or an integer")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Coins, E> {
+                Ok(Coins(value as u128))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Coins, E> {
+                let nanotons: u128 = value.parse().map_err(E::custom)?;
+                Some(Coins(nanotons)).filter(|coins| *coins <= Coins::MAX).ok_or_else(|| E::custom(CellError::InvalidAmount))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
@@ -0,0 +1,136 @@
+use crate::cell::{ArcCell, Cell, CellError, CellSlice};
+
+use super::message::store_snake;
+use super::metadata::{load_snake, text};
+
+/// Comment of a transfer, as shown by wallets in transaction histories.
+///
+/// ```tlb
+/// text_comment#00000000 text:SnakeData = InternalMsgBody;
+/// encrypted_comment#2167da4b data:SnakeData = InternalMsgBody;
+/// ```
+///
+/// Text longer than a cell continues in the chain of first references, and a UTF-8 character may be split
+/// between cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Comment {
+    /// Text, invalid UTF-8 is replaced
+    Text(String),
+    /// Comment encrypted for the sender and the receiver, see `Comment::decrypt` of the `crypto` feature
+    Encrypted(Vec<u8>),
+}
+
+impl Comment {
+    pub const TEXT_OP: u32 = 0;
+    pub const ENCRYPTED_OP: u32 = 0x2167da4b;
+
+    /// Load the comment and the chain of cells it continues in, fails with `UnexpectedTag` if the body isn't
+    /// a comment.
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let op = slice.load_u32()?;
+        match op {
+            Self::TEXT_OP => Ok(Self::Text(text(load_snake(slice)?))),
+            Self::ENCRYPTED_OP => Ok(Self::Encrypted(load_snake(slice)?)),
+            _ => Err(CellError::UnexpectedTag(op as u64)),
+        }
+    }
+
+    pub fn from_cell(cell: &Cell) -> Result<Self, CellError> {
+        Self::load(&mut cell.parser()?)
+    }
+
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        match self {
+            Self::Text(text) => store_snake(Self::TEXT_OP, text.as_bytes()),
+            Self::Encrypted(data) => store_snake(Self::ENCRYPTED_OP, data),
+        }
+    }
+}
+
+#[cfg(feature = "crypto")]
+mod encryption {
+    use adnl::crypto::{KeyPair, PublicKey};
+    use aes::cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit};
+    use aes::Aes256;
+    use hmac::{Hmac, Mac};
+    use rand::RngCore;
+    use sha2::Sha512;
+
+    use super::Comment;
+
+    impl Comment {
+        /// Comment encrypted by the owner of `key` for the owner of `receiver`, the way wallet apps do.
+        ///
+        /// `sender` is the user-friendly bounceable url-safe address of the sender's wallet, e.g.
+        /// `EQ...`, which both sides need to decrypt the comment.
+        pub fn encrypt(text: &str, key: &KeyPair, receiver: &PublicKey, sender: &str) -> Self {
+            let data = text.as_bytes();
+            // random prefix of 16 to 31 bytes starting with its length, padding the data to whole AES blocks
+            let prefix_len = ((16 + 15 + data.len()) & !15) - data.len();
+            let mut plain = vec![0; prefix_len];
+            rand::thread_rng().fill_bytes(&mut plain);
+            plain[0] = prefix_len as u8;
+            plain.extend_from_slice(data);
+            let msg_key: [u8; 16] = hmac(sender.as_bytes(), &plain)[..16].try_into().unwrap();
+            let (cipher, mut iv) = cipher(&key.compute_shared_secret(receiver), &msg_key);
+            // AES-256-CBC without padding
+            for block in plain.chunks_exact_mut(16) {
+                block.iter_mut().zip(iv).for_each(|(byte, iv)| *byte ^= iv);
+                cipher.encrypt_block(GenericArray::from_mut_slice(block));
+                iv.copy_from_slice(block);
+            }
+            let mut encrypted = xor(key.public_key.as_ref(), receiver.as_ref()).to_vec();
+            encrypted.extend_from_slice(&msg_key);
+            encrypted.extend(plain);
+            Self::Encrypted(encrypted)
+        }
+
+        /// Text of the comment, decrypted by its sender or receiver with their `key`.
+        ///
+        /// Returns the text of [`Comment::Text`], and `None` if the comment isn't encrypted for `key`, `sender`
+        /// is wrong or the data is corrupted.
+        pub fn decrypt(&self, key: &KeyPair, sender: &str) -> Option<String> {
+            let data = match self {
+                Self::Text(text) => return Some(text.clone()),
+                Self::Encrypted(data) => data,
+            };
+            if data.len() < 64 || data.len() % 16 != 0 {
+                return None;
+            }
+            let other = PublicKey::from_bytes(xor(key.public_key.as_ref(), data[..32].try_into().unwrap()))?;
+            let msg_key: [u8; 16] = data[32..48].try_into().unwrap();
+            let (cipher, mut iv) = cipher(&key.compute_shared_secret(&other), &msg_key);
+            let mut plain = data[48..].to_vec();
+            for block in plain.chunks_exact_mut(16) {
+                let next_iv: [u8; 16] = block.try_into().unwrap();
+                cipher.decrypt_block(GenericArray::from_mut_slice(block));
+                block.iter_mut().zip(iv).for_each(|(byte, iv)| *byte ^= iv);
+                iv = next_iv;
+            }
+            if hmac(sender.as_bytes(), &plain)[..16] != msg_key {
+                return None;
+            }
+            let prefix_len = plain[0] as usize;
+            if !(16..=plain.len()).contains(&prefix_len) {
+                return None;
+            }
+            Some(super::text(plain[prefix_len..].to_vec()))
+        }
+    }
+
+    fn hmac(key: &[u8], data: &[u8]) -> [u8; 64] {
+        let mut mac = <Hmac<Sha512> as Mac>::new_from_slice(key).expect("HMAC takes keys of any length");
+        mac.update(data);
+        mac.finalize().into_bytes().into()
+    }
+
+    /// AES key and IV derived from the shared secret and the message key.
+    fn cipher(shared_secret: &[u8; 32], msg_key: &[u8; 16]) -> (Aes256, [u8; 16]) {
+        let x = hmac(shared_secret, msg_key);
+        (Aes256::new(GenericArray::from_slice(&x[..32])), x[32..48].try_into().unwrap())
+    }
+
+    fn xor(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        std::array::from_fn(|i| a[i] ^ b[i])
+    }
+}
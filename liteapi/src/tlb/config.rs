@@ -0,0 +1,99 @@
+use crate::cell::{ArcCell, Cell, CellError, CellSlice, CellType};
+
+use super::{hashmap_entries, StorageConfig};
+
+/// Masterchain state fields available in the `config_proof` of `liteServer.configInfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct McStateConfig {
+    pub gen_utime: u32,
+    pub gen_lt: u64,
+    pub config_address: [u8; 32],
+    /// Root of `Hashmap 32 ^Cell` with configuration parameters
+    pub config: ArcCell,
+}
+
+impl McStateConfig {
+    /// Extract the configuration from a merkle proof of `ShardStateUnsplit` or from the state itself.
+    ///
+    /// ```tlb
+    /// shard_state#9023afe2 global_id:int32 shard_id:ShardIdent seq_no:uint32 vert_seq_no:#
+    ///   gen_utime:uint32 gen_lt:uint64 min_ref_mc_seqno:uint32 out_msg_queue_info:^OutMsgQueueInfo
+    ///   before_split:(## 1) accounts:^ShardAccounts ^[ ... ] custom:(Maybe ^McStateExtra) = ShardStateUnsplit;
+    /// masterchain_state_extra#cc26 shard_hashes:ShardHashes config:ConfigParams ^[ ... ] ... = McStateExtra;
+    /// _ config_addr:bits256 config:^(Hashmap 32 ^Cell) = ConfigParams;
+    /// ```
+    pub fn from_proof(proof: &Cell) -> Result<Self, CellError> {
+        let mut state = shard_state(proof)?;
+        let gen_utime = state.load_u32()?;
+        let gen_lt = state.load_u64()?;
+        let mut extra = load_mc_state_extra(&mut state)?;
+        extra.load_maybe_ref()?;
+        let config_address = extra.load_u256()?;
+        let config = extra.load_ref()?.clone();
+        Ok(Self { gen_utime, gen_lt, config_address, config })
+    }
+
+    /// Configuration parameter `index`, `None` if it's not set or pruned from the proof.
+    pub fn param(&self, index: u32) -> Result<Option<ArcCell>, CellError> {
+        let entry = hashmap_entries(&self.config, 32)?.into_iter()
+            .find(|(key, _)| key[..] == index.to_be_bytes());
+        match entry {
+            Some((_, mut value)) => Ok(Some(value.load_ref()?.clone())),
+            None => Ok(None),
+        }
+    }
+
+    /// Storage prices from configuration parameter 18, `None` if it's not set or pruned from the proof.
+    pub fn storage_config(&self) -> Result<Option<StorageConfig>, CellError> {
+        self.param(StorageConfig::PARAM)?.map(|cell| StorageConfig::load(&cell)).transpose()
+    }
+}
+
+/// `global_id` of the network of a `ShardStateUnsplit`, from its merkle proof or the state itself.
+pub fn state_global_id(proof: &Cell) -> Result<i32, CellError> {
+    Ok(shard_state_root(proof)?.load_int(32)? as i32)
+}
+
+/// `ShardStateUnsplit` in a merkle proof or the state itself, positioned at `gen_utime`.
+pub(crate) fn shard_state(proof: &Cell) -> Result<CellSlice<'_>, CellError> {
+    let mut state = shard_state_root(proof)?;
+    // global_id, shard_ident$00 shard_pfx_bits:(#<= 60) workchain_id:int32 shard_prefix:uint64, seq_no, vert_seq_no
+    state.skip_bits(32 + 2 + 6 + 32 + 64 + 32 + 32)?;
+    Ok(state)
+}
+
+fn shard_state_root(proof: &Cell) -> Result<CellSlice<'_>, CellError> {
+    let mut state = match proof.cell_type() {
+        CellType::MerkleProof => proof.reference(0)?.parser()?,
+        _ => proof.parser()?,
+    };
+    let tag = state.load_u32()?;
+    if tag != 0x9023afe2 {
+        return Err(CellError::UnexpectedTag(tag as u64));
+    }
+    Ok(state)
+}
+
+/// `McStateExtra` of a [`shard_state`] positioned after `gen_lt`, the result is positioned at `shard_hashes`.
+pub(crate) fn load_mc_state_extra<'a>(state: &mut CellSlice<'a>) -> Result<CellSlice<'a>, CellError> {
+    // min_ref_mc_seqno, before_split
+    state.skip_bits(32 + 1)?;
+    state.load_ref()?;
+    state.load_ref()?;
+    state.load_ref()?;
+    let extra = state.load_maybe_ref()?.ok_or(CellError::InvalidExotic("not a masterchain state"))?;
+    let mut extra = extra.parser()?;
+    let tag = extra.load_uint(16)?;
+    if tag != 0xcc26 {
+        return Err(CellError::UnexpectedTag(tag));
+    }
+    Ok(extra)
+}
+
+/// Root of the `shard_hashes` dictionary of a masterchain state in a merkle proof, `None` if there are no shards.
+pub fn shard_hashes_root(proof: &Cell) -> Result<Option<&ArcCell>, CellError> {
+    let mut state = shard_state(proof)?;
+    // gen_utime, gen_lt
+    state.skip_bits(32 + 64)?;
+    load_mc_state_extra(&mut state)?.load_maybe_ref()
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+macro_rules! exit_codes {
+    ($($(#[$meta:meta])* $name:ident = $code:literal => $description:literal,)*) => {
+        /// Exit code of the compute phase, e.g. `exit_code` of `runSmcMethod`.
+        ///
+        /// Codes without a variant, like the ones thrown by contracts themselves, are kept as
+        /// [`ExitCode::Other`]. Displayed with their description, e.g. `method not found (11)`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum ExitCode {
+            $($(#[$meta])* $name,)*
+            Other(i32),
+        }
+
+        impl ExitCode {
+            pub fn code(self) -> i32 {
+                match self {
+                    $(Self::$name => $code,)*
+                    Self::Other(code) => code,
+                }
+            }
+
+            /// Human-readable description, `None` for codes without a variant.
+            pub fn description(self) -> Option<&'static str> {
+                match self {
+                    $(Self::$name => Some($description),)*
+                    Self::Other(_) => None,
+                }
+            }
+        }
+
+        impl From<i32> for ExitCode {
+            fn from(code: i32) -> Self {
+                match code {
+                    $($code => Self::$name,)*
+                    code => Self::Other(code),
+                }
+            }
+        }
+    };
+}
+
+exit_codes! {
+    Success = 0 => "success",
+    AlternativeSuccess = 1 => "alternative success",
+    StackUnderflow = 2 => "stack underflow",
+    StackOverflow = 3 => "stack overflow",
+    IntegerOverflow = 4 => "integer overflow",
+    RangeCheck = 5 => "integer out of expected range",
+    InvalidOpcode = 6 => "invalid opcode",
+    TypeCheck = 7 => "type check error",
+    CellOverflow = 8 => "cell overflow",
+    /// Also thrown when a library cell or a pruned branch is loaded
+    CellUnderflow = 9 => "cell underflow",
+    DictionaryError = 10 => "dictionary error",
+    /// Thrown by the method selector of most contracts when there's no such get-method
+    MethodNotFound = 11 => "method not found",
+    FatalError = 12 => "fatal error",
+    OutOfGas = 13 => "out of gas",
+    /// Out of gas as reported by the compute phase of a transaction, `-14`
+    OutOfGasNegative = -14 => "out of gas",
+    VirtualizationError = 14 => "virtualization error",
+    InvalidActionList = 32 => "invalid action list",
+    ActionListTooLong = 33 => "action list too long",
+    InvalidAction = 34 => "invalid or unsupported action",
+    InvalidSourceAddress = 35 => "invalid source address in outbound message",
+    InvalidDestinationAddress = 36 => "invalid destination address in outbound message",
+    NotEnoughTon = 37 => "not enough TON",
+    NotEnoughExtraCurrencies = 38 => "not enough extra currencies",
+    OutboundMessageTooLarge = 39 => "outbound message does not fit into a cell",
+    NotEnoughFunds = 40 => "not enough funds to process a message",
+    LibraryNotFound = 41 => "library reference is null",
+    InvalidLibraryAction = 42 => "invalid library action",
+    LibraryLimitsExceeded = 43 => "library limits exceeded",
+    AccountStateTooLarge = 50 => "account state size exceeded limits",
+}
+
+impl ExitCode {
+    /// Whether the code is one of the two success codes, `0` and `1`.
+    pub fn is_success(self) -> bool {
+        matches!(self, Self::Success | Self::AlternativeSuccess)
+    }
+}
+
+impl From<ExitCode> for i32 {
+    fn from(code: ExitCode) -> Self {
+        code.code()
+    }
+}
+
+impl fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.description() {
+            Some(description) => write!(f, "{} ({})", description, self.code()),
+            None => write!(f, "exit code {}", self.code()),
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+
+use crate::cell::{Cell, CellError, CellSlice};
+
+use super::{hashmap_entries, Coins, McStateConfig};
+
+/// `storage_prices#cc utime_since:uint32 bit_price_ps:uint64 cell_price_ps:uint64 mc_bit_price_ps:uint64
+///   mc_cell_price_ps:uint64 = StoragePrices;`
+///
+/// Prices are in nanotons per second, as fixed point numbers with 16 fractional bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoragePrices {
+    pub utime_since: u32,
+    pub bit_price_ps: u64,
+    pub cell_price_ps: u64,
+    pub mc_bit_price_ps: u64,
+    pub mc_cell_price_ps: u64,
+}
+
+impl StoragePrices {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_u8()?;
+        if tag != 0xcc {
+            return Err(CellError::UnexpectedTag(tag as u64));
+        }
+        Ok(Self {
+            utime_since: slice.load_u32()?,
+            bit_price_ps: slice.load_u64()?,
+            cell_price_ps: slice.load_u64()?,
+            mc_bit_price_ps: slice.load_u64()?,
+            mc_cell_price_ps: slice.load_u64()?,
+        })
+    }
+
+    /// Fee for storing an account of `account_bits` bits in `account_cells` cells for `duration` seconds.
+    pub fn calc_storage_fee(&self, account_bits: u64, account_cells: u64, duration: u32, is_masterchain: bool) -> Coins {
+        let (bit_price, cell_price) = match is_masterchain {
+            true => (self.mc_bit_price_ps, self.mc_cell_price_ps),
+            false => (self.bit_price_ps, self.cell_price_ps),
+        };
+        let per_second = (account_bits as u128 * bit_price as u128).saturating_add(account_cells as u128 * cell_price as u128);
+        Coins(shift_ceil(per_second.saturating_mul(duration as u128), 16))
+    }
+}
+
+/// `_ (Hashmap 32 StoragePrices) = ConfigParam 18;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageConfig {
+    /// Prices ordered by `utime_since`
+    pub prices: Vec<StoragePrices>,
+}
+
+impl StorageConfig {
+    pub const PARAM: u32 = 18;
+
+    pub fn load(cell: &Cell) -> Result<Self, CellError> {
+        let mut prices = hashmap_entries(cell, 32)?.into_iter()
+            .map(|(_, mut value)| StoragePrices::load(&mut value))
+            .collect::<Result<Vec<_>, _>>()?;
+        prices.sort_by_key(|p| p.utime_since);
+        Ok(Self { prices })
+    }
+
+    /// Prices in effect at `utime`.
+    pub fn prices_at(&self, utime: u32) -> Option<&StoragePrices> {
+        self.prices.iter().rev().find(|p| p.utime_since <= utime)
+    }
+
+    /// Fee for storing an account of `account_bits` bits in `account_cells` cells for `duration` seconds at the
+    /// latest prices, zero if there are none.
+    pub fn calc_storage_fee(&self, account_bits: u64, account_cells: u64, duration: u32, is_masterchain: bool) -> Coins {
+        match self.prices.last() {
+            Some(prices) => prices.calc_storage_fee(account_bits, account_cells, duration, is_masterchain),
+            None => Coins::ZERO,
+        }
+    }
+}
+
+/// ```tlb
+/// gas_prices#dd gas_price:uint64 gas_limit:uint64 gas_credit:uint64 block_gas_limit:uint64
+///   freeze_due_limit:uint64 delete_due_limit:uint64 = GasLimitsPrices;
+/// gas_prices_ext#de gas_price:uint64 gas_limit:uint64 special_gas_limit:uint64 gas_credit:uint64
+///   block_gas_limit:uint64 freeze_due_limit:uint64 delete_due_limit:uint64 = GasLimitsPrices;
+/// gas_flat_pfx#d1 flat_gas_limit:uint64 flat_gas_price:uint64 other:GasLimitsPrices = GasLimitsPrices;
+/// ```
+///
+/// `gas_price` is in nanotons per unit of gas, as a fixed point number with 16 fractional bits.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GasLimitsPrices {
+    pub flat_gas_limit: u64,
+    pub flat_gas_price: u64,
+    pub gas_price: u64,
+    pub gas_limit: u64,
+    /// Gas limit of special accounts, same as `gas_limit` for `gas_prices#dd`
+    pub special_gas_limit: u64,
+    pub gas_credit: u64,
+    pub block_gas_limit: u64,
+    pub freeze_due_limit: u64,
+    pub delete_due_limit: u64,
+}
+
+impl GasLimitsPrices {
+    /// `config_mc_gas_prices#_ GasLimitsPrices = ConfigParam 20;`
+    pub const MC_PARAM: u32 = 20;
+    /// `config_gas_prices#_ GasLimitsPrices = ConfigParam 21;`
+    pub const PARAM: u32 = 21;
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_u8()?;
+        if tag == 0xd1 {
+            let flat_gas_limit = slice.load_u64()?;
+            let flat_gas_price = slice.load_u64()?;
+            return Ok(Self { flat_gas_limit, flat_gas_price, ..Self::load(slice)? });
+        }
+        let gas_price = slice.load_u64()?;
+        let gas_limit = slice.load_u64()?;
+        let special_gas_limit = match tag {
+            0xdd => gas_limit,
+            0xde => slice.load_u64()?,
+            _ => return Err(CellError::UnexpectedTag(tag as u64)),
+        };
+        Ok(Self {
+            flat_gas_limit: 0,
+            flat_gas_price: 0,
+            gas_price,
+            gas_limit,
+            special_gas_limit,
+            gas_credit: slice.load_u64()?,
+            block_gas_limit: slice.load_u64()?,
+            freeze_due_limit: slice.load_u64()?,
+            delete_due_limit: slice.load_u64()?,
+        })
+    }
+
+    /// Fee for `gas_used` units of gas: the flat price covers the first `flat_gas_limit` units.
+    pub fn calc_gas_fee(&self, gas_used: u64) -> Coins {
+        let extra_gas = gas_used.saturating_sub(self.flat_gas_limit) as u128;
+        Coins(self.flat_gas_price as u128 + shift_ceil(extra_gas * self.gas_price as u128, 16))
+    }
+}
+
+/// ```tlb
+/// msg_forward_prices#ea lump_price:uint64 bit_price:uint64 cell_price:uint64 ihr_price_factor:uint32
+///   first_frac:uint16 next_frac:uint16 = MsgForwardPrices;
+/// ```
+///
+/// `bit_price` and `cell_price` are in nanotons, as fixed point numbers with 16 fractional bits.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MsgForwardPrices {
+    pub lump_price: u64,
+    pub bit_price: u64,
+    pub cell_price: u64,
+    pub ihr_price_factor: u32,
+    pub first_frac: u16,
+    pub next_frac: u16,
+}
+
+impl MsgForwardPrices {
+    /// `config_mc_fwd_prices#_ MsgForwardPrices = ConfigParam 24;`
+    pub const MC_PARAM: u32 = 24;
+    /// `config_fwd_prices#_ MsgForwardPrices = ConfigParam 25;`
+    pub const PARAM: u32 = 25;
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_u8()?;
+        if tag != 0xea {
+            return Err(CellError::UnexpectedTag(tag as u64));
+        }
+        Ok(Self {
+            lump_price: slice.load_u64()?,
+            bit_price: slice.load_u64()?,
+            cell_price: slice.load_u64()?,
+            ihr_price_factor: slice.load_u32()?,
+            first_frac: slice.load_uint(16)? as u16,
+            next_frac: slice.load_uint(16)? as u16,
+        })
+    }
+
+    /// Forward fee of a message, `size` excludes its root cell.
+    pub fn calc_fwd_fee(&self, size: StorageUsed) -> Coins {
+        let price = (size.bits as u128 * self.bit_price as u128).saturating_add(size.cells as u128 * self.cell_price as u128);
+        Coins(self.lump_price as u128 + shift_ceil(price, 16))
+    }
+}
+
+/// Number of distinct cells in a tree and their total data bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StorageUsed {
+    pub bits: u64,
+    pub cells: u64,
+}
+
+impl StorageUsed {
+    /// Size of the tree of `root` including `root` itself.
+    pub fn of_tree(root: &Cell) -> Self {
+        let mut visited = HashSet::new();
+        let mut size = Self::default();
+        let mut stack = vec![root];
+        while let Some(cell) = stack.pop() {
+            if !visited.insert(cell.repr_hash()) {
+                continue;
+            }
+            size.bits += cell.bit_len() as u64;
+            size.cells += 1;
+            stack.extend(cell.references().iter().map(|r| r.as_ref()));
+        }
+        size
+    }
+
+    /// Size of a message as counted for forward fees, without its root cell.
+    pub fn of_message(message: &Cell) -> Self {
+        let size = Self::of_tree(message);
+        Self { bits: size.bits - message.bit_len() as u64, cells: size.cells - 1 }
+    }
+}
+
+/// Fee prices of configuration parameters 18, 20, 21, 24 and 25.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeConfig {
+    pub storage: StorageConfig,
+    pub mc_gas: GasLimitsPrices,
+    pub gas: GasLimitsPrices,
+    pub mc_fwd: MsgForwardPrices,
+    pub fwd: MsgForwardPrices,
+}
+
+impl FeeConfig {
+    pub const PARAMS: [u32; 5] = [
+        StorageConfig::PARAM, GasLimitsPrices::MC_PARAM, GasLimitsPrices::PARAM, MsgForwardPrices::MC_PARAM, MsgForwardPrices::PARAM,
+    ];
+
+    /// Fee prices from the configuration, `None` if any of [`FeeConfig::PARAMS`] is missing.
+    pub fn from_config(config: &McStateConfig) -> Result<Option<Self>, CellError> {
+        let (Some(storage), Some(mc_gas), Some(gas), Some(mc_fwd), Some(fwd)) = (
+            config.storage_config()?,
+            config.param(GasLimitsPrices::MC_PARAM)?,
+            config.param(GasLimitsPrices::PARAM)?,
+            config.param(MsgForwardPrices::MC_PARAM)?,
+            config.param(MsgForwardPrices::PARAM)?,
+        ) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            storage,
+            mc_gas: GasLimitsPrices::load(&mut mc_gas.parser()?)?,
+            gas: GasLimitsPrices::load(&mut gas.parser()?)?,
+            mc_fwd: MsgForwardPrices::load(&mut mc_fwd.parser()?)?,
+            fwd: MsgForwardPrices::load(&mut fwd.parser()?)?,
+        }))
+    }
+
+    pub fn gas_prices(&self, is_masterchain: bool) -> &GasLimitsPrices {
+        if is_masterchain { &self.mc_gas } else { &self.gas }
+    }
+
+    pub fn fwd_prices(&self, is_masterchain: bool) -> &MsgForwardPrices {
+        if is_masterchain { &self.mc_fwd } else { &self.fwd }
+    }
+
+    /// Estimate the fees of processing an inbound external message.
+    pub fn estimate(&self, params: &FeeParams) -> FeeEstimate {
+        let fwd = self.fwd_prices(params.is_masterchain);
+        FeeEstimate {
+            import_fee: fwd.calc_fwd_fee(params.in_msg),
+            storage_fee: self.storage.calc_storage_fee(
+                params.account.bits, params.account.cells, params.storage_duration, params.is_masterchain,
+            ),
+            gas_fee: self.gas_prices(params.is_masterchain).calc_gas_fee(params.gas_used),
+            fwd_fee: params.out_msgs.iter().map(|size| fwd.calc_fwd_fee(*size).0).sum::<u128>().into(),
+        }
+    }
+}
+
+/// Inputs of [`FeeConfig::estimate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeeParams {
+    /// Whether the account is in the masterchain
+    pub is_masterchain: bool,
+    /// Size of the inbound external message, see [`StorageUsed::of_message`]
+    pub in_msg: StorageUsed,
+    pub gas_used: u64,
+    /// Sizes of the outbound messages, see [`StorageUsed::of_message`]
+    pub out_msgs: Vec<StorageUsed>,
+    /// Size of the account's state
+    pub account: StorageUsed,
+    /// Seconds since the account last paid for storage
+    pub storage_duration: u32,
+}
+
+/// Fees estimated by [`FeeConfig::estimate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FeeEstimate {
+    /// Fee for importing the inbound external message
+    pub import_fee: Coins,
+    pub storage_fee: Coins,
+    pub gas_fee: Coins,
+    /// Forward fees of all outbound messages
+    pub fwd_fee: Coins,
+}
+
+impl FeeEstimate {
+    pub fn total(&self) -> Coins {
+        Coins(self.import_fee.0 + self.storage_fee.0 + self.gas_fee.0 + self.fwd_fee.0)
+    }
+}
+
+/// `value >> bits`, rounded up.
+fn shift_ceil(value: u128, bits: u32) -> u128 {
+    (value >> bits) + (value & ((1 << bits) - 1) != 0) as u128
+}
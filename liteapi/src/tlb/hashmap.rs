@@ -0,0 +1,207 @@
+use crate::cell::{ArcCell, Cell, CellBuilder, CellError, CellSlice, CellType};
+
+/// Entries of `Hashmap n X` with `key_bits` bit keys, as left-aligned keys and slices with the values.
+///
+/// Pruned subtrees, found in merkle proofs, are skipped.
+///
+/// ```tlb
+/// hm_edge#_ {n:#} {X:Type} {l:#} {m:#} label:(HmLabel ~l n) {n = (~m) + l} node:(HashmapNode m X) = Hashmap n X;
+/// hmn_leaf#_ {X:Type} value:X = HashmapNode 0 X;
+/// hmn_fork#_ {n:#} {X:Type} left:^(Hashmap n X) right:^(Hashmap n X) = HashmapNode (n + 1) X;
+/// ```
+pub fn hashmap_entries(root: &Cell, key_bits: usize) -> Result<Vec<(Vec<u8>, CellSlice<'_>)>, CellError> {
+    let mut entries = Vec::new();
+    let mut key = Vec::with_capacity(key_bits);
+    walk(root, key_bits, &mut key, &mut entries)?;
+    Ok(entries)
+}
+
+/// Same as [`hashmap_entries`] for `Hashmap n X` stored inline in `slice`, such as the transactions of an `AccountBlock`.
+pub fn hashmap_slice_entries(slice: CellSlice<'_>, key_bits: usize) -> Result<Vec<(Vec<u8>, CellSlice<'_>)>, CellError> {
+    let mut entries = Vec::new();
+    let mut key = Vec::with_capacity(key_bits);
+    walk_slice(slice, key_bits, &mut key, &mut entries)?;
+    Ok(entries)
+}
+
+/// Build `Hashmap n X` from left-aligned `key_bits` bit keys and cells whose data and references are
+/// stored inline as the values. Returns `None` for no entries, i.e. an empty `HashmapE n X`.
+///
+/// When a key repeats, the last of its entries is kept. Fails with `Underflow` if a key is shorter than `key_bits`.
+pub fn build_hashmap(entries: &[(Vec<u8>, ArcCell)], key_bits: usize) -> Result<Option<ArcCell>, CellError> {
+    if entries.iter().any(|(key, _)| key.len() * 8 < key_bits) {
+        return Err(CellError::Underflow);
+    }
+    let mut entries: Vec<(Vec<bool>, &Cell)> = entries
+        .iter()
+        .map(|(key, value)| ((0..key_bits).map(|i| key[i / 8] & (0x80 >> (i % 8)) != 0).collect(), &**value))
+        .collect();
+    entries.reverse();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries.dedup_by(|a, b| a.0 == b.0);
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    build_node(&entries, 0, key_bits).map(Some)
+}
+
+fn build_node(entries: &[(Vec<bool>, &Cell)], pos: usize, remaining: usize) -> Result<ArcCell, CellError> {
+    // keys are sorted, so the prefix common to all of them is the one of the first and the last key
+    let (first, last) = (&entries[0].0, &entries[entries.len() - 1].0);
+    let label_len = (pos..pos + remaining).take_while(|&i| first[i] == last[i]).count();
+    let mut builder = CellBuilder::new();
+    store_label(&mut builder, &first[pos..pos + label_len], remaining)?;
+    if label_len == remaining {
+        builder.store_cell_data(entries[0].1)?;
+    } else {
+        let fork = pos + label_len;
+        let right = entries.partition_point(|(key, _)| !key[fork]);
+        let remaining = remaining - label_len - 1;
+        builder.store_reference(build_node(&entries[..right], fork + 1, remaining)?)?;
+        builder.store_reference(build_node(&entries[right..], fork + 1, remaining)?)?;
+    }
+    builder.build()
+}
+
+/// Store `label` in the shortest of the `HmLabel` forms, see [`load_label`].
+fn store_label(builder: &mut CellBuilder, label: &[bool], max_len: usize) -> Result<(), CellError> {
+    let len_bits = (usize::BITS - max_len.leading_zeros()) as usize;
+    let short = 2 + 2 * label.len();
+    let long = 2 + len_bits + label.len();
+    let same = (label.len() > 1 && label.iter().all(|bit| *bit == label[0])).then_some(3 + len_bits);
+    if same.is_some_and(|same| same < short.min(long)) {
+        builder.store_uint(2, 0b11)?.store_bit(label[0])?.store_uint(len_bits, label.len() as u64)?;
+        return Ok(());
+    }
+    if short <= long {
+        builder.store_bit(false)?;
+        for _ in label {
+            builder.store_bit(true)?;
+        }
+        builder.store_bit(false)?;
+    } else {
+        builder.store_uint(2, 0b10)?.store_uint(len_bits, label.len() as u64)?;
+    }
+    for bit in label {
+        builder.store_bit(*bit)?;
+    }
+    Ok(())
+}
+
+/// Result of [`hashmap_get`].
+#[derive(Debug, Clone)]
+pub enum HashmapEntry<'a> {
+    /// Slice with the value of the key
+    Found(CellSlice<'a>),
+    /// The path to the key diverges from it, so the dictionary provably doesn't contain the key
+    Absent,
+    /// The path to the key leads into a subtree pruned from the merkle proof
+    Pruned,
+}
+
+/// Value of `key`, a left-aligned `key_bits` bit key, in `Hashmap n X` which may be part of a merkle proof.
+pub fn hashmap_get<'a>(root: &'a Cell, key: &[u8], key_bits: usize) -> Result<HashmapEntry<'a>, CellError> {
+    if root.cell_type() == CellType::PrunedBranch {
+        return Ok(HashmapEntry::Pruned);
+    }
+    hashmap_slice_get(root.parser()?, key, key_bits)
+}
+
+/// Same as [`hashmap_get`] for `Hashmap n X` stored inline in `slice`, such as the transactions of an `AccountBlock`.
+pub fn hashmap_slice_get<'a>(mut slice: CellSlice<'a>, key: &[u8], key_bits: usize) -> Result<HashmapEntry<'a>, CellError> {
+    let key_bit = |i: usize| key[i / 8] & (0x80 >> (i % 8)) != 0;
+    let mut pos = 0;
+    loop {
+        let mut label = Vec::new();
+        load_label(&mut slice, key_bits - pos, &mut label)?;
+        if pos + label.len() > key_bits {
+            return Err(CellError::Underflow);
+        }
+        if label.iter().enumerate().any(|(i, bit)| *bit != key_bit(pos + i)) {
+            return Ok(HashmapEntry::Absent);
+        }
+        pos += label.len();
+        if pos == key_bits {
+            return Ok(HashmapEntry::Found(slice));
+        }
+        let left = slice.load_ref()?;
+        let cell = if key_bit(pos) { slice.load_ref()? } else { left };
+        if cell.cell_type() == CellType::PrunedBranch {
+            return Ok(HashmapEntry::Pruned);
+        }
+        slice = cell.parser()?;
+        pos += 1;
+    }
+}
+
+/// Same as [`hashmap_entries`] for `HashmapE n X` stored in `slice`.
+pub fn hashmap_e_entries<'a>(slice: &mut CellSlice<'a>, key_bits: usize) -> Result<Vec<(Vec<u8>, CellSlice<'a>)>, CellError> {
+    match slice.load_maybe_ref()? {
+        Some(root) => hashmap_entries(root, key_bits),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn walk<'a>(cell: &'a Cell, remaining: usize, key: &mut Vec<bool>, entries: &mut Vec<(Vec<u8>, CellSlice<'a>)>) -> Result<(), CellError> {
+    if cell.cell_type() == CellType::PrunedBranch {
+        return Ok(());
+    }
+    walk_slice(cell.parser()?, remaining, key, entries)
+}
+
+fn walk_slice<'a>(mut slice: CellSlice<'a>, remaining: usize, key: &mut Vec<bool>, entries: &mut Vec<(Vec<u8>, CellSlice<'a>)>) -> Result<(), CellError> {
+    let prefix_len = key.len();
+    let label_len = load_label(&mut slice, remaining, key)?;
+    let remaining = remaining.checked_sub(label_len).ok_or(CellError::Underflow)?;
+    if remaining == 0 {
+        entries.push((pack_bits(key), slice));
+    } else {
+        for bit in [false, true] {
+            key.push(bit);
+            walk(slice.load_ref()?, remaining - 1, key, entries)?;
+            key.pop();
+        }
+    }
+    key.truncate(prefix_len);
+    Ok(())
+}
+
+/// ```tlb
+/// hml_short$0 {m:#} {n:#} len:(Unary ~n) {n <= m} s:(n * Bit) = HmLabel ~n m;
+/// hml_long$10 {m:#} n:(#<= m) s:(n * Bit) = HmLabel ~n m;
+/// hml_same$11 {m:#} v:Bit n:(#<= m) = HmLabel ~n m;
+/// ```
+fn load_label(slice: &mut CellSlice, max_len: usize, key: &mut Vec<bool>) -> Result<usize, CellError> {
+    let len_bits = (usize::BITS - max_len.leading_zeros()) as usize;
+    if !slice.load_bit()? {
+        let mut len = 0;
+        while slice.load_bit()? {
+            len += 1;
+        }
+        for _ in 0..len {
+            key.push(slice.load_bit()?);
+        }
+        Ok(len)
+    } else if !slice.load_bit()? {
+        let len = slice.load_uint(len_bits)? as usize;
+        for _ in 0..len {
+            key.push(slice.load_bit()?);
+        }
+        Ok(len)
+    } else {
+        let bit = slice.load_bit()?;
+        let len = slice.load_uint(len_bits)? as usize;
+        key.extend(std::iter::repeat_n(bit, len));
+        Ok(len)
+    }
+}
+
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    let mut data = vec![0u8; bits.len().div_ceil(8)];
+    for (i, bit) in bits.iter().enumerate() {
+        if *bit {
+            data[i / 8] |= 0x80 >> (i % 8);
+        }
+    }
+    data
+}
@@ -0,0 +1,200 @@
+use crate::cell::{ArcCell, Cell, CellBuilder, CellError, CellSlice};
+
+use super::{Coins, MsgAddressInt};
+
+/// Transfer of jettons, sent by the owner to its jetton wallet (TEP-74).
+///
+/// ```tlb
+/// transfer#0f8a7ea5 query_id:uint64 amount:(VarUInteger 16) destination:MsgAddress
+///   response_destination:MsgAddress custom_payload:(Maybe ^Cell)
+///   forward_ton_amount:(VarUInteger 16) forward_payload:(Either Cell ^Cell) = InternalMsgBody;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JettonTransfer {
+    pub query_id: u64,
+    /// Amount in the smallest units of the jetton
+    pub amount: u128,
+    /// Owner of the receiving wallet, not the wallet itself
+    pub destination: MsgAddressInt,
+    /// Address the remaining TON are returned to with `excesses`
+    pub response_destination: Option<MsgAddressInt>,
+    pub custom_payload: Option<ArcCell>,
+    /// TON attached to the `transfer_notification` sent to `destination`, none is sent if zero
+    pub forward_ton_amount: Coins,
+    /// Payload of the `transfer_notification`, e.g. a [`comment`](super::comment)
+    pub forward_payload: Option<ArcCell>,
+}
+
+impl JettonTransfer {
+    pub const OP: u32 = 0x0f8a7ea5;
+
+    /// Transfer of `amount` to the wallet of `destination`, without notification and excesses.
+    pub fn new(amount: u128, destination: MsgAddressInt) -> Self {
+        Self {
+            query_id: 0,
+            amount,
+            destination,
+            response_destination: None,
+            custom_payload: None,
+            forward_ton_amount: Coins::ZERO,
+            forward_payload: None,
+        }
+    }
+
+    pub fn with_query_id(mut self, query_id: u64) -> Self {
+        self.query_id = query_id;
+        self
+    }
+
+    pub fn with_response_destination(mut self, response_destination: MsgAddressInt) -> Self {
+        self.response_destination = Some(response_destination);
+        self
+    }
+
+    /// Notify `destination` with `payload`, attaching `ton_amount` to the notification.
+    pub fn with_forward_payload(mut self, ton_amount: Coins, payload: ArcCell) -> Self {
+        self.forward_ton_amount = ton_amount;
+        self.forward_payload = Some(payload);
+        self
+    }
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let query_id = load_op(slice, Self::OP)?;
+        let amount = slice.load_coins()?;
+        let destination = MsgAddressInt::load(slice)?;
+        let response_destination = MsgAddressInt::load_maybe(slice)?;
+        let custom_payload = slice.load_maybe_ref()?.cloned();
+        let forward_ton_amount = Coins::load(slice)?;
+        let forward_payload = load_payload(slice)?;
+        let forward_payload = (forward_payload.bit_len() > 0 || !forward_payload.references().is_empty()).then_some(forward_payload);
+        Ok(Self { query_id, amount, destination, response_destination, custom_payload, forward_ton_amount, forward_payload })
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        builder.store_u32(Self::OP)?.store_u64(self.query_id)?.store_coins(self.amount)?;
+        self.destination.store(builder)?;
+        MsgAddressInt::store_maybe(&self.response_destination, builder)?;
+        builder.store_maybe_reference(self.custom_payload.clone())?;
+        self.forward_ton_amount.store(builder)?;
+        match &self.forward_payload {
+            Some(payload) => builder.store_either(payload.clone(), 0)?,
+            None => builder.store_bit(false)?,
+        };
+        Ok(())
+    }
+
+    /// Message body, to be sent to the jetton wallet of the owner with enough TON for the fees.
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        let mut builder = CellBuilder::new();
+        self.store(&mut builder)?;
+        builder.build()
+    }
+}
+
+/// Notification sent by a jetton wallet to its owner on an incoming transfer with a `forward_ton_amount`.
+///
+/// ```tlb
+/// transfer_notification#7362d09c query_id:uint64 amount:(VarUInteger 16) sender:MsgAddress
+///   forward_payload:(Either Cell ^Cell) = InternalMsgBody;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JettonTransferNotification {
+    pub query_id: u64,
+    pub amount: u128,
+    /// Owner of the sending wallet
+    pub sender: MsgAddressInt,
+    /// `forward_payload` of the transfer, an empty cell if it had none
+    pub forward_payload: ArcCell,
+}
+
+impl JettonTransferNotification {
+    pub const OP: u32 = 0x7362d09c;
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let query_id = load_op(slice, Self::OP)?;
+        let amount = slice.load_coins()?;
+        let sender = MsgAddressInt::load(slice)?;
+        let forward_payload = load_payload(slice)?;
+        Ok(Self { query_id, amount, sender, forward_payload })
+    }
+
+    /// Parse the body of an inbound message, e.g. of a transaction of the jetton owner.
+    pub fn from_cell(cell: &Cell) -> Result<Self, CellError> {
+        Self::load(&mut cell.parser()?)
+    }
+}
+
+/// Burn of jettons, sent by the owner to its jetton wallet.
+///
+/// ```tlb
+/// burn#595f07bc query_id:uint64 amount:(VarUInteger 16)
+///   response_destination:MsgAddress custom_payload:(Maybe ^Cell) = InternalMsgBody;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JettonBurn {
+    pub query_id: u64,
+    pub amount: u128,
+    pub response_destination: Option<MsgAddressInt>,
+    pub custom_payload: Option<ArcCell>,
+}
+
+impl JettonBurn {
+    pub const OP: u32 = 0x595f07bc;
+
+    pub fn new(amount: u128) -> Self {
+        Self { query_id: 0, amount, response_destination: None, custom_payload: None }
+    }
+
+    pub fn with_query_id(mut self, query_id: u64) -> Self {
+        self.query_id = query_id;
+        self
+    }
+
+    pub fn with_response_destination(mut self, response_destination: MsgAddressInt) -> Self {
+        self.response_destination = Some(response_destination);
+        self
+    }
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let query_id = load_op(slice, Self::OP)?;
+        let amount = slice.load_coins()?;
+        let response_destination = MsgAddressInt::load_maybe(slice)?;
+        let custom_payload = slice.load_maybe_ref()?.cloned();
+        Ok(Self { query_id, amount, response_destination, custom_payload })
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        builder.store_u32(Self::OP)?.store_u64(self.query_id)?.store_coins(self.amount)?;
+        MsgAddressInt::store_maybe(&self.response_destination, builder)?;
+        builder.store_maybe_reference(self.custom_payload.clone())?;
+        Ok(())
+    }
+
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        let mut builder = CellBuilder::new();
+        self.store(&mut builder)?;
+        builder.build()
+    }
+}
+
+/// Check the `op` of a message body and load its `query_id`.
+pub(crate) fn load_op(slice: &mut CellSlice, op: u32) -> Result<u64, CellError> {
+    let tag = slice.load_u32()?;
+    if tag != op {
+        return Err(CellError::UnexpectedTag(tag as u64));
+    }
+    slice.load_u64()
+}
+
+/// `Either Cell ^Cell`, with the inline variant copied into its own cell.
+pub(crate) fn load_payload(slice: &mut CellSlice) -> Result<ArcCell, CellError> {
+    if slice.load_bit()? {
+        return Ok(slice.load_ref()?.clone());
+    }
+    let payload = CellBuilder::new().store_slice(slice)?.build()?;
+    slice.skip_bits(slice.remaining_bits())?;
+    while slice.remaining_refs() > 0 {
+        slice.load_ref()?;
+    }
+    Ok(payload)
+}
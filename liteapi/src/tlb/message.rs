@@ -0,0 +1,464 @@
+use crate::cell::{ArcCell, Cell, CellBuilder, CellError, CellSlice};
+
+use super::{Coins, CurrencyCollection, MsgAddress, MsgAddressInt};
+
+/// ```tlb
+/// addr_none$00 = MsgAddressExt;
+/// addr_extern$01 len:(## 9) external_address:(bits len) = MsgAddressExt;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MsgAddressExt {
+    #[default]
+    None,
+    Extern { len: u16, address: Vec<u8> },
+}
+
+impl MsgAddressExt {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_uint(2)?;
+        match tag {
+            0b00 => Ok(Self::None),
+            0b01 => {
+                let len = slice.load_uint(9)? as u16;
+                let address = slice.load_bits(len as usize)?;
+                Ok(Self::Extern { len, address })
+            }
+            _ => Err(CellError::UnexpectedTag(tag)),
+        }
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        match self {
+            Self::None => {
+                builder.store_uint(2, 0b00)?;
+            }
+            Self::Extern { len, address } => {
+                builder.store_uint(2, 0b01)?.store_uint(9, *len as u64)?;
+                builder.store_bits(address, *len as usize)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ```tlb
+/// _ fixed_prefix_length:(Maybe (## 5)) special:(Maybe TickTock)
+///   code:(Maybe ^Cell) data:(Maybe ^Cell) library:(Maybe ^Cell) = StateInit;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StateInit {
+    pub fixed_prefix_length: Option<u8>,
+    /// `tick_tock$_ tick:Bool tock:Bool = TickTock;`
+    pub special: Option<(bool, bool)>,
+    pub code: Option<ArcCell>,
+    pub data: Option<ArcCell>,
+    pub library: Option<ArcCell>,
+}
+
+impl StateInit {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let fixed_prefix_length = if slice.load_bit()? { Some(slice.load_uint(5)? as u8) } else { None };
+        let special = if slice.load_bit()? { Some((slice.load_bit()?, slice.load_bit()?)) } else { None };
+        let code = slice.load_maybe_ref()?.cloned();
+        let data = slice.load_maybe_ref()?.cloned();
+        let library = slice.load_maybe_ref()?.cloned();
+        Ok(Self { fixed_prefix_length, special, code, data, library })
+    }
+
+    /// StateInit with just `code` and `data`, as used by most contracts.
+    pub fn new(code: ArcCell, data: ArcCell) -> Self {
+        Self { code: Some(code), data: Some(data), ..Default::default() }
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        match self.fixed_prefix_length {
+            Some(length) => builder.store_bit(true)?.store_uint(5, length as u64)?,
+            None => builder.store_bit(false)?,
+        };
+        match self.special {
+            Some((tick, tock)) => builder.store_bit(true)?.store_bit(tick)?.store_bit(tock)?,
+            None => builder.store_bit(false)?,
+        };
+        builder.store_maybe_reference(self.code.clone())?;
+        builder.store_maybe_reference(self.data.clone())?;
+        builder.store_maybe_reference(self.library.clone())?;
+        Ok(())
+    }
+
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        let mut builder = CellBuilder::new();
+        self.store(&mut builder)?;
+        builder.build()
+    }
+
+    /// Address of the contract deployed with this StateInit.
+    pub fn address(&self, workchain: i8) -> Result<MsgAddressInt, CellError> {
+        Ok(MsgAddressInt::std(workchain, self.to_cell()?.repr_hash()))
+    }
+}
+
+/// `ext_in_msg_info$10 src:MsgAddressExt dest:MsgAddressInt import_fee:Grams = CommonMsgInfo;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtInMsgInfo {
+    pub src: MsgAddressExt,
+    pub dest: MsgAddressInt,
+    pub import_fee: Coins,
+}
+
+impl ExtInMsgInfo {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_uint(2)?;
+        if tag != 0b10 {
+            return Err(CellError::UnexpectedTag(tag));
+        }
+        let src = MsgAddressExt::load(slice)?;
+        let dest = MsgAddressInt::load(slice)?;
+        let import_fee = Coins::load(slice)?;
+        Ok(Self { src, dest, import_fee })
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        builder.store_uint(2, 0b10)?;
+        self.src.store(builder)?;
+        self.dest.store(builder)?;
+        self.import_fee.store(builder)?;
+        Ok(())
+    }
+}
+
+/// ```tlb
+/// int_msg_info$0 ihr_disabled:Bool bounce:Bool bounced:Bool src:MsgAddressInt dest:MsgAddressInt
+///   value:CurrencyCollection ihr_fee:Grams fwd_fee:Grams created_lt:uint64 created_at:uint32 = CommonMsgInfo;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntMsgInfo {
+    pub ihr_disabled: bool,
+    pub bounce: bool,
+    pub bounced: bool,
+    pub src: MsgAddressInt,
+    pub dest: MsgAddressInt,
+    pub value: CurrencyCollection,
+    pub ihr_fee: Coins,
+    pub fwd_fee: Coins,
+    pub created_lt: u64,
+    pub created_at: u32,
+}
+
+impl IntMsgInfo {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        if slice.load_bit()? {
+            return Err(CellError::UnexpectedTag(1));
+        }
+        Ok(Self {
+            ihr_disabled: slice.load_bit()?,
+            bounce: slice.load_bit()?,
+            bounced: slice.load_bit()?,
+            src: MsgAddressInt::load(slice)?,
+            dest: MsgAddressInt::load(slice)?,
+            value: CurrencyCollection::load(slice)?,
+            ihr_fee: Coins::load(slice)?,
+            fwd_fee: Coins::load(slice)?,
+            created_lt: slice.load_u64()?,
+            created_at: slice.load_u32()?,
+        })
+    }
+}
+
+/// `ext_out_msg_info$11 src:MsgAddressInt dest:MsgAddressExt created_lt:uint64 created_at:uint32 = CommonMsgInfo;`
+///
+/// Outbound external messages aren't delivered anywhere, contracts send them as logs and events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtOutMsgInfo {
+    pub src: MsgAddressInt,
+    pub dest: MsgAddressExt,
+    pub created_lt: u64,
+    pub created_at: u32,
+}
+
+impl ExtOutMsgInfo {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_uint(2)?;
+        if tag != 0b11 {
+            return Err(CellError::UnexpectedTag(tag));
+        }
+        Ok(Self {
+            src: MsgAddressInt::load(slice)?,
+            dest: MsgAddressExt::load(slice)?,
+            created_lt: slice.load_u64()?,
+            created_at: slice.load_u32()?,
+        })
+    }
+}
+
+/// Header of a message, telling internal, inbound external and outbound external messages apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommonMsgInfo {
+    Int(IntMsgInfo),
+    ExtIn(ExtInMsgInfo),
+    ExtOut(ExtOutMsgInfo),
+}
+
+impl CommonMsgInfo {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let mut tag = slice.clone();
+        match tag.load_uint(2)? {
+            0b10 => ExtInMsgInfo::load(slice).map(Self::ExtIn),
+            0b11 => ExtOutMsgInfo::load(slice).map(Self::ExtOut),
+            _ => IntMsgInfo::load(slice).map(Self::Int),
+        }
+    }
+}
+
+/// Any message, e.g. the inbound or an outbound message of a [`Transaction`](super::Transaction), with `init`
+/// and `body` moved out of the root cell.
+///
+/// The accessors cover the fields of all kinds of messages, with zero values and `false` flags where a kind
+/// doesn't have them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub hash: [u8; 32],
+    pub info: CommonMsgInfo,
+    pub init: Option<StateInit>,
+    pub body: ArcCell,
+}
+
+impl Message {
+    /// `message$_ {X:Type} info:CommonMsgInfo init:(Maybe (Either StateInit ^StateInit)) body:(Either X ^X) = Message X;`
+    pub fn load(cell: &Cell) -> Result<Self, CellError> {
+        let mut slice = cell.parser()?;
+        let info = CommonMsgInfo::load(&mut slice)?;
+        let (init, body) = load_init_and_body(&mut slice)?;
+        Ok(Self { hash: cell.repr_hash(), info, init, body })
+    }
+
+    pub fn is_internal(&self) -> bool {
+        matches!(self.info, CommonMsgInfo::Int(_))
+    }
+
+    pub fn src(&self) -> MsgAddress {
+        match &self.info {
+            CommonMsgInfo::Int(info) => info.src.clone().into(),
+            CommonMsgInfo::ExtIn(info) => info.src.clone().into(),
+            CommonMsgInfo::ExtOut(info) => info.src.clone().into(),
+        }
+    }
+
+    pub fn dest(&self) -> MsgAddress {
+        match &self.info {
+            CommonMsgInfo::Int(info) => info.dest.clone().into(),
+            CommonMsgInfo::ExtIn(info) => info.dest.clone().into(),
+            CommonMsgInfo::ExtOut(info) => info.dest.clone().into(),
+        }
+    }
+
+    /// TON carried by an internal message, without extra currencies.
+    pub fn value(&self) -> Coins {
+        match &self.info {
+            CommonMsgInfo::Int(info) => info.value.grams,
+            _ => Coins::ZERO,
+        }
+    }
+
+    pub fn ihr_fee(&self) -> Coins {
+        match &self.info {
+            CommonMsgInfo::Int(info) => info.ihr_fee,
+            _ => Coins::ZERO,
+        }
+    }
+
+    /// Forwarding fee of an internal message, or the import fee of an inbound external one.
+    pub fn fwd_fee(&self) -> Coins {
+        match &self.info {
+            CommonMsgInfo::Int(info) => info.fwd_fee,
+            CommonMsgInfo::ExtIn(info) => info.import_fee,
+            CommonMsgInfo::ExtOut(_) => Coins::ZERO,
+        }
+    }
+
+    pub fn bounce(&self) -> bool {
+        matches!(&self.info, CommonMsgInfo::Int(info) if info.bounce)
+    }
+
+    pub fn bounced(&self) -> bool {
+        matches!(&self.info, CommonMsgInfo::Int(info) if info.bounced)
+    }
+
+    /// Logical time of the creation of a message, `None` for inbound external messages.
+    pub fn created_lt(&self) -> Option<u64> {
+        match &self.info {
+            CommonMsgInfo::Int(info) => Some(info.created_lt),
+            CommonMsgInfo::ExtIn(_) => None,
+            CommonMsgInfo::ExtOut(info) => Some(info.created_lt),
+        }
+    }
+}
+
+/// `init:(Maybe (Either StateInit ^StateInit)) body:(Either X ^X)` of a message, an inline body is copied to
+/// its own cell.
+fn load_init_and_body(slice: &mut CellSlice) -> Result<(Option<StateInit>, ArcCell), CellError> {
+    let init = if slice.load_bit()? {
+        if slice.load_bit()? {
+            Some(StateInit::load(&mut slice.load_ref()?.parser()?)?)
+        } else {
+            Some(StateInit::load(slice)?)
+        }
+    } else {
+        None
+    };
+    let body = if slice.load_bit()? {
+        slice.load_ref()?.clone()
+    } else {
+        CellBuilder::new().store_slice(slice)?.build()?
+    };
+    Ok((init, body))
+}
+
+/// Inbound external message split into its parts, with `init` and `body` moved out of the root cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalMessage {
+    pub info: ExtInMsgInfo,
+    pub init: Option<StateInit>,
+    pub body: ArcCell,
+}
+
+impl ExternalMessage {
+    /// Message to `dest` carrying `body`, attach a StateInit with [`ExternalMessage::with_state_init`] to deploy.
+    pub fn new(dest: MsgAddressInt, body: ArcCell) -> Self {
+        Self {
+            info: ExtInMsgInfo { src: MsgAddressExt::None, dest, import_fee: Coins::ZERO },
+            init: None,
+            body,
+        }
+    }
+
+    pub fn with_state_init(mut self, init: StateInit) -> Self {
+        self.init = Some(init);
+        self
+    }
+
+    /// Build the message cell, `init` and `body` are stored inline when they fit and by reference otherwise.
+    pub fn build(&self) -> Result<ArcCell, CellError> {
+        let mut builder = CellBuilder::new();
+        self.info.store(&mut builder)?;
+        match &self.init {
+            Some(init) => {
+                builder.store_bit(true)?.store_either(init.to_cell()?, 1)?;
+            }
+            None => {
+                builder.store_bit(false)?;
+            }
+        }
+        builder.store_either(self.body.clone(), 0)?;
+        builder.build()
+    }
+
+    /// Serialized message, ready for [`LiteClient::send_message`](crate::client::LiteClient::send_message).
+    pub fn to_boc(&self) -> Result<Vec<u8>, CellError> {
+        Ok(self.build()?.to_boc())
+    }
+
+    /// `message$_ {X:Type} info:CommonMsgInfo init:(Maybe (Either StateInit ^StateInit)) body:(Either X ^X) = Message X;`
+    pub fn load(cell: &Cell) -> Result<Self, CellError> {
+        let mut slice = cell.parser()?;
+        let info = ExtInMsgInfo::load(&mut slice)?;
+        let (init, body) = load_init_and_body(&mut slice)?;
+        Ok(Self { info, init, body })
+    }
+
+    /// Message with `src`, `import_fee` and `init` cleared and the body stored by reference, as defined in TEP-467.
+    pub fn normalized(&self) -> Result<ArcCell, CellError> {
+        let info = ExtInMsgInfo { src: MsgAddressExt::None, dest: self.info.dest.clone(), import_fee: Coins::ZERO };
+        let mut builder = CellBuilder::new();
+        info.store(&mut builder)?;
+        builder.store_bit(false)?.store_bit(true)?.store_reference(self.body.clone())?;
+        builder.build()
+    }
+
+    /// Hash of the normalized message, which indexers use to look up the resulting transaction.
+    pub fn normalized_hash(&self) -> Result<[u8; 32], CellError> {
+        Ok(self.normalized()?.repr_hash())
+    }
+}
+
+/// Internal message to be sent by a contract, e.g. by a wallet on behalf of its owner.
+///
+/// Built as a `MessageRelaxed` with `src` set to `addr_none` and zero fees and times, which the sending contract
+/// fills in:
+///
+/// ```tlb
+/// int_msg_info$0 ihr_disabled:Bool bounce:Bool bounced:Bool src:MsgAddress dest:MsgAddressInt
+///   value:CurrencyCollection ihr_fee:Grams fwd_fee:Grams created_lt:uint64 created_at:uint32 = CommonMsgInfoRelaxed;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternalMessage {
+    pub dest: MsgAddressInt,
+    pub value: Coins,
+    /// Return the value if the destination fails to process the message, `true` by default
+    pub bounce: bool,
+    pub init: Option<StateInit>,
+    pub body: ArcCell,
+}
+
+impl InternalMessage {
+    pub fn new(dest: MsgAddressInt, value: Coins, body: ArcCell) -> Self {
+        Self { dest, value, bounce: true, init: None, body }
+    }
+
+    pub fn with_bounce(mut self, bounce: bool) -> Self {
+        self.bounce = bounce;
+        self
+    }
+
+    pub fn with_state_init(mut self, init: StateInit) -> Self {
+        self.init = Some(init);
+        self
+    }
+
+    /// Build the message cell, `init` and `body` are stored inline when they fit and by reference otherwise.
+    pub fn build(&self) -> Result<ArcCell, CellError> {
+        let mut builder = CellBuilder::new();
+        // int_msg_info$0 ihr_disabled bounce bounced, then src as addr_none$00
+        builder.store_bit(false)?.store_bit(true)?.store_bit(self.bounce)?.store_bit(false)?.store_uint(2, 0b00)?;
+        self.dest.store(&mut builder)?;
+        self.value.store(&mut builder)?;
+        // no extra currencies, ihr_fee, fwd_fee, created_lt and created_at
+        builder.store_bit(false)?.store_uint(4, 0)?.store_uint(4, 0)?.store_u64(0)?.store_u32(0)?;
+        match &self.init {
+            Some(init) => {
+                builder.store_bit(true)?.store_either(init.to_cell()?, 1)?;
+            }
+            None => {
+                builder.store_bit(false)?;
+            }
+        }
+        builder.store_either(self.body.clone(), 0)?;
+        builder.build()
+    }
+}
+
+/// Body of a transfer with a text comment, `text_comment#00000000 text:Text`, with the text split into a
+/// chain of cells when it doesn't fit into one, see [`Comment`](super::Comment).
+pub fn comment(text: &str) -> Result<ArcCell, CellError> {
+    store_snake(super::Comment::TEXT_OP, text.as_bytes())
+}
+
+/// `op` followed by `data`, split into a chain of cells when it doesn't fit into one.
+pub(super) fn store_snake(op: u32, data: &[u8]) -> Result<ArcCell, CellError> {
+    // the op takes 4 bytes of the first cell, which holds 127 bytes
+    let (head, rest) = data.split_at(data.len().min(123));
+    let mut tail = None;
+    for chunk in rest.chunks(127).rev() {
+        let mut builder = CellBuilder::new();
+        builder.store_bits(chunk, chunk.len() * 8)?;
+        if let Some(cell) = tail {
+            builder.store_reference(cell)?;
+        }
+        tail = Some(builder.build()?);
+    }
+    let mut builder = CellBuilder::new();
+    builder.store_u32(op)?.store_bits(head, head.len() * 8)?;
+    if let Some(cell) = tail {
+        builder.store_reference(cell)?;
+    }
+    builder.build()
+}
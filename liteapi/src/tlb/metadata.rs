@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+
+use sha2::{Digest, Sha256};
+
+use crate::cell::{Cell, CellError, CellSlice};
+
+use super::hashmap_e_entries;
+
+/// Token metadata (TEP-64) of a jetton or an NFT, e.g. the `jetton_content` returned by `get_jetton_data`.
+///
+/// ```tlb
+/// onchain#00 data:(HashmapE 256 ^ContentData) = FullContent;
+/// offchain#01 uri:Text = FullContent;
+/// snake#00 data:(SnakeData ~n) = ContentData;
+/// chunks#01 data:ChunkedData = ContentData;
+/// chunked_data#_ data:(HashmapE 32 ^(SnakeData ~0)) = ChunkedData;
+/// ```
+///
+/// Off-chain content only has `uri`. On-chain content may have it as well ("semi-chain" layout), in which
+/// case the JSON it points to holds the attributes missing on-chain, see `TokenMetadata::merge_json` (`json` feature).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TokenMetadata {
+    /// Off-chain JSON with the metadata
+    pub uri: Option<String>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Link to the image
+    pub image: Option<String>,
+    /// Image itself, only stored on-chain
+    pub image_data: Option<Vec<u8>>,
+    pub symbol: Option<String>,
+    /// Decimals of jetton amounts, see [`TokenMetadata::decimals`]
+    pub decimals: Option<u8>,
+    pub amount_style: Option<String>,
+    pub render_type: Option<String>,
+    /// Other on-chain attributes, keyed by the SHA-256 hash of their names
+    pub other: BTreeMap<[u8; 32], Vec<u8>>,
+}
+
+impl TokenMetadata {
+    /// Decimals of jetton amounts, 9 if they aren't specified.
+    pub fn decimals(&self) -> u8 {
+        self.decimals.unwrap_or(9)
+    }
+
+    /// Key of the attribute `name` in the on-chain dictionary.
+    pub fn attribute_key(name: &str) -> [u8; 32] {
+        Sha256::digest(name).into()
+    }
+
+    /// Parse a `FullContent` cell. Attributes which aren't valid UTF-8 are decoded lossily.
+    pub fn load(cell: &Cell) -> Result<Self, CellError> {
+        let mut slice = cell.parser()?;
+        let tag = slice.load_u8()?;
+        match tag {
+            0x00 => {
+                let mut metadata = Self::default();
+                for (key, mut value) in hashmap_e_entries(&mut slice, 256)? {
+                    let data = load_content_data(value.load_ref()?)?;
+                    metadata.set(key.try_into().map_err(|_| CellError::Underflow)?, data);
+                }
+                Ok(metadata)
+            }
+            0x01 => Ok(Self { uri: Some(text(load_snake(&mut slice)?)), ..Self::default() }),
+            _ => Err(CellError::UnexpectedTag(tag as u64)),
+        }
+    }
+
+    fn set(&mut self, key: [u8; 32], data: Vec<u8>) {
+        let name = ["image_data", "decimals"].into_iter().chain(STRING_ATTRIBUTES).find(|name| Self::attribute_key(name) == key);
+        match name {
+            Some("image_data") => self.image_data = Some(data),
+            Some("decimals") => match text(data.clone()).trim().parse() {
+                Ok(decimals) => self.decimals = Some(decimals),
+                Err(_) => {
+                    self.other.insert(key, data);
+                }
+            },
+            Some(name) => *self.string_attribute(name) = Some(text(data)),
+            None => {
+                self.other.insert(key, data);
+            }
+        }
+    }
+
+    fn string_attribute(&mut self, name: &str) -> &mut Option<String> {
+        match name {
+            "uri" => &mut self.uri,
+            "name" => &mut self.name,
+            "description" => &mut self.description,
+            "image" => &mut self.image,
+            "symbol" => &mut self.symbol,
+            "amount_style" => &mut self.amount_style,
+            "render_type" => &mut self.render_type,
+            _ => unreachable!("not a string attribute"),
+        }
+    }
+}
+
+const STRING_ATTRIBUTES: [&str; 7] = ["uri", "name", "description", "image", "symbol", "amount_style", "render_type"];
+
+/// Fetching of off-chain metadata, enabled by the `json` feature.
+#[cfg(feature = "json")]
+mod off_chain {
+    use futures::future::BoxFuture;
+
+    use crate::types::LiteError;
+
+    use super::{TokenMetadata, STRING_ATTRIBUTES};
+
+    /// Client fetching the off-chain JSON of [`TokenMetadata::uri`], e.g. over HTTP or IPFS.
+    ///
+    /// The crate doesn't bundle one, errors of the client can be returned as [`LiteError::UnknownError`].
+    pub trait ContentFetcher {
+        fn fetch<'a>(&'a self, uri: &'a str) -> BoxFuture<'a, Result<Vec<u8>, LiteError>>;
+    }
+
+    impl TokenMetadata {
+        /// Fill the attributes missing on-chain from the off-chain JSON, on-chain values take precedence.
+        ///
+        /// Unknown attributes and `image_data` of the JSON are ignored.
+        pub fn merge_json(&mut self, json: &[u8]) -> Result<(), serde_json::Error> {
+            let json: serde_json::Value = serde_json::from_slice(json)?;
+            for name in STRING_ATTRIBUTES.into_iter().filter(|name| *name != "uri") {
+                let field = self.string_attribute(name);
+                if field.is_none() {
+                    *field = json.get(name).and_then(|value| value.as_str()).map(String::from);
+                }
+            }
+            if self.decimals.is_none() {
+                // written either as a string, as TEP-64 requires, or as a number
+                self.decimals = match json.get("decimals") {
+                    Some(serde_json::Value::String(decimals)) => decimals.trim().parse().ok(),
+                    Some(decimals) => decimals.as_u64().and_then(|decimals| decimals.try_into().ok()),
+                    None => None,
+                };
+            }
+            Ok(())
+        }
+
+        /// Fetch the off-chain JSON with `fetcher` if there is one and merge it, see [`TokenMetadata::merge_json`].
+        pub async fn resolve<F: ContentFetcher>(mut self, fetcher: &F) -> Result<Self, LiteError> {
+            if let Some(uri) = self.uri.clone() {
+                let json = fetcher.fetch(&uri).await?;
+                self.merge_json(&json).map_err(|e| LiteError::UnknownError(Box::new(e)))?;
+            }
+            Ok(self)
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+pub use off_chain::ContentFetcher;
+
+fn load_content_data(cell: &Cell) -> Result<Vec<u8>, CellError> {
+    let mut slice = cell.parser()?;
+    let tag = slice.load_u8()?;
+    match tag {
+        0x00 => load_snake(&mut slice),
+        0x01 => {
+            let mut data = Vec::new();
+            // entries are ordered by key, i.e. by chunk index
+            for (_, mut chunk) in hashmap_e_entries(&mut slice, 32)? {
+                data.extend(load_snake(&mut chunk.load_ref()?.parser()?)?);
+            }
+            Ok(data)
+        }
+        _ => Err(CellError::UnexpectedTag(tag as u64)),
+    }
+}
+
+/// Bytes of `SnakeData`, stored in the rest of the slice and the chain of first references, which are loaded.
+pub(super) fn load_snake<'a>(slice: &mut CellSlice<'a>) -> Result<Vec<u8>, CellError> {
+    let mut data = slice.load_bits(slice.remaining_bits() / 8 * 8)?;
+    let mut next = if slice.remaining_refs() > 0 { Some(slice.load_ref()?) } else { None };
+    while let Some(cell) = next {
+        let mut chunk = cell.parser()?;
+        data.extend(chunk.load_bits(chunk.remaining_bits() / 8 * 8)?);
+        next = if chunk.remaining_refs() > 0 { Some(chunk.load_ref()?) } else { None };
+    }
+    Ok(data)
+}
+
+pub(super) fn text(data: Vec<u8>) -> String {
+    String::from_utf8(data).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
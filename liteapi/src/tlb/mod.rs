@@ -0,0 +1,44 @@
+//! Decoding and encoding of the few TL-B structures used by the client itself.
+
+mod account;
+mod address;
+mod block;
+mod coins;
+mod comment;
+mod config;
+mod exit_code;
+mod fees;
+mod hashmap;
+mod jetton;
+mod message;
+mod metadata;
+mod nft;
+mod phases;
+mod shard;
+mod stack;
+mod stats;
+mod transaction;
+mod validator;
+
+pub use account::*;
+pub use address::*;
+pub use block::*;
+pub use coins::*;
+pub use comment::*;
+pub use config::*;
+pub use exit_code::*;
+pub use fees::*;
+pub use hashmap::*;
+pub use jetton::*;
+pub use message::*;
+pub use metadata::*;
+pub use nft::*;
+pub use phases::*;
+pub use shard::*;
+pub use stack::*;
+pub use stats::*;
+pub use transaction::*;
+pub use validator::*;
+
+#[cfg(test)]
+mod tests;
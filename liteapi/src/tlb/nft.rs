@@ -0,0 +1,169 @@
+use crate::cell::{ArcCell, Cell, CellBuilder, CellError, CellSlice};
+
+use super::{jetton::{load_op, load_payload}, Coins, MsgAddressInt};
+
+/// Transfer of an NFT item to a new owner, sent by the owner to the item (TEP-62).
+///
+/// ```tlb
+/// transfer#5fcc3d14 query_id:uint64 new_owner:MsgAddress response_destination:MsgAddress
+///   custom_payload:(Maybe ^Cell) forward_amount:(VarUInteger 16) forward_payload:(Either Cell ^Cell) = InternalMsgBody;
+/// ```
+///
+/// Putting an item on sale is a transfer to the sale contract deployed by the marketplace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NftTransfer {
+    pub query_id: u64,
+    pub new_owner: MsgAddressInt,
+    /// Address the remaining TON are returned to with `excesses`
+    pub response_destination: Option<MsgAddressInt>,
+    pub custom_payload: Option<ArcCell>,
+    /// TON attached to the `ownership_assigned` sent to `new_owner`, none is sent if zero
+    pub forward_amount: Coins,
+    /// Payload of the `ownership_assigned`, e.g. a [`comment`](super::comment)
+    pub forward_payload: Option<ArcCell>,
+}
+
+impl NftTransfer {
+    pub const OP: u32 = 0x5fcc3d14;
+
+    /// Transfer to `new_owner`, without notification and excesses.
+    pub fn new(new_owner: MsgAddressInt) -> Self {
+        Self {
+            query_id: 0,
+            new_owner,
+            response_destination: None,
+            custom_payload: None,
+            forward_amount: Coins::ZERO,
+            forward_payload: None,
+        }
+    }
+
+    pub fn with_query_id(mut self, query_id: u64) -> Self {
+        self.query_id = query_id;
+        self
+    }
+
+    pub fn with_response_destination(mut self, response_destination: MsgAddressInt) -> Self {
+        self.response_destination = Some(response_destination);
+        self
+    }
+
+    /// Notify `new_owner` with `payload`, attaching `amount` to the notification.
+    pub fn with_forward_payload(mut self, amount: Coins, payload: ArcCell) -> Self {
+        self.forward_amount = amount;
+        self.forward_payload = Some(payload);
+        self
+    }
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let query_id = load_op(slice, Self::OP)?;
+        let new_owner = MsgAddressInt::load(slice)?;
+        let response_destination = MsgAddressInt::load_maybe(slice)?;
+        let custom_payload = slice.load_maybe_ref()?.cloned();
+        let forward_amount = Coins::load(slice)?;
+        let forward_payload = load_payload(slice)?;
+        let forward_payload = (forward_payload.bit_len() > 0 || !forward_payload.references().is_empty()).then_some(forward_payload);
+        Ok(Self { query_id, new_owner, response_destination, custom_payload, forward_amount, forward_payload })
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        builder.store_u32(Self::OP)?.store_u64(self.query_id)?;
+        self.new_owner.store(builder)?;
+        MsgAddressInt::store_maybe(&self.response_destination, builder)?;
+        builder.store_maybe_reference(self.custom_payload.clone())?;
+        self.forward_amount.store(builder)?;
+        match &self.forward_payload {
+            Some(payload) => builder.store_either(payload.clone(), 0)?,
+            None => builder.store_bit(false)?,
+        };
+        Ok(())
+    }
+
+    /// Message body, to be sent to the NFT item with enough TON for the fees and `forward_amount`.
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        let mut builder = CellBuilder::new();
+        self.store(&mut builder)?;
+        builder.build()
+    }
+}
+
+/// Notification sent by an NFT item to its new owner on a transfer with a `forward_amount`.
+///
+/// ```tlb
+/// ownership_assigned#05138d91 query_id:uint64 prev_owner:MsgAddress forward_payload:(Either Cell ^Cell) = InternalMsgBody;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NftOwnershipAssigned {
+    pub query_id: u64,
+    pub prev_owner: Option<MsgAddressInt>,
+    /// `forward_payload` of the transfer, an empty cell if it had none
+    pub forward_payload: ArcCell,
+}
+
+impl NftOwnershipAssigned {
+    pub const OP: u32 = 0x05138d91;
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let query_id = load_op(slice, Self::OP)?;
+        let prev_owner = MsgAddressInt::load_maybe(slice)?;
+        let forward_payload = load_payload(slice)?;
+        Ok(Self { query_id, prev_owner, forward_payload })
+    }
+
+    pub fn from_cell(cell: &Cell) -> Result<Self, CellError> {
+        Self::load(&mut cell.parser()?)
+    }
+}
+
+/// Messages accepted by the fixed price sale contracts of Getgems, the most common NFT marketplace contracts.
+///
+/// ```tlb
+/// accept_coins#00000001 query_id:uint64 = InternalMsgBody;
+/// buy#00000002 query_id:uint64 = InternalMsgBody;
+/// cancel#00000003 query_id:uint64 = InternalMsgBody;
+/// ```
+///
+/// A sale becomes active once it receives the `ownership_assigned` of the item, see [`NftOwnershipAssigned`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NftSaleMessage {
+    AcceptCoins { query_id: u64 },
+    /// Buy the item, sent by the buyer with the full price and fees attached
+    Buy { query_id: u64 },
+    /// Cancel the sale and return the item, sent by the seller
+    Cancel { query_id: u64 },
+}
+
+impl NftSaleMessage {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let op = slice.load_u32()?;
+        let query_id = slice.load_u64()?;
+        match op {
+            1 => Ok(Self::AcceptCoins { query_id }),
+            2 => Ok(Self::Buy { query_id }),
+            3 => Ok(Self::Cancel { query_id }),
+            _ => Err(CellError::UnexpectedTag(op as u64)),
+        }
+    }
+
+    pub fn from_cell(cell: &Cell) -> Result<Self, CellError> {
+        Self::load(&mut cell.parser()?)
+    }
+
+    pub fn op(&self) -> u32 {
+        match self {
+            Self::AcceptCoins { .. } => 1,
+            Self::Buy { .. } => 2,
+            Self::Cancel { .. } => 3,
+        }
+    }
+
+    pub fn query_id(&self) -> u64 {
+        match self {
+            Self::AcceptCoins { query_id } | Self::Buy { query_id } | Self::Cancel { query_id } => *query_id,
+        }
+    }
+
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        CellBuilder::new().store_u32(self.op())?.store_u64(self.query_id())?.build()
+    }
+}
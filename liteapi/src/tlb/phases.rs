@@ -0,0 +1,379 @@
+use crate::cell::{Cell, CellError, CellSlice};
+
+use super::{Coins, ExitCode, StorageUsed};
+
+/// Kind of a transaction, the constructor of its `TransactionDescr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionKind {
+    /// Processing of an inbound message, `trans_ord$0000`
+    Ordinary,
+    /// Storage fee collection, `trans_storage$0001`
+    Storage,
+    /// Special masterchain transactions at the start and the end of a block, `trans_tick_tock$001`
+    Tick,
+    Tock,
+    SplitPrepare,
+    SplitInstall,
+    MergePrepare,
+    MergeInstall,
+}
+
+/// `acst_unchanged$0 = AccStatusChange; acst_frozen$10 = AccStatusChange; acst_deleted$11 = AccStatusChange;`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccStatusChange {
+    Unchanged,
+    Frozen,
+    Deleted,
+}
+
+impl AccStatusChange {
+    fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        if !slice.load_bit()? {
+            return Ok(Self::Unchanged);
+        }
+        Ok(if slice.load_bit()? { Self::Deleted } else { Self::Frozen })
+    }
+}
+
+/// ```tlb
+/// tr_phase_storage$_ storage_fees_collected:Grams storage_fees_due:(Maybe Grams) status_change:AccStatusChange
+///   = TrStoragePhase;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoragePhase {
+    pub fees_collected: Coins,
+    /// Fees the account couldn't pay, it's frozen or deleted when they grow too large
+    pub fees_due: Option<Coins>,
+    pub status_change: AccStatusChange,
+}
+
+impl StoragePhase {
+    fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let fees_collected = Coins::load(slice)?;
+        let fees_due = load_maybe_coins(slice)?;
+        let status_change = AccStatusChange::load(slice)?;
+        Ok(Self { fees_collected, fees_due, status_change })
+    }
+}
+
+/// `cskip_no_state$00 cskip_bad_state$01 cskip_no_gas$10 cskip_suspended$110 = ComputeSkipReason;`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComputeSkipReason {
+    /// The account has no code, e.g. a transfer to an uninitialized address
+    NoState,
+    /// The `StateInit` of the message doesn't match the address
+    BadState,
+    /// The message doesn't carry enough TON to buy gas
+    NoGas,
+    Suspended,
+}
+
+/// ```tlb
+/// tr_phase_compute_skipped$0 reason:ComputeSkipReason = TrComputePhase;
+/// tr_phase_compute_vm$1 success:Bool msg_state_used:Bool account_activated:Bool gas_fees:Grams
+///   ^[ gas_used:(VarUInteger 7) gas_limit:(VarUInteger 7) gas_credit:(Maybe (VarUInteger 3)) mode:int8
+///      exit_code:int32 exit_arg:(Maybe int32) vm_steps:uint32 vm_init_state_hash:bits256 vm_final_state_hash:bits256 ]
+///   = TrComputePhase;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComputePhase {
+    Skipped(ComputeSkipReason),
+    Vm(ComputeVm),
+}
+
+/// Executed compute phase, see [`ComputePhase`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeVm {
+    pub success: bool,
+    /// Whether the `StateInit` of the inbound message was used
+    pub msg_state_used: bool,
+    pub account_activated: bool,
+    pub gas_fees: Coins,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    /// Gas available to external messages before the contract accepts them
+    pub gas_credit: Option<u64>,
+    pub mode: i8,
+    pub exit_code: ExitCode,
+    pub exit_arg: Option<i32>,
+    pub vm_steps: u32,
+}
+
+impl ComputePhase {
+    fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        if !slice.load_bit()? {
+            let reason = match slice.load_uint(2)? {
+                0b00 => ComputeSkipReason::NoState,
+                0b01 => ComputeSkipReason::BadState,
+                0b10 => ComputeSkipReason::NoGas,
+                _ => match slice.load_bit()? {
+                    false => ComputeSkipReason::Suspended,
+                    true => return Err(CellError::UnexpectedTag(0b111)),
+                },
+            };
+            return Ok(Self::Skipped(reason));
+        }
+        let success = slice.load_bit()?;
+        let msg_state_used = slice.load_bit()?;
+        let account_activated = slice.load_bit()?;
+        let gas_fees = Coins::load(slice)?;
+        let mut details = slice.load_ref()?.parser()?;
+        let gas_used = load_var_uint(&mut details, 3)?;
+        let gas_limit = load_var_uint(&mut details, 3)?;
+        let gas_credit = if details.load_bit()? { Some(load_var_uint(&mut details, 2)?) } else { None };
+        let mode = details.load_int(8)? as i8;
+        let exit_code = ExitCode::from(details.load_int(32)? as i32);
+        let exit_arg = load_maybe_i32(&mut details)?;
+        let vm_steps = details.load_u32()?;
+        Ok(Self::Vm(ComputeVm {
+            success, msg_state_used, account_activated, gas_fees,
+            gas_used, gas_limit, gas_credit, mode, exit_code, exit_arg, vm_steps,
+        }))
+    }
+}
+
+/// ```tlb
+/// tr_phase_action$_ success:Bool valid:Bool no_funds:Bool status_change:AccStatusChange
+///   total_fwd_fees:(Maybe Grams) total_action_fees:(Maybe Grams) result_code:int32 result_arg:(Maybe int32)
+///   tot_actions:uint16 spec_actions:uint16 skipped_actions:uint16 msgs_created:uint16
+///   action_list_hash:bits256 tot_msg_size:StorageUsedShort = TrActionPhase;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionPhase {
+    pub success: bool,
+    /// Whether the action list was well-formed
+    pub valid: bool,
+    /// Whether the actions failed because the account ran out of TON
+    pub no_funds: bool,
+    pub status_change: AccStatusChange,
+    pub total_fwd_fees: Option<Coins>,
+    pub total_action_fees: Option<Coins>,
+    /// Zero on success, otherwise the reason of the failure of the action `result_arg`
+    pub result_code: i32,
+    pub result_arg: Option<i32>,
+    pub tot_actions: u16,
+    pub spec_actions: u16,
+    pub skipped_actions: u16,
+    pub msgs_created: u16,
+    pub action_list_hash: [u8; 32],
+    pub tot_msg_size: StorageUsed,
+}
+
+impl ActionPhase {
+    fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let success = slice.load_bit()?;
+        let valid = slice.load_bit()?;
+        let no_funds = slice.load_bit()?;
+        let status_change = AccStatusChange::load(slice)?;
+        let total_fwd_fees = load_maybe_coins(slice)?;
+        let total_action_fees = load_maybe_coins(slice)?;
+        let result_code = slice.load_int(32)? as i32;
+        let result_arg = load_maybe_i32(slice)?;
+        let tot_actions = slice.load_uint(16)? as u16;
+        let spec_actions = slice.load_uint(16)? as u16;
+        let skipped_actions = slice.load_uint(16)? as u16;
+        let msgs_created = slice.load_uint(16)? as u16;
+        let action_list_hash = slice.load_u256()?;
+        let tot_msg_size = load_storage_used_short(slice)?;
+        Ok(Self {
+            success, valid, no_funds, status_change, total_fwd_fees, total_action_fees, result_code, result_arg,
+            tot_actions, spec_actions, skipped_actions, msgs_created, action_list_hash, tot_msg_size,
+        })
+    }
+}
+
+/// Bounce of the inbound message after a failed compute or action phase.
+///
+/// ```tlb
+/// tr_phase_bounce_negfunds$00 = TrBouncePhase;
+/// tr_phase_bounce_nofunds$01 msg_size:StorageUsedShort req_fwd_fees:Grams = TrBouncePhase;
+/// tr_phase_bounce_ok$1 msg_size:StorageUsedShort msg_fees:Grams fwd_fees:Grams = TrBouncePhase;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BouncePhase {
+    NegFunds,
+    /// The remaining value doesn't cover the forwarding fees, nothing is bounced
+    NoFunds { msg_size: StorageUsed, req_fwd_fees: Coins },
+    Ok { msg_size: StorageUsed, msg_fees: Coins, fwd_fees: Coins },
+}
+
+impl BouncePhase {
+    fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        if slice.load_bit()? {
+            let msg_size = load_storage_used_short(slice)?;
+            let msg_fees = Coins::load(slice)?;
+            let fwd_fees = Coins::load(slice)?;
+            return Ok(Self::Ok { msg_size, msg_fees, fwd_fees });
+        }
+        if !slice.load_bit()? {
+            return Ok(Self::NegFunds);
+        }
+        let msg_size = load_storage_used_short(slice)?;
+        let req_fwd_fees = Coins::load(slice)?;
+        Ok(Self::NoFunds { msg_size, req_fwd_fees })
+    }
+}
+
+/// Phases of a transaction from its `description`, see [`Transaction::summary`](super::Transaction::summary).
+///
+/// ```tlb
+/// trans_ord$0000 credit_first:Bool storage_ph:(Maybe TrStoragePhase) credit_ph:(Maybe TrCreditPhase)
+///   compute_ph:TrComputePhase action:(Maybe ^TrActionPhase) aborted:Bool bounce:(Maybe TrBouncePhase)
+///   destroyed:Bool = TransactionDescr;
+/// trans_storage$0001 storage_ph:TrStoragePhase = TransactionDescr;
+/// trans_tick_tock$001 is_tock:Bool storage_ph:TrStoragePhase compute_ph:TrComputePhase
+///   action:(Maybe ^TrActionPhase) aborted:Bool destroyed:Bool = TransactionDescr;
+/// tr_phase_credit$_ due_fees_collected:(Maybe Grams) credit:CurrencyCollection = TrCreditPhase;
+/// ```
+///
+/// Split and merge transactions only have their kind and phases, `aborted` and `destroyed` decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSummary {
+    pub kind: TransactionKind,
+    pub storage: Option<StoragePhase>,
+    /// TON of the inbound message credited to the account, without extra currencies
+    pub credit: Option<Coins>,
+    pub compute: Option<ComputePhase>,
+    pub action: Option<ActionPhase>,
+    pub bounce: Option<BouncePhase>,
+    pub aborted: bool,
+    pub destroyed: bool,
+    /// `total_fees` of the transaction, without extra currencies
+    pub total_fees: Coins,
+}
+
+impl TransactionSummary {
+    /// Decode `description` of a transaction with `total_fees`.
+    pub fn load(description: &Cell, total_fees: Coins) -> Result<Self, CellError> {
+        let mut slice = description.parser()?;
+        let mut summary = Self {
+            kind: TransactionKind::Ordinary,
+            storage: None,
+            credit: None,
+            compute: None,
+            action: None,
+            bounce: None,
+            aborted: false,
+            destroyed: false,
+            total_fees,
+        };
+        summary.kind = match slice.load_uint(3)? {
+            0b001 => if slice.load_bit()? { TransactionKind::Tock } else { TransactionKind::Tick },
+            tag => match (tag, slice.load_bit()?) {
+                (0b000, false) => TransactionKind::Ordinary,
+                (0b000, true) => TransactionKind::Storage,
+                (0b010, false) => TransactionKind::SplitPrepare,
+                (0b010, true) => TransactionKind::SplitInstall,
+                (0b011, false) => TransactionKind::MergePrepare,
+                (0b011, true) => TransactionKind::MergeInstall,
+                _ => return Err(CellError::UnexpectedTag(tag)),
+            },
+        };
+        if matches!(summary.kind, TransactionKind::SplitPrepare | TransactionKind::SplitInstall
+            | TransactionKind::MergePrepare | TransactionKind::MergeInstall)
+        {
+            // split_merge_info$_ cur_shard_pfx_len:(## 6) acc_split_depth:(## 6) this_addr:bits256 sibling_addr:bits256
+            slice.skip_bits(6 + 6 + 256 + 256)?;
+        }
+        match summary.kind {
+            TransactionKind::Storage => {
+                summary.storage = Some(StoragePhase::load(&mut slice)?);
+                return Ok(summary);
+            }
+            TransactionKind::SplitInstall => return Ok(summary),
+            TransactionKind::MergePrepare => {
+                summary.storage = Some(StoragePhase::load(&mut slice)?);
+                summary.aborted = slice.load_bit()?;
+                return Ok(summary);
+            }
+            TransactionKind::Ordinary => {
+                // credit_first:Bool
+                slice.skip_bits(1)?;
+                summary.storage = load_maybe_storage(&mut slice)?;
+                summary.credit = load_maybe_credit(&mut slice)?;
+            }
+            TransactionKind::Tick | TransactionKind::Tock => {
+                summary.storage = Some(StoragePhase::load(&mut slice)?);
+            }
+            TransactionKind::SplitPrepare => {
+                summary.storage = load_maybe_storage(&mut slice)?;
+            }
+            TransactionKind::MergeInstall => {
+                // prepare_transaction:^Transaction
+                slice.load_ref()?;
+                summary.storage = load_maybe_storage(&mut slice)?;
+                summary.credit = load_maybe_credit(&mut slice)?;
+            }
+        }
+        summary.compute = Some(ComputePhase::load(&mut slice)?);
+        summary.action = slice.load_maybe_ref()?.map(|action| ActionPhase::load(&mut action.parser()?)).transpose()?;
+        summary.aborted = slice.load_bit()?;
+        if summary.kind == TransactionKind::Ordinary && slice.load_bit()? {
+            summary.bounce = Some(BouncePhase::load(&mut slice)?);
+        }
+        summary.destroyed = slice.load_bit()?;
+        Ok(summary)
+    }
+
+    /// Whether the transaction did what it was meant to: it isn't aborted and its phases succeeded.
+    pub fn success(&self) -> bool {
+        let compute = !matches!(self.compute, Some(ComputePhase::Vm(ComputeVm { success: false, .. })));
+        let action = self.action.is_none_or(|action| action.success);
+        !self.aborted && compute && action
+    }
+
+    pub fn compute_vm(&self) -> Option<&ComputeVm> {
+        match &self.compute {
+            Some(ComputePhase::Vm(vm)) => Some(vm),
+            _ => None,
+        }
+    }
+
+    /// Exit code of the compute phase, `None` if it was skipped.
+    pub fn exit_code(&self) -> Option<ExitCode> {
+        self.compute_vm().map(|vm| vm.exit_code)
+    }
+
+    pub fn gas_used(&self) -> Option<u64> {
+        self.compute_vm().map(|vm| vm.gas_used)
+    }
+}
+
+fn load_maybe_storage(slice: &mut CellSlice) -> Result<Option<StoragePhase>, CellError> {
+    if slice.load_bit()? { StoragePhase::load(slice).map(Some) } else { Ok(None) }
+}
+
+fn load_maybe_credit(slice: &mut CellSlice) -> Result<Option<Coins>, CellError> {
+    if !slice.load_bit()? {
+        return Ok(None);
+    }
+    // due_fees_collected:(Maybe Grams), then credit:CurrencyCollection
+    load_maybe_coins(slice)?;
+    let credit = Coins::load(slice)?;
+    slice.load_maybe_ref()?;
+    Ok(Some(credit))
+}
+
+fn load_maybe_coins(slice: &mut CellSlice) -> Result<Option<Coins>, CellError> {
+    if slice.load_bit()? { Coins::load(slice).map(Some) } else { Ok(None) }
+}
+
+fn load_maybe_i32(slice: &mut CellSlice) -> Result<Option<i32>, CellError> {
+    if slice.load_bit()? { Ok(Some(slice.load_int(32)? as i32)) } else { Ok(None) }
+}
+
+/// `VarUInteger n` with `len_bits` bits of length, at most 8 bytes long.
+fn load_var_uint(slice: &mut CellSlice, len_bits: usize) -> Result<u64, CellError> {
+    let len = slice.load_uint(len_bits)? as usize;
+    if len > 8 {
+        return Err(CellError::Overflow);
+    }
+    slice.load_uint(len * 8)
+}
+
+/// `storage_used_short$_ cells:(VarUInteger 7) bits:(VarUInteger 7) = StorageUsedShort;`
+fn load_storage_used_short(slice: &mut CellSlice) -> Result<StorageUsed, CellError> {
+    let cells = load_var_uint(slice, 3)?;
+    let bits = load_var_uint(slice, 3)?;
+    Ok(StorageUsed { bits, cells })
+}
@@ -0,0 +1,304 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use crate::cell::{Cell, CellError, CellSlice, CellType};
+use crate::time;
+use crate::tl::common::{BlockId, BlockIdExt, Int256};
+
+use super::{hashmap_e_entries, load_mc_state_extra, shard_state, CurrencyCollection};
+
+/// Shard of a workchain: the prefix of its accounts followed by a terminating 1 bit, so `1 << 63` is the
+/// whole workchain, and `0x4000000000000000` and `0xc000000000000000` are its halves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShardIdent {
+    pub workchain: i32,
+    pub shard: u64,
+}
+
+impl ShardIdent {
+    pub const FULL: u64 = 1 << 63;
+
+    pub fn new(workchain: i32, shard: u64) -> Self {
+        Self { workchain, shard }
+    }
+
+    /// The whole `workchain` as a single shard.
+    pub fn full(workchain: i32) -> Self {
+        Self::new(workchain, Self::FULL)
+    }
+
+    /// Shard from the signed `long` shard field used by TL schemes and global configs.
+    pub fn from_i64(workchain: i32, shard: i64) -> Self {
+        Self::new(workchain, shard as u64)
+    }
+
+    /// Shard as the signed `long` used by TL schemes and global configs.
+    pub fn shard_i64(&self) -> i64 {
+        self.shard as i64
+    }
+
+    /// Number of prefix bits, 0 for the whole workchain.
+    pub fn prefix_len(&self) -> u32 {
+        63u32.saturating_sub(self.shard.trailing_zeros())
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.shard == Self::FULL
+    }
+
+    /// Whether the account with the given id belongs to this shard.
+    pub fn contains(&self, account: &[u8; 32]) -> bool {
+        shard_contains(self.shard, u64::from_be_bytes(account[..8].try_into().unwrap()))
+    }
+
+    /// Whether `other` is one of the two shards this one splits into.
+    pub fn is_parent_of(&self, other: &ShardIdent) -> bool {
+        other.merge() == Some(*self)
+    }
+
+    /// Whether `other` is a shard of this one after one or more splits.
+    pub fn is_ancestor_of(&self, other: &ShardIdent) -> bool {
+        self.workchain == other.workchain && self.prefix_len() < other.prefix_len() && shard_contains(self.shard, other.shard)
+    }
+
+    /// Whether the shards have accounts in common, i.e. one of them contains the other.
+    pub fn intersects(&self, other: &ShardIdent) -> bool {
+        self == other || self.is_ancestor_of(other) || other.is_ancestor_of(self)
+    }
+
+    /// Left and right halves of the shard, `None` if it can't be split any further.
+    pub fn split(&self) -> Option<(Self, Self)> {
+        let step = (self.shard & self.shard.wrapping_neg()) >> 1;
+        if step == 0 {
+            return None;
+        }
+        Some((Self::new(self.workchain, self.shard - step), Self::new(self.workchain, self.shard + step)))
+    }
+
+    /// Shard this one merges into with its sibling, `None` for the whole workchain.
+    pub fn merge(&self) -> Option<Self> {
+        if self.is_full() || self.shard == 0 {
+            return None;
+        }
+        let low_bit = self.shard & self.shard.wrapping_neg();
+        Some(Self::new(self.workchain, (self.shard - low_bit) | (low_bit << 1)))
+    }
+}
+
+impl From<&BlockId> for ShardIdent {
+    fn from(id: &BlockId) -> Self {
+        Self::new(id.workchain, id.shard)
+    }
+}
+
+impl From<&BlockIdExt> for ShardIdent {
+    fn from(id: &BlockIdExt) -> Self {
+        Self::new(id.workchain, id.shard)
+    }
+}
+
+impl fmt::Display for ShardIdent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{:016x}", self.workchain, self.shard)
+    }
+}
+
+/// ```tlb
+/// fsm_none$0 = FutureSplitMerge;
+/// fsm_split$10 split_utime:uint32 interval:uint32 = FutureSplitMerge;
+/// fsm_merge$11 merge_utime:uint32 interval:uint32 = FutureSplitMerge;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FutureSplitMerge {
+    #[default]
+    None,
+    Split { split_utime: u32, interval: u32 },
+    Merge { merge_utime: u32, interval: u32 },
+}
+
+impl FutureSplitMerge {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        if !slice.load_bit()? {
+            return Ok(Self::None);
+        }
+        let merge = slice.load_bit()?;
+        let utime = slice.load_u32()?;
+        let interval = slice.load_u32()?;
+        Ok(match merge {
+            false => Self::Split { split_utime: utime, interval },
+            true => Self::Merge { merge_utime: utime, interval },
+        })
+    }
+}
+
+/// Shard block description from `ShardHashes` or `liteServer.shardInfo`.
+///
+/// ```tlb
+/// shard_descr#b seq_no:uint32 reg_mc_seqno:uint32 start_lt:uint64 end_lt:uint64
+///   root_hash:bits256 file_hash:bits256 before_split:Bool before_merge:Bool
+///   want_split:Bool want_merge:Bool nx_cc_updated:Bool flags:(## 3) { flags = 0 }
+///   next_catchain_seqno:uint32 next_validator_shard:uint64 min_ref_mc_seqno:uint32
+///   gen_utime:uint32 split_merge_at:FutureSplitMerge fees_collected:CurrencyCollection
+///   funds_created:CurrencyCollection = ShardDescr;
+/// shard_descr_new#a ... split_merge_at:FutureSplitMerge
+///   ^[ fees_collected:CurrencyCollection funds_created:CurrencyCollection ] = ShardDescr;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardDescr {
+    pub seq_no: u32,
+    pub reg_mc_seqno: u32,
+    pub start_lt: u64,
+    pub end_lt: u64,
+    pub root_hash: [u8; 32],
+    pub file_hash: [u8; 32],
+    pub before_split: bool,
+    pub before_merge: bool,
+    pub want_split: bool,
+    pub want_merge: bool,
+    pub nx_cc_updated: bool,
+    pub next_catchain_seqno: u32,
+    pub next_validator_shard: u64,
+    pub min_ref_mc_seqno: u32,
+    pub gen_utime: u32,
+    pub split_merge_at: FutureSplitMerge,
+    /// Fees collected in the shard since the previous registered block, `None` if pruned from the proof
+    pub fees_collected: Option<CurrencyCollection>,
+    /// Funds created in the shard since the previous registered block, `None` if pruned from the proof
+    pub funds_created: Option<CurrencyCollection>,
+}
+
+impl ShardDescr {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_uint(4)?;
+        if tag != 0xb && tag != 0xa {
+            return Err(CellError::UnexpectedTag(tag));
+        }
+        let seq_no = slice.load_u32()?;
+        let reg_mc_seqno = slice.load_u32()?;
+        let start_lt = slice.load_u64()?;
+        let end_lt = slice.load_u64()?;
+        let root_hash = slice.load_u256()?;
+        let file_hash = slice.load_u256()?;
+        let before_split = slice.load_bit()?;
+        let before_merge = slice.load_bit()?;
+        let want_split = slice.load_bit()?;
+        let want_merge = slice.load_bit()?;
+        let nx_cc_updated = slice.load_bit()?;
+        slice.skip_bits(3)?;
+        let next_catchain_seqno = slice.load_u32()?;
+        let next_validator_shard = slice.load_u64()?;
+        let min_ref_mc_seqno = slice.load_u32()?;
+        let gen_utime = slice.load_u32()?;
+        let split_merge_at = FutureSplitMerge::load(slice)?;
+        let (fees_collected, funds_created) = match tag {
+            0xb => (Some(CurrencyCollection::load(slice)?), Some(CurrencyCollection::load(slice)?)),
+            _ => match slice.load_ref()? {
+                funds if funds.cell_type() == CellType::PrunedBranch => (None, None),
+                funds => {
+                    let mut funds = funds.parser()?;
+                    (Some(CurrencyCollection::load(&mut funds)?), Some(CurrencyCollection::load(&mut funds)?))
+                }
+            },
+        };
+        Ok(Self {
+            seq_no, reg_mc_seqno, start_lt, end_lt, root_hash, file_hash,
+            before_split, before_merge, want_split, want_merge, nx_cc_updated,
+            next_catchain_seqno, next_validator_shard, min_ref_mc_seqno, gen_utime,
+            split_merge_at, fees_collected, funds_created,
+        })
+    }
+
+    /// Time the shard block was generated at.
+    pub fn gen_time(&self) -> SystemTime {
+        time::system_time(self.gen_utime)
+    }
+
+    /// Parse the `shard_descr` of `liteServer.shardInfo`.
+    pub fn from_cell(cell: &Cell) -> Result<Self, CellError> {
+        Self::load(&mut cell.parser()?)
+    }
+}
+
+/// Shard block registered in a masterchain block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardEntry {
+    pub workchain: i32,
+    pub shard: u64,
+    pub descr: ShardDescr,
+}
+
+impl ShardEntry {
+    pub fn ident(&self) -> ShardIdent {
+        ShardIdent::new(self.workchain, self.shard)
+    }
+
+    pub fn block_id(&self) -> BlockIdExt {
+        BlockIdExt {
+            workchain: self.workchain,
+            shard: self.shard,
+            seqno: self.descr.seq_no,
+            root_hash: Int256(self.descr.root_hash),
+            file_hash: Int256(self.descr.file_hash),
+        }
+    }
+}
+
+/// `_ (HashmapE 32 ^(BinTree ShardDescr)) = ShardHashes;`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ShardHashes {
+    pub shards: Vec<ShardEntry>,
+}
+
+impl ShardHashes {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let mut shards = Vec::new();
+        for (key, mut value) in hashmap_e_entries(slice, 32)? {
+            let workchain = i32::from_be_bytes(key.try_into().unwrap());
+            load_bin_tree(value.load_ref()?, ShardIdent::full(workchain), &mut shards)?;
+        }
+        Ok(Self { shards })
+    }
+
+    /// Shard blocks registered in a masterchain state, from its merkle proof or the state itself.
+    ///
+    /// Shards pruned from the proof are missing.
+    pub fn from_state_proof(proof: &Cell) -> Result<Self, CellError> {
+        let mut state = shard_state(proof)?;
+        // gen_utime, gen_lt
+        state.skip_bits(32 + 64)?;
+        Self::load(&mut load_mc_state_extra(&mut state)?)
+    }
+
+    /// Parse the `data` of `liteServer.allShardsInfo`.
+    pub fn from_cell(cell: &Cell) -> Result<Self, CellError> {
+        Self::load(&mut cell.parser()?)
+    }
+
+    /// Shard of `workchain` containing the account with the given id.
+    pub fn find(&self, workchain: i32, account: &[u8; 32]) -> Option<&ShardEntry> {
+        self.shards.iter().find(|s| s.workchain == workchain && s.ident().contains(account))
+    }
+}
+
+/// ```tlb
+/// bt_leaf$0 {X:Type} leaf:X = BinTree X;
+/// bt_fork$1 {X:Type} left:^(BinTree X) right:^(BinTree X) = BinTree X;
+/// ```
+fn load_bin_tree(cell: &Cell, shard: ShardIdent, shards: &mut Vec<ShardEntry>) -> Result<(), CellError> {
+    let mut slice = cell.parser()?;
+    if slice.load_bit()? {
+        let (left, right) = shard.split().ok_or(CellError::InvalidExotic("shard tree too deep"))?;
+        load_bin_tree(slice.load_ref()?, left, shards)?;
+        load_bin_tree(slice.load_ref()?, right, shards)?;
+    } else {
+        shards.push(ShardEntry { workchain: shard.workchain, shard: shard.shard, descr: ShardDescr::load(&mut slice)? });
+    }
+    Ok(())
+}
+
+/// Whether `shard` contains accounts starting with the 64 bit `prefix`.
+pub fn shard_contains(shard: u64, prefix: u64) -> bool {
+    let low_bit = shard & shard.wrapping_neg();
+    let mask = !((low_bit << 1).wrapping_sub(1));
+    (shard ^ prefix) & mask == 0
+}
@@ -0,0 +1,299 @@
+use num_bigint::{BigInt, Sign};
+
+use crate::cell::{ArcCell, Cell, CellBuilder, CellError, CellSlice};
+
+/// `_ cell:^Cell st_bits:(## 10) end_bits:(## 10) { st_bits <= end_bits } st_ref:(#<= 4) end_ref:(#<= 4)
+///   { st_ref <= end_ref } = VmCellSlice;`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackSlice {
+    pub cell: ArcCell,
+    pub st_bits: u16,
+    pub end_bits: u16,
+    pub st_ref: u8,
+    pub end_ref: u8,
+}
+
+impl StackSlice {
+    /// Slice covering all of `cell`.
+    pub fn full(cell: ArcCell) -> Self {
+        let end_bits = cell.bit_len() as u16;
+        let end_ref = cell.references().len() as u8;
+        Self { cell, st_bits: 0, end_bits, st_ref: 0, end_ref }
+    }
+
+    /// Reader positioned at `st_bits` and `st_ref` of the cell.
+    ///
+    /// The reader isn't bounded by `end_bits` and `end_ref`, which are the cell's ends for slices
+    /// returned by get-methods.
+    pub fn parser(&self) -> Result<CellSlice<'_>, CellError> {
+        let mut slice = self.cell.parser()?;
+        slice.skip_bits(self.st_bits as usize)?;
+        for _ in 0..self.st_ref {
+            slice.load_ref()?;
+        }
+        Ok(slice)
+    }
+}
+
+/// ```tlb
+/// vm_stk_null#00 = VmStackValue;
+/// vm_stk_tinyint#01 value:int64 = VmStackValue;
+/// vm_stk_int#0201_ value:int257 = VmStackValue;
+/// vm_stk_nan#02ff = VmStackValue;
+/// vm_stk_cell#03 cell:^Cell = VmStackValue;
+/// vm_stk_slice#04 _:VmCellSlice = VmStackValue;
+/// vm_stk_builder#05 cell:^Cell = VmStackValue;
+/// vm_stk_cont#06 cont:VmCont = VmStackValue;
+/// vm_stk_tuple#07 len:(## 16) data:(VmTuple len) = VmStackValue;
+/// ```
+///
+/// Continuations are kept opaque: a value is always the last one in its cell, so [`StackValue::Cont`] holds
+/// the serialized `VmCont` as the remaining data and references of that cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StackValue {
+    Null,
+    /// Integer of up to 257 bits, stored as `vm_stk_tinyint` if it fits into 64 bits
+    Int(BigInt),
+    Nan,
+    Cell(ArcCell),
+    Slice(StackSlice),
+    Builder(ArcCell),
+    /// Serialized `VmCont`, without the tag
+    Cont(ArcCell),
+    Tuple(Vec<StackValue>),
+}
+
+impl StackValue {
+    pub fn int(value: impl Into<BigInt>) -> Self {
+        Self::Int(value.into())
+    }
+
+    pub fn as_int(&self) -> Option<&BigInt> {
+        match self {
+            Self::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Integer value if it fits into `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_int().and_then(|value| value.try_into().ok())
+    }
+
+    /// Integer value if it fits into `u128`, e.g. a jetton balance.
+    pub fn as_u128(&self) -> Option<u128> {
+        self.as_int().and_then(|value| value.try_into().ok())
+    }
+
+    pub fn as_tuple(&self) -> Option<&[StackValue]> {
+        match self {
+            Self::Tuple(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Cell of a cell, slice or builder.
+    pub fn as_cell(&self) -> Option<&ArcCell> {
+        match self {
+            Self::Cell(cell) | Self::Builder(cell) => Some(cell),
+            Self::Slice(slice) => Some(&slice.cell),
+            _ => None,
+        }
+    }
+
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_u8()?;
+        match tag {
+            0x00 => Ok(Self::Null),
+            0x01 => Ok(Self::Int(slice.load_int(64)?.into())),
+            0x02 => match slice.load_uint(7)? {
+                0 => {
+                    let negative = slice.load_bit()?;
+                    let value = BigInt::from_bytes_be(Sign::Plus, &slice.load_u256()?);
+                    Ok(Self::Int(if negative { value - (BigInt::from(1) << 256) } else { value }))
+                }
+                0x7f if slice.load_bit()? => Ok(Self::Nan),
+                _ => Err(CellError::UnexpectedTag(tag as u64)),
+            },
+            0x03 => Ok(Self::Cell(slice.load_ref()?.clone())),
+            0x04 => {
+                let cell = slice.load_ref()?.clone();
+                let st_bits = slice.load_uint(10)? as u16;
+                let end_bits = slice.load_uint(10)? as u16;
+                let st_ref = slice.load_uint(3)? as u8;
+                let end_ref = slice.load_uint(3)? as u8;
+                Ok(Self::Slice(StackSlice { cell, st_bits, end_bits, st_ref, end_ref }))
+            }
+            0x05 => Ok(Self::Builder(slice.load_ref()?.clone())),
+            0x06 => {
+                let cont = CellBuilder::new().store_slice(slice)?.build()?;
+                slice.skip_bits(slice.remaining_bits())?;
+                while slice.remaining_refs() > 0 {
+                    slice.load_ref()?;
+                }
+                Ok(Self::Cont(cont))
+            }
+            0x07 => {
+                let len = slice.load_uint(16)? as usize;
+                Ok(Self::Tuple(load_tuple(slice, len)?))
+            }
+            _ => Err(CellError::UnexpectedTag(tag as u64)),
+        }
+    }
+
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), CellError> {
+        match self {
+            Self::Null => {
+                builder.store_u8(0x00)?;
+            }
+            Self::Int(value) => match i64::try_from(value) {
+                Ok(value) => {
+                    builder.store_u8(0x01)?.store_int(64, value)?;
+                }
+                Err(_) => {
+                    let limit = BigInt::from(1) << 256;
+                    if *value >= limit || *value < -&limit {
+                        return Err(CellError::Overflow);
+                    }
+                    // two's complement: the sign bit followed by the value modulo 2^256
+                    let negative = value.sign() == Sign::Minus;
+                    let low = if negative { value + &limit } else { value.clone() };
+                    let (_, bytes) = low.to_bytes_be();
+                    let mut low = [0; 32];
+                    low[32 - bytes.len()..].copy_from_slice(&bytes);
+                    builder.store_u8(0x02)?.store_uint(7, 0)?.store_bit(negative)?.store_u256(&low)?;
+                }
+            },
+            Self::Nan => {
+                builder.store_u8(0x02)?.store_u8(0xff)?;
+            }
+            Self::Cell(cell) => {
+                builder.store_u8(0x03)?.store_reference(cell.clone())?;
+            }
+            Self::Slice(slice) => {
+                builder.store_u8(0x04)?.store_reference(slice.cell.clone())?;
+                builder.store_uint(10, slice.st_bits as u64)?.store_uint(10, slice.end_bits as u64)?;
+                builder.store_uint(3, slice.st_ref as u64)?.store_uint(3, slice.end_ref as u64)?;
+            }
+            Self::Builder(cell) => {
+                builder.store_u8(0x05)?.store_reference(cell.clone())?;
+            }
+            Self::Cont(cont) => {
+                builder.store_u8(0x06)?.store_cell_data(cont)?;
+            }
+            Self::Tuple(items) => {
+                builder.store_u8(0x07)?.store_uint(16, items.len() as u64)?;
+                store_tuple(builder, items)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn to_cell(&self) -> Result<ArcCell, CellError> {
+        let mut builder = CellBuilder::new();
+        self.store(&mut builder)?;
+        builder.build()
+    }
+}
+
+/// ```tlb
+/// vm_stack#_ depth:(## 24) stack:(VmStackList depth) = VmStack;
+/// vm_stk_nil#_ = VmStackList 0;
+/// vm_stk_cons#_ {n:#} rest:^(VmStackList n) tos:VmStackValue = VmStackList (n + 1);
+/// ```
+///
+/// The serialized form of get-method parameters and results, such as `params` and `result` of `runSmcMethod`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VmStack {
+    /// Values from the bottom to the top of the stack, i.e. in the order of get-method parameters
+    pub values: Vec<StackValue>,
+}
+
+impl VmStack {
+    pub fn new(values: Vec<StackValue>) -> Self {
+        Self { values }
+    }
+
+    pub fn load(cell: &Cell) -> Result<Self, CellError> {
+        let mut slice = cell.parser()?;
+        let depth = slice.load_uint(24)? as usize;
+        let mut values = Vec::with_capacity(depth);
+        for _ in 0..depth {
+            let rest = slice.load_ref()?;
+            values.push(StackValue::load(&mut slice)?);
+            slice = rest.parser()?;
+        }
+        values.reverse();
+        Ok(Self { values })
+    }
+
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        let mut rest = CellBuilder::new().build()?;
+        for (i, value) in self.values.iter().enumerate() {
+            let mut builder = CellBuilder::new();
+            if i + 1 == self.values.len() {
+                builder.store_uint(24, self.values.len() as u64)?;
+            }
+            builder.store_reference(rest)?;
+            value.store(&mut builder)?;
+            rest = builder.build()?;
+        }
+        if self.values.is_empty() {
+            return CellBuilder::new().store_uint(24, 0)?.build();
+        }
+        Ok(rest)
+    }
+
+    /// Parse a serialized stack, e.g. the `result` of `runSmcMethod`.
+    pub fn from_boc(boc: &[u8]) -> Result<Self, CellError> {
+        Self::load(&*Cell::from_boc(boc)?)
+    }
+
+    /// Serialize the stack, e.g. into the `params` of `runSmcMethod`.
+    pub fn to_boc(&self) -> Result<Vec<u8>, CellError> {
+        Ok(self.to_cell()?.to_boc())
+    }
+}
+
+/// ```tlb
+/// vm_tupref_nil$_ = VmTupleRef 0;
+/// vm_tupref_single$_ entry:^VmStackValue = VmTupleRef 1;
+/// vm_tupref_any$_ {n:#} ref:^(VmTuple (n + 2)) = VmTupleRef (n + 2);
+/// vm_tuple_nil$_ = VmTuple 0;
+/// vm_tuple_tcons$_ {n:#} head:(VmTupleRef n) tail:^VmStackValue = VmTuple (n + 1);
+/// ```
+fn load_tuple(slice: &mut CellSlice, len: usize) -> Result<Vec<StackValue>, CellError> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let mut items = match len - 1 {
+        0 => Vec::with_capacity(1),
+        1 => vec![StackValue::load(&mut slice.load_ref()?.parser()?)?],
+        head => load_tuple(&mut slice.load_ref()?.parser()?, head)?,
+    };
+    items.push(StackValue::load(&mut slice.load_ref()?.parser()?)?);
+    Ok(items)
+}
+
+fn store_tuple(builder: &mut CellBuilder, items: &[StackValue]) -> Result<(), CellError> {
+    let Some((tail, head)) = items.split_last() else {
+        return Ok(());
+    };
+    match head.len() {
+        0 => {}
+        1 => {
+            builder.store_reference(head[0].to_cell()?)?;
+        }
+        _ => {
+            let mut head_builder = CellBuilder::new();
+            store_tuple(&mut head_builder, head)?;
+            builder.store_reference(head_builder.build()?)?;
+        }
+    }
+    builder.store_reference(tail.to_cell()?)?;
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use crate::cell::{Cell, CellError, CellSlice, CellType};
+
+use super::hashmap_entries;
+
+/// ```tlb
+/// counters#_ last_updated:uint32 total:uint64 cnt2048:uint64 cnt65536:uint64 = Counters;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Counters {
+    pub last_updated: u32,
+    pub total: u64,
+    /// Decaying block counter with a 2048 second time constant
+    pub cnt2048: u64,
+    /// Decaying block counter with a 65536 second time constant
+    pub cnt65536: u64,
+}
+
+impl Counters {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        Ok(Self {
+            last_updated: slice.load_u32()?,
+            total: slice.load_u64()?,
+            cnt2048: slice.load_u64()?,
+            cnt65536: slice.load_u64()?,
+        })
+    }
+}
+
+/// Blocks created by a validator, keyed by its public key.
+///
+/// ```tlb
+/// creator_info#4 mc_blocks:Counters shard_blocks:Counters = CreatorStats;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreatorStats {
+    pub public_key: [u8; 32],
+    pub mc_blocks: Counters,
+    pub shard_blocks: Counters,
+}
+
+impl CreatorStats {
+    pub fn load(public_key: [u8; 32], slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_uint(4)?;
+        if tag != 0x4 {
+            return Err(CellError::UnexpectedTag(tag));
+        }
+        let mc_blocks = Counters::load(slice)?;
+        let shard_blocks = Counters::load(slice)?;
+        Ok(Self { public_key, mc_blocks, shard_blocks })
+    }
+
+    /// Entries of `block_create_stats` present in a merkle proof of the masterchain state, ordered by public key.
+    ///
+    /// ```tlb
+    /// masterchain_state_extra#cc26 shard_hashes:ShardHashes config:ConfigParams ^[ flags:(## 16) { flags <= 1 }
+    ///   validator_info:ValidatorInfo prev_blocks:OldMcBlocksInfo after_key_block:Bool last_key_block:(Maybe ExtBlkRef)
+    ///   block_create_stats:(flags . 0)?BlockCreateStats ] global_balance:CurrencyCollection = McStateExtra;
+    /// block_create_stats#17 counters:(HashmapE 256 CreatorStats) = BlockCreateStats;
+    /// block_create_stats_ext#34 counters:(HashmapAugE 256 CreatorStats uint32) = BlockCreateStats;
+    /// ```
+    pub fn from_state_proof(proof: &Cell) -> Result<Vec<Self>, CellError> {
+        let mut state = match proof.cell_type() {
+            CellType::MerkleProof => proof.reference(0)?.parser()?,
+            _ => proof.parser()?,
+        };
+        let tag = state.load_u32()?;
+        if tag != 0x9023afe2 {
+            return Err(CellError::UnexpectedTag(tag as u64));
+        }
+        // global_id, shard_ident, seq_no, vert_seq_no, gen_utime, gen_lt, min_ref_mc_seqno, before_split
+        state.skip_bits(32 + 2 + 6 + 32 + 64 + 32 + 32 + 32 + 64 + 32 + 1)?;
+        state.load_ref()?;
+        state.load_ref()?;
+        state.load_ref()?;
+        let extra = state.load_maybe_ref()?.ok_or(CellError::InvalidExotic("not a masterchain state"))?;
+        let mut extra = extra.parser()?;
+        let tag = extra.load_uint(16)?;
+        if tag != 0xcc26 {
+            return Err(CellError::UnexpectedTag(tag));
+        }
+        // shard_hashes, config_addr, config
+        extra.load_maybe_ref()?;
+        extra.skip_bits(256)?;
+        extra.load_ref()?;
+        let mut extra = extra.load_ref()?.parser()?;
+        let flags = extra.load_uint(16)?;
+        if flags & 1 == 0 {
+            return Ok(Vec::new());
+        }
+        // validator_info$_ validator_list_hash_short:uint32 catchain_seqno:uint32 nx_cc_updated:Bool
+        extra.skip_bits(32 + 32 + 1)?;
+        // prev_blocks:(HashmapAugE 32 KeyExtBlkRef KeyMaxLt), key_max_lt$_ key:Bool max_end_lt:uint64
+        extra.load_maybe_ref()?;
+        extra.skip_bits(1 + 64)?;
+        // after_key_block, ext_blk_ref$_ end_lt:uint64 seq_no:uint32 root_hash:bits256 file_hash:bits256
+        extra.skip_bits(1)?;
+        if extra.load_bit()? {
+            extra.skip_bits(64 + 32 + 256 + 256)?;
+        }
+        let tag = extra.load_u8()?;
+        let is_aug = match tag {
+            0x17 => false,
+            0x34 => true,
+            _ => return Err(CellError::UnexpectedTag(tag as u64)),
+        };
+        let Some(root) = extra.load_maybe_ref()? else {
+            return Ok(Vec::new());
+        };
+        hashmap_entries(root, 256)?.into_iter()
+            .map(|(key, mut value)| {
+                if is_aug {
+                    value.skip_bits(32)?;
+                }
+                Self::load(key.try_into().unwrap(), &mut value)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,637 @@
+use std::error::Error;
+
+use crate::cell::{deserialize_boc_single, ArcCell, Cell, CellBuilder, CellError};
+use crate::tlb::*;
+
+#[test]
+fn test_normalized_hash() -> Result<(), Box<dyn Error>> {
+    // same body and destination, with inline StateInit and body vs both in references
+    let inline = hex::decode("b5ee9c7241010301003e00035990655e4ff11111111111111111111111111111111111111111111111111111111111111112303997048d159e2c0102010008deadbeef000800000007c68fff2a")?;
+    let by_ref = hex::decode("b5ee9c7241010501004500024f90655e4ff111111111111111111111111111111111111111111111111111111111111111123039f0010202015d0304010912345678b0030008deadbeef000800000007d11983d6")?;
+    let normalized = hex::decode("fccb0bc7f2c49f2a86984a518b61a6175ab1ae2a61c64f16ad678d58e90b817e")?;
+
+    let cell = deserialize_boc_single(&inline)?;
+    assert_eq!(hex::encode(cell.repr_hash()), "e1f4bcdf8f4c6ea7896725e3236de9f8c41b19fbb744dcacf553af5748d897aa");
+    let message = ExternalMessage::load(&cell)?;
+    assert!(message.init.is_some());
+    assert_eq!(message.info.import_fee, Coins(12345));
+    assert_eq!(message.normalized_hash()?.as_slice(), normalized);
+
+    let cell = deserialize_boc_single(&by_ref)?;
+    assert_eq!(hex::encode(cell.repr_hash()), "7c8af2ee510f87147fd73bb04f86ce4d23393a66a66512d6429678624b25e363");
+    assert_eq!(ExternalMessage::load(&cell)?.normalized_hash()?.as_slice(), normalized);
+    Ok(())
+}
+
+#[test]
+fn test_deploy_message() -> Result<(), Box<dyn Error>> {
+    let code = CellBuilder::new().store_u32(0xdeadbeef)?.build()?;
+    let data = CellBuilder::new().store_u32(7)?.build()?;
+    let init = StateInit::new(code, data);
+    let dest = init.address(0)?;
+    assert_eq!(dest.to_string(), "0:11c451ffa44d553ec59c936a7dbfad642fab6fa1df93d3977876c6b6942ca283");
+    assert_eq!(dest.to_string().parse::<MsgAddressInt>()?, dest);
+
+    let body = CellBuilder::new().store_u64(42)?.build()?;
+    let message = ExternalMessage::new(dest, body).with_state_init(init);
+    let boc = message.to_boc()?;
+    assert_eq!(hex::encode(message.build()?.repr_hash()), "ee84fb759f81968d25c17bd18c747e2c0a10acf0ab715334878b20f42ad82589");
+    let cell = deserialize_boc_single(&boc)?;
+    assert_eq!(ExternalMessage::load(&cell)?, message);
+    Ok(())
+}
+
+#[test]
+fn test_shard_hashes() -> Result<(), Box<dyn Error>> {
+    let descr = |seq_no: u32| -> Result<_, CellError> {
+        let mut b = CellBuilder::new();
+        b.store_uint(4, 0xb)?.store_u32(seq_no)?.store_u32(1)?.store_u64(0)?.store_u64(0)?;
+        b.store_u256(&[seq_no as u8; 32])?.store_u256(&[0; 32])?.store_uint(8, 0)?;
+        b.store_u32(0)?.store_u64(0)?.store_u32(0)?.store_u32(1700000000)?;
+        b.store_bit(false)?.store_coins(seq_no as u128)?.store_bit(false)?.store_coins(0)?.store_bit(false)?;
+        b.build()
+    };
+    let mut left = CellBuilder::new();
+    left.store_bit(false)?.store_cell_data(&*descr(10)?)?;
+    let mut right = CellBuilder::new();
+    right.store_bit(false)?.store_cell_data(&*descr(11)?)?;
+    let mut tree = CellBuilder::new();
+    tree.store_bit(true)?.store_reference(left.build()?)?.store_reference(right.build()?)?;
+    // hml_long$10 n:(#<= 32) s:(n * Bit) with the whole workchain 0 key, followed by the leaf
+    let mut dict = CellBuilder::new();
+    dict.store_uint(2, 0b10)?.store_uint(6, 32)?.store_u32(0)?.store_reference(tree.build()?)?;
+    let mut root = CellBuilder::new();
+    root.store_maybe_reference(Some(dict.build()?))?;
+
+    let shards = ShardHashes::from_cell(&*root.build()?)?;
+    assert_eq!(shards.shards.iter().map(|s| s.shard).collect::<Vec<_>>(), vec![0x4000000000000000, 0xc000000000000000]);
+    let shard = shards.find(0, &[0xab; 32]).unwrap();
+    assert_eq!(shard.block_id().seqno, 11);
+    assert_eq!(shard.descr.fees_collected.as_ref().map(|fees| fees.grams), Some(Coins(11)));
+    assert_eq!(ShardDescr::from_cell(&*descr(10)?)?.split_merge_at, FutureSplitMerge::None);
+    assert!(shards.find(-1, &[0xab; 32]).is_none());
+    assert!(shard_contains(0x8000000000000000, 0x1234));
+    assert!(!shard_contains(0x6000000000000000, 0x8000000000000000));
+    Ok(())
+}
+
+#[test]
+fn test_block_transaction() -> Result<(), Box<dyn Error>> {
+    let empty = CellBuilder::new().build()?;
+    let transaction = CellBuilder::new().store_uint(4, 0b0111)?.store_u64(42)?.build()?;
+    // account_blocks with a single account and a single transaction, both keys as hml_long$10 labels
+    let mut account_block = CellBuilder::new();
+    account_block.store_uint(2, 0b10)?.store_uint(9, 256)?.store_u256(&[0x11; 32])?;
+    account_block.store_coins(0)?.store_bit(false)?;
+    account_block.store_uint(4, 0x5)?.store_u256(&[0x11; 32])?;
+    account_block.store_uint(2, 0b10)?.store_uint(7, 64)?.store_u64(1000)?;
+    account_block.store_coins(0)?.store_bit(false)?.store_reference(transaction.clone())?;
+    account_block.store_reference(empty.clone())?;
+    let mut account_blocks = CellBuilder::new();
+    account_blocks.store_maybe_reference(Some(account_block.build()?))?.store_coins(0)?.store_bit(false)?;
+    let block_with = |account_blocks: ArcCell| -> Result<_, CellError> {
+        let mut extra = CellBuilder::new();
+        extra.store_reference(empty.clone())?.store_reference(empty.clone())?.store_reference(account_blocks)?;
+        let mut block = CellBuilder::new();
+        block.store_u32(0x11ef55aa)?.store_u32(0)?;
+        for cell in [empty.clone(), empty.clone(), empty.clone(), extra.build()?] {
+            block.store_reference(cell)?;
+        }
+        block.build()
+    };
+    let block = block_with(account_blocks.build()?)?;
+
+    assert_eq!(block_transaction(&block, &[0x11; 32], 1000)?.map(|t| t.repr_hash()), Some(transaction.repr_hash()));
+    assert!(block_transaction(&block, &[0x11; 32], 999)?.is_none());
+    assert!(block_transaction(&block, &[0x22; 32], 1000)?.is_none());
+    assert_eq!(lookup_block_transaction(&block, &[0x11; 32], 999)?, ProvenTransaction::Nonexistent);
+    assert_eq!(lookup_block_transaction(&block, &[0x22; 32], 1000)?, ProvenTransaction::Nonexistent);
+    assert_eq!(block_hash(&block)?, block.repr_hash());
+    // a proof with the account blocks pruned says nothing about the transactions
+    let mut pruned = CellBuilder::new();
+    pruned.store_u8(1)?.store_u8(1)?.store_u256(&[0; 32])?.store_uint(16, 0)?;
+    let pruned = block_with(pruned.build_exotic()?)?;
+    assert_eq!(lookup_block_transaction(&pruned, &[0x11; 32], 1000)?, ProvenTransaction::Unknown);
+
+    // answers to getOneTransaction
+    use crate::tl::{common::{AccountId, BlockIdExt, Int256}, response::TransactionInfo};
+    use crate::types::LiteError;
+    let id = |block: &Cell| -> Result<_, CellError> {
+        Ok(BlockIdExt { workchain: 0, shard: 1 << 63, seqno: 1, root_hash: Int256(block_hash(block)?), file_hash: Int256([0; 32]) })
+    };
+    let info = |block: &Cell, transaction: Vec<u8>| -> Result<_, CellError> {
+        Ok(TransactionInfo { id: id(block)?, proof: block.to_boc(), transaction })
+    };
+    let account = AccountId { workchain: 0, id: Int256([0x11; 32]) };
+    assert!(info(&block, transaction.to_boc())?.verify(&id(&block)?, &account, 1000).is_ok());
+    assert!(info(&block, Vec::new())?.verify(&id(&block)?, &account, 999).is_ok());
+    // a transaction in the block can't be hidden by an empty answer
+    assert!(matches!(info(&block, Vec::new())?.verify(&id(&block)?, &account, 1000), Err(LiteError::HashMismatch)));
+    assert!(matches!(info(&block, empty.to_boc())?.verify(&id(&block)?, &account, 1000), Err(LiteError::HashMismatch)));
+    assert!(matches!(info(&pruned, Vec::new())?.verify(&id(&pruned)?, &account, 1000), Err(LiteError::Unverifiable(_))));
+    let unproven = TransactionInfo { proof: Vec::new(), ..info(&block, Vec::new())? };
+    assert!(matches!(unproven.verify(&id(&block)?, &account, 1000), Err(LiteError::Unverifiable(_))));
+    Ok(())
+}
+
+#[test]
+fn test_frozen_account() -> Result<(), Box<dyn Error>> {
+    let code = CellBuilder::new().store_u32(0xdeadbeef)?.build()?;
+    let data = CellBuilder::new().store_u32(7)?.build()?;
+    let init = StateInit::new(code.clone(), data);
+    let frozen = AccountStatus::Frozen(init.to_cell()?.repr_hash());
+    assert!(frozen.can_unfreeze_with(&init)?);
+    let other = StateInit::new(code, CellBuilder::new().store_u32(8)?.build()?);
+    assert!(!frozen.can_unfreeze_with(&other)?);
+    assert!(!AccountStatus::Active(init.clone()).can_unfreeze_with(&init)?);
+    Ok(())
+}
+
+#[test]
+fn test_hashmap_get() -> Result<(), Box<dyn Error>> {
+    // fork at the first bit: keys 0x12 and 0x13 on the left, the right subtree is pruned
+    let leaf = |value: u8| -> Result<_, CellError> {
+        CellBuilder::new().store_uint(2, 0b00)?.store_u8(value)?.build()
+    };
+    let mut left = CellBuilder::new();
+    left.store_uint(2, 0b10)?.store_uint(3, 6)?.store_uint(6, 0b001001)?;
+    left.store_reference(leaf(1)?)?.store_reference(leaf(2)?)?;
+    let mut pruned = CellBuilder::new();
+    pruned.store_u8(1)?.store_u8(1)?.store_u256(&[0; 32])?.store_uint(16, 0)?;
+    let mut root = CellBuilder::new();
+    root.store_uint(2, 0b00)?.store_reference(left.build()?)?.store_reference(pruned.build_exotic()?)?;
+    let root = root.build()?;
+
+    let value = |key: u8| -> Result<_, CellError> {
+        match hashmap_get(&root, &[key], 8)? {
+            HashmapEntry::Found(mut value) => Ok(Some(value.load_u8()?)),
+            HashmapEntry::Absent => Ok(None),
+            HashmapEntry::Pruned => Err(CellError::InvalidExotic("pruned")),
+        }
+    };
+    assert_eq!(value(0x12)?, Some(1));
+    assert_eq!(value(0x13)?, Some(2));
+    assert_eq!(value(0x22)?, None);
+    assert!(matches!(hashmap_get(&root, &[0x92], 8)?, HashmapEntry::Pruned));
+    Ok(())
+}
+
+#[test]
+fn test_shard_ident() {
+    let full = ShardIdent::full(0);
+    let (left, right) = full.split().unwrap();
+    assert_eq!((left.shard, right.shard), (0x4000000000000000, 0xc000000000000000));
+    assert_eq!((left.merge(), right.merge()), (Some(full), Some(full)));
+    assert_eq!(full.merge(), None);
+    assert!(full.is_parent_of(&left) && !left.is_parent_of(&full));
+    let (_, grandchild) = left.split().unwrap();
+    assert_eq!(grandchild.shard, 0x6000000000000000);
+    assert_eq!(grandchild.prefix_len(), 2);
+    assert!(full.is_ancestor_of(&grandchild) && !full.is_parent_of(&grandchild) && !right.is_ancestor_of(&grandchild));
+    assert!(grandchild.contains(&[0x7f; 32]) && !grandchild.contains(&[0x80; 32]));
+    assert!(grandchild.intersects(&full) && left.intersects(&grandchild) && !right.intersects(&grandchild));
+    assert_eq!(ShardIdent::from_i64(-1, i64::MIN), ShardIdent::full(-1));
+    assert_eq!(right.shard_i64(), -0x4000000000000000);
+    assert_eq!(ShardIdent::new(0, 1).split(), None);
+    assert_eq!(right.to_string(), "0:c000000000000000");
+}
+
+#[test]
+fn test_coins() -> Result<(), Box<dyn Error>> {
+    assert_eq!("1.5 TON".parse::<Coins>()?, Coins(1_500_000_000));
+    assert_eq!("0.000000001".parse::<Coins>()?, Coins(1));
+    assert_eq!("42".parse::<Coins>()?, Coins::from_ton(42));
+    assert!("1.0000000001".parse::<Coins>().is_err());
+    assert!("-1".parse::<Coins>().is_err());
+    assert_eq!(Coins(1_500_000_000).to_string(), "1.5 TON");
+    assert_eq!(Coins(1).to_string(), "0.000000001 TON");
+    assert_eq!(Coins::ZERO.to_string(), "0 TON");
+    assert_eq!(Coins::MAX.checked_add(Coins(1)), None);
+    assert_eq!(Coins(5).checked_sub(Coins(6)), None);
+    let mut b = CellBuilder::new();
+    Coins(12345).store(&mut b)?;
+    assert_eq!(Coins::load(&mut b.build()?.parser()?)?, Coins(12345));
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_coins_serde() -> Result<(), Box<dyn Error>> {
+    assert_eq!(serde_json::to_string(&Coins(1_500_000_000))?, r#""1500000000""#);
+    assert_eq!(serde_json::from_str::<Coins>(r#""1500000000""#)?, Coins(1_500_000_000));
+    assert_eq!(serde_json::from_str::<Coins>("12345")?, Coins(12345));
+    let max = Coins::MAX.nanotons().to_string();
+    assert_eq!(serde_json::from_str::<Coins>(&format!(r#""{}""#, max))?, Coins::MAX);
+    assert!(serde_json::from_str::<Coins>(&format!(r#""{}""#, Coins::MAX.nanotons() + 1)).is_err());
+    assert!(serde_json::from_str::<Coins>(&format!(r#""{}""#, u128::MAX)).is_err());
+    assert!(serde_json::from_str::<Coins>(r#""1.5""#).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_extra_currencies() -> Result<(), Box<dyn Error>> {
+    // a single currency 239 with an amount of 1000 in a 2 byte VarUInteger 32
+    let mut dict = CellBuilder::new();
+    dict.store_uint(2, 0b10)?.store_uint(6, 32)?.store_u32(239)?.store_uint(5, 2)?.store_uint(16, 1000)?;
+    let mut balance = CellBuilder::new();
+    balance.store_coins(5)?.store_maybe_reference(Some(dict.build()?))?;
+    let balance = CurrencyCollection::load(&mut balance.build()?.parser()?)?;
+    assert_eq!(balance.grams, Coins(5));
+    assert_eq!(balance.extra_currencies()?.into_iter().collect::<Vec<_>>(), vec![(239, 1000)]);
+    assert!(CurrencyCollection::default().extra_currencies()?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_storage_fee() -> Result<(), Box<dyn Error>> {
+    // mainnet prices of config param 18
+    let mut prices = CellBuilder::new();
+    prices.store_uint(2, 0b10)?.store_uint(6, 32)?.store_u32(0)?;
+    prices.store_u8(0xcc)?.store_u32(0)?.store_u64(1)?.store_u64(500)?.store_u64(1000)?.store_u64(500000)?;
+    let config = StorageConfig::load(&*prices.build()?)?;
+    assert_eq!(config.prices_at(1700000000).map(|p| p.cell_price_ps), Some(500));
+    // (1000 bits * 1 + 1 cell * 500) * one year / 2^16, rounded up
+    assert_eq!(config.calc_storage_fee(1000, 1, 31536000, false), Coins(721802));
+    assert_eq!(config.calc_storage_fee(1000, 1, 31536000, true), Coins(721801758));
+    assert_eq!(config.calc_storage_fee(0, 0, 31536000, false), Coins::ZERO);
+    Ok(())
+}
+
+#[test]
+fn test_gas_and_fwd_fees() -> Result<(), Box<dyn Error>> {
+    // mainnet basechain prices of config params 21 and 25
+    let mut gas = CellBuilder::new();
+    gas.store_u8(0xd1)?.store_u64(100)?.store_u64(40000)?;
+    gas.store_u8(0xde)?.store_u64(26214400)?.store_u64(1000000)?.store_u64(1000000)?.store_u64(10000)?;
+    gas.store_u64(10000000)?.store_u64(100000000)?.store_u64(1000000000)?;
+    let gas = GasLimitsPrices::load(&mut gas.build()?.parser()?)?;
+    assert_eq!((gas.flat_gas_limit, gas.gas_limit, gas.gas_credit), (100, 1000000, 10000));
+    // gas of a wallet v4 transfer
+    assert_eq!(gas.calc_gas_fee(3308), Coins(1323200));
+    assert_eq!(gas.calc_gas_fee(50), Coins(40000));
+
+    let mut fwd = CellBuilder::new();
+    fwd.store_u8(0xea)?.store_u64(400000)?.store_u64(26214400)?.store_u64(2621440000)?;
+    fwd.store_u32(98304)?.store_uint(16, 21845)?.store_uint(16, 21845)?;
+    let fwd = MsgForwardPrices::load(&mut fwd.build()?.parser()?)?;
+    assert_eq!(fwd.calc_fwd_fee(StorageUsed::default()), Coins(400000));
+    assert_eq!(fwd.calc_fwd_fee(StorageUsed { bits: 1000, cells: 2 }), Coins(880000));
+
+    let body = CellBuilder::new().store_u32(0)?.build()?;
+    let message = CellBuilder::new().store_u64(0)?.store_reference(body.clone())?.store_reference(body)?.build()?;
+    assert_eq!(StorageUsed::of_message(&message), StorageUsed { bits: 32, cells: 1 });
+
+    let config = FeeConfig {
+        storage: StorageConfig { prices: vec![] },
+        mc_gas: gas.clone(),
+        gas,
+        mc_fwd: fwd.clone(),
+        fwd,
+    };
+    let params = FeeParams { gas_used: 3308, out_msgs: vec![StorageUsed::default()], ..Default::default() };
+    let estimate = config.estimate(&params);
+    assert_eq!(estimate, FeeEstimate {
+        import_fee: Coins(400000),
+        storage_fee: Coins::ZERO,
+        gas_fee: Coins(1323200),
+        fwd_fee: Coins(400000),
+    });
+    assert_eq!(estimate.total(), Coins(2123200));
+    Ok(())
+}
+
+#[test]
+fn test_vm_stack() -> Result<(), Box<dyn Error>> {
+    use num_bigint::BigInt;
+
+    let cell = CellBuilder::new().store_u32(0xdeadbeef)?.build()?;
+    let total_supply = BigInt::from(10).pow(30);
+    let stack = VmStack::new(vec![
+        StackValue::int(-5),
+        StackValue::Int(total_supply.clone()),
+        StackValue::Int(-(BigInt::from(1) << 256u32)),
+        StackValue::Int((BigInt::from(1) << 256u32) - 1),
+        StackValue::Null,
+        StackValue::Nan,
+        StackValue::Cell(cell.clone()),
+        StackValue::Slice(StackSlice::full(cell.clone())),
+        StackValue::Tuple(vec![StackValue::int(1), StackValue::int(2), StackValue::Tuple(vec![StackValue::int(3)])]),
+        StackValue::Tuple(vec![]),
+    ]);
+    let parsed = VmStack::from_boc(&stack.to_boc()?)?;
+    assert_eq!(parsed, stack);
+    assert_eq!(parsed.values[0].as_i64(), Some(-5));
+    assert_eq!(parsed.values[1].as_int(), Some(&total_supply));
+    assert_eq!(parsed.values[1].as_u128(), Some(10u128.pow(30)));
+    assert_eq!(parsed.values[3].as_u128(), None);
+    assert_eq!(parsed.values[7].as_cell().map(|c| c.repr_hash()), Some(cell.repr_hash()));
+    assert!(VmStack::new(vec![StackValue::Int(BigInt::from(1) << 256u32)]).to_cell().is_err());
+    assert_eq!(VmStack::from_boc(&VmStack::default().to_boc()?)?, VmStack::default());
+
+    // continuations are kept as opaque cells, also inside nested tuples
+    let cont = CellBuilder::new().store_uint(2, 0)?.store_u32(0x12345678)?.store_reference(cell.clone())?.build()?;
+    let nested = VmStack::new(vec![
+        StackValue::Cont(cont.clone()),
+        StackValue::Tuple(vec![StackValue::Null, StackValue::Tuple(vec![StackValue::Cont(cont), StackValue::Null])]),
+    ]);
+    let parsed = VmStack::from_boc(&nested.to_boc()?)?;
+    assert_eq!(parsed, nested);
+    assert!(parsed.values[1].as_tuple().is_some_and(|items| items[0].is_null()));
+
+    // a single tinyint 7 on top of an empty stack, as serialized by the node
+    let tinyint = CellBuilder::new().store_uint(24, 1)?.store_reference(CellBuilder::new().build()?)?
+        .store_u8(1)?.store_u64(7)?.build()?;
+    assert_eq!(VmStack::load(&tinyint)?.values, vec![StackValue::int(7)]);
+    Ok(())
+}
+
+#[test]
+fn test_exit_code() {
+    assert_eq!(ExitCode::from(11), ExitCode::MethodNotFound);
+    assert_eq!(ExitCode::MethodNotFound.to_string(), "method not found (11)");
+    assert_eq!(ExitCode::from(-14), ExitCode::OutOfGasNegative);
+    assert_eq!(ExitCode::from(0xffff), ExitCode::Other(0xffff));
+    assert_eq!(ExitCode::from(0xffff).to_string(), "exit code 65535");
+    assert_eq!(i32::from(ExitCode::CellUnderflow), 9);
+    assert!(ExitCode::from(1).is_success());
+    assert!(!ExitCode::from(13).is_success());
+}
+
+#[test]
+fn test_token_metadata() -> Result<(), Box<dyn Error>> {
+    // on-chain dictionary with a single attribute, keyed with hml_long$10 n:(#<= 256)
+    let content = |name: &str, data: crate::cell::ArcCell| -> Result<_, CellError> {
+        let mut dict = CellBuilder::new();
+        dict.store_uint(2, 0b10)?.store_uint(9, 256)?.store_u256(&TokenMetadata::attribute_key(name))?.store_reference(data)?;
+        let mut root = CellBuilder::new();
+        root.store_u8(0x00)?.store_maybe_reference(Some(dict.build()?))?;
+        root.build()
+    };
+
+    // snake#00 split across two cells
+    let tail = CellBuilder::new().store_bits(b"Coin", 32)?.build()?;
+    let snake = CellBuilder::new().store_u8(0x00)?.store_bits(b"Test ", 40)?.store_reference(tail)?.build()?;
+    let metadata = TokenMetadata::load(&*content("name", snake)?)?;
+    assert_eq!(metadata.name.as_deref(), Some("Test Coin"));
+    assert_eq!(metadata.decimals(), 9);
+
+    // chunks#01 with a single chunk at index 0, keyed with hml_same$11 v:0 n:(#<= 32)
+    let chunk = CellBuilder::new().store_bits(b"6", 8)?.build()?;
+    let mut chunks = CellBuilder::new();
+    chunks.store_uint(3, 0b110)?.store_uint(6, 32)?.store_reference(chunk)?;
+    let chunked = CellBuilder::new().store_u8(0x01)?.store_maybe_reference(Some(chunks.build()?))?.build()?;
+    let metadata = TokenMetadata::load(&*content("decimals", chunked)?)?;
+    assert_eq!(metadata.decimals, Some(6));
+
+    let unknown = CellBuilder::new().store_u8(0x00)?.store_bits(b"x", 8)?.build()?;
+    let metadata = TokenMetadata::load(&*content("social", unknown)?)?;
+    assert_eq!(metadata.other.get(&TokenMetadata::attribute_key("social")).map(Vec::as_slice), Some(&b"x"[..]));
+
+    let off_chain = CellBuilder::new().store_u8(0x01)?.store_bits(b"ipfs://meta", 88)?.build()?;
+    let metadata = TokenMetadata::load(&off_chain)?;
+    assert_eq!(metadata, TokenMetadata { uri: Some("ipfs://meta".into()), ..Default::default() });
+    Ok(())
+}
+
+#[test]
+fn test_jetton_transfer() -> Result<(), Box<dyn Error>> {
+    let owner = MsgAddressInt::std(0, [1; 32]);
+    let transfer = JettonTransfer::new(1_000_000, MsgAddressInt::std(0, [2; 32]))
+        .with_query_id(7)
+        .with_response_destination(owner.clone())
+        .with_forward_payload(Coins(1), comment("thanks")?);
+    let cell = transfer.to_cell()?;
+    assert_eq!(cell.parser()?.load_u32()?, JettonTransfer::OP);
+    assert_eq!(JettonTransfer::load(&mut cell.parser()?)?, transfer);
+    let plain = JettonTransfer::new(5, owner.clone());
+    assert_eq!(JettonTransfer::load(&mut plain.to_cell()?.parser()?)?, plain);
+
+    let burn = JettonBurn::new(42).with_response_destination(owner.clone());
+    assert_eq!(JettonBurn::load(&mut burn.to_cell()?.parser()?)?, burn);
+    assert!(matches!(JettonBurn::load(&mut cell.parser()?), Err(CellError::UnexpectedTag(0x0f8a7ea5))));
+
+    // transfer_notification with the comment stored by reference
+    let mut notification = CellBuilder::new();
+    notification.store_u32(JettonTransferNotification::OP)?.store_u64(7)?.store_coins(1_000_000)?;
+    owner.store(&mut notification)?;
+    notification.store_bit(true)?.store_reference(comment("thanks")?)?;
+    let notification = JettonTransferNotification::from_cell(&*notification.build()?)?;
+    assert_eq!(notification.sender, owner);
+    assert_eq!(notification.forward_payload, comment("thanks")?);
+
+    // long comments continue in references
+    let long = comment(&"x".repeat(300))?;
+    assert_eq!((long.bit_len(), long.references()[0].bit_len()), ((4 + 123) * 8, 127 * 8));
+    assert_eq!(long.references()[0].references()[0].bit_len(), 50 * 8);
+    Ok(())
+}
+
+#[test]
+fn test_nft_transfer() -> Result<(), Box<dyn Error>> {
+    let owner = MsgAddressInt::std(0, [1; 32]);
+    let sale = MsgAddressInt::std(0, [3; 32]);
+    let transfer = NftTransfer::new(sale).with_response_destination(owner.clone()).with_forward_payload(Coins(10_000_000), comment("sale")?);
+    let cell = transfer.to_cell()?;
+    assert_eq!(cell.parser()?.load_u32()?, NftTransfer::OP);
+    assert_eq!(NftTransfer::load(&mut cell.parser()?)?, transfer);
+
+    let mut assigned = CellBuilder::new();
+    assigned.store_u32(NftOwnershipAssigned::OP)?.store_u64(0)?;
+    owner.store(&mut assigned)?;
+    assigned.store_bit(false)?.store_cell_data(&*comment("sale")?)?;
+    let assigned = NftOwnershipAssigned::from_cell(&*assigned.build()?)?;
+    assert_eq!(assigned.prev_owner, Some(owner));
+    assert_eq!(assigned.forward_payload, comment("sale")?);
+
+    let cancel = NftSaleMessage::Cancel { query_id: 5 };
+    assert_eq!(NftSaleMessage::from_cell(&*cancel.to_cell()?)?, cancel);
+    assert!(NftSaleMessage::from_cell(&cell).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_build_hashmap() -> Result<(), Box<dyn Error>> {
+    let value = |n: u32| CellBuilder::new().store_u32(n)?.build();
+    let keys: [u32; 5] = [0, 1, 7, 0x80000000, 0xffffffff];
+    let entries = keys.iter().map(|key| Ok((key.to_be_bytes().to_vec(), value(*key)?))).collect::<Result<Vec<_>, CellError>>()?;
+    let root = build_hashmap(&entries, 32)?.unwrap();
+    let loaded = hashmap_entries(&root, 32)?;
+    assert_eq!(loaded.len(), keys.len());
+    for ((key, mut slice), expected) in loaded.into_iter().zip(keys) {
+        assert_eq!(key, expected.to_be_bytes());
+        assert_eq!(slice.load_u32()?, expected);
+        assert!(slice.is_empty());
+    }
+    assert!(matches!(hashmap_get(&root, &7u32.to_be_bytes(), 32)?, HashmapEntry::Found(_)));
+    assert!(matches!(hashmap_get(&root, &8u32.to_be_bytes(), 32)?, HashmapEntry::Absent));
+
+    let single = build_hashmap(&[(vec![5], value(5)?)], 8)?.unwrap();
+    assert_eq!(hashmap_entries(&single, 8)?.len(), 1);
+    assert!(build_hashmap(&[], 8)?.is_none());
+    assert!(matches!(build_hashmap(&[(vec![5], value(5)?)], 16), Err(CellError::Underflow)));
+    Ok(())
+}
+
+#[test]
+fn test_validator_set() -> Result<(), Box<dyn Error>> {
+    let descr = |key: u8, weight: u64| CellBuilder::new()
+        .store_u8(0x53)?.store_u32(0x8e81278a)?.store_u256(&[key; 32])?.store_u64(weight)?
+        .build();
+    let entries = vec![(0u16.to_be_bytes().to_vec(), descr(1, 10)?), (1u16.to_be_bytes().to_vec(), descr(2, 20)?)];
+    let list = build_hashmap(&entries, 16)?.unwrap();
+    let header = |builder: &mut CellBuilder, tag: u8| -> Result<(), CellError> {
+        builder.store_u8(tag)?.store_u32(1000)?.store_u32(2000)?.store_uint(16, 2)?.store_uint(16, 1)?;
+        Ok(())
+    };
+
+    // `validators#11` keeps the non-empty hashmap inline
+    let mut builder = CellBuilder::new();
+    header(&mut builder, 0x11)?;
+    let set = ValidatorSet::load(&*builder.store_cell_data(&list)?.build()?)?;
+    assert_eq!((set.utime_since, set.utime_until, set.total, set.main), (1000, 2000, 2, 1));
+    assert_eq!(set.total_weight, 30);
+    assert_eq!(set.list.iter().map(|v| (v.public_key[0], v.weight)).collect::<Vec<_>>(), [(1, 10), (2, 20)]);
+
+    let mut builder = CellBuilder::new();
+    header(&mut builder, 0x12)?;
+    builder.store_u64(25)?.store_maybe_reference(Some(list))?;
+    let set = ValidatorSet::load(&*builder.build()?)?;
+    assert_eq!(set.total_weight, 25);
+    assert_eq!(set.list.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_comment() -> Result<(), Box<dyn Error>> {
+    // 'ü' is split between the first cell and the next one
+    let text = format!("{}ü{}", "x".repeat(122), "y".repeat(200));
+    let cell = comment(&text)?;
+    assert_eq!(cell.bit_len(), 127 * 8);
+    let mut slice = cell.parser()?;
+    assert_eq!(Comment::load(&mut slice)?, Comment::Text(text));
+    assert_eq!((slice.remaining_bits(), slice.remaining_refs()), (0, 0));
+
+    let encrypted = Comment::Encrypted(vec![7; 200]);
+    let cell = encrypted.to_cell()?;
+    assert_eq!(cell.parser()?.load_u32()?, Comment::ENCRYPTED_OP);
+    assert_eq!(Comment::from_cell(&cell)?, encrypted);
+
+    let transfer = NftSaleMessage::Buy { query_id: 1 }.to_cell()?;
+    assert!(matches!(Comment::from_cell(&transfer), Err(CellError::UnexpectedTag(2))));
+    Ok(())
+}
+
+#[test]
+fn test_transaction_summary() -> Result<(), Box<dyn Error>> {
+    // gas_used:(VarUInteger 7) = 1000, gas_limit = 0, no gas_credit, mode, exit_code = 0, no exit_arg, vm_steps
+    let mut details = CellBuilder::new();
+    details.store_uint(3, 2)?.store_uint(16, 1000)?.store_uint(3, 0)?.store_bit(false)?.store_u8(0)?;
+    details.store_u32(0)?.store_bit(false)?.store_u32(42)?.store_u256(&[0; 32])?.store_u256(&[0; 32])?;
+    let mut action = CellBuilder::new();
+    action.store_bit(true)?.store_bit(true)?.store_bit(false)?.store_bit(false)?;
+    action.store_bit(true)?.store_coins(300)?.store_bit(false)?.store_u32(0)?.store_bit(false)?;
+    action.store_uint(16, 1)?.store_uint(16, 0)?.store_uint(16, 0)?.store_uint(16, 1)?.store_u256(&[0; 32])?;
+    action.store_uint(3, 1)?.store_u8(1)?.store_uint(3, 1)?.store_u8(100)?;
+    // trans_ord with storage, credit and compute phases and an action phase
+    let mut ordinary = CellBuilder::new();
+    ordinary.store_uint(4, 0b0000)?.store_bit(false)?;
+    ordinary.store_bit(true)?.store_coins(5)?.store_bit(false)?.store_bit(false)?;
+    ordinary.store_bit(true)?.store_bit(false)?.store_coins(1_000_000)?.store_bit(false)?;
+    ordinary.store_bit(true)?.store_bit(true)?.store_bit(false)?.store_bit(false)?.store_coins(400)?.store_reference(details.build()?)?;
+    ordinary.store_bit(true)?.store_reference(action.build()?)?;
+    ordinary.store_bit(false)?.store_bit(false)?.store_bit(false)?;
+    let ordinary = ordinary.build()?;
+    let summary = TransactionSummary::load(&ordinary, Coins(705))?;
+    assert_eq!(summary.kind, TransactionKind::Ordinary);
+    assert_eq!(summary.storage.map(|storage| storage.fees_collected), Some(Coins(5)));
+    assert_eq!(summary.credit, Some(Coins(1_000_000)));
+    assert_eq!((summary.exit_code(), summary.gas_used()), (Some(ExitCode::Success), Some(1000)));
+    let action = summary.action.unwrap();
+    assert_eq!((action.total_fwd_fees, action.msgs_created, action.tot_msg_size), (Some(Coins(300)), 1, StorageUsed { bits: 100, cells: 1 }));
+    assert!(summary.success() && summary.bounce.is_none());
+
+    // compute phase skipped for lack of state, then bounced
+    let mut skipped = CellBuilder::new();
+    skipped.store_uint(4, 0b0000)?.store_bit(false)?.store_bit(false)?.store_bit(false)?;
+    skipped.store_bit(false)?.store_uint(2, 0b00)?.store_bit(false)?.store_bit(true)?;
+    skipped.store_bit(true)?.store_bit(true)?.store_uint(3, 0)?.store_uint(3, 0)?.store_coins(10)?.store_coins(20)?;
+    skipped.store_bit(false)?;
+    let skipped = skipped.build()?;
+    let summary = TransactionSummary::load(&skipped, Coins(0))?;
+    assert_eq!(summary.compute, Some(ComputePhase::Skipped(ComputeSkipReason::NoState)));
+    assert!(matches!(summary.bounce, Some(BouncePhase::Ok { msg_fees: Coins(10), fwd_fees: Coins(20), .. })));
+    assert!(!summary.success() && summary.exit_code().is_none());
+
+    let tock = CellBuilder::new().store_uint(3, 0b001)?.store_bit(true)?.build()?;
+    assert!(TransactionSummary::load(&tock, Coins(0)).is_err());
+    let storage = CellBuilder::new().store_uint(4, 0b0001)?.store_coins(7)?.store_bit(false)?.store_uint(2, 0b10)?.build()?;
+    let summary = TransactionSummary::load(&storage, Coins(7))?;
+    assert_eq!(summary.kind, TransactionKind::Storage);
+    assert_eq!(summary.storage.map(|storage| storage.status_change), Some(AccStatusChange::Frozen));
+    Ok(())
+}
+
+#[test]
+fn test_message() -> Result<(), Box<dyn Error>> {
+    let wallet = MsgAddressInt::std(0, [0x11; 32]);
+    let external = ExternalMessage::new(wallet.clone(), comment("hi")?).build()?;
+    let message = Message::load(&external)?;
+    assert_eq!((message.src(), message.dest()), (MsgAddress::None, MsgAddress::Int(wallet.clone())));
+    assert_eq!((message.created_lt(), message.value(), message.bounce()), (None, Coins::ZERO, false));
+    assert_eq!(Comment::from_cell(&message.body)?, Comment::Text("hi".to_owned()));
+
+    // int_msg_info with bounce set, from the wallet to a contract
+    let contract = MsgAddressInt::std(-1, [0x22; 32]);
+    let mut internal = CellBuilder::new();
+    internal.store_uint(4, 0b0010)?;
+    wallet.store(&mut internal)?;
+    contract.store(&mut internal)?;
+    internal.store_coins(1_000_000)?.store_bit(false)?.store_coins(0)?.store_coins(5)?.store_u64(100)?.store_u32(7)?;
+    internal.store_bit(false)?.store_bit(true)?.store_reference(comment("hi")?)?;
+    let internal = internal.build()?;
+    let message = Message::load(&internal)?;
+    assert!(message.is_internal() && message.bounce() && !message.bounced());
+    assert_eq!((message.src().as_int(), message.dest().as_int()), (Some(&wallet), Some(&contract)));
+    assert_eq!((message.value(), message.fwd_fee(), message.created_lt()), (Coins(1_000_000), Coins(5), Some(100)));
+    assert_eq!(message.body, comment("hi")?);
+
+    // ext_out_msg_info to addr_extern, e.g. an event log
+    let mut log = CellBuilder::new();
+    log.store_uint(2, 0b11)?;
+    contract.store(&mut log)?;
+    MsgAddressExt::Extern { len: 8, address: vec![0xab] }.store(&mut log)?;
+    log.store_u64(101)?.store_u32(7)?.store_bit(false)?.store_bit(false)?.store_u32(0xdead)?;
+    let log = log.build()?;
+    let message = Message::load(&log)?;
+    assert_eq!(message.dest(), MsgAddress::Extern { len: 8, address: vec![0xab] });
+    assert_eq!(message.dest().to_string(), "ext:ab");
+    assert_eq!(message.body.parser()?.load_u32()?, 0xdead);
+    Ok(())
+}
+
+#[test]
+fn test_value_flow() -> Result<(), Box<dyn Error>> {
+    let amounts = |values: [u128; 4]| -> Result<_, CellError> {
+        let mut builder = CellBuilder::new();
+        for value in values {
+            builder.store_coins(value)?.store_bit(false)?;
+        }
+        builder.build()
+    };
+    let mut value_flow = CellBuilder::new();
+    value_flow.store_u32(0x3ebf98b7)?.store_reference(amounts([1000, 1200, 300, 100])?)?;
+    value_flow.store_coins(50)?.store_bit(false)?.store_coins(25)?.store_bit(false)?;
+    value_flow.store_reference(amounts([40, 0, 1_700_000_000, 0])?)?;
+    let empty = CellBuilder::new().build()?;
+    let mut block = CellBuilder::new();
+    block.store_u32(0x11ef55aa)?.store_u32(0)?;
+    block.store_reference(empty.clone())?.store_reference(value_flow.build()?)?.store_reference(empty)?;
+    let block = block.build()?;
+
+    let value_flow = ValueFlow::from_proof(&block)?;
+    assert_eq!((value_flow.from_prev_blk.grams, value_flow.to_next_blk.grams), (Coins(1000), Coins(1200)));
+    assert_eq!((value_flow.imported.grams, value_flow.exported.grams), (Coins(300), Coins(100)));
+    assert_eq!((value_flow.fees_collected.grams, value_flow.burned.grams), (Coins(50), Coins(25)));
+    assert_eq!((value_flow.fees_imported.grams, value_flow.created.grams), (Coins(40), Coins(1_700_000_000)));
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use crate::cell::{ArcCell, Cell, CellError};
+
+use super::{hashmap_e_entries, CurrencyCollection, ExternalMessage, Message, TransactionSummary};
+
+/// ```tlb
+/// acc_state_uninit$00 = AccountStatus;
+/// acc_state_frozen$01 = AccountStatus;
+/// acc_state_active$10 = AccountStatus;
+/// acc_state_nonexist$11 = AccountStatus;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatusTag {
+    Uninit,
+    Frozen,
+    Active,
+    NonExist,
+}
+
+impl AccountStatusTag {
+    fn from_bits(bits: u64) -> Self {
+        match bits {
+            0b00 => Self::Uninit,
+            0b01 => Self::Frozen,
+            0b10 => Self::Active,
+            _ => Self::NonExist,
+        }
+    }
+}
+
+/// Transaction with its messages, `state_update` and `description` are kept as cells.
+///
+/// ```tlb
+/// transaction$0111 account_addr:bits256 lt:uint64 prev_trans_hash:bits256 prev_trans_lt:uint64 now:uint32
+///   outmsg_cnt:uint15 orig_status:AccountStatus end_status:AccountStatus
+///   ^[ in_msg:(Maybe ^(Message Any)) out_msgs:(HashmapE 15 ^(Message Any)) ]
+///   total_fees:CurrencyCollection state_update:^(HASH_UPDATE Account)
+///   description:^TransactionDescr = Transaction;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub hash: [u8; 32],
+    pub account_addr: [u8; 32],
+    pub lt: u64,
+    pub prev_trans_hash: [u8; 32],
+    pub prev_trans_lt: u64,
+    pub now: u32,
+    pub orig_status: AccountStatusTag,
+    pub end_status: AccountStatusTag,
+    pub in_msg: Option<ArcCell>,
+    /// Outbound messages in the order they were created
+    pub out_msgs: Vec<ArcCell>,
+    pub total_fees: CurrencyCollection,
+    pub state_update: ArcCell,
+    pub description: ArcCell,
+}
+
+impl Transaction {
+    pub fn load(cell: &Cell) -> Result<Self, CellError> {
+        let mut slice = cell.parser()?;
+        let tag = slice.load_uint(4)?;
+        if tag != 0b0111 {
+            return Err(CellError::UnexpectedTag(tag));
+        }
+        let account_addr = slice.load_u256()?;
+        let lt = slice.load_u64()?;
+        let prev_trans_hash = slice.load_u256()?;
+        let prev_trans_lt = slice.load_u64()?;
+        let now = slice.load_u32()?;
+        slice.skip_bits(15)?;
+        let orig_status = AccountStatusTag::from_bits(slice.load_uint(2)?);
+        let end_status = AccountStatusTag::from_bits(slice.load_uint(2)?);
+        let mut messages = slice.load_ref()?.parser()?;
+        let in_msg = messages.load_maybe_ref()?.cloned();
+        let out_msgs = hashmap_e_entries(&mut messages, 15)?
+            .into_iter()
+            .map(|(_, mut value)| value.load_ref().cloned())
+            .collect::<Result<_, _>>()?;
+        let total_fees = CurrencyCollection::load(&mut slice)?;
+        let state_update = slice.load_ref()?.clone();
+        let description = slice.load_ref()?.clone();
+        Ok(Self {
+            hash: cell.repr_hash(),
+            account_addr, lt, prev_trans_hash, prev_trans_lt, now, orig_status, end_status,
+            in_msg, out_msgs, total_fees, state_update, description,
+        })
+    }
+
+    /// Kind, phases and fees of the transaction, decoded from `description`.
+    pub fn summary(&self) -> Result<TransactionSummary, CellError> {
+        TransactionSummary::load(&self.description, self.total_fees.grams)
+    }
+
+    /// Decoded inbound message, `None` for transactions without one, e.g. tick-tock transactions.
+    pub fn in_message(&self) -> Result<Option<Message>, CellError> {
+        self.in_msg.as_deref().map(Message::load).transpose()
+    }
+
+    /// Decoded outbound messages in the order they were created.
+    pub fn out_messages(&self) -> Result<Vec<Message>, CellError> {
+        self.out_msgs.iter().map(|message| Message::load(message)).collect()
+    }
+
+    /// Whether the inbound message has the given hash or, for external messages, normalized hash.
+    pub fn in_msg_matches(&self, hash: &[u8; 32]) -> bool {
+        let Some(in_msg) = &self.in_msg else {
+            return false;
+        };
+        if in_msg.repr_hash() == *hash {
+            return true;
+        }
+        ExternalMessage::load(in_msg)
+            .and_then(|message| message.normalized_hash())
+            .is_ok_and(|normalized| normalized == *hash)
+    }
+}
@@ -0,0 +1,94 @@
+use sha2::{Digest, Sha256};
+
+use crate::cell::{Cell, CellError, CellSlice};
+
+use super::{hashmap_e_entries, hashmap_slice_entries};
+
+/// TL id of `pub.ed25519 key:int256 = PublicKey`
+const PUB_ED25519: u32 = 0x4813b4c6;
+
+/// ```tlb
+/// ed25519_pubkey#8e81278a pubkey:bits256 = SigPubKey;
+/// validator#53 public_key:SigPubKey weight:uint64 = ValidatorDescr;
+/// validator_addr#73 public_key:SigPubKey weight:uint64 adnl_addr:bits256 = ValidatorDescr;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorDescr {
+    pub public_key: [u8; 32],
+    pub weight: u64,
+    pub adnl_addr: Option<[u8; 32]>,
+}
+
+impl ValidatorDescr {
+    pub fn load(slice: &mut CellSlice) -> Result<Self, CellError> {
+        let tag = slice.load_u8()?;
+        if tag != 0x53 && tag != 0x73 {
+            return Err(CellError::UnexpectedTag(tag as u64));
+        }
+        let key_tag = slice.load_u32()?;
+        if key_tag != 0x8e81278a {
+            return Err(CellError::UnexpectedTag(key_tag as u64));
+        }
+        let public_key = slice.load_u256()?;
+        let weight = slice.load_u64()?;
+        let adnl_addr = if tag == 0x73 { Some(slice.load_u256()?) } else { None };
+        Ok(Self { public_key, weight, adnl_addr })
+    }
+
+    /// Short node id of the validator, as used in `liteServer.signature`.
+    pub fn node_id_short(&self) -> [u8; 32] {
+        node_id_short(&self.public_key)
+    }
+}
+
+/// Short id of an ed25519 `public_key`, hash of its TL-serialized `pub.ed25519`.
+pub fn node_id_short(public_key: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(PUB_ED25519.to_le_bytes());
+    hasher.update(public_key);
+    hasher.finalize().into()
+}
+
+/// Validator set from config params 32 (previous), 34 (current) or 36 (next).
+///
+/// ```tlb
+/// validators#11 utime_since:uint32 utime_until:uint32 total:(## 16) main:(## 16) { main <= total } { main >= 1 }
+///   list:(Hashmap 16 ValidatorDescr) = ValidatorSet;
+/// validators_ext#12 utime_since:uint32 utime_until:uint32 total:(## 16) main:(## 16) { main <= total } { main >= 1 }
+///   total_weight:uint64 list:(HashmapE 16 ValidatorDescr) = ValidatorSet;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorSet {
+    pub utime_since: u32,
+    pub utime_until: u32,
+    pub total: u16,
+    pub main: u16,
+    pub total_weight: u64,
+    /// Validators ordered by their index in the set
+    pub list: Vec<ValidatorDescr>,
+}
+
+impl ValidatorSet {
+    pub fn load(cell: &Cell) -> Result<Self, CellError> {
+        let mut slice = cell.parser()?;
+        let tag = slice.load_u8()?;
+        let utime_since = slice.load_u32()?;
+        let utime_until = slice.load_u32()?;
+        let total = slice.load_uint(16)? as u16;
+        let main = slice.load_uint(16)? as u16;
+        let (total_weight, entries) = match tag {
+            0x11 => (None, hashmap_slice_entries(slice, 16)?),
+            0x12 => (Some(slice.load_u64()?), hashmap_e_entries(&mut slice, 16)?),
+            _ => return Err(CellError::UnexpectedTag(tag as u64)),
+        };
+        let list = entries.into_iter()
+            .map(|(_, mut value)| ValidatorDescr::load(&mut value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let total_weight = total_weight.unwrap_or_else(|| list.iter().map(|v| v.weight).sum());
+        Ok(Self { utime_since, utime_until, total, main, total_weight, list })
+    }
+
+    pub fn find(&self, public_key: &[u8; 32]) -> Option<&ValidatorDescr> {
+        self.list.iter().find(|v| v.public_key == *public_key)
+    }
+}
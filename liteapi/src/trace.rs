@@ -0,0 +1,255 @@
+//! Tracing of message chains started by a transaction.
+//!
+//! Every internal message sent by a transaction is followed to the transaction which processed it on the
+//! destination account, possibly in another shard, and so on until the chain ends. Explorers call the
+//! resulting tree a trace.
+
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+
+use crate::cell::{ArcCell, Cell, CellError};
+use crate::client::LiteClient;
+use crate::tl::common::{AccountId, BlockIdExt, Int256};
+use crate::tl::response::TransactionInfo;
+use crate::tlb::{IntMsgInfo, MsgAddressInt, Transaction};
+use crate::types::LiteError;
+
+type Result<T> = std::result::Result<T, LiteError>;
+
+/// Transaction in a trace with the messages it sent.
+#[derive(Debug, Clone)]
+pub struct TraceNode {
+    /// Block containing the transaction
+    pub block: BlockIdExt,
+    pub transaction: Transaction,
+    /// Outbound messages in the order of `transaction.out_msgs`
+    pub children: Vec<TraceMessage>,
+}
+
+impl TraceNode {
+    /// Number of transactions in this subtree, including this one.
+    pub fn transaction_count(&self) -> usize {
+        1 + self.children.iter().filter_map(|c| c.next.as_ref()).map(|n| n.transaction_count()).sum::<usize>()
+    }
+}
+
+/// Outbound message of a traced transaction.
+#[derive(Debug, Clone)]
+pub struct TraceMessage {
+    pub message: ArcCell,
+    /// Transaction which processed the message, `None` for external messages, messages to
+    /// non-standard addresses, and messages which weren't processed before the timeout
+    pub next: Option<TraceNode>,
+}
+
+/// Lookup of the transaction which processed a message, implemented by [`LiteClient`].
+pub trait MessageFinder: Send {
+    /// Same as [`LiteClient::find_transaction_by_message`].
+    fn find_transaction_by_message(&mut self, account: AccountId, message_hash: Int256, after_lt: u64, timeout: Duration) -> BoxFuture<'_, Result<Option<TransactionInfo>>>;
+}
+
+impl MessageFinder for LiteClient {
+    fn find_transaction_by_message(&mut self, account: AccountId, message_hash: Int256, after_lt: u64, timeout: Duration) -> BoxFuture<'_, Result<Option<TransactionInfo>>> {
+        Box::pin(LiteClient::find_transaction_by_message(self, account, message_hash, after_lt, timeout))
+    }
+}
+
+/// Builder of traces, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Tracer {
+    timeout: Duration,
+    max_transactions: usize,
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Self { timeout: Duration::ZERO, max_transactions: 1000 }
+    }
+
+    /// How long to wait for each message which isn't processed yet, by default they are not waited for.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Stop following messages once the trace has `max_transactions` transactions, 1000 by default.
+    pub fn with_max_transactions(mut self, max_transactions: usize) -> Self {
+        self.max_transactions = max_transactions;
+        self
+    }
+
+    /// Build the trace starting from `start`, e.g. found with [`LiteClient::find_transaction_by_message`].
+    ///
+    /// Destination transactions are found in the history of the destination accounts, after the
+    /// logical time the message was created at.
+    pub async fn trace<F: MessageFinder>(&self, client: &mut F, start: TransactionInfo) -> Result<TraceNode> {
+        let mut remaining = self.max_transactions;
+        self.follow(client, start, &mut remaining).await
+    }
+
+    fn follow<'a, F: MessageFinder>(&'a self, client: &'a mut F, info: TransactionInfo, remaining: &'a mut usize) -> BoxFuture<'a, Result<TraceNode>> {
+        Box::pin(async move {
+            *remaining = remaining.saturating_sub(1);
+            let transaction = Transaction::load(&*Cell::from_boc(&info.transaction)?)?;
+            let mut children = Vec::with_capacity(transaction.out_msgs.len());
+            for message in &transaction.out_msgs {
+                let next = match destination(message)? {
+                    Some((account, created_lt)) if *remaining > 0 => {
+                        let hash = Int256(message.repr_hash());
+                        match client.find_transaction_by_message(account, hash, created_lt, self.timeout).await? {
+                            Some(next) => Some(self.follow(client, next, remaining).await?),
+                            None => None,
+                        }
+                    }
+                    _ => None,
+                };
+                children.push(TraceMessage { message: message.clone(), next });
+            }
+            Ok(TraceNode { block: info.id, transaction, children })
+        })
+    }
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Destination account and creation time of an internal message, `None` for other messages.
+fn destination(message: &Cell) -> Result<Option<(AccountId, u64)>> {
+    let info = match IntMsgInfo::load(&mut message.parser()?) {
+        Ok(info) => info,
+        Err(CellError::UnexpectedTag(_)) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    match info.dest {
+        MsgAddressInt::Std { workchain, address, .. } => {
+            Ok(Some((AccountId { workchain: workchain as i32, id: Int256(address) }, info.created_lt)))
+        }
+        MsgAddressInt::Var { .. } => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures::future;
+
+    use super::*;
+    use crate::cell::CellBuilder;
+    use crate::tlb::build_hashmap;
+
+    /// Transactions processing messages by the destination and the message hash, recording the lookups.
+    #[derive(Default)]
+    struct Finder {
+        transactions: HashMap<([u8; 32], [u8; 32]), TransactionInfo>,
+        lookups: Vec<(AccountId, u64)>,
+    }
+
+    impl MessageFinder for Finder {
+        fn find_transaction_by_message(&mut self, account: AccountId, message_hash: Int256, after_lt: u64, _: Duration) -> BoxFuture<'_, Result<Option<TransactionInfo>>> {
+            let found = self.transactions.get(&(account.id.0, message_hash.0)).cloned();
+            self.lookups.push((account, after_lt));
+            Box::pin(future::ok(found))
+        }
+    }
+
+    fn account(n: u8) -> AccountId {
+        AccountId { workchain: 0, id: Int256([n; 32]) }
+    }
+
+    /// Internal message from `src` to `dest` created at `created_lt`.
+    fn internal(src: u8, dest: u8, bounced: bool, created_lt: u64) -> std::result::Result<ArcCell, CellError> {
+        let mut message = CellBuilder::new();
+        message.store_bit(false)?.store_bit(true)?.store_bit(true)?.store_bit(bounced)?;
+        MsgAddressInt::std(0, [src; 32]).store(&mut message)?;
+        MsgAddressInt::std(0, [dest; 32]).store(&mut message)?;
+        message.store_coins(1000)?.store_bit(false)?.store_coins(0)?.store_coins(0)?.store_u64(created_lt)?.store_u32(0)?;
+        message.store_bit(false)?.store_bit(false)?;
+        message.build()
+    }
+
+    /// External outbound message of `src`.
+    fn external(src: u8) -> std::result::Result<ArcCell, CellError> {
+        let mut message = CellBuilder::new();
+        message.store_uint(2, 0b11)?;
+        MsgAddressInt::std(0, [src; 32]).store(&mut message)?;
+        message.store_uint(2, 0b00)?.store_u64(0)?.store_u32(0)?.store_bit(false)?.store_bit(false)?;
+        message.build()
+    }
+
+    /// Transaction of `account` at `lt` in the block with seqno `lt`.
+    fn transaction(account: u8, lt: u64, in_msg: Option<ArcCell>, out_msgs: &[ArcCell]) -> std::result::Result<TransactionInfo, CellError> {
+        let out_msgs: Vec<_> = out_msgs.iter().enumerate()
+            .map(|(i, message)| Ok((((i as u16) << 1).to_be_bytes().to_vec(), CellBuilder::new().store_reference(message.clone())?.build()?)))
+            .collect::<std::result::Result<_, CellError>>()?;
+        let mut messages = CellBuilder::new();
+        messages.store_maybe_reference(in_msg)?.store_maybe_reference(build_hashmap(&out_msgs, 15)?)?;
+        let empty = CellBuilder::new().build()?;
+        let mut transaction = CellBuilder::new();
+        transaction.store_uint(4, 0b0111)?.store_u256(&[account; 32])?.store_u64(lt)?;
+        transaction.store_u256(&[0; 32])?.store_u64(0)?.store_u32(0)?.store_uint(15, 0)?.store_uint(4, 0)?;
+        transaction.store_reference(messages.build()?)?.store_coins(0)?.store_bit(false)?;
+        transaction.store_reference(empty.clone())?.store_reference(empty)?;
+        let id = BlockIdExt { workchain: 0, shard: 1 << 63, seqno: lt as u32, root_hash: Int256([0; 32]), file_hash: Int256([0; 32]) };
+        Ok(TransactionInfo { id, proof: Vec::new(), transaction: transaction.build()?.to_boc() })
+    }
+
+    /// Account 1 sends a message to account 2 and an external message. Account 2 bounces the message
+    /// and sends another one to account 3, which never processes it.
+    fn bounced_trace() -> std::result::Result<(TransactionInfo, Finder), CellError> {
+        let to_second = internal(1, 2, false, 11)?;
+        let bounced = internal(2, 1, true, 21)?;
+        let to_third = internal(2, 3, false, 22)?;
+        let start = transaction(1, 10, None, &[to_second.clone(), external(1)?])?;
+        let second = transaction(2, 20, Some(to_second.clone()), &[bounced.clone(), to_third])?;
+        let returned = transaction(1, 30, Some(bounced.clone()), &[])?;
+        let mut finder = Finder::default();
+        finder.transactions.insert(([2; 32], to_second.repr_hash()), second);
+        finder.transactions.insert(([1; 32], bounced.repr_hash()), returned);
+        Ok((start, finder))
+    }
+
+    #[tokio::test]
+    async fn test_trace() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let (start, mut finder) = bounced_trace()?;
+        let trace = Tracer::new().trace(&mut finder, start).await?;
+        assert_eq!(trace.transaction_count(), 3);
+        // messages are looked up on their destination after their creation time
+        assert_eq!(finder.lookups, [(account(2), 11), (account(1), 21), (account(3), 22)]);
+
+        let [to_second, external] = &trace.children[..] else { panic!("expected two messages") };
+        assert!(external.next.is_none());
+        let second = to_second.next.as_ref().unwrap();
+        assert_eq!((second.block.seqno, second.transaction.account_addr), (20, [2; 32]));
+        let [bounced, to_third] = &second.children[..] else { panic!("expected two messages") };
+        assert!(IntMsgInfo::load(&mut bounced.message.parser()?)?.bounced);
+        let returned = bounced.next.as_ref().unwrap();
+        assert_eq!((returned.transaction.lt, returned.transaction.account_addr), (30, [1; 32]));
+        assert!(returned.children.is_empty());
+        // the message which wasn't processed ends the chain
+        assert!(to_third.next.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trace_limit() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let (start, mut finder) = bounced_trace()?;
+        let trace = Tracer::new().with_max_transactions(2).trace(&mut finder, start.clone()).await?;
+        assert_eq!(trace.transaction_count(), 2);
+        // messages of the last transaction aren't looked up
+        assert_eq!(finder.lookups, [(account(2), 11)]);
+        let second = trace.children[0].next.as_ref().unwrap();
+        assert_eq!(second.children.len(), 2);
+        assert!(second.children.iter().all(|child| child.next.is_none()));
+
+        finder.lookups.clear();
+        let trace = Tracer::new().with_max_transactions(1).trace(&mut finder, start).await?;
+        assert_eq!(trace.transaction_count(), 1);
+        assert!(finder.lookups.is_empty());
+        Ok(())
+    }
+}
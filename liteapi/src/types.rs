@@ -1,24 +1,457 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
 use adnl::AdnlError;
 use thiserror::Error;
 use tl_proto::TlError;
 use tower::Service;
 
-use crate::tl::{request::WrappedRequest, response::Response};
+use crate::cell::CellError;
+use crate::tl::{common::{BlockIdExt, Int256, LibraryEntry, ZeroStateIdExt}, request::WrappedRequest, response::{BlockHeader, Response, RunMethodResult}};
+use crate::tlb::{ShardHashes, Transaction};
 
 #[derive(Debug, Error)]
 pub enum LiteError {
-    #[error("Liteserver error")]
+    #[error("Liteserver error {}: {}", .0.code, .0.message)]
     ServerError(crate::tl::response::Error),
     #[error("TL parsing error")]
     TlError(TlError),
     #[error("Unexpected TL message")]
     UnexpectedMessage,
+    #[error("Connection closed")]
+    Closed,
+    /// The query was cancelled with [`LiteClient::with_cancellation`](crate::client::LiteClient::with_cancellation)
+    #[error("Query cancelled")]
+    Cancelled,
+    /// The liteserver closed the connection or the transport failed, the connection can't be used anymore
+    #[error("Connection closed by the liteserver")]
+    ConnectionClosed {
+        /// Whether the request was already sent, so it may have been processed by the server
+        during_query: bool,
+    },
+    /// The connection already has as many queries in flight as it allows, the request wasn't sent
+    #[error("Too many queries in flight")]
+    TooManyQueries,
+    #[error("No liteservers available")]
+    NoServers,
+    /// A background request was shed by a pool under load, see [`LitePool::with_priorities`](crate::pool::LitePool::with_priorities)
+    #[error("Liteserver pool is overloaded")]
+    Overloaded,
+    /// The liteserver has a different zerostate or history than the expected [`Network`]
+    #[error("Liteserver belongs to a different network")]
+    WrongNetwork,
+    #[error("Account is not active")]
+    InactiveAccount,
+    /// Configuration parameters needed for the query are missing from the answer
+    #[error("Configuration parameters are missing")]
+    MissingConfig,
+    #[error("Shard not found")]
+    ShardNotFound,
+    /// A get-method called through [`contract!`](crate::contract!) bindings exited with an error
+    #[error("Get-method failed with {0}")]
+    MethodFailed(crate::tlb::ExitCode),
+    /// The stack returned by a get-method doesn't match its declared result
+    #[error("Unexpected get-method result")]
+    UnexpectedResult,
+    /// A block doesn't follow the previously indexed block of its chain and the sink doesn't handle reorgs
+    #[error("Indexed chain switched to another fork")]
+    Reorg(Box<crate::indexer::Reorg>),
+    #[error("ADNL checksum or nonce validation failed")]
+    IntegrityError,
+    #[error("Frame exceeds the maximum length")]
+    FrameTooLong(Option<usize>),
+    #[error("Received data doesn't match the requested hash")]
+    HashMismatch,
+    /// The proof of an answer is missing or malformed, so the answer can't be verified
+    #[error("Invalid proof")]
+    InvalidProof(&'static str),
+    /// Strict verification is on and the answers to the function carry nothing [`verify_response`](crate::layers::verify_response) can check
+    #[error("Answers to {0} can't be verified")]
+    Unverifiable(&'static str),
+    #[error("Cell error")]
+    CellError(#[from] CellError),
+    #[error("IO error")]
+    IoError(#[from] std::io::Error),
+    #[cfg(feature = "parquet")]
+    #[error("Parquet error")]
+    ParquetError(#[from] parquet::errors::ParquetError),
     #[error("ADNL error")]
-    AdnlError(#[from] AdnlError),
+    AdnlError(#[source] AdnlError),
     #[error("Unknown error")]
-    UnknownError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>)
+    UnknownError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// Error of a query with the function and, if known, the liteserver which produced it
+    #[error("{method} failed{}", .server.as_ref().map(|server| format!(" on liteserver {}", server)).unwrap_or_default())]
+    Query {
+        /// Function name, see [`Request::name`](crate::tl::request::Request::name)
+        method: &'static str,
+        server: Option<LiteServer>,
+        #[source]
+        source: Box<LiteError>,
+    },
+    /// Error of a query with a correlation id, see [`crate::correlation`]
+    #[error("Query {id} failed: {source}")]
+    Correlated {
+        id: Arc<str>,
+        #[source]
+        source: Box<LiteError>,
+    },
+}
+
+impl LiteError {
+    /// Correlation id of the failed query, if it had one.
+    pub fn correlation_id(&self) -> Option<&str> {
+        match self {
+            LiteError::Correlated { id, .. } => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Function of the failed query, if known.
+    pub fn method(&self) -> Option<&'static str> {
+        match self {
+            LiteError::Query { method, .. } => Some(method),
+            LiteError::Correlated { source, .. } => source.method(),
+            _ => None,
+        }
+    }
+
+    /// Liteserver which produced the error, if known.
+    pub fn server(&self) -> Option<&LiteServer> {
+        match self {
+            LiteError::Query { server, .. } => server.as_ref(),
+            LiteError::Correlated { source, .. } => source.server(),
+            _ => None,
+        }
+    }
+
+    /// The error itself without the correlation id and the query context, to match on its kind.
+    ///
+    /// Errors of queries come wrapped in [`LiteError::Query`] and [`LiteError::Correlated`], so match on
+    /// `error.kind()` rather than on the error itself:
+    ///
+    /// ```
+    /// # use ton_liteapi::{tl::response::Error, types::LiteError};
+    /// fn is_server_error(error: &LiteError) -> bool {
+    ///     matches!(error.kind(), LiteError::ServerError(_))
+    /// }
+    ///
+    /// let error = LiteError::ServerError(Error { code: 651, message: "not found".into() });
+    /// let error = LiteError::Query { method: "liteServer.getAccountState", server: None, source: Box::new(error) };
+    /// assert!(!matches!(error, LiteError::ServerError(_)));
+    /// assert!(is_server_error(&error));
+    /// ```
+    pub fn kind(&self) -> &LiteError {
+        match self {
+            LiteError::Correlated { source, .. } | LiteError::Query { source, .. } => source.kind(),
+            e => e,
+        }
+    }
+
+    /// Attach the function and the liteserver of a failed query, unless the error already has them.
+    pub(crate) fn in_query(self, method: &'static str, server: Option<&LiteServer>) -> Self {
+        match self {
+            e @ LiteError::Query { .. } => e,
+            source => LiteError::Query { method, server: server.cloned(), source: Box::new(source) },
+        }
+    }
+}
+
+/// Keep transport failures which affect the connection state distinguishable from plain io errors.
+impl From<AdnlError> for LiteError {
+    fn from(error: AdnlError) -> Self {
+        match error {
+            AdnlError::IntegrityError => LiteError::IntegrityError,
+            AdnlError::TooLongPacket => LiteError::FrameTooLong(None),
+            error => LiteError::AdnlError(error),
+        }
+    }
 }
 
 pub trait LiteService: Service<WrappedRequest, Response = Response, Error = LiteError> where Self::Future: Send + 'static {}
 
-impl<T> LiteService for T where T: Service<WrappedRequest, Response = Response, Error = LiteError>, T::Future: Send + 'static {}
\ No newline at end of file
+impl<T> LiteService for T where T: Service<WrappedRequest, Response = Response, Error = LiteError>, T::Future: Send + 'static {}
+
+/// Address and public key of a liteserver.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct LiteServer {
+    pub address: SocketAddr,
+    pub public_key: [u8; 32],
+}
+
+impl LiteServer {
+    pub fn new(address: SocketAddr, public_key: [u8; 32]) -> Self {
+        Self { address, public_key }
+    }
+
+    /// Liteservers listed in a global config, without duplicate entries.
+    ///
+    /// Public configs list some servers more than once, which would skew random selection and pool sizing.
+    /// Dropped entries are logged, use [`LiteServer::dedup`] to get them or to also merge entries differing
+    /// only in the port.
+    #[cfg(feature = "network-config")]
+    pub fn from_config(config: &ton_networkconfig::ConfigGlobal) -> Vec<Self> {
+        Self::dedup(config.liteservers.iter().map(Self::from), false).log().servers
+    }
+
+    /// Remove entries with the same address and public key as an earlier one, and with `merge_ports` also
+    /// entries differing from an earlier one only in the port, i.e. the same server listening on several ports.
+    pub fn dedup(servers: impl IntoIterator<Item = Self>, merge_ports: bool) -> Dedup {
+        let mut dedup = Dedup::default();
+        for server in servers {
+            let kept = dedup.servers.iter().find(|kept| {
+                kept.public_key == server.public_key
+                    && kept.address.ip() == server.address.ip()
+                    && (merge_ports || kept.address.port() == server.address.port())
+            });
+            match kept {
+                Some(kept) => dedup.dropped.push((server, kept.clone())),
+                None => dedup.servers.push(server),
+            }
+        }
+        dedup
+    }
+
+    /// Connect with the default options, see [`LiteClientBuilder::connect_server`](crate::client::LiteClientBuilder::connect_server)
+    /// for other ones.
+    pub async fn connect(&self) -> Result<crate::client::LiteClient, LiteError> {
+        crate::client::LiteClient::connect(self.address, self.public_key).await
+    }
+}
+
+/// Liteservers left by [`LiteServer::dedup`].
+#[derive(Debug, Clone, Default)]
+pub struct Dedup {
+    /// Remaining servers in their original order
+    pub servers: Vec<LiteServer>,
+    /// Each dropped entry with the remaining one it duplicates
+    pub dropped: Vec<(LiteServer, LiteServer)>,
+}
+
+impl Dedup {
+    /// The remaining server standing for `server`, which is either itself or the one it duplicates.
+    pub fn kept<'a>(&'a self, server: &'a LiteServer) -> &'a LiteServer {
+        self.dropped.iter().find(|(dropped, _)| dropped == server).map_or(server, |(_, kept)| kept)
+    }
+
+    /// Log every dropped entry.
+    pub fn log(self) -> Self {
+        for (dropped, kept) in &self.dropped {
+            log::info!("Dropped liteserver {} duplicating {}", dropped, kept);
+        }
+        self
+    }
+}
+
+#[cfg(feature = "network-config")]
+impl From<&ton_networkconfig::ConfigLiteServer> for LiteServer {
+    fn from(server: &ton_networkconfig::ConfigLiteServer) -> Self {
+        Self::new(server.socket_addr().into(), server.public_key())
+    }
+}
+
+impl fmt::Debug for LiteServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LiteServer")
+            .field("address", &self.address)
+            .field("public_key", &hex::encode(self.public_key))
+            .finish()
+    }
+}
+
+impl fmt::Display for LiteServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.address, hex::encode(self.public_key))
+    }
+}
+
+/// Parses the [`Display`](fmt::Display) form, `IP:PORT#PUBLIC_KEY` with a hex-encoded key.
+impl FromStr for LiteServer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, public_key) = s.split_once('#').ok_or("expected IP:PORT#PUBLIC_KEY")?;
+        let address = address.parse().map_err(|e| format!("invalid address: {}", e))?;
+        let public_key = hex::decode(public_key).map_err(|e| format!("invalid public key: {}", e))?
+            .try_into().map_err(|_| "public key must be 32 bytes")?;
+        Ok(Self { address, public_key })
+    }
+}
+
+/// Identity of a TON network, see [`LiteClient::check_network`](crate::client::LiteClient::check_network).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    /// Masterchain zerostate, the same for all liteservers of the network
+    pub zero_state: ZeroStateIdExt,
+    /// Trusted masterchain block, usually a recent key block, which must be in the history of the liteservers
+    pub init_block: Option<BlockIdExt>,
+    /// `global_id` written into every block and state of the network, e.g. [`Network::MAINNET_GLOBAL_ID`]
+    pub global_id: Option<i32>,
+}
+
+impl Network {
+    pub const MAINNET_GLOBAL_ID: i32 = -239;
+    pub const TESTNET_GLOBAL_ID: i32 = -3;
+
+    pub fn new(zero_state: ZeroStateIdExt) -> Self {
+        Self { zero_state, init_block: None, global_id: None }
+    }
+
+    pub fn with_init_block(mut self, init_block: BlockIdExt) -> Self {
+        self.init_block = Some(init_block);
+        self
+    }
+
+    pub fn with_global_id(mut self, global_id: i32) -> Self {
+        self.global_id = Some(global_id);
+        self
+    }
+
+    /// Network of the `validator` section of a global config, `None` if the config has none.
+    #[cfg(feature = "network-config")]
+    pub fn from_config(config: &ton_networkconfig::ConfigGlobal) -> Option<Self> {
+        let validator = config.validator.as_ref()?;
+        let block_id = |id: &ton_networkconfig::ConfigBlockId| BlockIdExt {
+            workchain: id.workchain,
+            shard: id.shard as u64,
+            seqno: id.seqno,
+            root_hash: Int256(id.root_hash),
+            file_hash: Int256(id.file_hash),
+        };
+        let zero_state = &validator.zero_state;
+        Some(Self {
+            zero_state: ZeroStateIdExt { workchain: zero_state.workchain, root_hash: Int256(zero_state.root_hash), file_hash: Int256(zero_state.file_hash) },
+            init_block: validator.init_block.as_ref().map(block_id),
+            global_id: None,
+        })
+    }
+}
+
+/// Liteserver a connection was made to and the ADNL session with it, see
+/// [`LiteClient::peer_info`](crate::client::LiteClient::peer_info).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    /// Resolved address and public key of the liteserver
+    pub server: LiteServer,
+    /// ADNL id of the liteserver, the hash of its public key
+    pub server_adnl_id: [u8; 32],
+    /// Public key of the client used for this session
+    pub local_public_key: [u8; 32],
+    /// ADNL id of the client, the hash of `local_public_key`
+    pub local_adnl_id: [u8; 32],
+}
+
+/// Result of [`LiteClient::send_message_tracked`](crate::client::LiteClient::send_message_tracked).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentMessage {
+    /// Status returned by the liteserver
+    pub status: u32,
+    /// Representation hash of the message cell
+    pub hash: Int256,
+    /// Hash of the normalized message (TEP-467), stable across `src`, `import_fee` and `init` changes
+    pub normalized_hash: Int256,
+}
+
+/// Result of [`LiteClient::run_smc_method_with_libraries`](crate::client::LiteClient::run_smc_method_with_libraries).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunMethodWithLibraries {
+    pub result: RunMethodResult,
+    /// Libraries referenced by the account state, with the libraries they reference in turn
+    pub libraries: Vec<LibraryEntry>,
+    /// Referenced libraries which the liteserver doesn't know
+    pub missing: Vec<Int256>,
+}
+
+/// Result of [`LiteClient::get_block_full`](crate::client::LiteClient::get_block_full).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockFull {
+    pub header: BlockHeader,
+    /// All transactions of the block, ordered by account and logical time
+    pub transactions: Vec<Transaction>,
+    /// Shard blocks registered in a masterchain block, `None` for shardchain blocks
+    pub shards: Option<ShardHashes>,
+}
+
+/// Parsed answer of [`LiteClient::query_with_raw`](crate::client::LiteClient::query_with_raw) with its serialized form,
+/// e.g. to persist the proofs for an audit while working with the typed value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithRaw<T> {
+    pub value: T,
+    /// TL-serialized `liteServer` answer as received
+    pub raw: Vec<u8>,
+}
+
+/// Mode flags of [`LiteClient::get_config_all`](crate::client::LiteClient::get_config_all) and
+/// [`LiteClient::get_config_params`](crate::client::LiteClient::get_config_params),
+/// selecting which parts of the state are included into the proofs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConfigMode {
+    pub with_state_root: bool,
+    pub with_libraries: bool,
+    pub with_state_extra_root: bool,
+    pub with_shard_hashes: bool,
+    /// Include the current validator set
+    pub with_validator_set: bool,
+    pub with_special_smc: bool,
+    pub with_accounts_root: bool,
+    pub with_prev_blocks: bool,
+    pub with_workchain_info: bool,
+    pub with_capabilities: bool,
+    /// Read the config from the last key block preceding `id`
+    pub extract_from_key_block: bool,
+}
+
+impl ConfigMode {
+    const BITS: [u32; 11] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 15];
+
+    /// Flags from a raw `mode` bitmask, unknown bits are ignored.
+    pub fn from_bits(mode: u32) -> Self {
+        let [with_state_root, with_libraries, with_state_extra_root, with_shard_hashes, with_validator_set, with_special_smc,
+            with_accounts_root, with_prev_blocks, with_workchain_info, with_capabilities, extract_from_key_block] = Self::BITS.map(|bit| mode & (1 << bit) != 0);
+        Self {
+            with_state_root, with_libraries, with_state_extra_root, with_shard_hashes, with_validator_set, with_special_smc,
+            with_accounts_root, with_prev_blocks, with_workchain_info, with_capabilities, extract_from_key_block,
+        }
+    }
+
+    /// Raw `mode` bitmask as sent to the liteserver.
+    pub fn bits(&self) -> u32 {
+        self.flags().iter().zip(Self::BITS).fold(0, |mode, (set, bit)| mode | (*set as u32) << bit)
+    }
+
+    fn flags(&self) -> [bool; 11] {
+        [self.with_state_root, self.with_libraries, self.with_state_extra_root, self.with_shard_hashes, self.with_validator_set, self.with_special_smc,
+            self.with_accounts_root, self.with_prev_blocks, self.with_workchain_info, self.with_capabilities, self.extract_from_key_block]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(address: &str, key: u8) -> LiteServer {
+        LiteServer::new(address.parse().unwrap(), [key; 32])
+    }
+
+    #[test]
+    fn test_dedup() {
+        let servers = [
+            server("1.1.1.1:1000", 1),
+            server("2.2.2.2:1000", 2),
+            server("1.1.1.1:1000", 1),
+            // same address with another key is another server
+            server("1.1.1.1:1000", 3),
+            server("1.1.1.1:2000", 1),
+        ];
+        let dedup = LiteServer::dedup(servers.clone(), false);
+        assert_eq!(dedup.servers, [servers[0].clone(), servers[1].clone(), servers[3].clone(), servers[4].clone()]);
+        assert_eq!(dedup.dropped, [(servers[2].clone(), servers[0].clone())]);
+        assert_eq!(dedup.kept(&servers[2]), &servers[0]);
+        assert_eq!(dedup.kept(&servers[1]), &servers[1]);
+
+        let merged = LiteServer::dedup(servers.clone(), true);
+        assert_eq!(merged.servers, [servers[0].clone(), servers[1].clone(), servers[3].clone()]);
+        assert_eq!(merged.kept(&servers[4]), &servers[0]);
+    }
+}
@@ -0,0 +1,638 @@
+//! ADNL over UDP, the transport of TON nodes, as an alternative to the TCP liteserver protocol.
+//!
+//! Every datagram is encrypted to its receiver: `receiver_id:bytes32 sender_key:bytes32 checksum:bytes32 payload`,
+//! where `sender_key` is a one-time key, the checksum is the sha256 of the `adnl.packetContents` in the payload,
+//! and AES-CTR is keyed by the ECDH secret and the checksum. Such packets carry the key of the client, are signed by
+//! it and ask the node for a channel. Once the node confirms it, packets go as `channel_id:bytes32 checksum:bytes32
+//! payload`, encrypted with keys derived from the channel keys of both sides.
+//!
+//! [`AdnlUdp`] carries the same `adnl.message.query` and `adnl.message.answer` as a TCP connection, so a
+//! [`LiteClient`](crate::client::LiteClient) runs over it unchanged, see
+//! [`LiteClientBuilder::connect_udp`](crate::client::LiteClientBuilder::connect_udp). ADNL doesn't retransmit
+//! lost datagrams, a query or an answer lost on the way shows as a query timing out.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, VecDeque};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll, Waker};
+use std::time::SystemTime;
+
+use adnl::crypto::{KeyPair, PublicKey};
+use adnl::{AdnlAddress, AdnlError};
+use aes::cipher::{KeyIvInit, StreamCipher};
+use futures::{Sink, Stream};
+use sha2::{Digest, Sha256};
+use tokio::io::ReadBuf;
+use tokio::net::{ToSocketAddrs, UdpSocket};
+use tokio_util::bytes::Bytes;
+
+use crate::codec::Aes;
+use crate::peer::TRANSPORT_MAX_FRAME_LEN;
+use crate::tl::adnl::{AddressList, Message, PacketContents, PublicKey as TlPublicKey, UdpMessage};
+use crate::tl::common::Int256;
+use crate::time::unix_time;
+use crate::types::{LiteError, LiteServer, PeerInfo};
+
+type Result<T> = std::result::Result<T, LiteError>;
+
+/// Longest serialized message sent in one datagram, longer ones are split into `adnl.message.part`s
+const MAX_PART_LEN: usize = 768;
+/// Largest datagram received
+const MAX_DATAGRAM_LEN: usize = 65536;
+/// Sequence numbers remembered behind the highest received one, to drop repeated datagrams
+const SEQNO_WINDOW: u64 = 1024;
+/// Datagrams waiting for the socket before [`AdnlUdp`] stops taking messages
+const MAX_QUEUED_DATAGRAMS: usize = 64;
+
+/// Encryption keys of a channel and the ids its datagrams are addressed to.
+struct Channel {
+    out_id: [u8; 32],
+    in_id: [u8; 32],
+    encrypt: [u8; 32],
+    decrypt: [u8; 32],
+}
+
+impl Channel {
+    /// Channel between `local_id` with the channel key `key` and `peer_id` with `peer_key`, one side encrypts with
+    /// the shared secret and the other with it reversed.
+    fn new(key: &KeyPair, peer_key: &PublicKey, local_id: &[u8; 32], peer_id: &[u8; 32]) -> Self {
+        let secret = key.compute_shared_secret(peer_key);
+        let mut reversed = secret;
+        reversed.reverse();
+        let (decrypt, encrypt) = match local_id.cmp(peer_id) {
+            Ordering::Less => (secret, reversed),
+            Ordering::Greater => (reversed, secret),
+            Ordering::Equal => (secret, secret),
+        };
+        Self { out_id: channel_id(&encrypt), in_id: channel_id(&decrypt), encrypt, decrypt }
+    }
+
+    fn seal(&self, packet: &PacketContents) -> Vec<u8> {
+        [&self.out_id[..], &encrypt(&self.encrypt, &tl_proto::serialize(packet))].concat()
+    }
+
+    fn open(&self, datagram: &[u8]) -> Option<PacketContents> {
+        let payload = datagram.strip_prefix(&self.in_id)?;
+        tl_proto::deserialize(&decrypt(&self.decrypt, payload)?).ok()
+    }
+}
+
+/// Id of a channel direction, the hash of its key as `pub.aes`.
+fn channel_id(key: &[u8; 32]) -> [u8; 32] {
+    Sha256::digest(tl_proto::serialize(TlPublicKey::Aes { key: Int256(*key) })).into()
+}
+
+/// Encrypt `data` as `checksum:bytes32 payload`.
+fn encrypt(secret: &[u8; 32], data: &[u8]) -> Vec<u8> {
+    let checksum: [u8; 32] = Sha256::digest(data).into();
+    let mut sealed = [&checksum[..], data].concat();
+    cipher(secret, &checksum).apply_keystream(&mut sealed[32..]);
+    sealed
+}
+
+/// Decrypt `checksum:bytes32 payload`, `None` unless the checksum matches.
+fn decrypt(secret: &[u8; 32], sealed: &[u8]) -> Option<Vec<u8>> {
+    let (checksum, payload) = sealed.split_first_chunk::<32>()?;
+    let mut data = payload.to_vec();
+    cipher(secret, checksum).apply_keystream(&mut data);
+    (Sha256::digest(&data).as_slice() == checksum).then_some(data)
+}
+
+/// AES-CTR keyed by halves of the shared secret and of the checksum.
+fn cipher(secret: &[u8; 32], checksum: &[u8; 32]) -> Aes {
+    let mut key = [0; 32];
+    key[..16].copy_from_slice(&secret[..16]);
+    key[16..].copy_from_slice(&checksum[16..]);
+    let mut iv = [0; 16];
+    iv[..4].copy_from_slice(&checksum[..4]);
+    iv[4..].copy_from_slice(&secret[20..]);
+    Aes::new(&key.into(), &iv.into())
+}
+
+/// Datagram with `packet` encrypted to `peer_key`, whose ADNL id is `peer_id`, with a one-time key.
+fn seal_to(peer_id: &[u8; 32], peer_key: &PublicKey, packet: &PacketContents) -> Vec<u8> {
+    let once = KeyPair::generate(&mut rand::rngs::OsRng);
+    let secret = once.compute_shared_secret(peer_key);
+    [&peer_id[..], once.public_key.as_bytes(), &encrypt(&secret, &tl_proto::serialize(packet))].concat()
+}
+
+/// Packet contents of a datagram addressed to `local_id` with the key `local_key`.
+fn open_with(local_id: &[u8; 32], local_key: &KeyPair, datagram: &[u8]) -> Option<PacketContents> {
+    let (sender_key, sealed) = datagram.strip_prefix(local_id)?.split_first_chunk::<32>()?;
+    let secret = local_key.compute_shared_secret(&PublicKey::from_bytes(*sender_key)?);
+    tl_proto::deserialize(&decrypt(&secret, sealed)?).ok()
+}
+
+/// Packet with `messages` and the random padding around them, without any of the optional fields.
+fn packet(messages: Vec<UdpMessage>) -> PacketContents {
+    let padding = || {
+        let len = if rand::random() { 7 } else { 15 };
+        rand::random::<[u8; 15]>()[..len].to_vec()
+    };
+    let (message, messages) = match <[UdpMessage; 1]>::try_from(messages) {
+        Ok([message]) => (Some(message), None),
+        Err(messages) => (None, Some(messages)),
+    };
+    PacketContents {
+        rand1: padding(),
+        flags: (),
+        from: None,
+        from_short: None,
+        message,
+        messages,
+        address: None,
+        priority_address: None,
+        seqno: None,
+        confirm_seqno: None,
+        recv_addr_list_version: None,
+        recv_priority_addr_list_version: None,
+        reinit_date: None,
+        dst_reinit_date: None,
+        signature: None,
+        rand2: padding(),
+    }
+}
+
+/// `message` as is, or split into `adnl.message.part`s if it's longer than [`MAX_PART_LEN`].
+fn split(message: UdpMessage) -> Vec<UdpMessage> {
+    let serialized = tl_proto::serialize(&message);
+    if serialized.len() <= MAX_PART_LEN {
+        return vec![message];
+    }
+    let hash = Int256(Sha256::digest(&serialized).into());
+    let total_size = serialized.len() as u32;
+    serialized.chunks(MAX_PART_LEN).enumerate()
+        .map(|(i, data)| UdpMessage::Part { hash: hash.clone(), total_size, offset: (i * MAX_PART_LEN) as u32, data: data.to_vec() })
+        .collect()
+}
+
+/// Sign `packet` with `key`, the signature covering the packet without it.
+fn sign(mut packet: PacketContents, key: &KeyPair) -> PacketContents {
+    packet.signature = None;
+    packet.signature = Some(key.sign_raw(&tl_proto::serialize(&packet)).to_vec());
+    packet
+}
+
+/// Whether `packet` is signed by `key` and doesn't claim another sender.
+fn is_signed_by(packet: &mut PacketContents, key: &PublicKey) -> bool {
+    let Some(signature) = packet.signature.take() else {
+        return false;
+    };
+    let sender = TlPublicKey::Ed25519 { key: Int256(key.to_bytes()) };
+    if packet.from.as_ref().is_some_and(|from| *from != sender)
+        || packet.from_short.as_ref().is_some_and(|id| id.0 != AdnlAddress::from(key).to_bytes())
+    {
+        return false;
+    }
+    let Ok(signature) = <[u8; 64]>::try_from(signature.as_slice()) else {
+        return false;
+    };
+    key.verify_raw(&tl_proto::serialize(&*packet), &signature)
+}
+
+/// Message being received in `adnl.message.part`s, which come in order.
+struct Partial {
+    hash: Int256,
+    total_size: usize,
+    data: Vec<u8>,
+}
+
+/// Client side of an ADNL over UDP session with one node, see the [module docs](self).
+///
+/// As a [`Sink`] it takes serialized `adnl.message.query`, as a [`Stream`] it yields serialized
+/// `adnl.message.answer`. There is no `tcp.ping` over UDP, a ping is answered with a `tcp.pong` by `AdnlUdp` itself,
+/// so a [`KeepAlive`](crate::layers::KeepAlive) doesn't notice a node which stopped answering, query timeouts do.
+pub struct AdnlUdp {
+    socket: UdpSocket,
+    local_key: KeyPair,
+    local_id: [u8; 32],
+    peer_key: PublicKey,
+    peer_id: [u8; 32],
+    /// Time this session started, the node drops state kept for earlier sessions of the same key
+    reinit_date: u32,
+    /// Start of the session of the node, 0 until it's known
+    peer_reinit_date: u32,
+    out_seqno: u64,
+    /// Highest sequence number received, confirmed in every packet sent
+    in_seqno: u64,
+    /// Sequence numbers received within [`SEQNO_WINDOW`] of `in_seqno`
+    received: BTreeSet<u64>,
+    channel_key: KeyPair,
+    channel_date: u32,
+    channel: Option<Channel>,
+    partial: Option<Partial>,
+    incoming: VecDeque<Bytes>,
+    outgoing: VecDeque<Vec<u8>>,
+    /// Task waiting for `incoming`, woken when a pong is queued
+    reader: Option<Waker>,
+    buffer: Vec<u8>,
+}
+
+impl AdnlUdp {
+    /// Session over `socket`, connected to the node with the ADNL key `peer_key`, authenticated with `local_key`.
+    pub fn new(socket: UdpSocket, local_key: KeyPair, peer_key: PublicKey) -> Self {
+        let now = unix_time(SystemTime::now());
+        Self {
+            socket,
+            local_id: AdnlAddress::from(&local_key.public_key).to_bytes(),
+            local_key,
+            peer_id: AdnlAddress::from(&peer_key).to_bytes(),
+            peer_key,
+            reinit_date: now,
+            peer_reinit_date: 0,
+            out_seqno: 0,
+            in_seqno: 0,
+            received: BTreeSet::new(),
+            channel_key: KeyPair::generate(&mut rand::rngs::OsRng),
+            channel_date: now,
+            channel: None,
+            partial: None,
+            incoming: VecDeque::new(),
+            outgoing: VecDeque::new(),
+            reader: None,
+            buffer: vec![0; MAX_DATAGRAM_LEN],
+        }
+    }
+
+    /// Bind a local UDP socket and start a session with the node at `address` whose ADNL key is `public_key`.
+    pub async fn connect<A: ToSocketAddrs>(address: A, public_key: &[u8], local_key: KeyPair) -> Result<(Self, PeerInfo)> {
+        let server_key: [u8; 32] = public_key.try_into().map_err(|_| AdnlError::InvalidPublicKey)?;
+        let peer_key = PublicKey::from_bytes(server_key).ok_or(AdnlError::InvalidPublicKey)?;
+        let address = tokio::net::lookup_host(address).await?.next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "address resolved to nothing"))?;
+        let socket = UdpSocket::bind(if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).await?;
+        socket.connect(address).await?;
+        let peer = PeerInfo {
+            server: LiteServer::new(address, server_key),
+            server_adnl_id: AdnlAddress::from(&peer_key).to_bytes(),
+            local_public_key: local_key.public_key.to_bytes(),
+            local_adnl_id: AdnlAddress::from(&local_key.public_key).to_bytes(),
+        };
+        Ok((Self::new(socket, local_key, peer_key), peer))
+    }
+
+    /// Queue the datagrams of `message`, split into parts if it's too long for one.
+    fn send_message(&mut self, message: UdpMessage) {
+        for message in split(message) {
+            self.send_packet(message);
+        }
+    }
+
+    fn send_packet(&mut self, message: UdpMessage) {
+        self.out_seqno += 1;
+        let datagram = match &self.channel {
+            Some(channel) => {
+                let mut packet = packet(vec![message]);
+                packet.seqno = Some(self.out_seqno);
+                packet.confirm_seqno = Some(self.in_seqno);
+                channel.seal(&packet)
+            }
+            None => {
+                // every packet asks for the channel until the node confirms it
+                let create = UdpMessage::CreateChannel { key: Int256(self.channel_key.public_key.to_bytes()), date: self.channel_date };
+                let mut packet = packet(vec![create, message]);
+                packet.from = Some(TlPublicKey::Ed25519 { key: Int256(self.local_key.public_key.to_bytes()) });
+                packet.address = Some(AddressList { addrs: Vec::new(), version: self.reinit_date, reinit_date: self.reinit_date, priority: 0, expire_at: 0 });
+                packet.seqno = Some(self.out_seqno);
+                packet.confirm_seqno = Some(self.in_seqno);
+                packet.reinit_date = Some(self.reinit_date);
+                packet.dst_reinit_date = Some(self.peer_reinit_date);
+                seal_to(&self.peer_id, &self.peer_key, &sign(packet, &self.local_key))
+            }
+        };
+        self.outgoing.push_back(datagram);
+    }
+
+    fn receive(&mut self, datagram: &[u8]) {
+        let (mut packet, in_channel) = match self.channel.as_ref().and_then(|channel| channel.open(datagram)) {
+            Some(packet) => (packet, true),
+            None => match open_with(&self.local_id, &self.local_key, datagram) {
+                Some(packet) => (packet, false),
+                None => {
+                    log::debug!("Dropped an ADNL datagram which isn't addressed to this session or is corrupted");
+                    return;
+                }
+            },
+        };
+        if !in_channel && !is_signed_by(&mut packet, &self.peer_key) {
+            log::debug!("Dropped an ADNL packet which isn't signed by the node");
+            return;
+        }
+        if let (Some(date), Some(dst_date)) = (packet.reinit_date, packet.dst_reinit_date) {
+            if dst_date != 0 && dst_date != self.reinit_date {
+                log::debug!("Dropped an ADNL packet for another session of the client");
+                return;
+            }
+            if !self.peer_reinit(date) {
+                log::debug!("Dropped an ADNL packet of an earlier session of the node");
+                return;
+            }
+        }
+        if let Some(seqno) = packet.seqno {
+            if !self.accept_seqno(seqno) {
+                log::debug!("Dropped a repeated ADNL packet {}", seqno);
+                return;
+            }
+        }
+        for message in packet.message.into_iter().chain(packet.messages.into_iter().flatten()) {
+            self.process(message);
+        }
+    }
+
+    /// Note the start of the session of the node, which forgets the channel and the sequence numbers when it
+    /// restarts. `false` if `date` is of an earlier session.
+    fn peer_reinit(&mut self, date: u32) -> bool {
+        match date.cmp(&self.peer_reinit_date) {
+            Ordering::Less => false,
+            Ordering::Equal => true,
+            Ordering::Greater => {
+                if self.peer_reinit_date != 0 {
+                    self.out_seqno = 0;
+                    self.in_seqno = 0;
+                    self.received.clear();
+                    self.channel = None;
+                    self.partial = None;
+                }
+                self.peer_reinit_date = date;
+                true
+            }
+        }
+    }
+
+    fn accept_seqno(&mut self, seqno: u64) -> bool {
+        if seqno + SEQNO_WINDOW <= self.in_seqno || !self.received.insert(seqno) {
+            return false;
+        }
+        self.in_seqno = self.in_seqno.max(seqno);
+        self.received = self.received.split_off(&self.in_seqno.saturating_sub(SEQNO_WINDOW));
+        true
+    }
+
+    fn process(&mut self, message: UdpMessage) {
+        match message {
+            UdpMessage::Answer { .. } => self.incoming.push_back(tl_proto::serialize(&message).into()),
+            UdpMessage::ConfirmChannel { key, peer_key, .. } if peer_key.0 == self.channel_key.public_key.to_bytes() => {
+                if self.channel.is_none() {
+                    match PublicKey::from_bytes(key.0) {
+                        Some(key) => self.channel = Some(Channel::new(&self.channel_key, &key, &self.local_id, &self.peer_id)),
+                        None => log::debug!("Node confirmed the ADNL channel with an invalid key"),
+                    }
+                }
+            }
+            UdpMessage::Reinit { date } => {
+                self.peer_reinit(date);
+            }
+            UdpMessage::Part { hash, total_size, offset, data } => {
+                if let Some(message) = self.reassemble(hash, total_size as usize, offset as usize, data) {
+                    match message {
+                        UdpMessage::Part { .. } => log::debug!("Dropped an ADNL message part made of parts"),
+                        message => self.process(message),
+                    }
+                }
+            }
+            message => log::debug!("Ignored an ADNL message from the node: {:?}", message),
+        }
+    }
+
+    /// Add a part to the message being received, returning the message once it's complete. A part of another message
+    /// starts over, a part out of order is dropped since ADNL doesn't resend it.
+    fn reassemble(&mut self, hash: Int256, total_size: usize, offset: usize, data: Vec<u8>) -> Option<UdpMessage> {
+        if total_size > TRANSPORT_MAX_FRAME_LEN {
+            log::debug!("Dropped an ADNL message of {} bytes", total_size);
+            return None;
+        }
+        let partial = match &mut self.partial {
+            Some(partial) if partial.hash == hash && partial.total_size == total_size => partial,
+            partial => partial.insert(Partial { hash, total_size, data: Vec::new() }),
+        };
+        if offset != partial.data.len() || offset + data.len() > total_size {
+            log::debug!("Dropped an ADNL message part at {} out of order", offset);
+            return None;
+        }
+        partial.data.extend_from_slice(&data);
+        if partial.data.len() < total_size {
+            return None;
+        }
+        let partial = self.partial.take()?;
+        if Sha256::digest(&partial.data).as_slice() != partial.hash.0 {
+            log::debug!("Dropped an ADNL message not matching the hash of its parts");
+            return None;
+        }
+        tl_proto::deserialize(&partial.data).ok()
+    }
+}
+
+impl Stream for AdnlUdp {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(answer) = this.incoming.pop_front() {
+                return Poll::Ready(Some(Ok(answer)));
+            }
+            let mut buffer = std::mem::take(&mut this.buffer);
+            let received = {
+                let mut read = ReadBuf::new(&mut buffer);
+                this.socket.poll_recv(cx, &mut read).map_ok(|()| read.filled().len())
+            };
+            match received {
+                Poll::Ready(Ok(len)) => this.receive(&buffer[..len]),
+                Poll::Ready(Err(e)) => {
+                    this.buffer = buffer;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                Poll::Pending => {
+                    this.buffer = buffer;
+                    this.reader = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+            this.buffer = buffer;
+        }
+    }
+}
+
+impl Sink<Bytes> for AdnlUdp {
+    type Error = LiteError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        if self.outgoing.len() >= MAX_QUEUED_DATAGRAMS {
+            ready!(self.poll_flush(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> Result<()> {
+        let this = self.get_mut();
+        match tl_proto::deserialize::<UdpMessage>(&item) {
+            Ok(message) => this.send_message(message),
+            Err(e) => match tl_proto::deserialize::<Message>(&item) {
+                Ok(Message::Ping { random_id }) => {
+                    this.incoming.push_back(tl_proto::serialize(Message::Pong { random_id }).into());
+                    if let Some(reader) = this.reader.take() {
+                        reader.wake();
+                    }
+                }
+                _ => return Err(LiteError::TlError(e)),
+            },
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        while let Some(datagram) = this.outgoing.front() {
+            ready!(this.socket.poll_send(cx, datagram))?;
+            this.outgoing.pop_front();
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tl_proto::TlWrite;
+
+    use super::*;
+    use crate::client::LiteClient;
+    use crate::tl::adnl::Address;
+    use crate::tl::request::Request;
+    use crate::tl::response::{CurrentTime, Response};
+
+    fn constructor(message: impl TlWrite) -> u32 {
+        u32::from_le_bytes(tl_proto::serialize(message)[..4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_scheme_ids() {
+        let key = Int256([1; 32]);
+        let data = Vec::new();
+        assert_eq!(constructor(TlPublicKey::Ed25519 { key: key.clone() }), 0x4813b4c6);
+        assert_eq!(constructor(TlPublicKey::Aes { key: key.clone() }), 0x2dbcadd4);
+        assert_eq!(constructor(UdpMessage::CreateChannel { key: key.clone(), date: 0 }), 0xe673c3bb);
+        assert_eq!(constructor(UdpMessage::ConfirmChannel { key: key.clone(), peer_key: key.clone(), date: 0 }), 0x60dd1d69);
+        assert_eq!(constructor(UdpMessage::Query { query_id: key.clone(), query: data.clone() }), 0xb48bf97a);
+        assert_eq!(constructor(UdpMessage::Answer { query_id: key.clone(), answer: data.clone() }), 0x0fac8416);
+        assert_eq!(constructor(UdpMessage::Part { hash: key, total_size: 0, offset: 0, data }), 0xfd452d39);
+        assert_eq!(constructor(Address::Udp { ip: 0, port: 0 }), 0x670da6e7);
+        assert_eq!(constructor(packet(Vec::new())), 0xd142cd89);
+    }
+
+    #[test]
+    fn test_packet() {
+        let key = KeyPair::generate(&mut rand::rngs::OsRng);
+        let id = AdnlAddress::from(&key.public_key).to_bytes();
+        let mut signed = packet(vec![UdpMessage::Nop, UdpMessage::Reinit { date: 1 }]);
+        signed.from = Some(TlPublicKey::Ed25519 { key: Int256(key.public_key.to_bytes()) });
+        signed.seqno = Some(1);
+        signed.reinit_date = Some(2);
+        signed.dst_reinit_date = Some(0);
+        let signed = sign(signed, &key);
+
+        let mut opened = open_with(&id, &key, &seal_to(&id, &key.public_key, &signed)).unwrap();
+        assert_eq!(opened, signed);
+        assert!(is_signed_by(&mut opened, &key.public_key));
+        let other = KeyPair::generate(&mut rand::rngs::OsRng);
+        assert!(!is_signed_by(&mut signed.clone(), &other.public_key));
+        // a corrupted datagram fails its checksum
+        let mut datagram = seal_to(&id, &key.public_key, &signed);
+        *datagram.last_mut().unwrap() ^= 1;
+        assert!(open_with(&id, &key, &datagram).is_none());
+
+        // both sides of a channel derive each other's keys
+        let (a, b) = (KeyPair::generate(&mut rand::rngs::OsRng), KeyPair::generate(&mut rand::rngs::OsRng));
+        let (a_id, b_id) = ([1; 32], [2; 32]);
+        let (a_channel, b_channel) = (Channel::new(&a, &b.public_key, &a_id, &b_id), Channel::new(&b, &a.public_key, &b_id, &a_id));
+        assert_eq!(a_channel.out_id, b_channel.in_id);
+        assert_ne!(a_channel.out_id, a_channel.in_id);
+        assert_eq!(b_channel.open(&a_channel.seal(&signed)).unwrap(), signed);
+        assert_eq!(a_channel.open(&b_channel.seal(&signed)).unwrap(), signed);
+        assert!(a_channel.open(&a_channel.seal(&signed)).is_none());
+    }
+
+    /// Node answering the queries of one client with `answers` in turn. Until the client uses the channel, answers
+    /// are signed and confirm the channel.
+    async fn respond(socket: UdpSocket, key: KeyPair, answers: Vec<Vec<u8>>) -> usize {
+        let id = AdnlAddress::from(&key.public_key).to_bytes();
+        let date = unix_time(SystemTime::now());
+        let channel_key = KeyPair::generate(&mut rand::rngs::OsRng);
+        // key, session and channel key of the client, with the channel to it
+        let mut client: Option<(PublicKey, u32, Int256, Channel)> = None;
+        let mut seqno = 0;
+        let mut in_channel = 0;
+        let mut buffer = vec![0; MAX_DATAGRAM_LEN];
+        for answer in answers {
+            let (len, address) = socket.recv_from(&mut buffer).await.unwrap();
+            let datagram = &buffer[..len];
+            let opened = client.as_ref().and_then(|(.., channel)| channel.open(datagram));
+            let through_channel = opened.is_some();
+            let mut received = match opened {
+                Some(packet) => packet,
+                None => open_with(&id, &key, datagram).unwrap(),
+            };
+            if !through_channel {
+                let Some(TlPublicKey::Ed25519 { key: client_key }) = &received.from else { panic!("no key of the client") };
+                let client_key = PublicKey::from_bytes(client_key.0).unwrap();
+                assert!(is_signed_by(&mut received, &client_key));
+                let Some([UdpMessage::CreateChannel { key: client_channel, .. }, _]) = received.messages.as_deref() else {
+                    panic!("no channel requested")
+                };
+                let channel = Channel::new(&channel_key, &PublicKey::from_bytes(client_channel.0).unwrap(), &id, &AdnlAddress::from(&client_key).to_bytes());
+                client = Some((client_key, received.reinit_date.unwrap(), client_channel.clone(), channel));
+            } else {
+                in_channel += 1;
+            }
+            let query = received.message.or(received.messages.and_then(|messages| messages.into_iter().last()));
+            let Some(UdpMessage::Query { query_id, .. }) = query else { panic!("not a query: {:?}", query) };
+            let (client_key, client_date, client_channel, channel) = client.as_ref().unwrap();
+            for message in split(UdpMessage::Answer { query_id: query_id.clone(), answer: answer.clone() }) {
+                seqno += 1;
+                let datagram = if through_channel {
+                    let mut packet = packet(vec![message]);
+                    packet.seqno = Some(seqno);
+                    channel.seal(&packet)
+                } else {
+                    let confirm = UdpMessage::ConfirmChannel { key: Int256(channel_key.public_key.to_bytes()), peer_key: client_channel.clone(), date };
+                    let mut packet = packet(vec![confirm, message]);
+                    packet.from = Some(TlPublicKey::Ed25519 { key: Int256(key.public_key.to_bytes()) });
+                    packet.seqno = Some(seqno);
+                    packet.reinit_date = Some(date);
+                    packet.dst_reinit_date = Some(*client_date);
+                    seal_to(&AdnlAddress::from(client_key).to_bytes(), client_key, &sign(packet, &key))
+                };
+                socket.send_to(&datagram, address).await.unwrap();
+            }
+        }
+        in_channel
+    }
+
+    #[tokio::test]
+    async fn test_udp_client() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        let key = KeyPair::generate(&mut rand::rngs::OsRng);
+        let public_key = key.public_key.to_bytes();
+        let time = tl_proto::serialize(Response::CurrentTime(CurrentTime { now: 1234 }));
+        // an answer with trailing bytes, too long for one datagram
+        let mut long = time.clone();
+        long.extend([7; 3000]);
+        let node = tokio::spawn(respond(socket, key, vec![time.clone(), long.clone(), time]));
+
+        let timeout = Duration::from_secs(5);
+        let mut client = tokio::time::timeout(timeout, LiteClient::builder().connect_udp(address, public_key)).await.unwrap().unwrap();
+        assert_eq!(tokio::time::timeout(timeout, client.get_time()).await.unwrap().unwrap(), 1234);
+        let answer = tokio::time::timeout(timeout, client.query_with_raw::<CurrentTime>(Request::GetTime)).await.unwrap().unwrap();
+        assert_eq!(answer.value.now, 1234);
+        assert_eq!(answer.raw, long);
+        assert_eq!(tokio::time::timeout(timeout, client.get_time()).await.unwrap().unwrap(), 1234);
+        // the queries after the first went through the channel
+        assert_eq!(node.await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,157 @@
+use adnl::crypto::KeyPair;
+use num_bigint::BigInt;
+
+use crate::cell::{ArcCell, CellBuilder, CellError};
+use crate::client::LiteClient;
+use crate::contract::{decode_result, encode_args, method_id, RUN_METHOD_MODE};
+use crate::tl::common::BlockIdExt;
+use crate::tlb::{Coins, ExternalMessage, InternalMessage, MsgAddressInt};
+use crate::types::LiteError;
+
+crate::contract! {
+    /// Highload wallet v3, which sends batches of messages and protects from replays with query ids instead of a seqno.
+    ///
+    /// An external message is accepted if its `created_at` is within the last `timeout` seconds and its
+    /// [`HighloadQueryId`] wasn't processed during the last one to two timeouts. Resending the same signed
+    /// message is therefore safe and can't send the batch twice, e.g. when marked as
+    /// [`retryable`](crate::pool::retryable) in a pool, while a new message needs an unused query id.
+    pub struct HighloadWalletV3 {
+        /// Public key as an unsigned 256-bit integer
+        fn get_public_key() -> BigInt;
+        fn get_subwallet_id() -> u32;
+        fn get_timeout() -> u32;
+        fn get_last_clean_time() -> u64;
+    }
+}
+
+impl HighloadWalletV3 {
+    /// Whether the wallet processed `query_id` within the current timeouts, `processed?` get-method.
+    ///
+    /// With `need_clean` the result accounts for query ids which the wallet will forget on the next message.
+    pub async fn is_processed(&self, client: &mut LiteClient, id: BlockIdExt, query_id: HighloadQueryId, need_clean: bool) -> Result<bool, LiteError> {
+        let params = encode_args(&[&query_id.raw(), &need_clean])?;
+        let result = client.run_smc_method(RUN_METHOD_MODE, id, self.address.clone(), method_id("processed?"), params).await?;
+        decode_result(&result)
+    }
+}
+
+/// Query id of a highload wallet v3 message, `query_id$_ shift:uint13 bit_number:(## 10) { bit_number <= 1022 } = QueryId;`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct HighloadQueryId {
+    pub shift: u16,
+    pub bit_number: u16,
+}
+
+impl HighloadQueryId {
+    pub const MAX_SHIFT: u16 = 8191;
+    pub const MAX_BIT_NUMBER: u16 = 1022;
+
+    pub fn new(shift: u16, bit_number: u16) -> Option<Self> {
+        (shift <= Self::MAX_SHIFT && bit_number <= Self::MAX_BIT_NUMBER).then_some(Self { shift, bit_number })
+    }
+
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        Self::new((raw >> 10) as u16, (raw & 0x3ff) as u16)
+    }
+
+    pub fn raw(self) -> u32 {
+        (self.shift as u32) << 10 | self.bit_number as u32
+    }
+
+    /// The following query id, `None` after the last one.
+    ///
+    /// Query ids used within the last two timeouts must not be reused, so a sender taking them in turn
+    /// can send about 8 million messages per two timeouts.
+    pub fn next(self) -> Option<Self> {
+        match self.bit_number {
+            Self::MAX_BIT_NUMBER => Self::new(self.shift + 1, 0),
+            bit_number => Self::new(self.shift, bit_number + 1),
+        }
+    }
+}
+
+/// Batch of up to [`HighloadBatch::MAX_MESSAGES`] messages sent with a single external message of a [`HighloadWalletV3`].
+///
+/// ```tlb
+/// msg_body$_ subwallet_id:uint32 message_to_send:^Cell send_mode:uint8 query_id:QueryId created_at:uint64
+///   timeout:uint22 = MsgInner;
+/// _ signature:bits512 signed_msg:^MsgInner = ExternalInMsgBody;
+/// internal_transfer#ae42e5a4 query_id:uint64 actions:^(OutList n) = InternalMsgBody;
+/// action_send_msg#0ec3c86d mode:(## 8) out_msg:^(MessageRelaxed Any) = OutAction;
+/// ```
+///
+/// A single message is sent by the wallet directly. More messages are sent as an action list in an
+/// `internal_transfer` from the wallet to itself, which must carry enough TON for all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighloadBatch {
+    pub subwallet_id: u32,
+    /// Must be the `timeout` of the wallet, see [`HighloadWalletV3::get_timeout`]
+    pub timeout: u32,
+    pub query_id: HighloadQueryId,
+    /// Unix time of the message, a bit behind the time of the liteserver since blocks lag behind it
+    pub created_at: u64,
+    /// Messages with their send modes
+    pub messages: Vec<(u8, InternalMessage)>,
+    /// TON attached to the `internal_transfer`, by default the values of the messages with
+    /// [`HighloadBatch::FEE_RESERVE`] for each of them
+    pub value: Option<Coins>,
+}
+
+impl HighloadBatch {
+    /// Most messages a batch can send
+    pub const MAX_MESSAGES: usize = 254;
+    /// Reserve for the forwarding fees of a message in the default `value`, 0.01 TON
+    pub const FEE_RESERVE: Coins = Coins(10_000_000);
+    /// Send mode of the `internal_transfer`: pay the fees separately and ignore errors
+    pub const INTERNAL_TRANSFER_MODE: u8 = 3;
+
+    pub fn new(subwallet_id: u32, timeout: u32, query_id: HighloadQueryId, created_at: u64) -> Self {
+        Self { subwallet_id, timeout, query_id, created_at, messages: Vec::new(), value: None }
+    }
+
+    /// Add a message sent with `mode`, fails with `Overflow` when the batch is full.
+    pub fn push(&mut self, mode: u8, message: InternalMessage) -> Result<(), CellError> {
+        if self.messages.len() >= Self::MAX_MESSAGES {
+            return Err(CellError::Overflow);
+        }
+        self.messages.push((mode, message));
+        Ok(())
+    }
+
+    pub fn with_value(mut self, value: Coins) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// Signed external message to `wallet`, fails with `Underflow` for an empty batch.
+    pub fn sign(&self, wallet: &MsgAddressInt, key: &KeyPair) -> Result<ExternalMessage, CellError> {
+        let (message, mode) = match self.messages.as_slice() {
+            [] => return Err(CellError::Underflow),
+            [(mode, message)] => (message.build()?, *mode),
+            messages => (self.internal_transfer(wallet, messages)?, Self::INTERNAL_TRANSFER_MODE),
+        };
+        let mut inner = CellBuilder::new();
+        inner.store_u32(self.subwallet_id)?.store_reference(message)?.store_u8(mode)?;
+        inner.store_uint(23, self.query_id.raw() as u64)?.store_u64(self.created_at)?.store_uint(22, self.timeout as u64)?;
+        let inner = inner.build()?;
+        let signature = key.sign_raw(&inner.repr_hash());
+        let body = CellBuilder::new().store_bits(&signature, 512)?.store_reference(inner)?.build()?;
+        Ok(ExternalMessage::new(wallet.clone(), body))
+    }
+
+    fn internal_transfer(&self, wallet: &MsgAddressInt, messages: &[(u8, InternalMessage)]) -> Result<ArcCell, CellError> {
+        // out_list$_ prev:^(OutList n) action:OutAction, the first action is the innermost one
+        let mut actions = CellBuilder::new().build()?;
+        let mut value = Coins::ZERO;
+        for (mode, message) in messages {
+            let mut action = CellBuilder::new();
+            action.store_reference(actions)?.store_u32(0x0ec3c86d)?.store_u8(*mode)?.store_reference(message.build()?)?;
+            actions = action.build()?;
+            value = value.checked_add(message.value).and_then(|value| value.checked_add(Self::FEE_RESERVE)).ok_or(CellError::Overflow)?;
+        }
+        let mut body = CellBuilder::new();
+        body.store_u32(0xae42e5a4)?.store_u64(self.query_id.raw() as u64)?.store_reference(actions)?;
+        let value = self.value.unwrap_or(value);
+        InternalMessage::new(wallet.clone(), value, body.build()?).build()
+    }
+}
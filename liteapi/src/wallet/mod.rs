@@ -0,0 +1,15 @@
+//! Wallet contracts: bindings to their get-methods and the messages which control them.
+//!
+//! [`TransferBuilder`] covers the common case of signing a transfer with a wallet key, sending it and waiting
+//! for its transaction.
+
+mod highload;
+mod multisig;
+mod transfer;
+
+pub use highload::{HighloadBatch, HighloadQueryId, HighloadWalletV3};
+pub use multisig::{Multisig, MultisigAction, MultisigApprove, MultisigData, MultisigNewOrder};
+pub use transfer::{SeqnoWallet, TransferBuilder, WalletKind};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,186 @@
+use crate::cell::{ArcCell, Cell, CellBuilder, CellError};
+use crate::client::LiteClient;
+use crate::tl::common::BlockIdExt;
+use crate::tlb::{build_hashmap, hashmap_entries, MsgAddressInt};
+use crate::types::LiteError;
+
+crate::contract! {
+    /// Multisig wallet v2, which executes orders once `threshold` of its signers approved them.
+    ///
+    /// Orders aren't signed offline: a signer or proposer sends [`MultisigNewOrder`] to the multisig from its
+    /// wallet, which deploys an order contract at [`Multisig::get_order_address`], and the other signers send
+    /// [`MultisigApprove`] to the order from their wallets. An order proposed by a signer is approved by it.
+    pub struct Multisig {
+        /// `(next_order_seqno, threshold, signers, proposers)`, see [`Multisig::data`]
+        fn get_multisig_data() -> (i64, u8, ArcCell, Option<ArcCell>);
+        fn get_order_address(order_seqno: u64) -> MsgAddressInt;
+    }
+}
+
+impl Multisig {
+    /// Parsed result of `get_multisig_data`.
+    pub async fn data(&self, client: &mut LiteClient, id: BlockIdExt) -> Result<MultisigData, LiteError> {
+        let (next_order_seqno, threshold, signers, proposers) = self.get_multisig_data(client, id).await?;
+        Ok(MultisigData {
+            // -1 if the multisig allows arbitrary seqnos
+            next_order_seqno: u64::try_from(next_order_seqno).ok(),
+            threshold,
+            signers: load_address_list(&signers)?,
+            proposers: proposers.map(|proposers| load_address_list(&proposers)).transpose()?.unwrap_or_default(),
+        })
+    }
+}
+
+/// Configuration of a [`Multisig`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigData {
+    /// Seqno of the next order, `None` if the multisig allows arbitrary seqnos
+    pub next_order_seqno: Option<u64>,
+    /// Number of approvals needed to execute an order
+    pub threshold: u8,
+    /// Signers in the order of their indexes
+    pub signers: Vec<MsgAddressInt>,
+    /// Addresses which may propose orders, but not approve them
+    pub proposers: Vec<MsgAddressInt>,
+}
+
+impl MultisigData {
+    pub fn signer_index(&self, address: &MsgAddressInt) -> Option<u8> {
+        self.signers.iter().position(|signer| signer == address).map(|index| index as u8)
+    }
+
+    pub fn proposer_index(&self, address: &MsgAddressInt) -> Option<u8> {
+        self.proposers.iter().position(|proposer| proposer == address).map(|index| index as u8)
+    }
+}
+
+/// Action of a multisig order.
+///
+/// ```tlb
+/// send_message#f1381e5b mode:uint8 message:^MessageRelaxed = Action;
+/// update_multisig_params#1d0cfbd3 threshold:uint8 signers:^(Hashmap 8 MsgAddressInt)
+///   proposers:(HashmapE 8 MsgAddressInt) = Action;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultisigAction {
+    /// Send a message from the multisig, e.g. a built [`InternalMessage`](crate::tlb::InternalMessage)
+    SendMessage { mode: u8, message: ArcCell },
+    UpdateParams { threshold: u8, signers: Vec<MsgAddressInt>, proposers: Vec<MsgAddressInt> },
+}
+
+impl MultisigAction {
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        let mut builder = CellBuilder::new();
+        match self {
+            Self::SendMessage { mode, message } => {
+                builder.store_u32(0xf1381e5b)?.store_u8(*mode)?.store_reference(message.clone())?;
+            }
+            Self::UpdateParams { threshold, signers, proposers } => {
+                let signers = build_address_list(signers)?.ok_or(CellError::Underflow)?;
+                builder.store_u32(0x1d0cfbd3)?.store_u8(*threshold)?.store_reference(signers)?;
+                builder.store_maybe_reference(build_address_list(proposers)?)?;
+            }
+        }
+        builder.build()
+    }
+}
+
+/// Proposal of an order, sent to the multisig by a signer or a proposer with TON for the order deployment.
+///
+/// ```tlb
+/// new_order#f718510f query_id:uint64 order_seqno:uint256 signer:(## 1) index:uint8 expiration_date:uint48
+///   order:^(Hashmap 8 ^Action) = InternalMsgBody;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigNewOrder {
+    pub query_id: u64,
+    /// [`MultisigData::next_order_seqno`], or any unused seqno if the multisig allows arbitrary ones
+    pub order_seqno: u64,
+    /// Whether `index` is in the signers or in the proposers
+    pub signer: bool,
+    pub index: u8,
+    /// Unix time after which the order can't be executed
+    pub expiration_date: u64,
+    /// Actions executed in order, at least one
+    pub actions: Vec<MultisigAction>,
+}
+
+impl MultisigNewOrder {
+    pub const OP: u32 = 0xf718510f;
+
+    /// Order proposed by the first signer, see [`MultisigNewOrder::with_signer`].
+    pub fn new(order_seqno: u64, expiration_date: u64, actions: Vec<MultisigAction>) -> Self {
+        Self { query_id: 0, order_seqno, signer: true, index: 0, expiration_date, actions }
+    }
+
+    pub fn with_query_id(mut self, query_id: u64) -> Self {
+        self.query_id = query_id;
+        self
+    }
+
+    /// Propose as the signer with `index`, see [`MultisigData::signer_index`].
+    pub fn with_signer(mut self, index: u8) -> Self {
+        self.signer = true;
+        self.index = index;
+        self
+    }
+
+    /// Propose as the proposer with `index`, see [`MultisigData::proposer_index`].
+    pub fn with_proposer(mut self, index: u8) -> Self {
+        self.signer = false;
+        self.index = index;
+        self
+    }
+
+    /// Message body, fails with `Underflow` if there are no actions.
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        let actions = self.actions.iter().enumerate().map(|(index, action)| {
+            Ok((vec![index as u8], CellBuilder::new().store_reference(action.to_cell()?)?.build()?))
+        }).collect::<Result<Vec<_>, CellError>>()?;
+        let order = build_hashmap(&actions, 8)?.ok_or(CellError::Underflow)?;
+        let mut builder = CellBuilder::new();
+        builder.store_u32(Self::OP)?.store_u64(self.query_id)?;
+        // order_seqno:uint256
+        builder.store_bits(&[0; 24], 192)?.store_u64(self.order_seqno)?;
+        builder.store_bit(self.signer)?.store_u8(self.index)?.store_uint(48, self.expiration_date)?;
+        builder.store_reference(order)?;
+        builder.build()
+    }
+}
+
+/// Approval of an order, sent by a signer to the order contract.
+///
+/// ```tlb
+/// approve#a762230f query_id:uint64 signer_index:uint8 = InternalMsgBody;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultisigApprove {
+    pub query_id: u64,
+    pub signer_index: u8,
+}
+
+impl MultisigApprove {
+    pub const OP: u32 = 0xa762230f;
+
+    pub fn new(signer_index: u8) -> Self {
+        Self { query_id: 0, signer_index }
+    }
+
+    pub fn to_cell(&self) -> Result<ArcCell, CellError> {
+        CellBuilder::new().store_u32(Self::OP)?.store_u64(self.query_id)?.store_u8(self.signer_index)?.build()
+    }
+}
+
+/// `Hashmap 8 MsgAddressInt` keyed by consecutive indexes.
+fn load_address_list(root: &Cell) -> Result<Vec<MsgAddressInt>, CellError> {
+    hashmap_entries(root, 8)?.into_iter().map(|(_, mut address)| MsgAddressInt::load(&mut address)).collect()
+}
+
+fn build_address_list(addresses: &[MsgAddressInt]) -> Result<Option<ArcCell>, CellError> {
+    let entries = addresses.iter().enumerate().map(|(index, address)| {
+        let mut builder = CellBuilder::new();
+        address.store(&mut builder)?;
+        Ok((vec![index as u8], builder.build()?))
+    }).collect::<Result<Vec<_>, CellError>>()?;
+    build_hashmap(&entries, 8)
+}
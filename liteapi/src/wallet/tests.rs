@@ -0,0 +1,154 @@
+use std::error::Error;
+
+use crate::cell::{CellBuilder, CellError};
+use crate::tlb::*;
+use crate::wallet::*;
+
+#[test]
+fn test_multisig_new_order() -> Result<(), Box<dyn Error>> {
+    let signers = vec![MsgAddressInt::std(0, [1; 32]), MsgAddressInt::std(0, [2; 32])];
+    let message = comment("payout")?;
+    let actions = vec![
+        MultisigAction::SendMessage { mode: 3, message: message.clone() },
+        MultisigAction::UpdateParams { threshold: 2, signers: signers.clone(), proposers: vec![] },
+    ];
+    let order = MultisigNewOrder::new(7, 1_700_000_000, actions).with_query_id(42).with_proposer(1);
+    let cell = order.to_cell()?;
+    let mut slice = cell.parser()?;
+    assert_eq!(slice.load_u32()?, MultisigNewOrder::OP);
+    assert_eq!(slice.load_u64()?, 42);
+    assert_eq!(slice.load_bits(192)?, vec![0; 24]);
+    assert_eq!(slice.load_u64()?, 7);
+    assert!(!slice.load_bit()?);
+    assert_eq!(slice.load_u8()?, 1);
+    assert_eq!(slice.load_uint(48)?, 1_700_000_000);
+    let actions = hashmap_entries(slice.load_ref()?, 8)?;
+    assert!(slice.is_empty());
+    assert_eq!(actions.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>(), [vec![0], vec![1]]);
+
+    let mut send = actions[0].1.clone().load_ref()?.parser()?;
+    assert_eq!(send.load_u32()?, 0xf1381e5b);
+    assert_eq!(send.load_u8()?, 3);
+    assert_eq!(send.load_ref()?, &message);
+
+    let mut update = actions[1].1.clone().load_ref()?.parser()?;
+    assert_eq!(update.load_u32()?, 0x1d0cfbd3);
+    assert_eq!(update.load_u8()?, 2);
+    let loaded = hashmap_entries(update.load_ref()?, 8)?.into_iter()
+        .map(|(_, mut address)| MsgAddressInt::load(&mut address))
+        .collect::<Result<Vec<_>, CellError>>()?;
+    assert_eq!(loaded, signers);
+    // no proposers
+    assert!(!update.load_bit()?);
+    assert!(update.is_empty());
+
+    assert!(matches!(MultisigNewOrder::new(7, 0, vec![]).to_cell(), Err(CellError::Underflow)));
+    let no_signers = MultisigAction::UpdateParams { threshold: 1, signers: vec![], proposers: signers };
+    assert!(matches!(no_signers.to_cell(), Err(CellError::Underflow)));
+    Ok(())
+}
+
+#[test]
+fn test_multisig_approve() -> Result<(), Box<dyn Error>> {
+    let data = MultisigData {
+        next_order_seqno: None,
+        threshold: 2,
+        signers: vec![MsgAddressInt::std(0, [1; 32]), MsgAddressInt::std(-1, [2; 32])],
+        proposers: vec![MsgAddressInt::std(0, [3; 32])],
+    };
+    let index = data.signer_index(&MsgAddressInt::std(-1, [2; 32])).unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(data.signer_index(&MsgAddressInt::std(0, [3; 32])), None);
+    assert_eq!(data.proposer_index(&MsgAddressInt::std(0, [3; 32])), Some(0));
+
+    let cell = MultisigApprove { query_id: 9, signer_index: index }.to_cell()?;
+    let mut slice = cell.parser()?;
+    assert_eq!(slice.load_u32()?, MultisigApprove::OP);
+    assert_eq!(slice.load_u64()?, 9);
+    assert_eq!(slice.load_u8()?, 1);
+    assert!(slice.is_empty());
+    assert_eq!(MultisigApprove::new(1).to_cell()?.bit_len(), 32 + 64 + 8);
+    Ok(())
+}
+
+#[test]
+fn test_highload_query_id() {
+    let query_id = HighloadQueryId::new(3, 1022).unwrap();
+    assert_eq!(query_id.raw(), 3 << 10 | 1022);
+    assert_eq!(HighloadQueryId::from_raw(query_id.raw()), Some(query_id));
+    // bit_number wraps into the next shift
+    assert_eq!(query_id.next(), HighloadQueryId::new(4, 0));
+    assert_eq!(HighloadQueryId::new(3, 5).unwrap().next(), HighloadQueryId::new(3, 6));
+    assert_eq!(HighloadQueryId::new(HighloadQueryId::MAX_SHIFT, HighloadQueryId::MAX_BIT_NUMBER).unwrap().next(), None);
+    assert_eq!(HighloadQueryId::new(HighloadQueryId::MAX_SHIFT, 7).unwrap().raw(), (1 << 23) - 1024 + 7);
+
+    assert_eq!(HighloadQueryId::new(0, 1023), None);
+    assert_eq!(HighloadQueryId::new(8192, 0), None);
+    assert_eq!(HighloadQueryId::from_raw(1023), None);
+    assert_eq!(HighloadQueryId::from_raw(1 << 23), None);
+}
+
+#[test]
+fn test_highload_batch() -> Result<(), Box<dyn Error>> {
+    use adnl::crypto::{KeyPair, SecretKey};
+
+    let key = KeyPair::from(&SecretKey::from_bytes([9; 32]));
+    let wallet = MsgAddressInt::std(0, [5; 32]);
+    let query_id = HighloadQueryId::new(1, 2).unwrap();
+    let messages: Vec<_> = (0..3u8)
+        .map(|i| InternalMessage::new(MsgAddressInt::std(0, [i; 32]), Coins(100 * (i as u128 + 1)), comment("hi").unwrap()))
+        .collect();
+
+    // a single message is sent directly
+    let mut batch = HighloadBatch::new(0x10ad, 3600, query_id, 1_700_000_000);
+    assert!(matches!(batch.sign(&wallet, &key), Err(CellError::Underflow)));
+    batch.push(1, messages[0].clone())?;
+    let external = batch.sign(&wallet, &key)?;
+    assert_eq!(external.info.dest, wallet);
+    let mut body = external.body.parser()?;
+    let signature: [u8; 64] = body.load_bits(512)?.try_into().unwrap();
+    let signed = body.load_ref()?;
+    assert!(body.is_empty());
+    assert!(key.public_key.verify_raw(&signed.repr_hash(), &signature));
+    let mut inner = signed.parser()?;
+    assert_eq!(inner.load_u32()?, 0x10ad);
+    assert_eq!(inner.load_ref()?, &messages[0].build()?);
+    assert_eq!(inner.load_u8()?, 1);
+    assert_eq!(inner.load_uint(23)?, query_id.raw() as u64);
+    assert_eq!(inner.load_u64()?, 1_700_000_000);
+    assert_eq!(inner.load_uint(22)?, 3600);
+    assert!(inner.is_empty());
+
+    // more messages are sent with an internal_transfer to the wallet itself
+    for message in &messages[1..] {
+        batch.push(2, message.clone())?;
+    }
+    let external = batch.sign(&wallet, &key)?;
+    let mut body = external.body.parser()?;
+    let signature: [u8; 64] = body.load_bits(512)?.try_into().unwrap();
+    let signed = body.load_ref()?;
+    assert!(key.public_key.verify_raw(&signed.repr_hash(), &signature));
+    let mut inner = signed.parser()?;
+    assert_eq!(inner.load_u32()?, 0x10ad);
+    let transfer = inner.load_ref()?;
+    assert_eq!(inner.load_u8()?, HighloadBatch::INTERNAL_TRANSFER_MODE);
+    // out_list$_ prev:^(OutList n) action:OutAction, the last message is the outermost action
+    let mut actions = CellBuilder::new().build()?;
+    for (mode, message) in [(1, &messages[0]), (2, &messages[1]), (2, &messages[2])] {
+        let mut action = CellBuilder::new();
+        action.store_reference(actions)?.store_u32(0x0ec3c86d)?.store_u8(mode)?.store_reference(message.build()?)?;
+        actions = action.build()?;
+    }
+    let mut transfer_body = CellBuilder::new();
+    transfer_body.store_u32(0xae42e5a4)?.store_u64(query_id.raw() as u64)?.store_reference(actions)?;
+    let transfer_body = transfer_body.build()?;
+    // the values of the messages with a fee reserve for each of them
+    let value = Coins(600 + 3 * HighloadBatch::FEE_RESERVE.0);
+    assert_eq!(transfer, &InternalMessage::new(wallet.clone(), value, transfer_body.clone()).build()?);
+
+    let external = batch.with_value(Coins(1000)).sign(&wallet, &key)?;
+    let signed = external.body.parser()?.load_ref()?.clone();
+    let transfer = signed.parser()?.load_ref()?.clone();
+    assert_eq!(transfer, InternalMessage::new(wallet, Coins(1000), transfer_body).build()?);
+    Ok(())
+}
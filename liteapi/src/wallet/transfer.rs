@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use adnl::crypto::KeyPair;
+use num_bigint::BigInt;
+
+use crate::cell::{ArcCell, CellBuilder, CellError};
+use crate::client::LiteClient;
+use crate::tl::common::{AccountId, BlockIdExt};
+use crate::tl::response::TransactionInfo;
+use crate::tlb::{comment, Coins, ExternalMessage, InternalMessage, MsgAddressInt};
+use crate::types::LiteError;
+
+use super::{HighloadBatch, HighloadQueryId, HighloadWalletV3};
+
+crate::contract! {
+    /// Standard wallet v3 or v4, which protects from replays with a seqno.
+    pub struct SeqnoWallet {
+        fn seqno() -> u32;
+        /// Public key as an unsigned 256-bit integer
+        fn get_public_key() -> BigInt;
+    }
+}
+
+/// Contract of the wallet sending a [`TransferBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletKind {
+    V3R2 { subwallet_id: u32 },
+    V4R2 { subwallet_id: u32 },
+    /// Highload wallet v3 sending with an unused query id, see [`HighloadQueryId::next`]
+    HighloadV3 { query_id: HighloadQueryId },
+}
+
+impl WalletKind {
+    /// Subwallet id of the wallets created by wallet apps in the basechain
+    pub const DEFAULT_SUBWALLET_ID: u32 = 698983191;
+
+    pub fn v3r2() -> Self {
+        Self::V3R2 { subwallet_id: Self::DEFAULT_SUBWALLET_ID }
+    }
+
+    pub fn v4r2() -> Self {
+        Self::V4R2 { subwallet_id: Self::DEFAULT_SUBWALLET_ID }
+    }
+
+    /// Most messages a single external message of the wallet can send.
+    pub fn max_messages(&self) -> usize {
+        match self {
+            Self::V3R2 { .. } | Self::V4R2 { .. } => 4,
+            Self::HighloadV3 { .. } => HighloadBatch::MAX_MESSAGES,
+        }
+    }
+}
+
+/// Transfer of TON from a wallet, signed with its key and sent with [`TransferBuilder::send_and_confirm`]:
+///
+/// ```no_run
+/// # use ton_liteapi::{client::LiteClient, tl::common::AccountId, tlb::{Coins, MsgAddressInt}, types::LiteError};
+/// # use ton_liteapi::wallet::{TransferBuilder, WalletKind};
+/// # async fn f(client: &mut LiteClient, key: adnl::crypto::KeyPair, wallet: AccountId, to: MsgAddressInt) -> Result<(), LiteError> {
+/// let transaction = TransferBuilder::new(WalletKind::v4r2(), key, wallet)
+///     .to(to)
+///     .amount(Coins::from_ton(1))
+///     .comment("thanks")
+///     .send_and_confirm(client)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The wallet must be deployed, the crate doesn't bundle the wallet code needed to deploy it or to derive its
+/// address from the key.
+#[derive(Clone)]
+pub struct TransferBuilder {
+    kind: WalletKind,
+    key: KeyPair,
+    wallet: AccountId,
+    messages: Vec<InternalMessage>,
+    mode: u8,
+    timeout: Duration,
+}
+
+impl TransferBuilder {
+    /// Send mode of the messages: pay the fees separately and ignore errors
+    pub const DEFAULT_MODE: u8 = 3;
+    /// How long a message of a seqno wallet stays valid
+    pub const VALID_FOR: u32 = 60;
+
+    pub fn new(kind: WalletKind, key: KeyPair, wallet: AccountId) -> Self {
+        Self { kind, key, wallet, messages: Vec::new(), mode: Self::DEFAULT_MODE, timeout: Duration::from_secs(60) }
+    }
+
+    /// Start a message to `dest`, the following calls set its amount and body.
+    pub fn to(mut self, dest: MsgAddressInt) -> Self {
+        self.messages.push(InternalMessage::new(dest, Coins::ZERO, CellBuilder::new().build().unwrap()));
+        self
+    }
+
+    pub fn amount(self, amount: Coins) -> Self {
+        self.update(|message| message.value = amount)
+    }
+
+    /// Text comment of the message, see [`comment`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the comment doesn't fit into 4 cells per kilobyte, i.e. is longer than a few kilobytes.
+    pub fn comment(self, text: &str) -> Self {
+        let body = comment(text).expect("comment is too long");
+        self.body(body)
+    }
+
+    pub fn body(self, body: ArcCell) -> Self {
+        self.update(|message| message.body = body)
+    }
+
+    pub fn bounce(self, bounce: bool) -> Self {
+        self.update(|message| message.bounce = bounce)
+    }
+
+    /// Send mode of all messages, [`TransferBuilder::DEFAULT_MODE`] by default.
+    pub fn mode(mut self, mode: u8) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// How long [`TransferBuilder::send_and_confirm`] waits for the transaction, a minute by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Add a prepared message, e.g. with a jetton transfer.
+    pub fn message(mut self, message: InternalMessage) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    fn update(mut self, f: impl FnOnce(&mut InternalMessage)) -> Self {
+        f(self.messages.last_mut().expect("TransferBuilder::to must be called first"));
+        self
+    }
+
+    /// Signed external message, using the state of the wallet at the masterchain block `id` and the time `now`.
+    ///
+    /// Fails with `Overflow` if there are more messages than the wallet can send at once.
+    pub async fn sign(&self, client: &mut LiteClient, id: BlockIdExt, now: u32) -> Result<ExternalMessage, LiteError> {
+        if self.messages.is_empty() || self.messages.len() > self.kind.max_messages() {
+            return Err(CellError::Overflow.into());
+        }
+        let address = MsgAddressInt::std(self.wallet.workchain as i8, self.wallet.id.0);
+        let (subwallet_id, op) = match self.kind {
+            WalletKind::V3R2 { subwallet_id } => (subwallet_id, None),
+            WalletKind::V4R2 { subwallet_id } => (subwallet_id, Some(0)),
+            WalletKind::HighloadV3 { query_id } => {
+                let wallet = HighloadWalletV3::new(self.wallet.clone());
+                let subwallet_id = wallet.get_subwallet_id(client, id.clone()).await?;
+                let timeout = wallet.get_timeout(client, id).await?;
+                // blocks lag behind the liteserver time, and messages from the future are rejected
+                let mut batch = HighloadBatch::new(subwallet_id, timeout, query_id, now.saturating_sub(30) as u64);
+                for message in &self.messages {
+                    batch.push(self.mode, message.clone())?;
+                }
+                return Ok(batch.sign(&address, &self.key)?);
+            }
+        };
+        let seqno = SeqnoWallet::new(self.wallet.clone()).seqno(client, id).await?;
+        // subwallet_id:uint32 valid_until:uint32 seqno:uint32, wallet v4 adds op:uint8, then mode:uint8 ^Message per message
+        let mut signed = CellBuilder::new();
+        signed.store_u32(subwallet_id)?.store_u32(now + Self::VALID_FOR)?.store_u32(seqno)?;
+        if let Some(op) = op {
+            signed.store_u8(op)?;
+        }
+        for message in &self.messages {
+            signed.store_u8(self.mode)?.store_reference(message.build()?)?;
+        }
+        let signed = signed.build()?;
+        let signature = self.key.sign_raw(&signed.repr_hash());
+        let body = CellBuilder::new().store_bits(&signature, 512)?.store_cell_data(&signed)?.build()?;
+        Ok(ExternalMessage::new(address, body))
+    }
+
+    /// Sign the transfer at the last masterchain block, send it and wait for the transaction of the wallet
+    /// processing it. Returns `None` if there's no such transaction before the timeout.
+    pub async fn send_and_confirm(&self, client: &mut LiteClient) -> Result<Option<TransactionInfo>, LiteError> {
+        let last = client.get_masterchain_info().await?.last;
+        let state = client.get_account_state(last.clone(), self.wallet.clone()).await?;
+        let after_lt = state.shard_account(&self.wallet.id.0)?.map_or(0, |account| account.last_trans_lt);
+        let now = client.get_time().await?;
+        let message = self.sign(client, last, now).await?;
+        let sent = client.send_message_tracked(message.to_boc()?).await?;
+        client.find_transaction_by_message(self.wallet.clone(), sent.normalized_hash, after_lt, self.timeout).await
+    }
+}
@@ -17,25 +17,67 @@ pub enum ConfigPublicKey {
 #[derive(Debug, Clone)]
 pub struct LiteServerAddress(Ipv4Addr);
 
+/// Liteserver entry of a global config.
+///
+/// Since `weight` and `priority` were added the struct can't be built with a literal anymore, which broke
+/// such code in 0.2.0: use [`ConfigLiteServer::new`] and the `with_*` methods instead.
 #[serde_with::serde_as]
 #[derive(Serialize, Deserialize, Debug)]
+#[non_exhaustive]
 pub struct ConfigLiteServer {
     #[serde_as(as = "serde_with::FromInto<i32>")]
     pub ip: LiteServerAddress,
     pub port: u16,
     pub id: ConfigPublicKey,
+    /// Share of the queries the server gets relative to the others, an extension of the standard format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+    /// Servers of a lower priority are only used while no server of a higher one is available,
+    /// an extension of the standard format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+}
+
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigBlockId {
+    pub workchain: i32,
+    pub shard: i64,
+    pub seqno: u32,
+    #[serde_as(as = "serde_with::base64::Base64")]
+    pub root_hash: [u8; 32],
+    #[serde_as(as = "serde_with::base64::Base64")]
+    pub file_hash: [u8; 32],
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigValidator {
+    pub zero_state: ConfigBlockId,
+    #[serde(default)]
+    pub init_block: Option<ConfigBlockId>,
+    #[serde(default)]
+    pub hardforks: Vec<ConfigBlockId>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ConfigGlobal {
     pub liteservers: Vec<ConfigLiteServer>,
+    #[serde(default)]
+    pub validator: Option<ConfigValidator>,
+}
+
+impl ConfigGlobal {
+    /// Parse a global config such as `global.config.json`, without connecting anywhere.
+    pub fn parse(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
 }
 
 impl FromStr for ConfigGlobal {
     type Err = serde_json::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        serde_json::from_str(s)
+        Self::parse(s)
     }
 }
 
@@ -74,7 +116,25 @@ impl From<LiteServerAddress> for i32 {
 }
 
 impl ConfigLiteServer {
+    pub fn new(ip: Ipv4Addr, port: u16, public_key: [u8; 32]) -> Self {
+        Self { ip: LiteServerAddress(ip), port, id: ConfigPublicKey::Ed25519 { key: public_key }, weight: None, priority: None }
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
     pub fn socket_addr(&self) -> SocketAddrV4 {
         SocketAddrV4::new(*self.ip, self.port)
     }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.id.clone().into()
+    }
 }